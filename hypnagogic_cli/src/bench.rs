@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use hypnagogic_core::process::ProcessEvent;
+
+/// How long each operation of a pipeline took, over any number of runs
+#[derive(Default, Debug)]
+pub struct Timings {
+    /// The mode and every time taken of each operation that ran, by index
+    operations: BTreeMap<usize, (&'static str, Vec<Duration>)>,
+    /// How long each whole run took
+    runs: Vec<Duration>,
+    started: Option<Instant>,
+}
+
+impl Timings {
+    /// Notes the time of an operation starting or finishing
+    pub fn record(&mut self, event: &ProcessEvent) {
+        match event {
+            ProcessEvent::StartedOperation { .. } => self.started = Some(Instant::now()),
+            ProcessEvent::FinishedOperation { index, mode } => {
+                let Some(started) = self.started.take() else {
+                    return;
+                };
+                self.operations
+                    .entry(*index)
+                    .or_insert_with(|| (mode, vec![]))
+                    .1
+                    .push(started.elapsed());
+            }
+            _ => {}
+        }
+    }
+
+    /// Notes a whole run taking `taken`
+    pub fn finish_run(&mut self, taken: Duration) {
+        self.runs.push(taken);
+    }
+
+    /// A table of the min, median and max time of every operation that ran,
+    /// then of whole runs
+    pub fn report(&self) -> String {
+        let mut text = format!(
+            "{:>4}  {:<24} {:>12} {:>12} {:>12}\n",
+            "#", "operation", "min", "median", "max"
+        );
+        let mut row = |position: &str, name: &str, times: &[Duration]| {
+            let (min, median, max) = spread(times);
+            let _ = writeln!(
+                text,
+                "{position:>4}  {name:<24} {:>12} {:>12} {:>12}",
+                format!("{min:.2?}"),
+                format!("{median:.2?}"),
+                format!("{max:.2?}")
+            );
+        };
+        for (index, (mode, times)) in &self.operations {
+            row(&(index + 1).to_string(), mode, times);
+        }
+        row("", "total", &self.runs);
+        text
+    }
+}
+
+/// The shortest, middle and longest of `times`. For an even count, the
+/// middle is the later of the two in the middle
+fn spread(times: &[Duration]) -> (Duration, Duration, Duration) {
+    let mut sorted = times.to_vec();
+    sorted.sort_unstable();
+    match (sorted.first(), sorted.last()) {
+        (Some(&min), Some(&max)) => (min, sorted[sorted.len() / 2], max),
+        _ => Default::default(),
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use hypnagogic_core::operations::pipeline::Pipeline;
+use toml::Value;
+
+use crate::progress::json_string;
+
+/// Ways of printing out a resolved config
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+pub enum ConfigFormat {
+    /// Indented json, for reading or handing to other tools
+    Json,
+    /// Toml, the same as configs are written in
+    Toml,
+}
+
+impl ConfigFormat {
+    /// The whole of `config`, in this format
+    pub fn format(self, config: &Pipeline) -> Result<String, toml::ser::Error> {
+        match self {
+            ConfigFormat::Json => {
+                let mut text = String::new();
+                write_json(&mut text, &Value::try_from(config)?, 0);
+                text.push('\n');
+                Ok(text)
+            }
+            ConfigFormat::Toml => toml::to_string(config),
+        }
+    }
+}
+
+/// Writes `value` as json, with nested arrays and tables indented a level
+/// further than `depth`
+fn write_json(text: &mut String, value: &Value, depth: usize) {
+    let indent = |depth: usize| "  ".repeat(depth);
+    match value {
+        Value::String(string) => text.push_str(&json_string(string)),
+        Value::Integer(integer) => {
+            let _ = write!(text, "{integer}");
+        }
+        // Json has no way of writing these as numbers
+        Value::Float(float) if !float.is_finite() => {
+            text.push_str(&json_string(&float.to_string()))
+        }
+        Value::Float(float) => {
+            let _ = write!(text, "{float:?}");
+        }
+        Value::Boolean(boolean) => {
+            let _ = write!(text, "{boolean}");
+        }
+        Value::Datetime(datetime) => text.push_str(&json_string(&datetime.to_string())),
+        Value::Array(items) if items.is_empty() => text.push_str("[]"),
+        Value::Array(items) => {
+            text.push_str("[\n");
+            for (index, item) in items.iter().enumerate() {
+                text.push_str(&indent(depth + 1));
+                write_json(text, item, depth + 1);
+                text.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            let _ = write!(text, "{}]", indent(depth));
+        }
+        Value::Table(table) if table.is_empty() => text.push_str("{}"),
+        Value::Table(table) => {
+            text.push_str("{\n");
+            for (index, (key, item)) in table.iter().enumerate() {
+                let _ = write!(text, "{}{}: ", indent(depth + 1), json_string(key));
+                write_json(text, item, depth + 1);
+                text.push_str(if index + 1 < table.len() { ",\n" } else { "\n" });
+            }
+            let _ = write!(text, "{}}}", indent(depth));
+        }
+    }
+}
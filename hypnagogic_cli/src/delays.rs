@@ -0,0 +1,62 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use hypnagogic_core::util::delays::{text_delays, DirDelays};
+
+use crate::progress::json_string;
+
+/// Ways of writing out frame delays
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+pub enum DelayFormat {
+    /// A header line, then one line per direction of each icon state, for
+    /// spreadsheets
+    Csv,
+    /// An array with one object per direction of each icon state
+    Json,
+}
+
+impl DelayFormat {
+    /// Every row, in this format. Delays and totals are in deciseconds
+    pub fn format(self, rows: &[DirDelays]) -> String {
+        match self {
+            DelayFormat::Csv => {
+                let mut text = "state,dir,delays,total\n".to_string();
+                for row in rows {
+                    let _ = writeln!(
+                        text,
+                        "{},{},{},{}",
+                        csv_field(&row.state),
+                        row.dir,
+                        csv_field(&text_delays(&row.delays, "")),
+                        row.total()
+                    );
+                }
+                text
+            }
+            DelayFormat::Json => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        format!(
+                            "{{\"state\":{},\"dir\":{},\"delays\":{},\"total\":{}}}",
+                            json_string(&row.state),
+                            json_string(row.dir),
+                            text_delays(&row.delays, "").replace(' ', ""),
+                            row.total()
+                        )
+                    })
+                    .collect();
+                format!("[{}]\n", rows.join(","))
+            }
+        }
+    }
+}
+
+/// Quotes `text` if it needs it to stay a single csv field
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -1,9 +1,12 @@
 use std::io;
 use std::path::PathBuf;
+use std::process::ExitCode;
 
 use hypnagogic_core::config::error::ConfigError;
-use hypnagogic_core::operations::error::ProcessorError;
-use hypnagogic_core::operations::{InputError, OutputError};
+use hypnagogic_core::operations::error::{ErrorCode, ProcessorError, ProcessorWarning};
+use hypnagogic_core::operations::pipeline::PipelineError;
+use hypnagogic_core::operations::{InputError, OutputError, OutputFormat};
+use hypnagogic_core::util::state_diff::PixelChange;
 use thiserror::Error;
 use user_error::UFE;
 
@@ -30,14 +33,137 @@ pub enum Error {
     InputParsingFailed(#[from] InputError),
     #[error("Processing Failed")]
     ProcessorFailed(#[from] ProcessorError),
+    #[error("{error}")]
+    PipelineFailed {
+        source_config: String,
+        error: PipelineError,
+    },
     #[error("Output Failed")]
-    OutputWriteFailed(#[from] OutputError),
+    OutputWriteFailed {
+        format: OutputFormat,
+        error: OutputError,
+    },
+    #[error("Output Already Exists")]
+    OutputExists(PathBuf),
+    #[error("Too Much Changed")]
+    TooMuchChange {
+        path: PathBuf,
+        change: PixelChange,
+        limit: f64,
+    },
+    #[error("Output Differs From Lockfile")]
+    LockMismatch(PathBuf),
     #[error("No template folder")]
     NoTemplateFolder(PathBuf),
+    #[error("Icon State Not Found")]
+    StateNotFound {
+        state: String,
+        available: Vec<String>,
+        suggestion: Option<String>,
+    },
+    #[error("Network Error")]
+    Network { url: String, reason: String },
+    #[error("No Embedded Config")]
+    NoEmbeddedConfig(PathBuf),
+    #[error("{0}")]
+    Strict(ProcessorWarning),
     #[error("Generic IO Error")]
     IO(#[from] io::Error),
 }
 
+impl Error {
+    /// Which kind of error this is, see [`ErrorCode`] for what stays stable
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::InputNotFound { .. } => ErrorCode::InputNotFound,
+            Error::InvalidConfig { .. } => ErrorCode::InvalidConfig,
+            Error::TemplateNotFound { .. } => ErrorCode::TemplateNotFound,
+            Error::InputParsingFailed(_) => ErrorCode::InputParsingFailed,
+            Error::ProcessorFailed(error) => error.code(),
+            Error::PipelineFailed { error, .. } => error.code(),
+            Error::OutputWriteFailed { .. } | Error::OutputExists(_) => {
+                ErrorCode::OutputWriteFailed
+            }
+            Error::TooMuchChange { .. } => ErrorCode::TooMuchChange,
+            Error::LockMismatch(_) => ErrorCode::LockMismatch,
+            Error::NoTemplateFolder(_) => ErrorCode::NoTemplateFolder,
+            Error::StateNotFound { .. } => ErrorCode::StateNotFound,
+            Error::Network { .. } => ErrorCode::Network,
+            Error::NoEmbeddedConfig(_) => ErrorCode::NoEmbeddedConfig,
+            Error::Strict(_) => ErrorCode::StrictWarning,
+            Error::IO(_) => ErrorCode::Io,
+        }
+    }
+
+    /// What hypnagogic should exit with, if this error stops it
+    pub fn exit_status(&self) -> ExitStatus {
+        self.code().into()
+    }
+}
+
+/// What hypnagogic exits with, by what kind of error stopped it, so scripts
+/// can tell a bad config apart from a failed write. `0` is success.
+///
+/// | Code | Meaning                                                   |
+/// |------|-----------------------------------------------------------|
+/// | 1    | Anything else, such as bad arguments or a failed file read |
+/// | 2    | A config or template is missing, malformed or invalid     |
+/// | 3    | An input, or something asked for in it, couldn't be found |
+/// | 4    | Processing an input failed                                |
+/// | 5    | An output couldn't be written                             |
+///
+/// When several files fail for different reasons, the highest code is used
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum ExitStatus {
+    Other = 1,
+    Config = 2,
+    InputNotFound = 3,
+    Processing = 4,
+    Output = 5,
+}
+
+impl From<ErrorCode> for ExitStatus {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::InvalidConfig
+            | ErrorCode::EmptyPipeline
+            | ErrorCode::TemplateNotFound
+            | ErrorCode::NoTemplateFolder
+            | ErrorCode::NoEmbeddedConfig => ExitStatus::Config,
+            ErrorCode::InputNotFound | ErrorCode::StateNotFound | ErrorCode::Network => {
+                ExitStatus::InputNotFound
+            }
+            ErrorCode::OutputWriteFailed => ExitStatus::Output,
+            ErrorCode::Io => ExitStatus::Other,
+            _ => ExitStatus::Processing,
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+/// A failure that stops hypnagogic with `status`. Anything worth explaining
+/// about it has already been printed, `message` just sums it up
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct Failed {
+    pub status: ExitStatus,
+    pub message: String,
+}
+
+impl Failed {
+    pub fn new(status: ExitStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
 impl UFE for Error {
     fn summary(&self) -> String {
         format!("{}", self)
@@ -82,9 +208,70 @@ impl UFE for Error {
                     format!("Expected template folder at {folder:?}"),
                 ])
             }
+            Error::StateNotFound {
+                state, available, ..
+            } => {
+                Some(vec![
+                    format!("No icon state named \"{state}\""),
+                    format!("Available icon states are [{}]", available.join(", ")),
+                ])
+            }
             Error::InputParsingFailed(image_error) => image_error.reasons(),
             Error::ProcessorFailed(process_error) => process_error.reasons(),
-            Error::OutputWriteFailed(output_error) => output_error.reasons(),
+            Error::PipelineFailed {
+                source_config,
+                error,
+            } => {
+                let mut reasons = vec![format!("Error within config \"{source_config}\"")];
+                reasons.extend(error.reasons().unwrap_or_default());
+                Some(reasons)
+            }
+            Error::OutputWriteFailed { format, error } => {
+                let mut reasons = vec![format!("Failed to write output as {format:?}")];
+                reasons.extend(error.reasons().unwrap_or_default());
+                Some(reasons)
+            }
+            Error::OutputExists(path) => {
+                Some(vec![format!(
+                    "{path:?} already exists, and differs from what would be written over it"
+                )])
+            }
+            Error::TooMuchChange {
+                path,
+                change,
+                limit,
+            } => {
+                Some(vec![format!(
+                    "{} of {} pixels in {path:?} changed ({:.2}%), more than the limit of {limit}%",
+                    change.changed,
+                    change.total,
+                    change.percent()
+                )])
+            }
+            Error::LockMismatch(path) => {
+                Some(vec![format!(
+                    "{} would come out differently than hypnagogic.lock records, so it was left \
+                     as it was",
+                    path.display()
+                )])
+            }
+            Error::Network { url, reason } => {
+                Some(vec![format!("Failed to fetch {url}"), reason.clone()])
+            }
+            Error::NoEmbeddedConfig(file) => {
+                Some(vec![format!(
+                    "{file:?} doesn't have a config embedded in it"
+                )])
+            }
+            Error::Strict(warning) => {
+                let mut reasons = warning.reasons().unwrap_or_default();
+                reasons.push(format!(
+                    "Warnings are errors under --strict. Pass --allow-warning {} to let this kind \
+                     through",
+                    warning.code().as_str()
+                ));
+                Some(reasons)
+            }
             Error::IO(err) => {
                 Some(vec![format!(
                     "Operation failed for reason of \"{:?}\"",
@@ -114,6 +301,13 @@ impl UFE for Error {
                         .to_string(),
                 )
             }
+            Error::StateNotFound {
+                suggestion: Some(suggestion),
+                ..
+            } => Some(format!("Did you mean \"{suggestion}\"?")),
+            Error::StateNotFound { .. } => {
+                Some("Check the spelling of the icon state, names are case sensitive".to_string())
+            }
             Error::NoTemplateFolder(_) => {
                 Some(
                     "Check that you have spelled your template dir correctly, and make sure it \
@@ -123,7 +317,39 @@ impl UFE for Error {
             }
             Error::InputParsingFailed(image_error) => image_error.helptext(),
             Error::ProcessorFailed(process_error) => process_error.helptext(),
-            Error::OutputWriteFailed(output_error) => output_error.helptext(),
+            Error::PipelineFailed { error, .. } => error.helptext(),
+            Error::OutputWriteFailed { error, .. } => error.helptext(),
+            Error::OutputExists(_) => {
+                Some("Pass --force to overwrite it, or move it out of the way first".to_string())
+            }
+            Error::TooMuchChange { .. } => {
+                Some(
+                    "The output was left as it was. If the change is meant to happen, raise \
+                     --max-pixel-change or delete the output and run again"
+                        .to_string(),
+                )
+            }
+            Error::LockMismatch(_) => {
+                Some(
+                    "Run with --lock instead of --frozen to update the lockfile, if the change is \
+                     meant to happen"
+                        .to_string(),
+                )
+            }
+            Error::Network { .. } => {
+                Some(
+                    "Check that the url is right and reachable, and that hypnagogic was built \
+                     with the network feature"
+                        .to_string(),
+                )
+            }
+            Error::Strict(warning) => warning.helptext(),
+            Error::NoEmbeddedConfig(_) => {
+                Some(
+                    "Give the dmi a config file next to it instead, or embed one in it first"
+                        .to_string(),
+                )
+            }
             Error::IO(_) => {
                 Some(
                     "Make sure the directories or files aren't in use, and you have permission to \
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, io};
+
+use crate::error::Error;
+use crate::progress::json_string;
+
+/// Name of the lockfile, kept in the folder hypnagogic is run over
+pub const LOCKFILE_NAME: &str = "hypnagogic.lock";
+
+/// A checksum of `bytes`, as it's written in the lockfile
+pub fn hash(bytes: &[u8]) -> String {
+    format!("{:08x}", crc32fast::hash(bytes))
+}
+
+/// Records what every output of a run was made from, for `hypnagogic.lock`.
+/// Each output gets a line of its own, so the lockfile diffs cleanly and a
+/// frozen run can check outputs one at a time
+pub struct Lockfile {
+    path: PathBuf,
+    /// Paths are written relative to this where they can be
+    root: PathBuf,
+    /// What the lockfile held before the run, if it's frozen
+    frozen: Option<String>,
+    entries: Mutex<Vec<String>>,
+}
+
+impl Lockfile {
+    /// A lockfile for a run over `input`. A frozen one has to exist already,
+    /// since it's what the run is checked against
+    pub fn new(input: &Path, frozen: bool) -> io::Result<Self> {
+        let root = if input.is_dir() {
+            input.to_path_buf()
+        } else {
+            input.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        let path = root.join(LOCKFILE_NAME);
+        let frozen = if frozen {
+            Some(fs::read_to_string(&path)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            path,
+            root,
+            frozen,
+            entries: Mutex::new(vec![]),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records `output` as made from `input` by `config`, whose resolved form
+    /// hashes to `config_hash`. When frozen, fails instead if the lockfile
+    /// doesn't already have exactly this entry
+    #[allow(clippy::result_large_err)]
+    pub fn record(
+        &self,
+        config: &Path,
+        config_hash: &str,
+        input: &Path,
+        input_hash: &str,
+        output: &Path,
+        output_bytes: &[u8],
+    ) -> Result<(), Error> {
+        let entry = format!(
+            "{{\"output\":{},\"config\":{},\"input\":{},\"config_hash\":\"{config_hash}\",\"\
+             input_hash\":\"{input_hash}\",\"output_hash\":\"{}\"}}",
+            json_string(&self.relative(output)),
+            json_string(&self.relative(config)),
+            json_string(&self.relative(input)),
+            hash(output_bytes)
+        );
+        if let Some(locked) = &self.frozen {
+            if !locked
+                .lines()
+                .any(|line| line.trim().trim_end_matches(',') == entry)
+            {
+                return Err(Error::LockMismatch(output.to_path_buf()));
+            }
+        }
+        self.entries.lock().unwrap().push(entry);
+        Ok(())
+    }
+
+    /// The lockfile as it should be after the run
+    pub fn contents(&self, version: &str) -> String {
+        let mut entries = self.entries.lock().unwrap().clone();
+        // Outputs are made in parallel, so they're recorded in any order
+        entries.sort();
+        let entries: Vec<String> = entries.iter().map(|entry| format!("    {entry}")).collect();
+        let outputs = if entries.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[\n{}\n  ]", entries.join(",\n"))
+        };
+        format!(
+            "{{\n  \"version\": {},\n  \"outputs\": {outputs}\n}}\n",
+            json_string(version)
+        )
+    }
+
+    /// Whether the run would leave a frozen lockfile as it was. Always true
+    /// for lockfiles that aren't frozen
+    pub fn unchanged(&self, version: &str) -> bool {
+        self.frozen
+            .as_ref()
+            .is_none_or(|locked| *locked == self.contents(version))
+    }
+
+    /// Writes the lockfile out, unless it's frozen
+    pub fn write(&self, version: &str) -> io::Result<()> {
+        if self.frozen.is_some() {
+            return Ok(());
+        }
+        fs::write(&self.path, self.contents(version))
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
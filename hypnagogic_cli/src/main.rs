@@ -1,42 +1,92 @@
+mod bench;
+mod config_format;
+mod delays;
 mod error;
+mod lockfile;
+mod metadata;
+mod output_name;
+mod progress;
+mod remote;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::{metadata, File};
-use std::io::BufReader;
+use std::io::{self, BufReader, Cursor};
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::{ArgAction, Parser, Subcommand};
+use dmi::icon::Icon;
+use hypnagogic_core::config::blocks::modifiers::StateTargets;
 use hypnagogic_core::config::error::ConfigError;
-use hypnagogic_core::config::read_config;
 use hypnagogic_core::config::template_resolver::error::TemplateError;
 use hypnagogic_core::config::template_resolver::file_resolver::FileResolver;
+use hypnagogic_core::config::{read_pipeline, read_pipeline_file, trace_pipeline_file};
+use hypnagogic_core::operations::error::{ErrorCode, ProcessorWarning, WarningCode};
+use hypnagogic_core::operations::limits::{
+    FrameLimits,
+    OutputSizeLimit,
+    StateNameLimit,
+    DEFAULT_OUTPUT_SIZE_WARNING,
+    DEFAULT_STATE_NAME_WARNING,
+};
+use hypnagogic_core::operations::modifiers::fix_delays::check_delays;
+use hypnagogic_core::operations::modifiers::passthrough::Passthrough;
+use hypnagogic_core::operations::modifiers::silhouette::Silhouette;
+use hypnagogic_core::operations::modifiers::validate_dimensions::check_dimensions;
+use hypnagogic_core::operations::pipeline::Pipeline;
 use hypnagogic_core::operations::{
+    DmiStage,
+    IconOperation,
     IconOperationConfig,
+    InputError,
     InputIcon,
     NamedIcon,
     OperationMode,
     Output,
-    OutputError,
+    OutputFormat,
     OutputImage,
     OutputText,
     ProcessorPayload,
 };
+use hypnagogic_core::process::ProcessEvent;
+use hypnagogic_core::util::color::Color;
+use hypnagogic_core::util::contact_sheet::ContactSheet;
+use hypnagogic_core::util::delays::dir_delays;
+use hypnagogic_core::util::embedded_config::{embed_config, read_embedded_config};
+use hypnagogic_core::util::onion_skin::OnionSkin;
+use hypnagogic_core::util::state_diff::{find_duplicate_states, PixelChange, StateChanges};
+use hypnagogic_core::util::{diff_toml, TomlChange};
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
-use tracing::{debug, info, Level};
+use tracing::{debug, info, info_span, Level};
+use tracing_subscriber::fmt::format::FmtSpan;
 use user_error::UFE;
 use walkdir::WalkDir;
 
-use crate::error::Error;
+use crate::bench::Timings;
+use crate::config_format::ConfigFormat;
+use crate::delays::DelayFormat;
+use crate::error::{Error, ExitStatus, Failed};
+use crate::lockfile::{hash, Lockfile, LOCKFILE_NAME};
+use crate::metadata::{metadata_json, sidecar_path};
+use crate::output_name::OutputNameTemplate;
+use crate::progress::{Progress, ProgressFormat};
+use crate::remote::UrlResolver;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    /// Print paths and operations
-    #[arg(short, long)]
-    verbose: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Print paths and operations. Given twice (-vv), also traces each config
+    /// and each of its operations, with how long they took
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
     /// Output as flat files instead of mirroring directory tree
     #[arg(short, long)]
     flatten: bool,
@@ -46,33 +96,343 @@ struct Args {
     /// Doesn't wait for a keypress after running. For CI or toolchain usage.
     #[arg(short = 'w', long)]
     dont_wait: bool,
+    /// Salvage dmis with partially corrupt pixel data, skipping (and warning
+    /// about) any icon states that can't be decoded
+    #[arg(long)]
+    skip_corrupt_states: bool,
+    /// Check that every frame of every input dmi is the size the dmi says it
+    /// is, and that every animated icon state has a delay per frame, before
+    /// processing it
+    #[arg(long)]
+    validate: bool,
+    /// Warn about any input icon state with more than this many frames
+    #[arg(long, value_name = "N")]
+    warn_frame_count: Option<u32>,
+    /// Refuse to process any input icon state with more than this many frames
+    #[arg(long, value_name = "N")]
+    max_frame_count: Option<u32>,
+    /// Warn about any output dmi bigger than this many bytes
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_OUTPUT_SIZE_WARNING)]
+    warn_output_size: u64,
+    /// Refuse to write any output dmi bigger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_output_bytes: Option<u64>,
+    /// Warn about any output icon state with a name longer than this many
+    /// characters
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_STATE_NAME_WARNING)]
+    warn_state_name_length: usize,
+    /// Fail a config instead of warning about it
+    #[arg(long)]
+    strict: bool,
+    /// Drop warnings with this code, like large_output, instead of reporting
+    /// them or failing on them under --strict. Can be given more than once,
+    /// and adds to each config's allow_warnings
+    #[arg(long = "allow-warning", value_name = "CODE", value_parser = parse_warning_code)]
+    allow_warnings: Vec<WarningCode>,
+    /// Copy the input of a config with no operations through unchanged, with
+    /// a warning, instead of failing it
+    #[arg(long)]
+    allow_empty_configs: bool,
+    /// Process the valid configs even if some are invalid. Otherwise every
+    /// config is checked first, and nothing is processed if any fail
+    #[arg(long)]
+    continue_on_error: bool,
+    /// Refuse to overwrite existing outputs, unless they already hold exactly
+    /// what would be written
+    #[arg(long)]
+    no_clobber: bool,
+    /// Overwrite existing outputs even with --no-clobber
+    #[arg(long)]
+    force: bool,
+    /// Fail instead of overwriting an existing dmi output if more than this
+    /// percentage of its pixels would change, to catch config changes that
+    /// do more than meant
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    max_pixel_change: Option<f64>,
+    /// Set the modification time of outputs to that of their newest input
+    /// (the config or the icon it's for), so builds that go off mtimes don't
+    /// redo work when nothing changed
+    #[arg(long)]
+    preserve_mtime: bool,
+    /// Print which icon states each output added, removed or changed
+    /// compared to its input
+    #[arg(long)]
+    report_changes: bool,
+    /// Write what the icon states of each dmi output mean, like which
+    /// neighbours a smoothing state connects to, to a json file next to it.
+    /// Only some operations describe their states
+    #[arg(long)]
+    metadata: bool,
+    /// Record what every output was made from in hypnagogic.lock, in the
+    /// folder being processed: hashes of its input, its resolved config and
+    /// itself, and the version of hypnagogic. Only written once every config
+    /// has processed without failing
+    #[arg(long)]
+    lock: bool,
+    /// Check every output against hypnagogic.lock instead of updating it.
+    /// Outputs that would come out differently aren't written, and the run
+    /// fails if the lockfile would change at all
+    #[arg(long, conflicts_with = "lock")]
+    frozen: bool,
+    /// Print what each config would do instead of processing anything: its
+    /// operations in the order they'd run, with every setting they resolve to
+    #[arg(long)]
+    explain: bool,
+    /// Report progress for a frontend to follow, on stderr
+    #[arg(long, value_name = "FORMAT")]
+    progress: Option<ProgressFormat>,
+    /// Print which file set each setting of every config, the config itself
+    /// or one of the templates it inherits from, instead of processing
+    /// anything. Profiles aren't applied
+    #[arg(long, conflicts_with = "explain")]
+    trace_resolution: bool,
     /// Output directory of folders. If not set, output will match the file tree
     /// and output adjacent to input
     #[arg(short, long)]
     output: Option<String>,
+    /// Name outputs after this pattern instead of their inputs. Can use
+    /// {config_name}, {input_stem}, {date} (as YYYY-MM-DD) and {version}
+    #[arg(long, value_name = "TEMPLATE", value_parser = OutputNameTemplate::parse)]
+    output_name_template: Option<OutputNameTemplate>,
     /// Location of the templates folder
     #[arg(short, long, default_value_t = String::from("templates"))]
     templates: String,
+    /// Apply this profile from each config's profiles over the rest of it.
+    /// Configs without any profiles are used as they are
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
     /// Input directory/file
-    input: String,
+    #[arg(required = true)]
+    input: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print a config with all of its templates resolved, then exit
+    PrintConfig {
+        /// Config file to resolve
+        config: PathBuf,
+        /// How to print the config out
+        #[arg(long, value_name = "FORMAT", default_value = "json")]
+        format: ConfigFormat,
+    },
+    /// Resolve two configs and print the settings that differ between them
+    ConfigDiff {
+        /// Config to compare from
+        old: PathBuf,
+        /// Config to compare to
+        new: PathBuf,
+    },
+    /// Render the first frame of every icon state in a dmi to a labeled png
+    /// grid, then exit
+    ContactSheet {
+        /// Dmi to render
+        file: PathBuf,
+        /// Png to write the sheet to. Extra pages get a number added to the
+        /// name (sheet.png, sheet-2.png, ...)
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How many icon states go in each row
+        #[arg(long, default_value_t = ContactSheet::default().columns)]
+        columns: u32,
+        /// How many icon states go on each page
+        #[arg(long, default_value_t = ContactSheet::default().states_per_page)]
+        per_page: usize,
+    },
+    /// Copy a single icon state out of a dmi in to a dmi of its own, then exit
+    Extract {
+        /// Dmi to take the icon state from
+        file: PathBuf,
+        /// Name of the icon state
+        state: String,
+        /// Dmi to write the icon state to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Store a config inside a dmi, for process-embedded to use, then exit
+    EmbedConfig {
+        /// Dmi to store the config in
+        file: PathBuf,
+        /// Config to store. Templates are left for process-embedded to
+        /// resolve
+        config: PathBuf,
+    },
+    /// Process a dmi using the config embedded inside it, then exit
+    ProcessEmbedded {
+        /// Dmi with an embedded config
+        file: PathBuf,
+        /// Folder to write the outputs to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// List icon states in a dmi that are exact copies of an earlier one, then
+    /// exit
+    DedupStates {
+        /// Dmi to check
+        file: PathBuf,
+        /// Remove the copies from the dmi, keeping the first of each
+        #[arg(long)]
+        fix: bool,
+        /// Write which state each copy duplicated to this file, as toml
+        #[arg(long, value_name = "PATH")]
+        aliases: Option<PathBuf>,
+    },
+    /// Lay two versions of a dmi over each other for review, then exit. The
+    /// old version is tinted red and the new one green, so anything unchanged
+    /// comes out gray
+    OnionSkin {
+        /// Dmi to compare from
+        old: PathBuf,
+        /// Dmi to compare to
+        new: PathBuf,
+        /// Dmi to write the result to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How much of the new version shows through, from 0 to 1
+        #[arg(long, default_value_t = OnionSkin::default().new_weight)]
+        new_weight: f32,
+    },
+    /// Process a single input with a single config, without searching for
+    /// configs, then exit. The input and outputs are checked the same as in a
+    /// full run, so flags like --validate and --max-output-bytes still apply
+    Process {
+        /// Config to process the input with
+        #[arg(long)]
+        config: PathBuf,
+        /// Dmi or png to process
+        #[arg(long)]
+        input: PathBuf,
+        /// Where to write the output. Its extension is replaced to match the
+        /// output format, and any extra outputs are named after it
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Print the frame delays of every direction of every icon state in a
+    /// dmi, with how long each animation takes in total, then exit
+    DumpDelays {
+        /// Dmi to read
+        file: PathBuf,
+        /// How to write the delays out
+        #[arg(long, value_name = "FORMAT", default_value = "csv")]
+        format: DelayFormat,
+    },
+    /// Check that every config in a folder loads and is valid, without
+    /// processing anything, then exit. Fails if any config doesn't
+    ValidateAll {
+        /// Folder to search for configs, or a single config
+        input: PathBuf,
+    },
+    /// Replace every icon state in a dmi with a flat color silhouette of
+    /// itself, keeping its alpha, for shadow sprites, then exit
+    Silhouette {
+        /// Dmi to make silhouettes of
+        file: PathBuf,
+        /// Dmi to write the silhouettes to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// What to draw the silhouettes in, as a hex code
+        #[arg(long, default_value = "#000000", value_parser = Color::from_hex_str)]
+        color: Color,
+    },
+    /// Run a config over an input over and over, then print the shortest,
+    /// median and longest time each operation took, then exit. The input is
+    /// read once up front and nothing is written, so only the operations are
+    /// timed
+    Bench {
+        /// Config to run
+        config: PathBuf,
+        /// Dmi or png to run it on
+        input: PathBuf,
+        /// How many times to run the config
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+    },
 }
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
+    let Err(error) = run() else {
+        return ExitCode::SUCCESS;
+    };
+    eprintln!("Error: {error:?}");
+    error
+        .downcast_ref::<Failed>()
+        .map_or(ExitStatus::Other, |failed| failed.status)
+        .into()
+}
+
+fn run() -> Result<()> {
     let now = Instant::now();
     let args = Args::parse();
     let Args {
+        command,
         verbose,
         flatten,
         debug,
         dont_wait,
+        skip_corrupt_states,
+        validate,
+        warn_frame_count,
+        max_frame_count,
+        warn_output_size,
+        max_output_bytes,
+        warn_state_name_length,
+        strict,
+        allow_warnings,
+        allow_empty_configs,
+        continue_on_error,
+        no_clobber,
+        force,
+        max_pixel_change,
+        preserve_mtime,
+        report_changes,
+        metadata,
+        lock,
+        frozen,
+        explain,
+        progress,
+        trace_resolution,
         output,
+        output_name_template,
         templates,
+        profile,
         input,
     } = args;
-
-    println!("Hypnagogic CLI v{VERSION}");
+    let profile = profile.as_deref();
+    let mut options = RunOptions {
+        flatten,
+        debug,
+        skip_corrupt_states,
+        validate,
+        frame_limits: FrameLimits {
+            warn_above: warn_frame_count,
+            max: max_frame_count,
+        },
+        output_size_limit: OutputSizeLimit {
+            warn_above: warn_output_size,
+            max: max_output_bytes,
+        },
+        state_name_limit: StateNameLimit {
+            warn_above: warn_state_name_length,
+        },
+        warning_policy: WarningPolicy {
+            strict,
+            allowed: allow_warnings,
+        },
+        allow_empty_configs,
+        refuse_clobber: no_clobber && !force,
+        max_pixel_change,
+        preserve_mtime,
+        report_changes,
+        write_metadata: metadata,
+        // Only processing a whole folder keeps a lockfile, so it's made later
+        lockfile: None,
+        progress: Progress(progress),
+        output,
+        output_name_template,
+    };
+    let progress = options.progress;
 
     // subscribers are of different generic types so can't be put into one binding
     // this is why each branch has its own binding and call to set_global_default
@@ -82,7 +442,14 @@ fn main() -> Result<()> {
             .with_max_level(Level::DEBUG)
             .finish();
         tracing::subscriber::set_global_default(subscriber)?;
-    } else if verbose {
+    } else if verbose >= 2 {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .compact()
+            .finish();
+        tracing::subscriber::set_global_default(subscriber)?;
+    } else if verbose == 1 {
         let subscriber = tracing_subscriber::fmt()
             .with_max_level(Level::INFO)
             .compact()
@@ -96,44 +463,177 @@ fn main() -> Result<()> {
         tracing::subscriber::set_global_default(subscriber)?;
     };
 
+    match command {
+        Some(Command::PrintConfig { config, format }) => {
+            return print_config(&templates, profile, &config, format)
+        }
+        Some(Command::ConfigDiff { old, new }) => {
+            return config_diff(&templates, profile, &old, &new)
+        }
+        Some(Command::ContactSheet {
+            file,
+            output,
+            columns,
+            per_page,
+        }) => {
+            let sheet = ContactSheet {
+                columns,
+                states_per_page: per_page,
+                ..ContactSheet::default()
+            };
+            return contact_sheet(&file, &output, sheet);
+        }
+        Some(Command::Extract {
+            file,
+            state,
+            output,
+        }) => return extract(&file, &state, &output),
+        Some(Command::EmbedConfig { file, config }) => return embed(&file, &config),
+        Some(Command::ProcessEmbedded { file, output }) => {
+            return process_embedded(&templates, profile, &options.warning_policy, &file, &output)
+        }
+        Some(Command::DedupStates { file, fix, aliases }) => {
+            return dedup_states(&file, fix, aliases.as_deref())
+        }
+        Some(Command::OnionSkin {
+            old,
+            new,
+            output,
+            new_weight,
+        }) => return onion_skin(&old, &new, &output, OnionSkin { new_weight }),
+        Some(Command::Process {
+            config,
+            input,
+            output,
+        }) => return process_single(&options, &templates, profile, &config, &input, &output),
+        Some(Command::DumpDelays { file, format }) => return dump_delays(&file, format),
+        Some(Command::ValidateAll { input }) => return validate_all(&templates, profile, &input),
+        Some(Command::Silhouette {
+            file,
+            output,
+            color,
+        }) => return silhouette(&file, &output, color),
+        Some(Command::Bench {
+            config,
+            input,
+            iterations,
+        }) => return bench(&templates, profile, &config, &input, iterations),
+        None => {}
+    }
+    // Only reachable without a subcommand, where clap requires an input
+    let input = input.expect("input is required without a subcommand");
+
+    println!("Hypnagogic CLI v{VERSION}");
+
     if !Path::new(&input).exists() {
-        return Err(anyhow!("Input path does not exist!"));
+        return Err(Failed::new(ExitStatus::InputNotFound, "Input path does not exist!").into());
     }
 
-    let files_to_process: Vec<PathBuf> = if metadata(&input)?.is_file() {
-        vec![Path::new(&input).to_path_buf()]
-    } else {
-        WalkDir::new(&input)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| {
-                if let Some(extension) = e.path().extension() {
-                    extension == "toml"
-                } else {
-                    false
-                }
-            })
-            .map(|e| e.into_path())
-            .collect()
-    };
+    if explain {
+        return explain_all(&templates, profile, Path::new(&input));
+    }
+
+    if lock || frozen {
+        let lockfile = Lockfile::new(Path::new(&input), frozen).map_err(|error| {
+            Failed::new(
+                ExitStatus::Other,
+                format!("--frozen needs an existing {LOCKFILE_NAME} to check against ({error})"),
+            )
+        })?;
+        options.lockfile = Some(lockfile);
+    }
+
+    let files_to_process = find_configs(Path::new(&input))?;
     debug!(files = ?files_to_process, "Files to process");
 
+    if trace_resolution {
+        return trace_all(&templates, &files_to_process);
+    }
+
     let num_files = files_to_process.len();
     println!("Found {num_files} files!");
+    progress.started(num_files);
 
-    let files_failed = files_to_process
+    // Every config is checked before any input is read or output written, so
+    // a bad one is caught straight away instead of part way through a run
+    let invalid: Vec<(PathBuf, ExitStatus)> = files_to_process
         .par_iter()
-        .filter(|path| {
-            let Err(error) = process_icon(flatten, debug, &output, &templates, path) else {
-                return false;
+        .filter_map(|path| {
+            let Err(error) = check_config(&templates, profile, options.allow_empty_configs, path)
+            else {
+                return None;
             };
+            // Frontends see every config start before it fails
+            progress.event(path, &ProcessEvent::StartedFile(path.clone()));
+            progress.error(path, &error);
             println!("{}", path.display().blue().italic());
             error.print();
-            true
+            Some((path.clone(), error.exit_status()))
         })
-        .count();
+        .collect();
+    if let Some(&(_, status)) = invalid
+        .iter()
+        .max_by_key(|(_, status)| *status)
+        .filter(|_| !continue_on_error)
+    {
+        progress.finished(0, invalid.len());
+        println!(
+            "{}",
+            format!(
+                "Found {} invalid configs, so nothing was processed! Pass --continue-on-error to \
+                 process the rest anyway",
+                invalid.len()
+            )
+            .bright_red()
+        );
+        if !dont_wait {
+            dont_disappear::any_key_to_continue::default();
+        }
+        return Err(Failed::new(status, format!("{} configs are invalid", invalid.len())).into());
+    }
+    let files_to_process: Vec<PathBuf> = files_to_process
+        .into_iter()
+        .filter(|path| !invalid.iter().any(|(invalid, _)| invalid == path))
+        .collect();
+
+    let groups = group_by_input(files_to_process);
+    let inputs_read = AtomicUsize::new(0);
+    // Why each file that failed did
+    let mut failures: Vec<ExitStatus> = groups
+        .par_iter()
+        .flat_map(|configs| {
+            // Configs sharing an input run one after another, so the first to
+            // need the input can read it for the rest
+            let mut shared_input = None;
+            configs
+                .iter()
+                .filter_map(|path| {
+                    let Err(error) = process_icon(
+                        &options,
+                        &templates,
+                        profile,
+                        &mut shared_input,
+                        &inputs_read,
+                        path,
+                    ) else {
+                        return None;
+                    };
+                    progress.error(path, &error);
+                    println!("{}", path.display().blue().italic());
+                    error.print();
+                    Some(error.exit_status())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    failures.extend(invalid.iter().map(|&(_, status)| status));
+    let files_failed = failures.len();
+    info!(
+        "Read {} inputs for {num_files} files",
+        inputs_read.load(Ordering::Relaxed)
+    );
     let files_succeeded = num_files - files_failed;
+    progress.finished(files_succeeded, files_failed);
 
     if files_failed > 0 {
         println!(
@@ -145,81 +645,330 @@ fn main() -> Result<()> {
         "{}",
         format!("Successfully processed {files_succeeded} files!").bright_green()
     );
+    // Only a run that went through cleanly says what every output is made from
+    let mut stale_lock = false;
+    if let Some(lockfile) = options.lockfile.as_ref().filter(|_| failures.is_empty()) {
+        if lockfile.unchanged(VERSION) {
+            lockfile.write(VERSION)?;
+        } else {
+            stale_lock = true;
+            println!(
+                "{}",
+                format!(
+                    "{} is out of date! Run with --lock instead of --frozen to update it",
+                    lockfile.path().display()
+                )
+                .bright_red()
+            );
+        }
+    }
     println!("{}", format!("Took {:.2?}", now.elapsed()).blue());
 
     if !dont_wait {
         dont_disappear::any_key_to_continue::default();
     }
 
+    if let Some(&status) = failures.iter().max() {
+        return Err(Failed::new(status, format!("Failed to process {files_failed} files")).into());
+    }
+    if stale_lock {
+        return Err(Failed::new(
+            ErrorCode::LockMismatch.into(),
+            format!("{LOCKFILE_NAME} is out of date"),
+        )
+        .into());
+    }
     Ok(())
 }
 
-/// Gnarly, effectful function hoisted out here so that I can still use ? but
-/// parallelize with rayon
+/// Every config at `input`: itself if it's a file, or any `.toml` file under it
+fn find_configs(input: &Path) -> Result<Vec<PathBuf>> {
+    if metadata(input)?.is_file() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+    // Following links means walkdir has to watch for loops, which it hands
+    // back as errors
+    Ok(WalkDir::new(input)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|entry| {
+            let error = match entry {
+                Ok(entry) => return Some(entry),
+                Err(error) => error,
+            };
+            if let (Some(path), Some(ancestor)) = (error.path(), error.loop_ancestor()) {
+                println!(
+                    "{} Skipping symlink loop at {} (leads back to {})",
+                    "Warning:".yellow().bold(),
+                    path.display(),
+                    ancestor.display()
+                );
+            }
+            None
+        })
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            if let Some(extension) = e.path().extension() {
+                extension == "toml"
+            } else {
+                false
+            }
+        })
+        .map(|e| e.into_path())
+        .collect())
+}
+
+/// The input a config is for, which is the config with its `.toml` taken off
+fn input_path_for(config: &Path) -> PathBuf {
+    let mut input_icon_path = config.to_path_buf();
+    // funny hack: for double extensioned files (eg, .png.toml) calling
+    // set_extension with a blank string clears out the second extension,
+    // (.png.toml -> .png)
+    input_icon_path.set_extension("");
+    input_icon_path
+}
+
+/// Groups configs by the input they're for, in the order each input is first
+/// used. Inputs are told apart by where they really are, so configs that reach
+/// the same file through symlinks share a group
+fn group_by_input(configs: Vec<PathBuf>) -> Vec<Vec<PathBuf>> {
+    let mut groups: Vec<Vec<PathBuf>> = vec![];
+    let mut group_of: HashMap<PathBuf, usize> = HashMap::new();
+    for config in configs {
+        let input = input_path_for(&config);
+        // Missing inputs are left for processing to report
+        let input = fs::canonicalize(&input).unwrap_or(input);
+        match group_of.get(&input) {
+            Some(&index) => groups[index].push(config),
+            None => {
+                group_of.insert(input, groups.len());
+                groups.push(vec![config]);
+            }
+        }
+    }
+    debug!(groups = ?groups, "Configs grouped by input");
+    groups
+}
+
+/// An input as read from disk, with any problems found reading it
+struct LoadedInput {
+    input: InputIcon,
+    warnings: Vec<ProcessorWarning>,
+}
+
+/// Reads the input at `path`, checking it over as it goes
 #[allow(clippy::result_large_err)]
-fn process_icon(
-    flatten: bool,
-    debug: bool,
-    output: &Option<String>,
-    templates: &String,
-    path: &PathBuf,
-) -> Result<(), Error> {
-    info!(path = ?path, "Found toml at path");
-    let in_file_toml = File::open(path.as_path())?;
-    let mut in_toml_reader = BufReader::new(in_file_toml);
-    let config = read_config(
-        &mut in_toml_reader,
-        FileResolver::new(Path::new(&templates))
-            .map_err(|_err| Error::NoTemplateFolder(PathBuf::from(templates)))?,
-    )
-    .map_err(|err| {
-        let source_config = path
-            .clone()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        match err {
-            ConfigError::Template(template_err) => {
-                match template_err {
-                    TemplateError::FailedToFindTemplate(template_string, expected_path) => {
-                        Error::TemplateNotFound {
-                            source_config,
-                            template_string,
-                            expected_path,
-                        }
-                    }
-                    TemplateError::TOMLError(err) => {
-                        Error::InvalidConfig {
-                            source_config,
-                            config_error: err.into(),
-                        }
+fn read_input(
+    path: &Path,
+    skip_corrupt_states: bool,
+    validate: bool,
+    frame_limits: FrameLimits,
+) -> Result<LoadedInput, Error> {
+    let actual_extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let icon_file = File::open(path)?;
+    let mut reader = BufReader::new(icon_file);
+    let (input, mut warnings) = if skip_corrupt_states {
+        InputIcon::from_reader_recovering(&mut reader, &actual_extension)?
+    } else {
+        (
+            InputIcon::from_reader(&mut reader, &actual_extension)?,
+            vec![],
+        )
+    };
+    if let InputIcon::Dmi(icon) = &input {
+        if validate {
+            check_dimensions(icon)?;
+            check_delays(icon)?;
+        }
+        warnings.extend(frame_limits.check(icon)?);
+    }
+    Ok(LoadedInput { input, warnings })
+}
+
+/// Loads and verifies every config at or under `input` without processing
+/// anything, printing whether each passed
+#[allow(clippy::result_large_err)]
+fn validate_all(templates: &String, profile: Option<&str>, input: &Path) -> Result<()> {
+    if !input.exists() {
+        return Err(Failed::new(
+            ExitStatus::InputNotFound,
+            format!("Input path {} does not exist!", input.display()),
+        )
+        .into());
+    }
+    let configs = find_configs(input)?;
+    let failures: Vec<ExitStatus> = configs
+        .iter()
+        .filter_map(|path| {
+            match check_config(templates, profile, false, path) {
+                Ok(warnings) => {
+                    println!("{} {}", "PASS".green(), path.display());
+                    for warning in &warnings {
+                        print_warning(path, warning);
                     }
-                    TemplateError::IOError(err) => err.into(),
+                    None
                 }
-            }
-            ConfigError::Toml(err) => {
-                Error::InvalidConfig {
-                    source_config,
-                    config_error: ConfigError::Toml(err),
+                Err(error) => {
+                    println!("{} {}", "FAIL".bright_red(), path.display());
+                    error.print();
+                    Some(error.exit_status())
                 }
             }
-            ConfigError::Config(_) => {
-                Error::InvalidConfig {
-                    source_config,
-                    config_error: err,
+        })
+        .collect();
+
+    let failed = failures.len();
+    let passed = configs.len() - failed;
+    println!("{passed} passed, {failed} failed");
+    if let Some(&status) = failures.iter().max() {
+        return Err(Failed::new(status, format!("{failed} configs failed validation")).into());
+    }
+    Ok(())
+}
+
+/// Prints the plan of every config under `input`, see [`Pipeline::explain`],
+/// without reading any of their inputs or writing anything
+#[allow(clippy::result_large_err)]
+fn explain_all(templates: &String, profile: Option<&str>, input: &Path) -> Result<()> {
+    let configs = find_configs(input)?;
+    let failures: Vec<ExitStatus> = configs
+        .iter()
+        .filter_map(|path| {
+            println!("{}", path.display().blue().italic());
+            match load_config(templates, profile, path) {
+                Ok(pipeline) => {
+                    for line in pipeline.explain() {
+                        println!("  {line}");
+                    }
+                    None
+                }
+                Err(error) => {
+                    error.print();
+                    Some(error.exit_status())
                 }
             }
-            _ => panic!("Unexpected error: {:#?}", err),
+        })
+        .collect();
+
+    if let Some(&status) = failures.iter().max() {
+        let failed = failures.len();
+        return Err(Failed::new(status, format!("{failed} configs failed to load")).into());
+    }
+    Ok(())
+}
+
+/// Loads and verifies the config at `path`, without touching its input
+#[allow(clippy::result_large_err)]
+fn check_config(
+    templates: &String,
+    profile: Option<&str>,
+    allow_empty_configs: bool,
+    path: &PathBuf,
+) -> Result<Vec<ProcessorWarning>, Error> {
+    let pipeline = load_config(templates, profile, path)?;
+    // Processing stands in a passthrough for these
+    if allow_empty_configs && pipeline.operations.is_empty() {
+        return Ok(vec![]);
+    }
+    pipeline.verify().map_err(|error| {
+        Error::PipelineFailed {
+            source_config: path.file_name().unwrap().to_str().unwrap().to_string(),
+            error,
         }
-    })?;
+    })
+}
 
-    let mut input_icon_path = path.clone();
-    // funny hack: for double extensioned files (eg, .png.toml) calling
-    // set_extension with a blank string clears out the second extension,
-    // (.png.toml -> .png)
-    input_icon_path.set_extension("");
+/// Settings that apply to every config in a run, from the command line
+struct RunOptions {
+    flatten: bool,
+    debug: bool,
+    skip_corrupt_states: bool,
+    validate: bool,
+    frame_limits: FrameLimits,
+    output_size_limit: OutputSizeLimit,
+    state_name_limit: StateNameLimit,
+    warning_policy: WarningPolicy,
+    allow_empty_configs: bool,
+    /// `--no-clobber`, unless `--force` overrides it
+    refuse_clobber: bool,
+    max_pixel_change: Option<f64>,
+    preserve_mtime: bool,
+    report_changes: bool,
+    write_metadata: bool,
+    lockfile: Option<Lockfile>,
+    progress: Progress,
+    output: Option<String>,
+    output_name_template: Option<OutputNameTemplate>,
+}
+
+impl RunOptions {
+    /// What `output` is written to `path` as, once it's passed the limits on
+    /// how much it changes and how big it is
+    #[allow(clippy::result_large_err)]
+    fn checked_output_bytes(
+        &self,
+        path: &Path,
+        output: &Output,
+        format: OutputFormat,
+        warnings: &WarningPolicy,
+    ) -> Result<Vec<u8>, Error> {
+        if let (Some(limit), Output::Image(OutputImage::Dmi(icon)), OutputFormat::Dmi) =
+            (self.max_pixel_change, output, format)
+        {
+            check_pixel_change(path, icon, limit)?;
+        }
+        let bytes = output_bytes(output, format)?;
+        if let Output::Image(OutputImage::Dmi(icon)) = output {
+            let size = bytes.len() as u64;
+            if let Some(warning) = self.output_size_limit.check(size, icon.states.len())? {
+                warnings.report(path, warning)?;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// Gnarly, effectful function hoisted out here so that I can still use ? but
+/// parallelize with rayon
+#[allow(clippy::result_large_err)]
+fn process_icon(
+    options: &RunOptions,
+    templates: &String,
+    profile: Option<&str>,
+    shared_input: &mut Option<LoadedInput>,
+    inputs_read: &AtomicUsize,
+    path: &PathBuf,
+) -> Result<(), Error> {
+    let RunOptions {
+        flatten,
+        debug,
+        skip_corrupt_states,
+        validate,
+        frame_limits,
+        state_name_limit,
+        ref warning_policy,
+        allow_empty_configs,
+        refuse_clobber,
+        preserve_mtime,
+        report_changes,
+        write_metadata,
+        ref lockfile,
+        progress,
+        ref output,
+        ref output_name_template,
+        ..
+    } = *options;
+    let lockfile = lockfile.as_ref();
+    let _span = info_span!("config", config = %path.display()).entered();
+    progress.event(path, &ProcessEvent::StartedFile(path.clone()));
+    let config = load_config(templates, profile, path)?;
+    let warnings = warning_policy.for_config(&config);
+
+    let input_icon_path = input_path_for(path);
 
     if !input_icon_path.exists() {
         let source_config = path.file_name().unwrap().to_str().unwrap().to_string();
@@ -236,31 +985,122 @@ fn process_icon(
             search_dir,
         });
     }
-    let actual_extension = input_icon_path
-        .extension()
-        .unwrap()
-        .to_os_string()
-        .into_string()
-        .unwrap();
-    let icon_file = File::open(&input_icon_path)?;
-    let mut reader = BufReader::new(icon_file);
-    let input = InputIcon::from_reader(&mut reader, &actual_extension)?;
+    // Only a good read is kept, so every config sharing a bad input tries it
+    // for itself and gets the error
+    let loaded = match shared_input {
+        Some(loaded) => loaded,
+        None => {
+            let loaded = read_input(
+                &input_icon_path,
+                skip_corrupt_states,
+                validate,
+                frame_limits,
+            )?;
+            inputs_read.fetch_add(1, Ordering::Relaxed);
+            shared_input.insert(loaded)
+        }
+    };
+    for warning in &loaded.warnings {
+        warnings.report(path, warning.clone())?;
+    }
+    let input = &loaded.input;
+    // Stand in a single passthrough, so the rest of the config (like its
+    // output format and assert) still applies
+    let config = if allow_empty_configs && config.operations.is_empty() {
+        warnings.report(path, ProcessorWarning::EmptyConfig)?;
+        Pipeline {
+            operations: vec![IconOperation::Passthrough(Passthrough {}).into()],
+            order: None,
+            ..config
+        }
+    } else {
+        config
+    };
+    let config_warnings = config.verify().map_err(|error| {
+        Error::PipelineFailed {
+            source_config: path.file_name().unwrap().to_str().unwrap().to_string(),
+            error,
+        }
+    })?;
+    for warning in config_warnings {
+        warnings.report(path, warning)?;
+    }
 
     let mode = if debug {
         OperationMode::Debug
     } else {
         OperationMode::Standard
     };
-    let out = config.do_operation(&input, mode)?;
+    let raised = RefCell::new(vec![]);
+    let (out, state_metadata) = config
+        .run_with_metadata(input, mode, |event| {
+            if let ProcessEvent::Warning(warning) = &event {
+                raised.borrow_mut().push(warning.clone());
+            }
+            progress.event(path, &event);
+        })
+        .map_err(|error| {
+            Error::PipelineFailed {
+                source_config: path.file_name().unwrap().to_str().unwrap().to_string(),
+                error,
+            }
+        })?;
+    for warning in raised.into_inner() {
+        warnings.report(path, warning)?;
+    }
 
     if let Some(output) = &output {
         let output_path = Path::new(output);
         fs::create_dir_all(output_path)?;
     }
 
-    let out_paths: Vec<(PathBuf, Output)> = handle_payload(out, input_icon_path, output, flatten);
+    let source_mtime = if preserve_mtime {
+        let config_mtime = metadata(path)?.modified()?;
+        let icon_mtime = metadata(&input_icon_path)?.modified()?;
+        Some(config_mtime.max(icon_mtime))
+    } else {
+        None
+    };
 
-    for (mut path, output) in out_paths {
+    // What the outputs are made from, for recording them in the lockfile
+    let lock_sources = match lockfile {
+        Some(_) => {
+            let resolved = toml::to_string(&config)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            let input_hash = hash(&fs::read(&input_icon_path)?);
+            Some((
+                hash(resolved.as_bytes()),
+                input_icon_path.clone(),
+                input_hash,
+            ))
+        }
+        None => None,
+    };
+
+    let format = config.output_format;
+    let output_name_path = match output_name_template.as_ref() {
+        Some(template) => template.rename(path, &input_icon_path, VERSION),
+        None => input_icon_path,
+    };
+    let out_paths: Vec<(PathBuf, Output)> =
+        handle_payload(out, output_name_path, output, flatten, format);
+
+    if report_changes {
+        // Pngs don't have any states to start with
+        let no_states = Icon::default();
+        let input_icon = match input {
+            InputIcon::Dmi(icon) => icon,
+            InputIcon::DynamicImage(_) => &no_states,
+        };
+        for (path, output) in &out_paths {
+            if let Output::Image(OutputImage::Dmi(icon)) = output {
+                print_state_changes(path, &StateChanges::between(input_icon, icon));
+            }
+        }
+    }
+
+    let config_path = path.as_path();
+    for (path, output) in out_paths {
         let parent_dir = path.parent().expect(
             "Failed to get parent? (this is a program error, not a config error! Please report!)",
         );
@@ -269,37 +1109,744 @@ fn process_icon(
             "Failed to create dirs (This is a program error, not a config error! Please report!)",
         );
 
-        let mut file = File::create(path.as_path()).expect(
-            "Failed to create output file (This is a program error, not a config error! Please \
-             report!)",
-        );
+        let bytes = options.checked_output_bytes(&path, &output, format, &warnings)?;
+        if let Some((lockfile, (config_hash, input_path, input_hash))) =
+            lockfile.zip(lock_sources.as_ref())
+        {
+            lockfile.record(
+                config_path,
+                config_hash,
+                input_path,
+                input_hash,
+                &path,
+                &bytes,
+            )?;
+        }
+        write_bytes(&path, bytes, refuse_clobber)?;
+        if let Output::Image(OutputImage::Dmi(icon)) = &output {
+            for warning in state_name_limit.check(icon) {
+                warnings.report(&path, warning)?;
+            }
+            progress.states(config_path, icon);
+            if write_metadata {
+                let mut described = state_metadata.for_icon(icon).peekable();
+                if described.peek().is_some() {
+                    write_bytes(
+                        &sidecar_path(&path),
+                        metadata_json(described).into_bytes(),
+                        refuse_clobber,
+                    )?;
+                }
+            }
+        }
+
+        if let Some(mtime) = source_mtime {
+            File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(mtime)?;
+        }
+        progress.event(config_path, &ProcessEvent::WroteOutput(path));
+    }
+    progress.finished_config(config_path);
+    Ok(())
+}
+
+/// Fails if writing `icon` over the dmi at `path` would change more than
+/// `limit` percent of its pixels. Passes if there's no readable dmi there to
+/// compare against
+#[allow(clippy::result_large_err)]
+fn check_pixel_change(path: &Path, icon: &Icon, limit: f64) -> Result<(), Error> {
+    let Ok(file) = File::open(path) else {
+        return Ok(());
+    };
+    let Ok(InputIcon::Dmi(baseline)) = InputIcon::from_reader(&mut BufReader::new(file), "dmi")
+    else {
+        return Ok(());
+    };
+    let change = PixelChange::between(&baseline, icon);
+    if change.percent() > limit {
+        return Err(Error::TooMuchChange {
+            path: path.to_path_buf(),
+            change,
+            limit,
+        });
+    }
+    Ok(())
+}
+
+/// Reads a warning code by its name, like large_output
+fn parse_warning_code(text: &str) -> Result<WarningCode, String> {
+    WarningCode::from_name(text).ok_or_else(|| format!("{text} isn't a warning code"))
+}
+
+/// Reads a percentage from 0 to 100
+fn parse_percent(text: &str) -> Result<f64, String> {
+    let percent: f64 = text.parse().map_err(|_| format!("{text} isn't a number"))?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("{percent} isn't between 0 and 100"));
+    }
+    Ok(percent)
+}
+
+/// Writes one output of a config to `path`. With `refuse_clobber`, an existing
+/// file at `path` is only left alone if it already holds the same bytes, and is
+/// otherwise an error
+#[allow(clippy::result_large_err)]
+fn write_output(
+    path: &Path,
+    output: &Output,
+    format: OutputFormat,
+    refuse_clobber: bool,
+) -> Result<(), Error> {
+    write_bytes(path, output_bytes(output, format)?, refuse_clobber)
+}
+
+/// What `output` is written as in `format`
+#[allow(clippy::result_large_err)]
+fn output_bytes(output: &Output, format: OutputFormat) -> Result<Vec<u8>, Error> {
+    Ok(match output {
+        Output::Image(icon) => {
+            let mut bytes = Cursor::new(vec![]);
+            icon.write_as(format, &mut bytes)
+                .map_err(|error| Error::OutputWriteFailed { format, error })?;
+            bytes.into_inner()
+        }
+        Output::Text(OutputText::PngConfig(config) | OutputText::DmiConfig(config)) => {
+            config.clone().into_bytes()
+        }
+    })
+}
+
+/// Writes `bytes` to `path`, minding `refuse_clobber` the same way as
+/// `write_output`
+#[allow(clippy::result_large_err)]
+fn write_bytes(path: &Path, bytes: Vec<u8>, refuse_clobber: bool) -> Result<(), Error> {
+    if refuse_clobber && path.exists() {
+        if fs::read(path)? == bytes {
+            return Ok(());
+        }
+        return Err(Error::OutputExists(path.to_path_buf()));
+    }
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Reads the config at `path` and resolves all of its templates
+#[allow(clippy::result_large_err)]
+fn load_config(
+    templates: &String,
+    profile: Option<&str>,
+    path: &PathBuf,
+) -> Result<Pipeline, Error> {
+    info!(path = ?path, "Found toml at path");
+    let pipeline = if let Some(url) = remote::as_url(path) {
+        let config = remote::fetch(url).map_err(|reason| {
+            Error::Network {
+                url: url.to_string(),
+                reason,
+            }
+        })?;
+        read_pipeline(&mut Cursor::new(config), UrlResolver::new(url), profile)
+    } else {
+        read_pipeline_file(
+            path,
+            FileResolver::new(Path::new(&templates))
+                .map_err(|_err| Error::NoTemplateFolder(PathBuf::from(templates)))?,
+            profile,
+        )
+    };
+    pipeline.map_err(|err| config_error(path, err))
+}
 
-        match output {
-            Output::Image(icon) => {
-                match icon {
-                    OutputImage::Png(png) => {
-                        if let Err(error) = png.save(&mut path) {
-                            return Err(Error::from(OutputError::from(error)));
-                        };
+/// Turns an error from reading the config at `path` in to one for the user
+fn config_error(path: &Path, err: ConfigError) -> Error {
+    let source_config = path.file_name().unwrap().to_str().unwrap().to_string();
+    match err {
+        ConfigError::Template(template_err) => {
+            match template_err {
+                TemplateError::FailedToFindTemplate(template_string, expected_path) => {
+                    Error::TemplateNotFound {
+                        source_config,
+                        template_string,
+                        expected_path,
                     }
-                    OutputImage::Dmi(dmi) => {
-                        if let Err(error) = dmi.save(&mut file) {
-                            return Err(Error::from(OutputError::from(error)));
-                        };
+                }
+                TemplateError::TOMLError(err) => {
+                    Error::InvalidConfig {
+                        source_config,
+                        config_error: err.into(),
                     }
                 }
+                TemplateError::IOError(err) => err.into(),
             }
-            Output::Text(text) => {
-                match text {
-                    OutputText::PngConfig(config) | OutputText::DmiConfig(config) => {
-                        fs::write(path, config).expect(
-                            "Failed to write config text, (This is a program error, not a config \
-                             error! Please report!)",
-                        )
-                    }
+        }
+        ConfigError::Toml(err) => {
+            Error::InvalidConfig {
+                source_config,
+                config_error: ConfigError::Toml(err),
+            }
+        }
+        ConfigError::Config(_)
+        | ConfigError::IncludeNotFound { .. }
+        | ConfigError::IncludeCycle { .. }
+        | ConfigError::UnknownProfile { .. } => {
+            Error::InvalidConfig {
+                source_config,
+                config_error: err,
+            }
+        }
+        ConfigError::IO(err) => err.into(),
+    }
+}
+
+/// Like `load_config`, but prints any error for the user instead of returning
+/// it, since the subcommands have no batch of failures to report at the end
+fn load_config_reporting(
+    templates: &String,
+    profile: Option<&str>,
+    path: &PathBuf,
+) -> Result<Pipeline> {
+    if remote::as_url(path).is_none() && !path.exists() {
+        return Err(Failed::new(
+            ExitStatus::Config,
+            format!("Config path {} does not exist!", path.display()),
+        )
+        .into());
+    }
+    load_config(templates, profile, path).map_err(|error| {
+        println!("{}", path.display().blue().italic());
+        error.print();
+        Failed::new(error.exit_status(), "Failed to resolve config").into()
+    })
+}
+
+/// Prints the fully resolved form of a config to stdout, in `format`
+fn print_config(
+    templates: &String,
+    profile: Option<&str>,
+    path: &PathBuf,
+    format: ConfigFormat,
+) -> Result<()> {
+    let config = load_config_reporting(templates, profile, path)?;
+    print!("{}", format.format(&config)?);
+    Ok(())
+}
+
+/// Prints every setting that differs between two fully resolved configs
+fn config_diff(
+    templates: &String,
+    profile: Option<&str>,
+    old: &PathBuf,
+    new: &PathBuf,
+) -> Result<()> {
+    let old_pipeline = load_config_reporting(templates, profile, old)?;
+    let new_pipeline = load_config_reporting(templates, profile, new)?;
+    let mut old_config = toml::Value::try_from(&old_pipeline)?;
+    let mut new_config = toml::Value::try_from(&new_pipeline)?;
+    // Single operations are written flat, so if either side is a list put
+    // both in list form, or adding an operation would look like every setting
+    // being removed
+    let is_list = |config: &toml::Value| config.get("operations").is_some();
+    if is_list(&old_config) || is_list(&new_config) {
+        old_config = old_pipeline.to_list_toml()?;
+        new_config = new_pipeline.to_list_toml()?;
+    }
+
+    let changes = diff_toml(&old_config, &new_config);
+    if changes.is_empty() {
+        println!("{}", "Configs are equivalent".bright_green());
+    }
+    for change in changes {
+        match change {
+            TomlChange::Added { path, value } => {
+                println!("{}", format!("+ {path} = {value}").green());
+            }
+            TomlChange::Removed { path, value } => {
+                println!("{}", format!("- {path} = {value}").red());
+            }
+            TomlChange::Changed { path, old, new } => {
+                println!("{}", format!("~ {path}: {old} -> {new}").yellow());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes a contact sheet of the dmi at `path`, one png per page
+fn contact_sheet(path: &PathBuf, output: &Path, sheet: ContactSheet) -> Result<()> {
+    let icon = read_dmi_reporting(path)?;
+    let pages = sheet.render(&icon);
+    let page_count = pages.len();
+    for (number, page) in (1..).zip(pages) {
+        let mut page_path = output.to_path_buf();
+        if number > 1 {
+            let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+            page_path.set_file_name(format!("{stem}-{number}.png"));
+        }
+        page.save(&page_path)?;
+        println!("Wrote {}", page_path.display());
+    }
+    if page_count > 1 {
+        println!(
+            "{}",
+            format!("Contact sheet split over {page_count} pages").blue()
+        );
+    }
+    Ok(())
+}
+
+/// Writes one icon state of the dmi at `path` out on its own, as a new dmi
+fn extract(path: &PathBuf, state_name: &str, output: &Path) -> Result<()> {
+    let icon = read_dmi_reporting(path)?;
+    let Some(state) = icon.states.iter().find(|state| state.name == state_name) else {
+        let available: Vec<String> = icon.states.iter().map(|state| state.name.clone()).collect();
+        let suggestion = available
+            .iter()
+            .map(|name| (strsim::jaro_winkler(state_name, name), name))
+            .filter(|(similarity, _)| *similarity > 0.8)
+            .max_by(|(first, _), (second, _)| first.total_cmp(second))
+            .map(|(_, name)| name.clone());
+        println!("{}", path.display().blue().italic());
+        let error = Error::StateNotFound {
+            state: state_name.to_string(),
+            available,
+            suggestion,
+        };
+        error.print();
+        return Err(Failed::new(error.exit_status(), "Failed to extract icon state").into());
+    };
+
+    let extracted = Icon {
+        states: vec![state.clone()],
+        ..icon
+    };
+    extracted.save(&mut File::create(output)?)?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+/// Onion skins the dmis at `old` and `new` in to a dmi at `output`, listing
+/// any icon states only one of them has
+fn onion_skin(old: &PathBuf, new: &PathBuf, output: &Path, skin: OnionSkin) -> Result<()> {
+    let old_icon = read_dmi_reporting(old)?;
+    let new_icon = read_dmi_reporting(new)?;
+    let skinned = skin.render(&old_icon, &new_icon)?;
+    for name in &skinned.only_old {
+        println!(
+            "{}",
+            format!("\"{name}\" is only in {}", old.display()).yellow()
+        );
+    }
+    for name in &skinned.only_new {
+        println!(
+            "{}",
+            format!("\"{name}\" is only in {}", new.display()).yellow()
+        );
+    }
+    skinned.icon.save(&mut File::create(output)?)?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+/// Reports the icon states in the dmi at `path` that copy an earlier state,
+/// removing them if `fix` is set
+fn dedup_states(path: &PathBuf, fix: bool, aliases: Option<&Path>) -> Result<()> {
+    let mut icon = read_dmi_reporting(path)?;
+    let duplicates = find_duplicate_states(&icon);
+    if duplicates.is_empty() {
+        println!("No duplicate icon states in {}", path.display());
+        return Ok(());
+    }
+    println!("{}", path.display().blue().italic());
+    for duplicate in &duplicates {
+        println!(
+            "\"{}\" is a copy of \"{}\"",
+            duplicate.name.yellow(),
+            duplicate.original
+        );
+    }
+
+    if let Some(aliases) = aliases {
+        let mapping: String = duplicates
+            .iter()
+            .map(|duplicate| {
+                format!(
+                    "{} = {}\n",
+                    toml::Value::String(duplicate.name.clone()),
+                    toml::Value::String(duplicate.original.clone())
+                )
+            })
+            .collect();
+        fs::write(aliases, mapping)?;
+        println!("Wrote {}", aliases.display());
+    }
+
+    if fix {
+        icon.states = icon
+            .states
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !duplicates.iter().any(|duplicate| duplicate.index == *index))
+            .map(|(_, state)| state)
+            .collect();
+        icon.save(&mut File::create(path)?)?;
+        println!("Removed {} duplicate icon states", duplicates.len());
+    } else {
+        println!(
+            "{}",
+            format!(
+                "{} duplicate icon states, run with --fix to remove them",
+                duplicates.len()
+            )
+            .blue()
+        );
+    }
+    Ok(())
+}
+
+/// Writes a copy of the dmi at `path` to `output` with every icon state
+/// replaced by a silhouette of itself
+fn silhouette(path: &PathBuf, output: &Path, color: Color) -> Result<()> {
+    let icon = read_dmi_reporting(path)?;
+    let shadows = Silhouette {
+        color,
+        replace: true,
+        keep_alpha: true,
+        targets: StateTargets::default(),
+    };
+    let payload = shadows.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?;
+    let ProcessorPayload::Single(image) = payload else {
+        return Err(anyhow!("Silhouette produced more than one output"));
+    };
+    let OutputImage::Dmi(silhouettes) = *image else {
+        return Err(anyhow!("Silhouette produced something other than a dmi"));
+    };
+    silhouettes.save(&mut File::create(output)?)?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+fn dump_delays(path: &PathBuf, format: DelayFormat) -> Result<()> {
+    let icon = read_dmi_reporting(path)?;
+    print!("{}", format.format(&dir_delays(&icon)));
+    Ok(())
+}
+
+/// Runs the config at `config` over the input at `input` `iterations` times,
+/// printing how long each of its operations took
+fn bench(
+    templates: &String,
+    profile: Option<&str>,
+    config: &PathBuf,
+    input: &Path,
+    iterations: u32,
+) -> Result<()> {
+    if !input.exists() {
+        return Err(Failed::new(
+            ExitStatus::InputNotFound,
+            format!("Input path {} does not exist!", input.display()),
+        )
+        .into());
+    }
+    let timings = run_bench(templates, profile, config, input, iterations).map_err(|error| {
+        println!("{}", config.display().blue().italic());
+        error.print();
+        Failed::new(
+            error.exit_status(),
+            format!("Failed to benchmark {}", config.display()),
+        )
+    })?;
+    println!(
+        "Ran {} over {} {iterations} times",
+        config.display(),
+        input.display()
+    );
+    print!("{}", timings.report());
+    Ok(())
+}
+
+#[allow(clippy::result_large_err)]
+fn run_bench(
+    templates: &String,
+    profile: Option<&str>,
+    config_path: &PathBuf,
+    input_path: &Path,
+    iterations: u32,
+) -> Result<Timings, Error> {
+    let config = load_config(templates, profile, config_path)?;
+    let extension = input_path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let input = InputIcon::from_reader(&mut BufReader::new(File::open(input_path)?), &extension)?;
+
+    let timings = RefCell::new(Timings::default());
+    for _ in 0..iterations {
+        let started = Instant::now();
+        config
+            .run_with_progress(&input, OperationMode::Standard, |event| {
+                timings.borrow_mut().record(&event);
+            })
+            .map_err(|error| {
+                Error::PipelineFailed {
+                    source_config: config_path
+                        .file_name()
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_string(),
+                    error,
                 }
+            })?;
+        timings.borrow_mut().finish_run(started.elapsed());
+    }
+    Ok(timings.into_inner())
+}
+
+/// Stores the config at `config_path` inside the dmi at `path`, replacing any
+/// config already there
+fn embed(path: &PathBuf, config_path: &Path) -> Result<()> {
+    // Make sure it's a dmi we can process before changing it
+    read_dmi_reporting(path)?;
+    let config = fs::read_to_string(config_path)?;
+    let embedded = embed_config(&fs::read(path)?, &config)?;
+    fs::write(path, embedded)?;
+    println!("Embedded {} in {}", config_path.display(), path.display());
+    Ok(())
+}
+
+/// Runs the config at `config` over the icon at `input` and writes what it
+/// makes to `output`, checking the input and outputs the same way as a full
+/// run. Prints any error for the user instead of returning it
+fn process_single(
+    options: &RunOptions,
+    templates: &String,
+    profile: Option<&str>,
+    config: &PathBuf,
+    input: &Path,
+    output: &Path,
+) -> Result<()> {
+    if !input.exists() {
+        return Err(Failed::new(
+            ExitStatus::InputNotFound,
+            format!("Input path {} does not exist!", input.display()),
+        )
+        .into());
+    }
+    run_single(options, templates, profile, config, input, output).map_err(|error| {
+        println!("{}", config.display().blue().italic());
+        error.print();
+        Failed::new(
+            error.exit_status(),
+            format!("Failed to process {}", input.display()),
+        )
+        .into()
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn run_single(
+    options: &RunOptions,
+    templates: &String,
+    profile: Option<&str>,
+    config_path: &PathBuf,
+    input_path: &Path,
+    output: &Path,
+) -> Result<(), Error> {
+    let _span = info_span!("config", config = %config_path.display()).entered();
+    let config = load_config(templates, profile, config_path)?;
+    let warnings = options.warning_policy.for_config(&config);
+    let loaded = read_input(
+        input_path,
+        options.skip_corrupt_states,
+        options.validate,
+        options.frame_limits,
+    )?;
+    for warning in loaded.warnings {
+        warnings.report(input_path, warning)?;
+    }
+
+    let pipeline_failed = |error| {
+        Error::PipelineFailed {
+            source_config: config_path
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string(),
+            error,
+        }
+    };
+    for warning in config.verify().map_err(pipeline_failed)? {
+        warnings.report(config_path, warning)?;
+    }
+    let mode = if options.debug {
+        OperationMode::Debug
+    } else {
+        OperationMode::Standard
+    };
+    let raised = RefCell::new(vec![]);
+    let out = config
+        .run_with_progress(&loaded.input, mode, |event| {
+            if let ProcessEvent::Warning(warning) = event {
+                raised.borrow_mut().push(warning);
+            }
+        })
+        .map_err(pipeline_failed)?;
+    for warning in raised.into_inner() {
+        warnings.report(config_path, warning)?;
+    }
+
+    let format = config.output_format;
+    for (path, output) in handle_payload(out, output.to_path_buf(), &None, false, format) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let bytes = options.checked_output_bytes(&path, &output, format, &warnings)?;
+        write_bytes(&path, bytes, options.refuse_clobber)?;
+        if let Output::Image(OutputImage::Dmi(icon)) = &output {
+            for warning in options.state_name_limit.check(icon) {
+                warnings.report(&path, warning)?;
             }
         }
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn process_embedded(
+    templates: &String,
+    profile: Option<&str>,
+    warning_policy: &WarningPolicy,
+    path: &Path,
+    output: &Path,
+) -> Result<()> {
+    if !path.exists() {
+        return Err(Failed::new(
+            ExitStatus::InputNotFound,
+            format!("Input path {} does not exist!", path.display()),
+        )
+        .into());
+    }
+    run_embedded(templates, profile, warning_policy, path, output).map_err(|error| {
+        println!("{}", path.display().blue().italic());
+        error.print();
+        Failed::new(error.exit_status(), "Failed to process embedded config").into()
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn run_embedded(
+    templates: &String,
+    profile: Option<&str>,
+    warning_policy: &WarningPolicy,
+    path: &Path,
+    output: &Path,
+) -> Result<(), Error> {
+    let bytes = fs::read(path)?;
+    let Some(config_text) = read_embedded_config(&bytes).map_err(|error| {
+        InputError::DmiRead {
+            stage: DmiStage::Png,
+            error,
+        }
+    })?
+    else {
+        return Err(Error::NoEmbeddedConfig(path.to_path_buf()));
+    };
+    let resolver = FileResolver::new(Path::new(&templates))
+        .map_err(|_err| Error::NoTemplateFolder(PathBuf::from(templates)))?;
+    let config = read_pipeline(&mut Cursor::new(config_text), resolver, profile)
+        .map_err(|err| config_error(path, err))?;
+    let warnings = warning_policy.for_config(&config);
+
+    let input = InputIcon::from_reader(&mut Cursor::new(bytes), "dmi")?;
+    let pipeline_failed = |error| {
+        Error::PipelineFailed {
+            source_config: path.file_name().unwrap().to_str().unwrap().to_string(),
+            error,
+        }
+    };
+    for warning in config.verify().map_err(pipeline_failed)? {
+        warnings.report(path, warning)?;
+    }
+    let raised = RefCell::new(vec![]);
+    let out = config
+        .run_with_progress(&input, OperationMode::Standard, |event| {
+            if let ProcessEvent::Warning(warning) = event {
+                raised.borrow_mut().push(warning);
+            }
+        })
+        .map_err(pipeline_failed)?;
+    for warning in raised.into_inner() {
+        warnings.report(path, warning)?;
+    }
+
+    fs::create_dir_all(output)?;
+    let format = config.output_format;
+    let output = Some(output.to_string_lossy().into_owned());
+    for (path, output) in handle_payload(out, path.to_path_buf(), &output, true, format) {
+        write_output(&path, &output, format, false)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Reads the dmi at `path`, printing any error for the user instead of
+/// returning it
+#[allow(clippy::result_large_err)]
+fn read_dmi_reporting(path: &PathBuf) -> Result<Icon> {
+    if !path.exists() {
+        return Err(Failed::new(
+            ExitStatus::InputNotFound,
+            format!("Input path {} does not exist!", path.display()),
+        )
+        .into());
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let icon = InputIcon::from_reader(&mut reader, "dmi")
+        .map_err(Error::from)
+        .and_then(|input| Ok(input.require_states()?.clone()));
+    icon.map_err(|error| {
+        println!("{}", path.display().blue().italic());
+        error.print();
+        Failed::new(error.exit_status(), "Failed to read dmi").into()
+    })
+}
+
+/// Prints where every setting of each of `configs` came from, see
+/// [`trace_pipeline_file`], without reading any of their inputs or writing
+/// anything
+#[allow(clippy::result_large_err)]
+fn trace_all(templates: &String, configs: &[PathBuf]) -> Result<()> {
+    let failures: Vec<ExitStatus> = configs
+        .iter()
+        .filter_map(|path| {
+            println!("{}", path.display().blue().italic());
+            let sources = FileResolver::new(Path::new(templates))
+                .map_err(|_err| Error::NoTemplateFolder(PathBuf::from(templates)))
+                .and_then(|resolver| {
+                    trace_pipeline_file(path, resolver).map_err(|err| config_error(path, err))
+                });
+            match sources {
+                Ok(sources) => {
+                    for (field, source) in sources {
+                        println!("  {field}: {source}");
+                    }
+                    None
+                }
+                Err(error) => {
+                    error.print();
+                    Some(error.exit_status())
+                }
+            }
+        })
+        .collect();
+
+    if let Some(&status) = failures.iter().max() {
+        let failed = failures.len();
+        return Err(Failed::new(status, format!("{failed} configs failed to load")).into());
     }
     Ok(())
 }
@@ -310,6 +1857,7 @@ fn handle_payload(
     input_path: PathBuf,
     output_at: &Option<String>,
     flatten: bool,
+    format: OutputFormat,
 ) -> Vec<(PathBuf, Output)> {
     let mut out_paths: Vec<(PathBuf, Output)> = vec![];
     let process_path = |path: PathBuf, named_img: Option<&NamedIcon>| -> PathBuf {
@@ -340,18 +1888,18 @@ fn handle_payload(
     match payload {
         ProcessorPayload::Single(inner) => {
             let mut processed_path = process_path(input_path.clone(), None);
-            processed_path.set_extension(inner.extension());
+            processed_path.set_extension(inner.extension_for(format));
             out_paths.push((processed_path, Output::Image(*inner)));
         }
         ProcessorPayload::SingleNamed(named) => {
             let mut processed_path = process_path(input_path.clone(), Some(&named));
-            processed_path.set_extension(named.image.extension());
+            processed_path.set_extension(named.image.extension_for(format));
             out_paths.push((processed_path, Output::Image(named.image)))
         }
         ProcessorPayload::MultipleNamed(icons) => {
             for icon in icons {
                 let mut processed_path = process_path(input_path.clone(), Some(&icon));
-                processed_path.set_extension(icon.image.extension());
+                processed_path.set_extension(icon.image.extension_for(format));
                 out_paths.push((processed_path, Output::Image(icon.image)))
             }
         }
@@ -361,9 +1909,82 @@ fn handle_payload(
             processed_path.set_extension(config_text.extension());
             out_paths.push((processed_path, Output::Text(*config_text)));
             // Then we recurse and handle the enclosed payload
-            let mut contained = handle_payload(*payload, input_path, output_at, flatten);
+            let mut contained = handle_payload(*payload, input_path, output_at, flatten, format);
             out_paths.append(&mut contained);
         }
     }
     out_paths
 }
+
+/// What to do with warnings: drop the allowed ones, and either print or fail
+/// on the rest
+#[derive(Clone, Debug)]
+struct WarningPolicy {
+    strict: bool,
+    allowed: Vec<WarningCode>,
+}
+
+impl WarningPolicy {
+    /// This policy, also allowing what `config` allows
+    fn for_config(&self, config: &Pipeline) -> Self {
+        let mut allowed = self.allowed.clone();
+        allowed.extend_from_slice(&config.allow_warnings);
+        Self {
+            strict: self.strict,
+            allowed,
+        }
+    }
+
+    /// Prints a non fatal problem with a file, or fails on it under
+    /// `--strict`, unless it's allowed
+    #[allow(clippy::result_large_err)]
+    fn report(&self, path: &Path, warning: ProcessorWarning) -> Result<(), Error> {
+        if self.allowed.contains(&warning.code()) {
+            return Ok(());
+        }
+        if self.strict {
+            return Err(Error::Strict(warning));
+        }
+        print_warning(path, &warning);
+        Ok(())
+    }
+}
+
+/// Lists what an output did to its input's icon states
+fn print_state_changes(path: &Path, changes: &StateChanges) {
+    let mut message = format!("{}", path.display().blue().italic());
+    if changes.is_empty() {
+        message.push_str(&format!("\n{}", "No icon states changed".bright_green()));
+    }
+    for name in &changes.added {
+        message.push_str(&format!("\n{}", format!("+ {name}").green()));
+    }
+    for name in &changes.removed {
+        message.push_str(&format!("\n{}", format!("- {name}").red()));
+    }
+    for name in &changes.modified {
+        message.push_str(&format!("\n{}", format!("~ {name}").yellow()));
+    }
+    if !changes.unchanged.is_empty() {
+        message.push_str(&format!("\n{} unchanged", changes.unchanged.len()));
+    }
+    println!("{message}");
+}
+
+/// Prints a non fatal problem with a file, formatted like `UFE::print` but in
+/// yellow. Built up into one string so parallel output doesn't interleave
+fn print_warning(path: &Path, warning: &impl UFE) {
+    let mut message = format!(
+        "{}\n{} {}",
+        path.display().blue().italic(),
+        "Warning:".yellow().bold(),
+        warning.summary()
+    );
+    for reason in warning.reasons().unwrap_or_default() {
+        message.push_str(&format!("\n - {}", reason.yellow()));
+    }
+    if let Some(help) = warning.helptext() {
+        message.push_str(&format!("\n{help}"));
+    }
+    println!("{message}");
+}
@@ -0,0 +1,38 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use hypnagogic_core::operations::metadata::{MetadataValue, StateMetadata};
+
+use crate::progress::json_string;
+
+/// Where the metadata for the output at `path` goes, right next to it
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".json");
+    PathBuf::from(sidecar)
+}
+
+/// An array with one object per icon state, holding its name and whatever
+/// was said about it
+pub fn metadata_json<'a>(states: impl Iterator<Item = &'a StateMetadata>) -> String {
+    let states: Vec<String> = states
+        .map(|metadata| {
+            let mut object = format!("{{\"state\":{}", json_string(&metadata.state));
+            for (key, value) in &metadata.fields {
+                let value = match value {
+                    MetadataValue::Number(number) => number.to_string(),
+                    MetadataValue::Text(text) => json_string(text),
+                    MetadataValue::List(items) => {
+                        let items: Vec<String> =
+                            items.iter().map(|item| json_string(item)).collect();
+                        format!("[{}]", items.join(","))
+                    }
+                };
+                let _ = write!(object, ",{}:{value}", json_string(key));
+            }
+            object.push('}');
+            object
+        })
+        .collect();
+    format!("[{}]\n", states.join(","))
+}
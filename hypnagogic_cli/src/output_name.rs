@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Every placeholder an output name template can use
+const PLACEHOLDERS: [&str; 4] = ["config_name", "input_stem", "date", "version"];
+
+/// A pattern for naming outputs, like `{config_name}_{date}`. Checked for
+/// unknown placeholders when it's parsed, so mistakes are caught before
+/// anything gets processed
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OutputNameTemplate(String);
+
+impl OutputNameTemplate {
+    /// Parses a template, for clap to use on the command line
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                return Err(format!("\"{{\" at \"{}\" is never closed", &rest[start..]));
+            };
+            let placeholder = &rest[start + 1..start + length];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                return Err(format!(
+                    "Unknown placeholder {{{placeholder}}}, the supported ones are {}",
+                    PLACEHOLDERS.map(|known| format!("{{{known}}}")).join(", ")
+                ));
+            }
+            rest = &rest[start + length + 1..];
+        }
+        Ok(Self(template.to_string()))
+    }
+
+    /// Works out the new path of the input at `input`, as named by the config
+    /// at `config`. The extension is left as the input's, and gets replaced
+    /// with the output's later along with everything else
+    pub fn rename(&self, config: &Path, input: &Path, version: &str) -> PathBuf {
+        let file_name = |path: &Path| path.file_name().unwrap().to_string_lossy().into_owned();
+        let config_name = file_name(config);
+        let config_name = config_name.split('.').next().unwrap_or_default();
+        let input_stem = input.file_stem().unwrap().to_string_lossy();
+        let mut name = self
+            .0
+            .replace("{config_name}", config_name)
+            .replace("{input_stem}", &input_stem)
+            .replace("{date}", &today())
+            .replace("{version}", version);
+        // The output format picks the extension, so don't double it up
+        for extension in [".dmi", ".png"] {
+            if let Some(stripped) = name.strip_suffix(extension) {
+                name = stripped.to_string();
+            }
+        }
+        let extension = input.extension().unwrap_or_default().to_string_lossy();
+        input.with_file_name(format!("{name}.{extension}"))
+    }
+}
+
+/// Today's date in UTC, as YYYY-MM-DD
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    #[allow(clippy::cast_possible_wrap)]
+    let days = (seconds / 86_400) as i64;
+
+    // Days since the epoch to a calendar date, from Howard Hinnant's
+    // civil_from_days
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
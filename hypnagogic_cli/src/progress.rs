@@ -0,0 +1,159 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use clap::ValueEnum;
+use dmi::icon::Icon;
+use hypnagogic_core::process::ProcessEvent;
+use user_error::UFE;
+
+use crate::error::Error;
+
+/// Ways of reporting progress for other programs to follow along with
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+pub enum ProgressFormat {
+    /// One json object per line on stderr, each with an "event" field saying
+    /// what happened
+    Ndjson,
+}
+
+/// Sends progress events out in whatever format was asked for, if any
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Progress(pub Option<ProgressFormat>);
+
+impl Progress {
+    /// The run is starting, with this many configs to go through
+    pub fn started(self, configs: usize) {
+        self.emit("started", &[("configs", Field::Number(configs))]);
+    }
+
+    /// Something happened while processing the config at `config`
+    pub fn event(self, config: &Path, event: &ProcessEvent) {
+        let config = Field::Path(config);
+        match event {
+            ProcessEvent::StartedFile(_) => self.emit("config_started", &[("config", config)]),
+            ProcessEvent::StartedOperation { index, mode } => {
+                self.emit(
+                    "operation_started",
+                    &[
+                        ("config", config),
+                        ("index", Field::Number(*index)),
+                        ("mode", Field::Text(mode)),
+                    ],
+                );
+            }
+            ProcessEvent::FinishedOperation { index, mode } => {
+                self.emit(
+                    "operation_finished",
+                    &[
+                        ("config", config),
+                        ("index", Field::Number(*index)),
+                        ("mode", Field::Text(mode)),
+                    ],
+                );
+            }
+            // Reported along with every other warning, rather than as progress
+            ProcessEvent::Warning(_) => {}
+            ProcessEvent::WroteOutput(path) => {
+                self.emit(
+                    "output_written",
+                    &[("config", config), ("path", Field::Path(path))],
+                );
+            }
+        }
+    }
+
+    /// Each icon state of a dmi output, once it's been written
+    pub fn states(self, config: &Path, icon: &Icon) {
+        for state in &icon.states {
+            self.emit(
+                "state",
+                &[
+                    ("config", Field::Path(config)),
+                    ("state", Field::Text(&state.name)),
+                ],
+            );
+        }
+    }
+
+    /// Every output of the config at `config` has been written
+    pub fn finished_config(self, config: &Path) {
+        self.emit("config_finished", &[("config", Field::Path(config))]);
+    }
+
+    /// The config at `config` failed
+    pub fn error(self, config: &Path, error: &Error) {
+        self.emit(
+            "error",
+            &[
+                ("config", Field::Path(config)),
+                ("code", Field::Text(error.code().as_str())),
+                ("message", Field::Text(&error.summary())),
+                ("reasons", Field::List(error.reasons().unwrap_or_default())),
+            ],
+        );
+    }
+
+    /// The whole run is over
+    pub fn finished(self, succeeded: usize, failed: usize) {
+        self.emit(
+            "finished",
+            &[
+                ("succeeded", Field::Number(succeeded)),
+                ("failed", Field::Number(failed)),
+            ],
+        );
+    }
+
+    fn emit(self, event: &str, fields: &[(&str, Field)]) {
+        match self.0 {
+            None => {}
+            // A single eprintln keeps lines from different threads apart
+            Some(ProgressFormat::Ndjson) => eprintln!("{}", json_line(event, fields)),
+        }
+    }
+}
+
+enum Field<'a> {
+    Number(usize),
+    Text(&'a str),
+    Path(&'a Path),
+    List(Vec<String>),
+}
+
+fn json_line(event: &str, fields: &[(&str, Field)]) -> String {
+    let mut line = format!("{{\"event\":{}", json_string(event));
+    for (key, value) in fields {
+        let value = match value {
+            Field::Number(number) => number.to_string(),
+            Field::Text(text) => json_string(text),
+            Field::Path(path) => json_string(&path.to_string_lossy()),
+            Field::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+                format!("[{}]", items.join(","))
+            }
+        };
+        let _ = write!(line, ",{}:{value}", json_string(key));
+    }
+    line.push('}');
+    line
+}
+
+pub(crate) fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char if char.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", char as u32);
+            }
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use hypnagogic_core::config::template_resolver::error::{TemplateError, TemplateResult};
+use hypnagogic_core::config::template_resolver::TemplateResolver;
+
+/// If `path` is really an http(s) url, hands it back as one
+pub fn as_url(path: &Path) -> Option<&str> {
+    let path = path.to_str()?;
+    (path.starts_with("http://") || path.starts_with("https://")).then_some(path)
+}
+
+/// Downloads the text at `url`. Goes through curl rather than an http crate,
+/// since it's on every CI image (and Windows 10 onward) already and handles
+/// proxies and certificates the way the rest of the system does.
+///
+/// Errors are a reason the download failed, for the user to read
+#[cfg(feature = "network")]
+pub fn fetch(url: &str) -> Result<String, String> {
+    let output = std::process::Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .map_err(|error| format!("Couldn't run curl: {error}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    String::from_utf8(output.stdout).map_err(|_| "Response wasn't valid utf-8".to_string())
+}
+
+#[cfg(not(feature = "network"))]
+pub fn fetch(_url: &str) -> Result<String, String> {
+    Err("This build of hypnagogic doesn't include network support".to_string())
+}
+
+/// Resolves templates from next to a config that was downloaded, so a config
+/// at `https://example.com/configs/wall.toml` with a template of
+/// `bitmask/slice` gets it from `https://example.com/configs/bitmask/slice.toml`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UrlResolver {
+    base: String,
+}
+
+impl UrlResolver {
+    /// Makes a resolver for templates used by the config at `url`
+    pub fn new(url: &str) -> Self {
+        let base = match url.rfind('/') {
+            Some(last_slash) => &url[..=last_slash],
+            None => url,
+        };
+        Self {
+            base: base.to_string(),
+        }
+    }
+}
+
+impl TemplateResolver for UrlResolver {
+    fn resolve(&self, input: &str) -> TemplateResult {
+        let url = self.source_name(input);
+        let text = fetch(&url).map_err(|_| {
+            TemplateError::FailedToFindTemplate(input.to_string(), PathBuf::from(&url))
+        })?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    fn source_name(&self, input: &str) -> String {
+        format!("{}{input}.toml", self.base)
+    }
+}
@@ -0,0 +1,109 @@
+#[macro_use]
+mod util;
+
+mod allow_warnings {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a small dmi with a half transparent mask, checked by `config`
+    fn write_icon(dir: &Path, config: &str) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "mask".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([255, 255, 255, 128]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("mask.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("mask.dmi.toml"), config).unwrap();
+    }
+
+    /// Runs over the dmi, returning the exit code and everything printed
+    fn run(dir: &Path, extra_args: &[&str]) -> (i32, String) {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.join("mask.dmi.toml").to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn allowed_warnings_are_dropped_under_strict() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "mode = \"ValidateMask\"\n");
+        let large = ["--warn-output-size", "10", "--strict"];
+
+        let (code, stdout) = run(dir.path(), &large);
+        assert_eq!(code, 4, "{stdout}");
+        assert!(stdout.contains("--allow-warning soft_mask"), "{stdout}");
+
+        // Allowing the soft mask lets the large output through to fail instead
+        let (code, stdout) = run(
+            dir.path(),
+            &[&large[..], &["--allow-warning", "soft_mask"]].concat(),
+        );
+        assert_eq!(code, 4, "{stdout}");
+        assert!(!stdout.contains("Soft Mask"), "{stdout}");
+        assert!(stdout.contains("Large Output"), "{stdout}");
+
+        let (code, stdout) = run(
+            dir.path(),
+            &[
+                &large[..],
+                &[
+                    "--allow-warning",
+                    "soft_mask",
+                    "--allow-warning",
+                    "large_output",
+                ],
+            ]
+            .concat(),
+        );
+        assert_eq!(code, 0, "{stdout}");
+        assert!(!stdout.contains("Warning"), "{stdout}");
+    }
+
+    #[test]
+    fn configs_can_allow_warnings() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(
+            dir.path(),
+            "mode = \"ValidateMask\"\nallow_warnings = [\"soft_mask\"]\n",
+        );
+
+        let (code, stdout) = run(dir.path(), &["--warn-output-size", "10"]);
+        assert_eq!(code, 0, "{stdout}");
+        assert!(!stdout.contains("Soft Mask"), "{stdout}");
+        assert!(stdout.contains("Large Output"), "{stdout}");
+    }
+
+    #[test]
+    fn unknown_codes_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "mode = \"ValidateMask\"\n");
+
+        let (code, stdout) = run(dir.path(), &["--allow-warning", "not_a_warning"]);
+        assert_ne!(code, 0, "{stdout}");
+        assert!(
+            stdout.contains("not_a_warning isn't a warning code"),
+            "{stdout}"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+#[macro_use]
+mod util;
+
+mod bench {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[operations]]
+mode = "Blur"
+radius = 1.0
+
+[[operations]]
+mode = "CapAlpha"
+max_alpha = 100
+"#;
+
+    fn write_icon(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), CONFIG).unwrap();
+    }
+
+    fn bench(dir: &Path, iterations: &str) -> (i32, String) {
+        let output = run_with_args(vec![
+            "bench".to_string(),
+            dir.join("wall.dmi.toml").to_str().unwrap().to_string(),
+            dir.join("wall.dmi").to_str().unwrap().to_string(),
+            "--iterations".to_string(),
+            iterations.to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn times_every_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let (code, stdout) = bench(dir.path(), "3");
+        assert_eq!(code, 0, "{stdout}");
+        assert!(stdout.contains("3 times"), "{stdout}");
+        let rows: Vec<&str> = stdout
+            .lines()
+            .skip_while(|line| !line.contains("median"))
+            .skip(1)
+            .collect();
+        assert_eq!(rows.len(), 3, "{stdout}");
+        assert!(rows[0].trim_start().starts_with("1  Blur"), "{stdout}");
+        assert!(rows[1].trim_start().starts_with("2  CapAlpha"), "{stdout}");
+        assert!(rows[2].trim_start().starts_with("total"), "{stdout}");
+        // Nothing is written
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn needs_at_least_one_iteration() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let (code, stdout) = bench(dir.path(), "0");
+        assert_ne!(code, 0, "{stdout}");
+    }
+}
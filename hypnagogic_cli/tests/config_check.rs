@@ -0,0 +1,79 @@
+#[macro_use]
+mod util;
+
+mod config_check {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a good config and its dmi, plus a config that deserializes but
+    /// fails verification, for a dmi that doesn't exist
+    fn write_configs(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+        fs::write(
+            dir.join("floor.dmi.toml"),
+            "mode = \"Trim\"\nround_to = 0\n",
+        )
+        .unwrap();
+    }
+
+    /// Runs over everything in `dir`, returning the exit code and everything
+    /// printed
+    fn run(dir: &Path, extra_args: &[&str]) -> (i32, String) {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--flatten".to_string());
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn invalid_config_stops_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        write_configs(dir.path());
+
+        let (code, stdout) = run(dir.path(), &[]);
+        assert_eq!(code, 2, "{stdout}");
+        assert!(stdout.contains("round_to must be at least 1"), "{stdout}");
+        assert!(stdout.contains("nothing was processed"), "{stdout}");
+        // The config was caught before looking for its input
+        assert!(!stdout.contains("Input not found"), "{stdout}");
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn continue_on_error_processes_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        write_configs(dir.path());
+
+        let (code, stdout) = run(dir.path(), &["--continue-on-error"]);
+        assert_eq!(code, 2, "{stdout}");
+        assert!(stdout.contains("Failed to process 1 files!"), "{stdout}");
+        assert!(
+            stdout.contains("Successfully processed 1 files!"),
+            "{stdout}"
+        );
+        assert!(dir.path().join("out").join("wall.dmi").exists());
+    }
+}
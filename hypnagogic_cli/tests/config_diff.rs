@@ -0,0 +1,62 @@
+#[macro_use]
+mod util;
+
+mod config_diff {
+    use std::fs;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn diff_configs(old: &str, new: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let old_path = dir.path().join("old.png.toml");
+        let new_path = dir.path().join("new.png.toml");
+        fs::write(&old_path, old).unwrap();
+        fs::write(&new_path, new).unwrap();
+
+        let output = run_with_args(vec![
+            "config-diff".to_string(),
+            old_path.to_str().unwrap().to_string(),
+            new_path.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn shows_changed_field() {
+        let stdout = diff_configs(
+            "template = \"bitmask/slice-32x32\"\n",
+            "template = \"bitmask/slice-32x32\"\n[icon_size]\ny = 48\n",
+        );
+        assert!(stdout.contains("~ icon_size.y: 32 -> 48"));
+        assert!(!stdout.contains("icon_size.x"));
+    }
+
+    #[test]
+    fn adding_an_operation_only_shows_the_new_one() {
+        let stdout = diff_configs(
+            "mode = \"Blur\"\nradius = 1.0\n",
+            "[[operations]]\nmode = \"Blur\"\nradius = 1.0\n\n[[operations]]\nmode = \
+             \"Blur\"\nradius = 2.0\n",
+        );
+        assert!(stdout.contains("+ operations.1 = "));
+        assert!(!stdout.contains("operations.0"));
+        assert!(!stdout.contains("- "));
+    }
+
+    #[test]
+    fn template_and_inline_are_equivalent() {
+        let inline = fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../templates/bitmask/slice-32x32.toml"
+        ))
+        .unwrap();
+        let stdout = diff_configs("template = \"bitmask/slice-32x32\"\n", &inline);
+        assert!(stdout.contains("Configs are equivalent"));
+    }
+}
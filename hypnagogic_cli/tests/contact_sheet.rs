@@ -0,0 +1,89 @@
+#[macro_use]
+mod util;
+
+mod contact_sheet {
+    use std::fs::File;
+
+    use dmi::icon::{Icon, IconState};
+    use hypnagogic_core::util::contact_sheet::ContactSheet;
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &std::path::Path, states: usize) -> Icon {
+        let icon = Icon {
+            width: 32,
+            height: 32,
+            states: (0..states)
+                .map(|index| {
+                    IconState {
+                        name: format!("state{index}"),
+                        images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                            32,
+                            32,
+                            Rgba([255, 0, 0, 255]),
+                        ))],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+        icon
+    }
+
+    #[test]
+    fn sheet_has_a_cell_per_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("things.dmi");
+        let icon = write_icon(&input, 3);
+        let output = dir.path().join("sheet.png");
+
+        let result = run_with_args(vec![
+            "contact-sheet".to_string(),
+            input.to_str().unwrap().to_string(),
+            "-o".to_string(),
+            output.to_str().unwrap().to_string(),
+            "--columns".to_string(),
+            "2".to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(result.status.success());
+
+        // Two cells in each row, so two rows
+        let sheet = image::open(&output).unwrap();
+        let (cell_width, cell_height) = ContactSheet::cell_size(&icon);
+        assert_eq!(sheet.width(), 2 * (cell_width + 2) + 2);
+        assert_eq!(sheet.height(), 2 * (cell_height + 2) + 2);
+    }
+
+    #[test]
+    fn big_dmis_are_paginated() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("things.dmi");
+        write_icon(&input, 5);
+        let output = dir.path().join("sheet.png");
+
+        let result = run_with_args(vec![
+            "contact-sheet".to_string(),
+            input.to_str().unwrap().to_string(),
+            "-o".to_string(),
+            output.to_str().unwrap().to_string(),
+            "--per-page".to_string(),
+            "2".to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(result.status.success());
+
+        assert!(output.exists());
+        assert!(dir.path().join("sheet-2.png").exists());
+        assert!(dir.path().join("sheet-3.png").exists());
+        assert!(!dir.path().join("sheet-4.png").exists());
+    }
+}
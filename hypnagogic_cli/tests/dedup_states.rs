@@ -0,0 +1,88 @@
+#[macro_use]
+mod util;
+
+mod dedup_states {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &Path) {
+        let state = |name: &str, red: u8| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([red, 0, 0, 255]),
+                ))],
+                ..Default::default()
+            }
+        };
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![
+                state("crate", 255),
+                state("box", 200),
+                state("crate_old", 255),
+            ],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+    }
+
+    fn dedup(dir: &Path, extra: &[&str]) -> std::process::Output {
+        let mut args = vec![
+            "dedup-states".to_string(),
+            dir.join("crates.dmi").to_str().unwrap().to_string(),
+        ];
+        args.extend(extra.iter().map(ToString::to_string));
+        run_with_args(args).unwrap().output().unwrap()
+    }
+
+    fn state_names(path: &Path) -> Vec<String> {
+        Icon::load(File::open(path).unwrap())
+            .unwrap()
+            .states
+            .into_iter()
+            .map(|state| state.name)
+            .collect()
+    }
+
+    #[test]
+    fn reports_without_changing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crates.dmi");
+        write_icon(&path);
+
+        let output = dedup(dir.path(), &[]);
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("is a copy of"), "{stdout}");
+        assert_eq!(state_names(&path), vec!["crate", "box", "crate_old"]);
+    }
+
+    #[test]
+    fn fix_removes_copies_and_records_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crates.dmi");
+        write_icon(&path);
+        let aliases = dir.path().join("aliases.toml");
+
+        let output = dedup(
+            dir.path(),
+            &["--fix", "--aliases", aliases.to_str().unwrap()],
+        );
+        assert!(output.status.success());
+        assert_eq!(state_names(&path), vec!["crate", "box"]);
+        assert_eq!(
+            fs::read_to_string(aliases).unwrap(),
+            "\"crate_old\" = \"crate\"\n"
+        );
+    }
+}
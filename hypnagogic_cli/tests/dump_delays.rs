@@ -0,0 +1,96 @@
+#[macro_use]
+mod util;
+
+mod dump_delays {
+    use std::fs::File;
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &Path) {
+        let frames = |count: usize| vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4)); count];
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![
+                IconState {
+                    name: "spin".to_string(),
+                    dirs: 4,
+                    frames: 3,
+                    images: frames(12),
+                    delay: Some(vec![1.0, 2.0, 0.5]),
+                    ..Default::default()
+                },
+                IconState {
+                    name: "idle".to_string(),
+                    images: frames(1),
+                    ..Default::default()
+                },
+                IconState {
+                    name: "blink, slow".to_string(),
+                    frames: 2,
+                    images: frames(2),
+                    delay: Some(vec![10.0, 4.0]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+    }
+
+    fn dump(path: &Path, format: &str) -> String {
+        let output = run_with_args(vec![
+            "dump-delays".to_string(),
+            path.to_str().unwrap().to_string(),
+            "--format".to_string(),
+            format.to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn csv_matches_source_delays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anims.dmi");
+        write_icon(&path);
+
+        let csv = dump(&path, "csv");
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                "state,dir,delays,total",
+                "spin,south,\"[1, 2, 0.5]\",3.5",
+                "spin,north,\"[1, 2, 0.5]\",3.5",
+                "spin,east,\"[1, 2, 0.5]\",3.5",
+                "spin,west,\"[1, 2, 0.5]\",3.5",
+                "idle,south,[],0",
+                "\"blink, slow\",south,\"[10, 4]\",14",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_matches_source_delays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anims.dmi");
+        write_icon(&path);
+
+        let json = dump(&path, "json");
+        assert!(json.starts_with(
+            "[{\"state\":\"spin\",\"dir\":\"south\",\"delays\":[1,2,0.5],\"total\":3.5},"
+        ));
+        assert!(json.contains("{\"state\":\"idle\",\"dir\":\"south\",\"delays\":[],\"total\":0}"));
+        assert!(json.trim_end().ends_with(
+            "{\"state\":\"blink, slow\",\"dir\":\"south\",\"delays\":[10,4],\"total\":14}]"
+        ));
+    }
+}
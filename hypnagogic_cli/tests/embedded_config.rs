@@ -0,0 +1,183 @@
+#[macro_use]
+mod util;
+
+mod embedded_config {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn write_icon(path: &Path) {
+        let mut mask = RgbaImage::new(4, 4);
+        mask.put_pixel(0, 0, BLUE);
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![
+                IconState {
+                    name: "wall".to_string(),
+                    images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, RED))],
+                    ..Default::default()
+                },
+                IconState {
+                    name: "mask".to_string(),
+                    images: vec![DynamicImage::ImageRgba8(mask)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+    }
+
+    fn run(args: &[&str]) -> (bool, String) {
+        let output = run_with_args(args.iter().map(|arg| arg.to_string()).collect())
+            .unwrap()
+            .output()
+            .unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.success(), printed)
+    }
+
+    #[test]
+    fn processes_from_the_dmi_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon_path = dir.path().join("wall.dmi");
+        write_icon(&icon_path);
+        let config_path = dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "mode = \"ApplyLayer\"\nlayer_state = \"mask\"\nposition = \"over\"\nremove_layer = \
+             true\n",
+        )
+        .unwrap();
+
+        let (success, printed) = run(&[
+            "embed-config",
+            icon_path.to_str().unwrap(),
+            config_path.to_str().unwrap(),
+        ]);
+        assert!(success, "{printed}");
+        // Nothing but the dmi is needed from here on
+        fs::remove_file(&config_path).unwrap();
+
+        let out_dir = dir.path().join("out");
+        let (success, printed) = run(&[
+            "process-embedded",
+            icon_path.to_str().unwrap(),
+            "--output",
+            out_dir.to_str().unwrap(),
+        ]);
+        assert!(success, "{printed}");
+
+        let output = Icon::load(File::open(out_dir.join("wall.dmi")).unwrap()).unwrap();
+        assert_eq!(output.states.len(), 1);
+        let wall = &output.states[0].images[0];
+        assert_eq!(wall.get_pixel(0, 0), BLUE);
+        assert_eq!(wall.get_pixel(1, 1), RED);
+    }
+
+    #[test]
+    fn malformed_embedded_config_is_a_config_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon_path = dir.path().join("wall.dmi");
+        write_icon(&icon_path);
+        let config_path = dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "mode = \"ApplyLayer\"\nposition = \"sideways\"\n",
+        )
+        .unwrap();
+        run(&[
+            "embed-config",
+            icon_path.to_str().unwrap(),
+            config_path.to_str().unwrap(),
+        ]);
+
+        let (success, printed) = run(&[
+            "process-embedded",
+            icon_path.to_str().unwrap(),
+            "--output",
+            dir.path().join("out").to_str().unwrap(),
+        ]);
+        assert!(!success);
+        assert!(printed.contains("Invalid Config File"), "{printed}");
+    }
+
+    #[test]
+    fn warnings_follow_strict_and_allow_warning() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon_path = dir.path().join("mask.dmi");
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "mask".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([255, 255, 255, 128]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(&icon_path).unwrap()).unwrap();
+        let config_path = dir.path().join("config.toml");
+        fs::write(&config_path, "mode = \"ValidateMask\"\n").unwrap();
+        run(&[
+            "embed-config",
+            icon_path.to_str().unwrap(),
+            config_path.to_str().unwrap(),
+        ]);
+        let out_dir = dir.path().join("out");
+        let process = |flags: &[&str]| {
+            let args = [
+                flags,
+                &[
+                    "process-embedded",
+                    icon_path.to_str().unwrap(),
+                    "--output",
+                    out_dir.to_str().unwrap(),
+                ],
+            ]
+            .concat();
+            run(&args)
+        };
+
+        let (success, printed) = process(&[]);
+        assert!(success, "{printed}");
+        assert!(printed.contains("Warning:"), "{printed}");
+
+        let (success, printed) = process(&["--strict"]);
+        assert!(!success, "{printed}");
+
+        let (success, printed) = process(&["--strict", "--allow-warning", "soft_mask"]);
+        assert!(success, "{printed}");
+        assert!(!printed.contains("Warning:"), "{printed}");
+    }
+
+    #[test]
+    fn missing_embedded_config_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon_path = dir.path().join("wall.dmi");
+        write_icon(&icon_path);
+
+        let (success, printed) = run(&[
+            "process-embedded",
+            icon_path.to_str().unwrap(),
+            "--output",
+            dir.path().join("out").to_str().unwrap(),
+        ]);
+        assert!(!success);
+        assert!(printed.contains("No Embedded Config"), "{printed}");
+    }
+}
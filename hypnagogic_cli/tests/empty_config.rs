@@ -0,0 +1,78 @@
+#[macro_use]
+mod util;
+
+mod empty_config {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), "operations = []\n").unwrap();
+    }
+
+    /// Runs over the dmi, returning everything printed
+    fn run(dir: &Path, extra_args: &[&str]) -> String {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--flatten".to_string());
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.join("wall.dmi.toml").to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap()
+    }
+
+    #[test]
+    fn empty_config_fails_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let stdout = run(dir.path(), &[]);
+        assert!(stdout.contains("--allow-empty-configs"), "{stdout}");
+        assert!(stdout.contains("Found 1 invalid configs"), "{stdout}");
+        assert!(!dir.path().join("out").join("wall.dmi").exists());
+    }
+
+    #[test]
+    fn allowed_empty_config_copies_input_through() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let stdout = run(dir.path(), &["--allow-empty-configs"]);
+        assert!(stdout.contains("Empty Config"), "{stdout}");
+        assert!(!stdout.contains("Failed to process"), "{stdout}");
+
+        let output =
+            Icon::load(File::open(dir.path().join("out").join("wall.dmi")).unwrap()).unwrap();
+        let input = Icon::load(File::open(dir.path().join("wall.dmi")).unwrap()).unwrap();
+        assert_eq!(output.states.len(), 1);
+        assert_eq!(output.states[0].name, "wall");
+        assert_eq!(output.states[0].images, input.states[0].images);
+    }
+
+    #[test]
+    fn strict_fails_allowed_empty_config() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let stdout = run(dir.path(), &["--allow-empty-configs", "--strict"]);
+        assert!(stdout.contains("Empty Config"), "{stdout}");
+        assert!(stdout.contains("Failed to process 1 files!"), "{stdout}");
+    }
+}
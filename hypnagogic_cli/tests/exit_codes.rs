@@ -0,0 +1,128 @@
+#[macro_use]
+mod util;
+
+mod exit_codes {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes `name`.dmi into `dir`, with `config` next to it
+    fn write_icon(dir: &Path, name: &str, config: &str) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join(format!("{name}.dmi"))).unwrap())
+            .unwrap();
+        fs::write(dir.join(format!("{name}.dmi.toml")), config).unwrap();
+    }
+
+    /// Runs over everything in `dir`, returning the exit code
+    fn run(dir: &Path, extra_args: &[&str]) -> i32 {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--flatten".to_string());
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        output.status.code().unwrap()
+    }
+
+    #[test]
+    fn success() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "wall", "mode = \"Passthrough\"\n");
+        assert_eq!(run(dir.path(), &[]), 0);
+    }
+
+    #[test]
+    fn bad_config() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "wall", "mode = 3\n");
+        assert_eq!(run(dir.path(), &[]), 2);
+    }
+
+    #[test]
+    fn missing_input() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("wall.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+        assert_eq!(run(dir.path(), &[]), 3);
+
+        let output = run_with_args(vec![dir
+            .path()
+            .join("nowhere")
+            .to_str()
+            .unwrap()
+            .to_string()])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+    }
+
+    #[test]
+    fn failed_processing() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "wall", "mode = \"Passthrough\"\n");
+        assert_eq!(
+            run(dir.path(), &["--strict", "--warn-output-size", "10"]),
+            4
+        );
+    }
+
+    #[test]
+    fn failed_output() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "wall", "mode = \"Passthrough\"\n");
+        assert_eq!(run(dir.path(), &[]), 0);
+        fs::write(dir.path().join("out").join("wall.dmi"), "hand edited").unwrap();
+        assert_eq!(run(dir.path(), &["--no-clobber"]), 5);
+    }
+
+    #[test]
+    fn highest_code_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "wall", "mode = 3\n");
+        fs::write(dir.path().join("door.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+        write_icon(dir.path(), "floor", "mode = \"Passthrough\"\n");
+        assert_eq!(run(dir.path(), &["--continue-on-error"]), 3);
+    }
+
+    #[test]
+    fn subcommands_use_the_same_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "wall", "mode = 3\n");
+
+        let output = run_with_args(vec![
+            "extract".to_string(),
+            dir.path().join("wall.dmi").to_str().unwrap().to_string(),
+            "window".to_string(),
+            "--output".to_string(),
+            dir.path().join("window.dmi").to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+
+        let output = run_with_args(vec![
+            "validate-all".to_string(),
+            dir.path().to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+    }
+}
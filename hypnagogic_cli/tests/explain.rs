@@ -0,0 +1,67 @@
+#[macro_use]
+mod util;
+
+mod explain {
+    use std::fs;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn lists_operations_in_the_order_they_run() {
+        let dir = tempfile::tempdir().unwrap();
+        // Never read, explaining doesn't look at inputs
+        fs::write(dir.path().join("wall.dmi"), "").unwrap();
+        fs::write(
+            dir.path().join("wall.dmi.toml"),
+            r#"
+            order = [2, 1]
+
+            [[operations]]
+            mode = "Gamma"
+            gamma = 2.0
+
+            [[operations]]
+            mode = "Passthrough"
+            "#,
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "--explain".to_string(),
+            "--output".to_string(),
+            dir.path().join("out").to_str().unwrap().to_string(),
+            dir.path().to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(output.status.success(), "{stdout}");
+
+        let passthrough = stdout.find("  2. Passthrough\n").expect(&stdout);
+        let gamma = stdout.find("  1. Gamma (gamma = 2.0)\n").expect(&stdout);
+        assert!(passthrough < gamma, "{stdout}");
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn broken_configs_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("wall.dmi.toml"), "mode = \"NotAMode\"\n").unwrap();
+
+        let output = run_with_args(vec![
+            "--explain".to_string(),
+            dir.path()
+                .join("wall.dmi.toml")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(!output.status.success());
+    }
+}
@@ -0,0 +1,81 @@
+#[macro_use]
+mod util;
+
+mod extract {
+    use std::fs::File;
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &Path) -> Icon {
+        let frame = |value: u8| {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([value, 0, 0, 255])))
+        };
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![
+                IconState {
+                    name: "still".to_string(),
+                    images: vec![frame(1)],
+                    ..Default::default()
+                },
+                IconState {
+                    name: "walking".to_string(),
+                    dirs: 4,
+                    frames: 2,
+                    images: (10..18).map(frame).collect(),
+                    delay: Some(vec![1.0, 3.0]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+        icon
+    }
+
+    fn extract(dir: &Path, state: &str) -> std::process::Output {
+        run_with_args(vec![
+            "extract".to_string(),
+            dir.join("mob.dmi").to_str().unwrap().to_string(),
+            state.to_string(),
+            "-o".to_string(),
+            dir.join("out.dmi").to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap()
+    }
+
+    #[test]
+    fn extracts_animated_directional_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon = write_icon(&dir.path().join("mob.dmi"));
+
+        let output = extract(dir.path(), "walking");
+        assert!(output.status.success());
+
+        let extracted = Icon::load(File::open(dir.path().join("out.dmi")).unwrap()).unwrap();
+        assert_eq!((extracted.width, extracted.height), (4, 4));
+        assert_eq!(extracted.states, vec![icon.states[1].clone()]);
+    }
+
+    #[test]
+    fn missing_state_suggests_close_names() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(&dir.path().join("mob.dmi"));
+
+        let output = extract(dir.path(), "walkin");
+        assert!(!output.status.success());
+        assert!(!dir.path().join("out.dmi").exists());
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("still, walking"), "{stderr}");
+        assert!(stderr.contains("Did you mean \"walking\"?"), "{stderr}");
+    }
+}
@@ -0,0 +1,76 @@
+#[macro_use]
+mod util;
+
+mod frame_limits {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a dmi with a single 5 frame animation, set up to pass through
+    /// untouched
+    fn write_animation(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "anim".to_string(),
+                frames: 5,
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4)); 5],
+                delay: Some(vec![1.0; 5]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("anim.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("anim.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+    }
+
+    /// Runs over the dmi, returning the exit code and everything printed.
+    /// Errors go to stderr
+    fn run(dir: &Path, limit_args: &[&str]) -> (i32, String) {
+        let mut args: Vec<String> = limit_args.iter().map(ToString::to_string).collect();
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.join("anim.dmi.toml").to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn warns_at_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        write_animation(dir.path());
+
+        let (code, stdout) = run(dir.path(), &["--warn-frame-count", "5"]);
+        assert_eq!(code, 0, "{stdout}");
+        assert!(!stdout.contains("Long Animation"), "{stdout}");
+
+        let (code, stdout) = run(dir.path(), &["--warn-frame-count", "4"]);
+        assert_eq!(code, 0, "{stdout}");
+        assert!(stdout.contains("Long Animation"), "{stdout}");
+        assert!(
+            stdout.contains("Successfully processed 1 files!"),
+            "{stdout}"
+        );
+    }
+
+    #[test]
+    fn errors_past_max() {
+        let dir = tempfile::tempdir().unwrap();
+        write_animation(dir.path());
+
+        let (code, stdout) = run(dir.path(), &["--max-frame-count", "4"]);
+        // Processing failed
+        assert_eq!(code, 4, "{stdout}");
+        assert!(stdout.contains("more than the limit of 4"), "{stdout}");
+        assert!(stdout.contains("Failed to process 1 files!"), "{stdout}");
+    }
+}
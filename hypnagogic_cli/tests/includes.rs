@@ -0,0 +1,79 @@
+#[macro_use]
+mod util;
+
+mod includes {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Output;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn print_config(config_path: &Path) -> Output {
+        run_with_args(vec![
+            "print-config".to_string(),
+            "--format".to_string(),
+            "toml".to_string(),
+            config_path.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap()
+    }
+
+    #[test]
+    fn includes_are_pasted_in() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        fs::write(
+            dir.path().join("shared/hue.toml"),
+            "hue = 90.0\n!include value.toml\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("shared/value.toml"), "value = 0.5\n").unwrap();
+        let config_path = dir.path().join("light.dmi.toml");
+        fs::write(
+            &config_path,
+            "mode = \"HsvShift\"\n!include shared/hue.toml\n",
+        )
+        .unwrap();
+
+        let output = print_config(&config_path);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(output.status.success(), "{stdout}");
+        assert!(stdout.contains("mode = \"HsvShift\""), "{stdout}");
+        assert!(stdout.contains("hue = 90.0"), "{stdout}");
+        // Nested includes are relative to the file that includes them
+        assert!(stdout.contains("value = 0.5"), "{stdout}");
+    }
+
+    #[test]
+    fn missing_include_names_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("light.dmi.toml");
+        fs::write(&config_path, "mode = \"HsvShift\"\n!include nowhere.toml\n").unwrap();
+
+        let output = print_config(&config_path);
+        assert!(!output.status.success());
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert!(printed.contains("nowhere.toml"), "{printed}");
+        assert!(printed.contains("light.dmi.toml"), "{printed}");
+    }
+
+    #[test]
+    fn include_cycles_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.toml"), "!include b.toml\n").unwrap();
+        fs::write(dir.path().join("b.toml"), "!include a.toml\n").unwrap();
+        let config_path = dir.path().join("light.dmi.toml");
+        fs::write(&config_path, "mode = \"HsvShift\"\n!include a.toml\n").unwrap();
+
+        let output = print_config(&config_path);
+        assert!(!output.status.success());
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert!(printed.contains("including itself"), "{printed}");
+    }
+}
@@ -0,0 +1,117 @@
+#[macro_use]
+mod util;
+
+mod lockfile {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a 4x4 dmi of `color` named `name`, with a passthrough config
+    fn write_icon(dir: &Path, name: &str, color: [u8; 4]) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "crate".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba(color),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join(format!("{name}.dmi"))).unwrap())
+            .unwrap();
+        fs::write(
+            dir.join(format!("{name}.dmi.toml")),
+            "mode = \"Passthrough\"\n",
+        )
+        .unwrap();
+    }
+
+    /// Runs over `dir` with `flag`, returning the exit code and everything
+    /// printed
+    fn run(dir: &Path, flag: &str) -> (i32, String) {
+        let output = run_with_args(vec![
+            flag.to_string(),
+            "--flatten".to_string(),
+            "--output".to_string(),
+            dir.join("out").to_str().unwrap().to_string(),
+            dir.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    fn entry<'a>(lock: &'a str, output: &str) -> &'a str {
+        lock.lines()
+            .find(|line| line.contains(&format!("{{\"output\":\"out/{output}\",")))
+            .unwrap_or_else(|| panic!("No entry for {output} in {lock}"))
+    }
+
+    #[test]
+    fn changed_inputs_change_their_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "red", [255, 0, 0, 255]);
+        write_icon(dir.path(), "blue", [0, 0, 255, 255]);
+        let lock_path = dir.path().join("hypnagogic.lock");
+
+        let (code, printed) = run(dir.path(), "--lock");
+        assert_eq!(code, 0, "{printed}");
+        let lock = fs::read_to_string(&lock_path).unwrap();
+        assert!(
+            lock.contains(&format!("\"version\": \"{}\"", env!("CARGO_PKG_VERSION"))),
+            "{lock}"
+        );
+        assert!(
+            entry(&lock, "red.dmi")
+                .contains("\"config\":\"red.dmi.toml\",\"input\":\"red.dmi\",\"config_hash\":"),
+            "{lock}"
+        );
+
+        // Nothing changed, so the lockfile holds
+        let (code, printed) = run(dir.path(), "--frozen");
+        assert_eq!(code, 0, "{printed}");
+
+        write_icon(dir.path(), "red", [200, 0, 0, 255]);
+        let output = fs::read(dir.path().join("out").join("red.dmi")).unwrap();
+        let (code, printed) = run(dir.path(), "--frozen");
+        assert_eq!(code, 4, "{printed}");
+        assert!(
+            printed.contains("Output Differs From Lockfile"),
+            "{printed}"
+        );
+        assert_eq!(fs::read_to_string(&lock_path).unwrap(), lock);
+        assert_eq!(
+            fs::read(dir.path().join("out").join("red.dmi")).unwrap(),
+            output
+        );
+
+        let (code, printed) = run(dir.path(), "--lock");
+        assert_eq!(code, 0, "{printed}");
+        let relocked = fs::read_to_string(&lock_path).unwrap();
+        assert_ne!(entry(&relocked, "red.dmi"), entry(&lock, "red.dmi"));
+        assert_eq!(entry(&relocked, "blue.dmi"), entry(&lock, "blue.dmi"));
+    }
+
+    #[test]
+    fn frozen_needs_a_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "red", [255, 0, 0, 255]);
+
+        let (code, printed) = run(dir.path(), "--frozen");
+        assert_ne!(code, 0, "{printed}");
+        assert!(!dir.path().join("out").exists());
+    }
+}
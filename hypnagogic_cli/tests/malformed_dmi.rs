@@ -0,0 +1,40 @@
+#[macro_use]
+mod util;
+
+mod malformed_dmi {
+    use std::fs;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn garbage_dmi_gets_a_friendly_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("wall.dmi"), "not a dmi at all").unwrap();
+        fs::write(dir.path().join("wall.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+
+        let output = run_with_args(vec![
+            "--flatten".to_string(),
+            "--output".to_string(),
+            dir.path().join("out").to_str().unwrap().to_string(),
+            dir.path()
+                .join("wall.dmi.toml")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert!(printed.contains("Image Parsing Failed"), "{printed}");
+        assert!(
+            printed.contains("The file isn't a png, or its png data is cut short or corrupt"),
+            "{printed}"
+        );
+        assert!(printed.contains("not a real dmi"), "{printed}");
+        assert!(printed.contains("Failed to process 1 files!"), "{printed}");
+    }
+}
@@ -0,0 +1,82 @@
+#[macro_use]
+mod util;
+
+mod max_frames {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a dmi with a 12 frame animation, and a config holding it to 10
+    fn write_configs(dir: &Path, on_overflow: &str) {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "spin".to_string(),
+                frames: 12,
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(1, 1)); 12],
+                delay: Some(vec![1.0; 12]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("fan.dmi")).unwrap())
+            .unwrap();
+        fs::write(
+            dir.join("fan.dmi.toml"),
+            format!("mode = \"MaxFrames\"\nmax_frames = 10\non_overflow = \"{on_overflow}\"\n"),
+        )
+        .unwrap();
+    }
+
+    /// Runs over everything in `dir`, returning the exit code and everything
+    /// printed
+    fn run(dir: &Path, extra_args: &[&str]) -> (i32, String) {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--flatten".to_string());
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn over_budget_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        write_configs(dir.path(), "error");
+
+        let (code, printed) = run(dir.path(), &[]);
+        assert_eq!(code, 4, "{printed}");
+        assert!(
+            printed.contains("Icon state \"spin\" has 12 frames, over 10"),
+            "{printed}"
+        );
+        assert!(!dir.path().join("out").join("fan.dmi").exists());
+    }
+
+    #[test]
+    fn warn_only_fails_under_strict() {
+        let dir = tempfile::tempdir().unwrap();
+        write_configs(dir.path(), "warn");
+
+        let (code, printed) = run(dir.path(), &[]);
+        assert_eq!(code, 0, "{printed}");
+        assert!(printed.contains("Long Animation"), "{printed}");
+        assert!(dir.path().join("out").join("fan.dmi").exists());
+
+        let (code, printed) = run(dir.path(), &["--strict"]);
+        assert_ne!(code, 0, "{printed}");
+        assert!(
+            printed.contains("Warnings are errors under --strict"),
+            "{printed}"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+#[macro_use]
+mod util;
+
+mod metadata {
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+
+    use dmi::icon::Icon;
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Sets up a bitmask cut in `root`, runs it with `extra_args`, and returns
+    /// the output folder
+    fn cut(root: &Path, extra_args: &[&str]) -> PathBuf {
+        let repo = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let input = root.join("input");
+        fs::create_dir(&input).unwrap();
+        fs::copy(
+            repo.join("tests/test_files/simple_cuts/input/4-corners.png"),
+            input.join("wall.png"),
+        )
+        .unwrap();
+        fs::write(
+            input.join("wall.png.toml"),
+            "template = \"bitmask/slice-32x32\"\n",
+        )
+        .unwrap();
+
+        let out = root.join("out");
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--flatten".to_string());
+        args.push("--output".to_string());
+        args.push(out.to_str().unwrap().to_string());
+        args.push(input.to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+        out
+    }
+
+    #[test]
+    fn bitmask_states_are_described_next_to_the_dmi() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = cut(dir.path(), &["--metadata"]);
+
+        let icon = Icon::load(File::open(out.join("wall.dmi")).unwrap()).unwrap();
+        let sidecar = fs::read_to_string(out.join("wall.dmi.json")).unwrap();
+        for state in &icon.states {
+            assert!(
+                sidecar.contains(&format!("{{\"state\":\"{}\",", state.name)),
+                "{sidecar}"
+            );
+        }
+        assert_eq!(sidecar.matches("\"state\"").count(), icon.states.len());
+        assert!(
+            sidecar.contains("{\"state\":\"0\",\"adjacency\":0,\"connected\":[]}"),
+            "{sidecar}"
+        );
+        assert!(
+            sidecar.contains(
+                "{\"state\":\"15\",\"adjacency\":15,\"connected\":[\"north\",\"south\",\"east\",\"\
+                 west\"]}"
+            ),
+            "{sidecar}"
+        );
+    }
+
+    #[test]
+    fn nothing_is_written_without_the_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = cut(dir.path(), &[]);
+
+        assert!(out.join("wall.dmi").exists());
+        assert!(!out.join("wall.dmi.json").exists());
+    }
+}
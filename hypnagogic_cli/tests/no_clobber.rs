@@ -0,0 +1,82 @@
+#[macro_use]
+mod util;
+
+mod no_clobber {
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), "mode = \"Emissive\"\n").unwrap();
+    }
+
+    /// Runs over the dmi, returning everything printed
+    fn run(dir: &Path, extra_args: &[&str]) -> String {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--flatten".to_string());
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.join("wall.dmi.toml").to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap()
+    }
+
+    fn output_path(dir: &Path) -> PathBuf {
+        dir.join("out").join("wall.dmi")
+    }
+
+    #[test]
+    fn identical_output_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+        run(dir.path(), &[]);
+
+        let stdout = run(dir.path(), &["--no-clobber"]);
+        assert!(!stdout.contains("Failed to process"), "{stdout}");
+    }
+
+    #[test]
+    fn changed_output_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+        run(dir.path(), &[]);
+        let output = output_path(dir.path());
+        fs::write(&output, "hand edited").unwrap();
+
+        let stdout = run(dir.path(), &["--no-clobber"]);
+        assert!(stdout.contains("Output Already Exists"), "{stdout}");
+        assert!(stdout.contains("Failed to process 1 files!"), "{stdout}");
+        assert_eq!(fs::read_to_string(&output).unwrap(), "hand edited");
+    }
+
+    #[test]
+    fn force_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+        run(dir.path(), &[]);
+        let output = output_path(dir.path());
+        let original = fs::read(&output).unwrap();
+        fs::write(&output, "hand edited").unwrap();
+
+        let stdout = run(dir.path(), &["--no-clobber", "--force"]);
+        assert!(!stdout.contains("Failed to process"), "{stdout}");
+        assert_eq!(fs::read(&output).unwrap(), original);
+    }
+}
@@ -0,0 +1,66 @@
+#[macro_use]
+mod util;
+
+mod onion_skin {
+    use std::fs::File;
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &Path, states: &[&str]) {
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states: states
+                .iter()
+                .map(|name| {
+                    IconState {
+                        name: (*name).to_string(),
+                        images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                            2,
+                            2,
+                            Rgba([255, 255, 255, 255]),
+                        ))],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn skins_shared_states_and_reports_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(&dir.path().join("old.dmi"), &["lamp", "removed"]);
+        write_icon(&dir.path().join("new.dmi"), &["lamp", "added"]);
+
+        let output = run_with_args(vec![
+            "onion-skin".to_string(),
+            dir.path().join("old.dmi").to_str().unwrap().to_string(),
+            dir.path().join("new.dmi").to_str().unwrap().to_string(),
+            "-o".to_string(),
+            dir.path().join("skin.dmi").to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("\"removed\" is only in"), "{stdout}");
+        assert!(stdout.contains("\"added\" is only in"), "{stdout}");
+
+        let skinned = Icon::load(File::open(dir.path().join("skin.dmi")).unwrap()).unwrap();
+        assert_eq!(skinned.states.len(), 1);
+        assert_eq!(skinned.states[0].name, "lamp");
+        assert_eq!(
+            skinned.states[0].images[0].get_pixel(0, 0),
+            Rgba([255, 255, 255, 255])
+        );
+    }
+}
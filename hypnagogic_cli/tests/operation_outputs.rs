@@ -0,0 +1,75 @@
+#[macro_use]
+mod util;
+
+mod operation_outputs {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[operations]]
+mode = "SwapChannels"
+preset = "rgb_to_bgr"
+output = "bgr/wall.dmi"
+
+[[operations]]
+mode = "CapAlpha"
+max_alpha = 100
+"#;
+
+    /// The color of the first pixel of the dmi at `path`
+    fn first_pixel(path: &Path) -> Rgba<u8> {
+        let icon = Icon::load(File::open(path).unwrap()).unwrap();
+        *icon.states[0].images[0].to_rgba8().get_pixel(0, 0)
+    }
+
+    #[test]
+    fn each_output_gets_its_own_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([200, 0, 50, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.path().join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.path().join("wall.dmi.toml"), CONFIG).unwrap();
+        let out = dir.path().join("out");
+
+        let output = run_with_args(vec![
+            "--flatten".to_string(),
+            "--output".to_string(),
+            out.to_str().unwrap().to_string(),
+            dir.path()
+                .join("wall.dmi.toml")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(output.status.code(), Some(0), "{stdout}");
+
+        assert_eq!(first_pixel(&out.join("wall.dmi")), Rgba([200, 0, 50, 100]));
+        assert_eq!(
+            first_pixel(&out.join("bgr").join("wall.dmi")),
+            Rgba([50, 0, 200, 255])
+        );
+    }
+}
@@ -0,0 +1,47 @@
+#[macro_use]
+mod util;
+
+mod output_format {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn gif_output_format_writes_a_gif() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+        let input = root.join("input");
+        fs::create_dir(&input).unwrap();
+        fs::copy(
+            repo.join("tests/test_files/basic_cut/input/tall-5-corners-debug.png"),
+            input.join("wall.png"),
+        )
+        .unwrap();
+        fs::write(
+            input.join("wall.png.toml"),
+            "template = \"bitmask/slice-tallwalls-directionalvis\"\noutput_format = \"Gif\"\n",
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "--flatten".to_string(),
+            "--output".to_string(),
+            root.join("out").to_str().unwrap().to_string(),
+            input.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+
+        let gif = root.join("out/wall.gif");
+        assert!(gif.exists(), "{}", String::from_utf8_lossy(&output.stdout));
+        assert!(!root.join("out/wall.dmi").exists());
+        image::open(gif).unwrap();
+    }
+}
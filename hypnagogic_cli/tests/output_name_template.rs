@@ -0,0 +1,103 @@
+#[macro_use]
+mod util;
+
+mod output_name_template {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes `wall.dmi` and a config for it named `wall.dmi.toml`
+    fn write_icon(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+    }
+
+    /// Runs with `template`, returning the names of the files written
+    fn output_names(template: &str) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+        let out = dir.path().join("out");
+        let output = run_with_args(vec![
+            "--flatten".to_string(),
+            "--output-name-template".to_string(),
+            template.to_string(),
+            "--output".to_string(),
+            out.to_str().unwrap().to_string(),
+            dir.path()
+                .join("wall.dmi.toml")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+        fs::read_dir(out)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn expands_each_placeholder() {
+        assert_eq!(output_names("{config_name}_icons"), vec!["wall_icons.dmi"]);
+        assert_eq!(output_names("{input_stem}-v2.dmi"), vec!["wall-v2.dmi"]);
+        assert_eq!(
+            output_names("wall_{version}"),
+            vec![format!("wall_{}.dmi", env!("CARGO_PKG_VERSION"))]
+        );
+
+        let dated = output_names("{date}");
+        let date = dated[0].strip_suffix(".dmi").unwrap();
+        let parts: Vec<&str> = date.split('-').collect();
+        assert_eq!(
+            parts.iter().map(|part| part.len()).collect::<Vec<_>>(),
+            vec![4, 2, 2],
+            "{date}"
+        );
+        assert!(date
+            .chars()
+            .all(|char| char.is_ascii_digit() || char == '-'));
+    }
+
+    #[test]
+    fn unknown_placeholder_errors_before_processing() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+        let output = run_with_args(vec![
+            "--output-name-template".to_string(),
+            "{config_name}_{time}".to_string(),
+            "--output".to_string(),
+            dir.path().join("out").to_str().unwrap().to_string(),
+            dir.path()
+                .join("wall.dmi.toml")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("Unknown placeholder {time}"), "{stderr}");
+        assert!(!dir.path().join("out").exists());
+    }
+}
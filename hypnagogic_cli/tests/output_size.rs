@@ -0,0 +1,96 @@
+#[macro_use]
+mod util;
+
+mod output_size {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a small dmi, set up to pass through untouched
+    fn write_icon(dir: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+    }
+
+    /// Runs over the dmi, returning the exit code and everything printed
+    fn run(dir: &Path, extra_args: &[&str]) -> (i32, String) {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.join("wall.dmi.toml").to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn warns_past_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let (code, stdout) = run(dir.path(), &[]);
+        assert_eq!(code, 0, "{stdout}");
+        assert!(!stdout.contains("Large Output"), "{stdout}");
+
+        let (code, stdout) = run(dir.path(), &["--warn-output-size", "10"]);
+        assert_eq!(code, 0, "{stdout}");
+        assert!(stdout.contains("Large Output"), "{stdout}");
+        assert!(stdout.contains("with 1 icon states"), "{stdout}");
+        assert!(
+            stdout.contains("Successfully processed 1 files!"),
+            "{stdout}"
+        );
+    }
+
+    #[test]
+    fn strict_makes_it_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+
+        let (code, stdout) = run(dir.path(), &["--warn-output-size", "10", "--strict"]);
+        assert_eq!(code, 4, "{stdout}");
+        assert!(stdout.contains("Large Output"), "{stdout}");
+        assert!(
+            stdout.contains("Warnings are errors under --strict"),
+            "{stdout}"
+        );
+        assert!(stdout.contains("Failed to process 1 files!"), "{stdout}");
+    }
+
+    #[test]
+    fn max_output_bytes_stops_the_write() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path());
+        let written = dir.path().join("out").join("wall.dmi");
+
+        let (code, stdout) = run(dir.path(), &["--flatten", "--max-output-bytes", "10"]);
+        assert_eq!(code, 4, "{stdout}");
+        assert!(
+            stdout.contains("bytes with 1 icon states, more than the limit of 10 bytes"),
+            "{stdout}"
+        );
+        assert!(!written.exists());
+
+        let (code, stdout) = run(dir.path(), &["--flatten", "--max-output-bytes", "100000"]);
+        assert_eq!(code, 0, "{stdout}");
+        assert!(!stdout.contains("more than the limit"), "{stdout}");
+        assert!(written.exists());
+    }
+}
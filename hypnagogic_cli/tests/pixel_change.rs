@@ -0,0 +1,79 @@
+#[macro_use]
+mod util;
+
+mod pixel_change {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a 10x10 black crate with a single red pixel, and `config` for it
+    fn write_crate(dir: &Path, config: &str) {
+        let mut image = RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255]));
+        image.put_pixel(4, 4, Rgba([255, 0, 0, 255]));
+        let icon = Icon {
+            width: 10,
+            height: 10,
+            states: vec![IconState {
+                name: "crate".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("crate.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("crate.dmi.toml"), config).unwrap();
+    }
+
+    fn painted(color: &str) -> String {
+        format!("mode = \"Silhouette\"\ncolor = \"{color}\"\nreplace = true\n")
+    }
+
+    /// Runs over `dir` with a 5% limit, returning the exit code and
+    /// everything printed
+    fn run(dir: &Path) -> (i32, String) {
+        let output = run_with_args(vec![
+            "--max-pixel-change".to_string(),
+            "5".to_string(),
+            "--flatten".to_string(),
+            "--output".to_string(),
+            dir.join("out").to_str().unwrap().to_string(),
+            dir.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.code().unwrap(), printed)
+    }
+
+    #[test]
+    fn small_changes_pass_and_big_ones_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out").join("crate.dmi");
+
+        // With no earlier output there's nothing to compare against
+        write_crate(dir.path(), &painted("#000000"));
+        let (code, printed) = run(dir.path());
+        assert_eq!(code, 0, "{printed}");
+
+        // Leaving the red pixel unpainted only changes that one pixel
+        write_crate(dir.path(), "mode = \"Passthrough\"\n");
+        let (code, printed) = run(dir.path());
+        assert_eq!(code, 0, "{printed}");
+        let baseline = fs::read(&output).unwrap();
+
+        write_crate(dir.path(), &painted("#FFFFFF"));
+        let (code, printed) = run(dir.path());
+        assert_eq!(code, 4, "{printed}");
+        assert!(printed.contains("Too Much Changed"), "{printed}");
+        assert!(printed.contains("100 of 100 pixels"), "{printed}");
+        assert_eq!(fs::read(&output).unwrap(), baseline);
+    }
+}
@@ -0,0 +1,71 @@
+#[macro_use]
+mod util;
+
+mod preserve_mtime {
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn set_mtime(path: &Path, mtime: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    #[test]
+    fn output_takes_newest_input_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "thing".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(root.join("thing.dmi")).unwrap())
+            .unwrap();
+        fs::write(root.join("thing.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+
+        // The dmi was touched more recently than its config, so it wins
+        let config_mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let icon_mtime = config_mtime + Duration::from_secs(60 * 60);
+        set_mtime(&root.join("thing.dmi.toml"), config_mtime);
+        set_mtime(&root.join("thing.dmi"), icon_mtime);
+
+        let output = run_with_args(vec![
+            "--preserve-mtime".to_string(),
+            "--flatten".to_string(),
+            "--output".to_string(),
+            root.join("out").to_str().unwrap().to_string(),
+            root.join("thing.dmi.toml").to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+
+        let written = root.join("out/thing.dmi");
+        assert!(
+            written.exists(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        assert_eq!(
+            fs::metadata(written).unwrap().modified().unwrap(),
+            icon_mtime
+        );
+    }
+}
@@ -0,0 +1,85 @@
+#[macro_use]
+mod util;
+
+mod print_config {
+    use std::fs;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn prints_resolved_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wall.png.toml");
+        fs::write(
+            &config_path,
+            "template = \"bitmask/slice-32x32\"\nsmooth_diagonally = true\n",
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "print-config".to_string(),
+            config_path.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        // Pulled in from the template
+        assert!(stdout.contains("\"mode\": \"BitmaskSlice\""), "{stdout}");
+        // Overridden by the config itself
+        assert!(stdout.contains("\"smooth_diagonally\": true"), "{stdout}");
+        assert!(!stdout.contains("template"));
+        assert!(!stdout.contains("Hypnagogic CLI"));
+        assert!(
+            stdout.starts_with("{\n") && stdout.ends_with("}\n"),
+            "{stdout}"
+        );
+    }
+
+    #[test]
+    fn prints_toml_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wall.png.toml");
+        fs::write(
+            &config_path,
+            "template = \"bitmask/slice-32x32\"\nsmooth_diagonally = true\n",
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "print-config".to_string(),
+            "--format".to_string(),
+            "toml".to_string(),
+            config_path.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("mode = \"BitmaskSlice\""));
+        assert!(stdout.contains("smooth_diagonally = true"));
+        assert!(!stdout.contains("template"));
+    }
+
+    #[test]
+    fn missing_template_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wall.png.toml");
+        fs::write(&config_path, "template = \"does/not-exist\"\n").unwrap();
+
+        let output = run_with_args(vec![
+            "print-config".to_string(),
+            config_path.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(!output.status.success());
+    }
+}
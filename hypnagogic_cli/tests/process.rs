@@ -0,0 +1,106 @@
+#[macro_use]
+mod util;
+
+mod process {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([255, 0, 0, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+    }
+
+    fn process(dir: &Path, config: &str) -> std::process::Output {
+        process_with(dir, config, &[])
+    }
+
+    /// `process`, with run-wide `flags` given before the subcommand
+    fn process_with(dir: &Path, config: &str, flags: &[&str]) -> std::process::Output {
+        fs::write(dir.join("recolor.toml"), config).unwrap();
+        let mut args: Vec<String> = flags.iter().map(ToString::to_string).collect();
+        args.extend([
+            "process".to_string(),
+            "--config".to_string(),
+            dir.join("recolor.toml").to_str().unwrap().to_string(),
+            "--input".to_string(),
+            dir.join("bricks.dmi").to_str().unwrap().to_string(),
+            "--output".to_string(),
+            dir.join("out")
+                .join("result.dmi")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ]);
+        run_with_args(args).unwrap().output().unwrap()
+    }
+
+    #[test]
+    fn explicit_pair_is_processed() {
+        let dir = tempfile::tempdir().unwrap();
+        // Named nothing like the config, so it would never be found by search
+        write_icon(&dir.path().join("bricks.dmi"));
+
+        let output = process(dir.path(), "mode = \"HsvShift\"\nhue = 120.0\n");
+        assert!(output.status.success(), "{output:?}");
+
+        let icon =
+            Icon::load(File::open(dir.path().join("out").join("result.dmi")).unwrap()).unwrap();
+        assert_eq!(icon.states[0].name, "wall");
+        let pixel = icon.states[0].images[0].to_rgba8().get_pixel(0, 0).0;
+        assert_eq!(pixel, [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(&dir.path().join("bricks.dmi"));
+
+        let output = process(dir.path(), "mode = \"Blur\"\nradius = -1.0\n");
+        assert!(!output.status.success());
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert!(printed.contains("recolor.toml"), "{printed}");
+        assert!(printed.contains("Blur"), "{printed}");
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn run_wide_checks_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(&dir.path().join("bricks.dmi"));
+        let config = "mode = \"HsvShift\"\nhue = 120.0\n";
+
+        let output = process_with(dir.path(), config, &["--warn-state-name-length", "2"]);
+        assert!(output.status.success(), "{output:?}");
+        let printed = String::from_utf8(output.stdout).unwrap();
+        assert!(printed.contains("Warning:"), "{printed}");
+
+        // Written by the run above
+        fs::remove_file(dir.path().join("out").join("result.dmi")).unwrap();
+        let output = process_with(dir.path(), config, &["--max-output-bytes", "10"]);
+        assert!(!output.status.success(), "{output:?}");
+        assert!(!dir.path().join("out").join("result.dmi").exists());
+
+        let output = process_with(dir.path(), config, &["--max-frame-count", "0"]);
+        assert!(!output.status.success(), "{output:?}");
+    }
+}
@@ -0,0 +1,70 @@
+#[macro_use]
+mod util;
+
+mod profiles {
+    use std::fs;
+    use std::path::Path;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[operations]]
+mode = "Passthrough"
+
+[[profiles.release.operations]]
+mode = "Trim"
+
+[profiles.fast]
+output_format = "PngSheet"
+"#;
+
+    fn print_config(config: &Path, profile: Option<&str>) -> std::process::Output {
+        let mut args = vec![];
+        if let Some(profile) = profile {
+            args.push("--profile".to_string());
+            args.push(profile.to_string());
+        }
+        args.push("print-config".to_string());
+        args.push("--format".to_string());
+        args.push("toml".to_string());
+        args.push(config.to_str().unwrap().to_string());
+        run_with_args(args).unwrap().output().unwrap()
+    }
+
+    #[test]
+    fn profile_changes_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("wall.dmi.toml");
+        fs::write(&config, CONFIG).unwrap();
+
+        let output = print_config(&config, None);
+        assert!(output.status.success(), "{output:?}");
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("mode = \"Passthrough\""), "{stdout}");
+        assert!(!stdout.contains("profiles"), "{stdout}");
+
+        let output = print_config(&config, Some("release"));
+        assert!(output.status.success(), "{output:?}");
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("mode = \"Trim\""), "{stdout}");
+        assert!(!stdout.contains("Passthrough"), "{stdout}");
+    }
+
+    #[test]
+    fn unknown_profile_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("wall.dmi.toml");
+        fs::write(&config, CONFIG).unwrap();
+
+        let output = print_config(&config, Some("relase"));
+        assert!(!output.status.success());
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert!(
+            printed.contains("No profile named \"relase\", this config has fast, release"),
+            "{printed}"
+        );
+    }
+}
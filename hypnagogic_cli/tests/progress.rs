@@ -0,0 +1,120 @@
+#[macro_use]
+mod util;
+
+mod progress {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(dir: &Path, name: &str) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: ["on", "off"]
+                .into_iter()
+                .map(|state| {
+                    IconState {
+                        name: state.to_string(),
+                        images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join(format!("{name}.dmi"))).unwrap())
+            .unwrap();
+    }
+
+    /// The "event" field of an ndjson line
+    fn event(line: &str) -> &str {
+        let rest = line.strip_prefix("{\"event\":\"").unwrap();
+        &rest[..rest.find('"').unwrap()]
+    }
+
+    #[test]
+    fn ndjson_events_follow_the_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input");
+        fs::create_dir(&input).unwrap();
+        write_icon(&input, "light");
+        fs::write(
+            input.join("light.dmi.toml"),
+            "[[operations]]\nmode = \"Passthrough\"\n[[operations]]\nmode = \"HsvShift\"\nhue = \
+             90.0\n",
+        )
+        .unwrap();
+        write_icon(&input, "broken");
+        fs::write(input.join("broken.dmi.toml"), "mode = \"DropFrames\"\n").unwrap();
+
+        let output = run_with_args(vec![
+            "--progress".to_string(),
+            "ndjson".to_string(),
+            "--continue-on-error".to_string(),
+            "--output".to_string(),
+            dir.path().join("out").to_str().unwrap().to_string(),
+            input.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        // The broken config fails, so the run does too
+        assert_eq!(output.status.code(), Some(2), "{output:?}");
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let lines: Vec<&str> = stderr
+            .lines()
+            .filter(|line| line.starts_with('{'))
+            .collect();
+        for line in &lines {
+            assert!(line.ends_with('}'), "{line}");
+        }
+        assert_eq!(
+            lines.first().unwrap(),
+            &"{\"event\":\"started\",\"configs\":2}"
+        );
+        assert_eq!(
+            lines.last().unwrap(),
+            &"{\"event\":\"finished\",\"succeeded\":1,\"failed\":1}"
+        );
+
+        // Configs run in parallel, so look at each on its own
+        let events_for = |config: &str| -> Vec<&str> {
+            lines
+                .iter()
+                .filter(|line| line.contains(config))
+                .map(|line| event(line))
+                .collect()
+        };
+        assert_eq!(
+            events_for("light.dmi.toml"),
+            vec![
+                "config_started",
+                "operation_started",
+                "operation_finished",
+                "operation_started",
+                "operation_finished",
+                "state",
+                "state",
+                "output_written",
+                "config_finished",
+            ]
+        );
+        assert!(lines.contains(&&*format!(
+            "{{\"event\":\"operation_started\",\"config\":\"{}\",\"index\":1,\"mode\":\"HsvShift\"\
+             }}",
+            input.join("light.dmi.toml").display()
+        )));
+        assert_eq!(
+            events_for("broken.dmi.toml"),
+            vec!["config_started", "error"]
+        );
+        let error = lines.iter().find(|line| event(line) == "error").unwrap();
+        assert!(error.contains("\"code\":\"invalid_config\""), "{error}");
+    }
+}
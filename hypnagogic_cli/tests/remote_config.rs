@@ -0,0 +1,82 @@
+#[macro_use]
+mod util;
+
+mod remote_config {
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn print_config(url: &str) -> std::process::Output {
+        run_with_args(vec![
+            "print-config".to_string(),
+            "--format".to_string(),
+            "toml".to_string(),
+            url.to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap()
+    }
+
+    #[test]
+    fn unreachable_url_is_a_network_error() {
+        // Nothing listens on port 1
+        let output = print_config("http://127.0.0.1:1/wall.png.toml");
+        assert!(!output.status.success());
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert!(printed.contains("Network Error"), "{printed}");
+        assert!(
+            printed.contains("http://127.0.0.1:1/wall.png.toml"),
+            "{printed}"
+        );
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn templates_resolve_next_to_remote_config() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Serves the config, then the template it uses
+        let server = std::thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request_line = String::new();
+                BufReader::new(&stream)
+                    .read_line(&mut request_line)
+                    .unwrap();
+                let body = if request_line.contains("/configs/wall.png.toml ") {
+                    "template = \"shared/recolor\"\nhue = 45.0\n"
+                } else if request_line.contains("/configs/shared/recolor.toml ") {
+                    "mode = \"HsvShift\"\nhue = 90.0\nvalue = 0.5\n"
+                } else {
+                    ""
+                };
+                let status = if body.is_empty() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        let output = print_config(&format!("http://127.0.0.1:{port}/configs/wall.png.toml"));
+        server.join().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(output.status.success(), "{stdout}");
+        // From the template
+        assert!(stdout.contains("mode = \"HsvShift\""), "{stdout}");
+        assert!(stdout.contains("value = 0.5"), "{stdout}");
+        // Overridden by the config
+        assert!(stdout.contains("hue = 45.0"), "{stdout}");
+    }
+}
@@ -0,0 +1,62 @@
+#[macro_use]
+mod util;
+
+mod report_changes {
+    use std::fs::{self, File};
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn reports_modified_and_unchanged_states() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: ["light_on", "light_off", "broken"]
+                .into_iter()
+                .map(|name| {
+                    IconState {
+                        name: name.to_string(),
+                        images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                            4,
+                            4,
+                            Rgba([255, 0, 0, 255]),
+                        ))],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        icon.save(&mut File::create(root.join("light.dmi")).unwrap())
+            .unwrap();
+        fs::write(
+            root.join("light.dmi.toml"),
+            "mode = \"HsvShift\"\nhue = 120.0\ntarget_states = [\"light_on\"]\n",
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "--report-changes".to_string(),
+            "--flatten".to_string(),
+            "--output".to_string(),
+            root.join("out").to_str().unwrap().to_string(),
+            root.join("light.dmi.toml").to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("~ light_on"), "{stdout}");
+        assert!(stdout.contains("2 unchanged"), "{stdout}");
+        assert!(!stdout.contains("+ "), "{stdout}");
+        assert!(!stdout.contains("- "), "{stdout}");
+    }
+}
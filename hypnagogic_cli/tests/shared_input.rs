@@ -0,0 +1,84 @@
+#![cfg(unix)]
+
+#[macro_use]
+mod util;
+
+mod shared_input {
+    use std::fs::{self, File};
+    use std::os::unix::fs::symlink;
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn write_icon(path: &Path) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([255, 0, 0, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(path).unwrap()).unwrap();
+    }
+
+    fn pixel(path: &Path) -> Rgba<u8> {
+        let icon = Icon::load(File::open(path).unwrap()).unwrap();
+        icon.states[0].images[0].get_pixel(0, 0)
+    }
+
+    #[test]
+    fn configs_sharing_an_input_read_it_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input");
+        fs::create_dir(&input).unwrap();
+        write_icon(&input.join("wall.dmi"));
+        symlink(input.join("wall.dmi"), input.join("wall_green.dmi")).unwrap();
+        symlink(input.join("wall.dmi"), input.join("wall_blue.dmi")).unwrap();
+        fs::write(input.join("wall.dmi.toml"), "mode = \"Passthrough\"\n").unwrap();
+        fs::write(
+            input.join("wall_green.dmi.toml"),
+            "mode = \"HsvShift\"\nhue = 120.0\n",
+        )
+        .unwrap();
+        fs::write(
+            input.join("wall_blue.dmi.toml"),
+            "mode = \"HsvShift\"\nhue = 240.0\n",
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "--verbose".to_string(),
+            "--flatten".to_string(),
+            "--output".to_string(),
+            dir.path().join("out").to_str().unwrap().to_string(),
+            input.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("Successfully processed 3 files!"),
+            "{stdout}"
+        );
+        assert!(stdout.contains("Read 1 inputs for 3 files"), "{stdout}");
+
+        let out = dir.path().join("out");
+        assert_eq!(pixel(&out.join("wall.dmi")), Rgba([255, 0, 0, 255]));
+        assert_eq!(pixel(&out.join("wall_green.dmi")), Rgba([0, 255, 0, 255]));
+        assert_eq!(pixel(&out.join("wall_blue.dmi")), Rgba([0, 0, 255, 255]));
+    }
+}
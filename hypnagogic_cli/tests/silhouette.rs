@@ -0,0 +1,71 @@
+#[macro_use]
+mod util;
+
+mod silhouette {
+    use std::fs::File;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn every_state_becomes_a_shadow() {
+        let dir = tempfile::tempdir().unwrap();
+        let frame = |color: [u8; 3]| {
+            let mut image = RgbaImage::new(2, 2);
+            image.put_pixel(0, 0, Rgba([color[0], color[1], color[2], 255]));
+            image.put_pixel(1, 0, Rgba([color[0], color[1], color[2], 120]));
+            DynamicImage::ImageRgba8(image)
+        };
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states: vec![
+                IconState {
+                    name: "crate".to_string(),
+                    images: vec![frame([200, 150, 40])],
+                    ..Default::default()
+                },
+                IconState {
+                    name: "locker".to_string(),
+                    frames: 2,
+                    images: vec![frame([20, 90, 200]), frame([60, 60, 60])],
+                    delay: Some(vec![1.0, 3.0]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let input = dir.path().join("storage.dmi");
+        icon.save(&mut File::create(&input).unwrap()).unwrap();
+        let output_path = dir.path().join("shadows.dmi");
+
+        let output = run_with_args(vec![
+            "silhouette".to_string(),
+            input.to_str().unwrap().to_string(),
+            "--output".to_string(),
+            output_path.to_str().unwrap().to_string(),
+            "--color".to_string(),
+            "#102030".to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let shadows = Icon::load(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(shadows.states.len(), 2);
+        for (shadow, original) in shadows.states.iter().zip(&icon.states) {
+            assert_eq!(shadow.name, original.name);
+            assert_eq!(shadow.frames, original.frames);
+            assert_eq!(shadow.delay, original.delay);
+            for image in &shadow.images {
+                assert_eq!(image.get_pixel(0, 0), Rgba([16, 32, 48, 255]));
+                assert_eq!(image.get_pixel(1, 0), Rgba([16, 32, 48, 120]));
+                assert_eq!(image.get_pixel(0, 1).0[3], 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+#[macro_use]
+mod util;
+
+mod split_emissive {
+    use std::fs::{self, File};
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    #[test]
+    fn separate_outputs_get_a_dmi_each() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut lamp = RgbaImage::new(2, 1);
+        lamp.put_pixel(0, 0, Rgba([255, 240, 100, 255]));
+        lamp.put_pixel(1, 0, Rgba([60, 60, 60, 255]));
+        let state = |name: &str| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(lamp.clone())],
+                ..Default::default()
+            }
+        };
+        let icon = Icon {
+            width: 2,
+            height: 1,
+            states: vec![state("lamp"), state("sign")],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.path().join("lamps.dmi")).unwrap())
+            .unwrap();
+        let config = dir.path().join("lamps.dmi.toml");
+        fs::write(
+            &config,
+            "mode = \"SplitEmissive\"\nstate = \"lamp\"\nseparate_outputs = \
+             true\n\n[emissive]\nfrom = \"channel\"\nchannel = \"red\"\n",
+        )
+        .unwrap();
+        let out = dir.path().join("out");
+
+        let output = run_with_args(vec![
+            "--flatten".to_string(),
+            "--output".to_string(),
+            out.to_str().unwrap().to_string(),
+            config.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let state_names = |file: &str| -> Vec<String> {
+            Icon::load(File::open(out.join(file)).unwrap())
+                .unwrap()
+                .states
+                .into_iter()
+                .map(|state| state.name)
+                .collect()
+        };
+        assert_eq!(state_names("lamps-diffuse.dmi"), vec!["lamp_diffuse"]);
+        assert_eq!(state_names("lamps-emissive.dmi"), vec!["lamp_emissive"]);
+        assert!(!out.join("lamps.dmi").exists());
+    }
+}
@@ -0,0 +1,69 @@
+#[macro_use]
+mod util;
+
+mod state_name_length {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// Writes a dmi with a state named `name`, set up to get an emissive
+    /// state added after it
+    fn write_icon(dir: &Path, name: &str) {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(4, 4))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        icon.save(&mut File::create(dir.join("wall.dmi")).unwrap())
+            .unwrap();
+        fs::write(dir.join("wall.dmi.toml"), "mode = \"Emissive\"\n").unwrap();
+    }
+
+    /// Runs over the dmi, returning everything printed
+    fn run(dir: &Path, extra_args: &[&str]) -> (bool, String) {
+        let mut args: Vec<String> = extra_args.iter().map(ToString::to_string).collect();
+        args.push("--output".to_string());
+        args.push(dir.join("out").to_str().unwrap().to_string());
+        args.push(dir.join("wall.dmi.toml").to_str().unwrap().to_string());
+        let output = run_with_args(args).unwrap().output().unwrap();
+        let printed =
+            String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        (output.status.success(), printed)
+    }
+
+    #[test]
+    fn long_suffixed_names_warn() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write_icon(dir.path(), "wall");
+        let (success, stdout) = run(dir.path(), &["--warn-state-name-length", "16"]);
+        assert!(success);
+        assert!(!stdout.contains("Long Icon State Name"), "{stdout}");
+
+        write_icon(dir.path(), "reinforced_wall");
+        let (success, stdout) = run(dir.path(), &["--warn-state-name-length", "16"]);
+        assert!(success);
+        assert!(stdout.contains("Long Icon State Name"), "{stdout}");
+        assert!(stdout.contains("\"reinforced_wall_emissive\""), "{stdout}");
+    }
+
+    #[test]
+    fn strict_makes_it_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write_icon(dir.path(), "reinforced_wall");
+
+        let (_, stdout) = run(dir.path(), &["--warn-state-name-length", "16", "--strict"]);
+        assert!(stdout.contains("Long Icon State Name"), "{stdout}");
+        assert!(stdout.contains("Failed to process 1 files!"), "{stdout}");
+    }
+}
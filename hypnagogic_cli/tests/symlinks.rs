@@ -0,0 +1,61 @@
+#![cfg(unix)]
+
+#[macro_use]
+mod util;
+
+mod symlinks {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+
+    use util::run::run_with_templates;
+
+    use super::*;
+
+    #[test]
+    fn follows_symlinked_templates_and_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+        symlink(repo.join("../templates"), root.join("templates")).unwrap();
+
+        let real_icons = root.join("real_icons");
+        fs::create_dir(&real_icons).unwrap();
+        fs::copy(
+            repo.join("tests/test_files/basic_cut/input/tall-5-corners-debug.png"),
+            real_icons.join("wall.png"),
+        )
+        .unwrap();
+        fs::write(
+            real_icons.join("wall.png.toml"),
+            "template = \"bitmask/slice-tallwalls-directionalvis\"\n",
+        )
+        .unwrap();
+
+        let input = root.join("input");
+        fs::create_dir(&input).unwrap();
+        symlink(&real_icons, input.join("linked")).unwrap();
+        // Must not send discovery around in circles
+        symlink(&input, input.join("loop")).unwrap();
+
+        let output = run_with_templates(
+            &root.join("templates"),
+            vec![
+                "--output".to_string(),
+                "out".to_string(),
+                "input".to_string(),
+            ],
+        )
+        .unwrap()
+        .current_dir(root)
+        .output()
+        .unwrap();
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("Found 1 files!"), "{stdout}");
+        assert!(stdout.contains("symlink loop"), "{stdout}");
+        assert!(root.join("out/input/linked/wall.dmi").exists());
+    }
+}
@@ -0,0 +1,63 @@
+#[macro_use]
+mod util;
+
+mod trace_resolution {
+    use std::fs;
+
+    use util::run::run_with_templates;
+
+    use super::*;
+
+    #[test]
+    fn inherited_settings_name_their_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates = dir.path().join("templates");
+        fs::create_dir_all(templates.join("walls")).unwrap();
+        fs::write(
+            templates.join("walls/base.toml"),
+            "mode = \"BitmaskSlice\"\nsmooth_diagonally = false\n[icon_size]\nx = 32\ny = 32\n",
+        )
+        .unwrap();
+        fs::write(
+            templates.join("walls/tall.toml"),
+            "template = \"walls/base\"\n[icon_size]\ny = 48\n",
+        )
+        .unwrap();
+        let configs = dir.path().join("configs");
+        fs::create_dir(&configs).unwrap();
+        let config = configs.join("wall.png.toml");
+        fs::write(
+            &config,
+            "template = \"walls/tall\"\nsmooth_diagonally = true\n",
+        )
+        .unwrap();
+
+        let output = run_with_templates(
+            &templates,
+            vec![
+                "--trace-resolution".to_string(),
+                configs.to_str().unwrap().to_string(),
+            ],
+        )
+        .unwrap()
+        .output()
+        .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(output.status.success(), "{stdout}");
+
+        let source = |field: &str| {
+            let prefix = format!("  {field}: ");
+            let line = stdout
+                .lines()
+                .find(|line| line.starts_with(&prefix))
+                .unwrap_or_else(|| panic!("{field} missing from {stdout}"));
+            line[prefix.len()..].to_string()
+        };
+        assert!(source("mode").ends_with("base.toml"), "{stdout}");
+        assert!(source("icon_size.x").ends_with("base.toml"), "{stdout}");
+        assert!(source("icon_size.y").ends_with("tall.toml"), "{stdout}");
+        assert_eq!(source("smooth_diagonally"), config.display().to_string());
+        // Tracing doesn't process anything
+        assert!(!configs.join("wall.dmi").exists());
+    }
+}
@@ -0,0 +1,73 @@
+#[macro_use]
+mod util;
+
+mod tracing_spans {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    /// `text` without any terminal color codes
+    fn strip_colors(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = text.chars();
+        while let Some(char) = chars.next() {
+            if char == '\x1b' {
+                chars.by_ref().find(|&char| char == 'm');
+            } else {
+                stripped.push(char);
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn operations_are_traced_within_their_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        fs::copy(
+            repo.join("tests/test_files/basic_cut/input/tall-5-corners-debug.png"),
+            dir.path().join("wall.png"),
+        )
+        .unwrap();
+        let config = dir.path().join("wall.png.toml");
+        fs::write(
+            &config,
+            "[[operations]]\nmode = \"Passthrough\"\n\n[[operations]]\nmode = \"Passthrough\"\n",
+        )
+        .unwrap();
+
+        let output = run_with_args(vec![
+            "-vv".to_string(),
+            "--output".to_string(),
+            dir.path().join("out").to_str().unwrap().to_string(),
+            config.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let stdout = strip_colors(&String::from_utf8(output.stdout).unwrap());
+        // Each operation's span closes with how long it took, nested in the
+        // config it belongs to
+        let closed: Vec<&str> = stdout
+            .lines()
+            .filter(|line| {
+                line.contains(":operation: hypnagogic_core::operations::pipeline: close")
+            })
+            .collect();
+        assert_eq!(closed.len(), 2, "{stdout}");
+        let config_field = format!("config={}", config.display());
+        for (index, line) in closed.iter().enumerate() {
+            assert!(line.contains("time.busy="), "{line}");
+            assert!(line.contains(&config_field), "{line}");
+            assert!(
+                line.contains(&format!("index={index} mode=\"Passthrough\"")),
+                "{line}"
+            );
+        }
+    }
+}
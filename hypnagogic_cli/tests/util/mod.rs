@@ -1,3 +1,6 @@
+// Shared between several test crates, not all of which use everything
+#![allow(dead_code)]
+
 pub mod deep_dir_compare;
 #[macro_use]
 pub mod dir_tester;
@@ -0,0 +1,57 @@
+#[macro_use]
+mod util;
+
+mod validate_all {
+    use std::fs;
+    use std::path::Path;
+
+    use util::run::run_with_args;
+
+    use super::*;
+
+    fn validate(dir: &Path) -> std::process::Output {
+        run_with_args(vec![
+            "validate-all".to_string(),
+            dir.to_str().unwrap().to_string(),
+        ])
+        .unwrap()
+        .output()
+        .unwrap()
+    }
+
+    #[test]
+    fn sorts_configs_into_pass_and_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(
+            dir.path().join("ghost.dmi.toml"),
+            "mode = \"CapAlpha\"\nmax_alpha = 128\n",
+        )
+        .unwrap();
+        // Parses fine, but fails verification
+        fs::write(
+            nested.join("lamp.dmi.toml"),
+            "mode = \"Gamma\"\ngamma = 0.0\n",
+        )
+        .unwrap();
+
+        let output = validate(dir.path());
+        assert!(!output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let line_for = |config: &str| {
+            stdout
+                .lines()
+                .find(|line| line.ends_with(config))
+                .unwrap_or_else(|| panic!("{config} not listed in {stdout}"))
+                .to_string()
+        };
+        assert!(line_for("ghost.dmi.toml").contains("PASS"), "{stdout}");
+        assert!(line_for("lamp.dmi.toml").contains("FAIL"), "{stdout}");
+        assert!(stdout.contains("1 passed, 1 failed"), "{stdout}");
+
+        fs::remove_file(nested.join("lamp.dmi.toml")).unwrap();
+        let output = validate(dir.path());
+        assert!(output.status.success());
+    }
+}
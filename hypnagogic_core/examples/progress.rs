@@ -0,0 +1,37 @@
+//! Runs a config over a dmi, printing progress to stderr as it goes.
+//!
+//! `cargo run --example progress -- input.dmi config.toml output.dmi`
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+use hypnagogic_core::operations::pipeline::Pipeline;
+use hypnagogic_core::process::{process_bytes_with_progress, ProcessEvent};
+
+fn report(event: ProcessEvent) {
+    match event {
+        ProcessEvent::StartedFile(path) => eprintln!("Reading {}", path.display()),
+        ProcessEvent::StartedOperation { index, mode } => eprintln!("Running {mode} ({index})"),
+        ProcessEvent::FinishedOperation { index, mode } => eprintln!("Finished {mode} ({index})"),
+        ProcessEvent::WroteOutput(path) => eprintln!("Wrote {}", path.display()),
+        ProcessEvent::Warning(warning) => eprintln!("Warning: {warning}"),
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let [input, config, output]: [PathBuf; 3] = env::args_os()
+        .skip(1)
+        .map(PathBuf::from)
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| "usage: progress <input.dmi> <config.toml> <output.dmi>")?;
+
+    // Templates aren't resolved here, so the config needs to be self contained
+    let config: Pipeline = toml::from_str(&fs::read_to_string(config)?)?;
+
+    report(ProcessEvent::StartedFile(input.clone()));
+    let processed = process_bytes_with_progress(&fs::read(&input)?, "dmi", &config, report)?;
+    fs::write(&output, processed)?;
+    report(ProcessEvent::WroteOutput(output));
+    Ok(())
+}
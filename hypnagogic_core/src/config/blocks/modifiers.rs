@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::InputIcon;
+
+fn yes() -> bool {
+    true
+}
+
+// serde hands skip_serializing_if a reference
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// Which icon states a modifier operation applies to.
+/// Meant to be `#[serde(flatten)]`ed in to the operation's config
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct StateTargets {
+    /// Names of the icon states to modify. If empty, every state is modified
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub target_states: Vec<String>,
+    /// Names of icon states to leave alone, even if `target_states` lists them
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_states: Vec<String>,
+    /// Whether every entry in `target_states` has to match an icon state, so
+    /// a typo fails loudly instead of quietly doing nothing
+    #[serde(default = "yes")]
+    #[serde(skip_serializing_if = "is_true")]
+    pub require_match: bool,
+}
+
+impl Default for StateTargets {
+    fn default() -> Self {
+        Self {
+            target_states: vec![],
+            exclude_states: vec![],
+            require_match: true,
+        }
+    }
+}
+
+impl StateTargets {
+    /// Whether the icon state with this name should be modified
+    #[must_use]
+    pub fn matches(&self, state_name: &str) -> bool {
+        let included =
+            self.target_states.is_empty() || self.target_states.iter().any(|x| x == state_name);
+        included && !self.exclude_states.iter().any(|x| x == state_name)
+    }
+
+    /// Checks every entry in `target_states` names an icon state in `input`,
+    /// unless `require_match` is off. Only dmis have icon states to check
+    /// # Errors
+    /// Returns `ProcessorError::UnmatchedTargets` listing the entries that
+    /// matched nothing
+    pub fn check_matched(&self, input: &InputIcon) -> ProcessorResult<()> {
+        let InputIcon::Dmi(icon) = input else {
+            return Ok(());
+        };
+        if !self.require_match {
+            return Ok(());
+        }
+        let unmatched: Vec<String> = self
+            .target_states
+            .iter()
+            .filter(|target| !icon.states.iter().any(|state| &state.name == *target))
+            .cloned()
+            .collect();
+        if !unmatched.is_empty() {
+            return Err(ProcessorError::UnmatchedTargets(unmatched));
+        }
+        Ok(())
+    }
+}
+
+/// How far to shift something, in pixels. Positive values move right and down
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Offset {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exclusions_win_over_targets() {
+        let names = ["wall", "wall_ui", "floor", "floor_ui"];
+        let matching = |targets: &StateTargets| -> Vec<&str> {
+            names
+                .into_iter()
+                .filter(|name| targets.matches(name))
+                .collect()
+        };
+
+        let everything_but_ui = StateTargets {
+            exclude_states: vec!["wall_ui".to_string(), "floor_ui".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(matching(&everything_but_ui), vec!["wall", "floor"]);
+
+        let walls_but_ui = StateTargets {
+            target_states: vec!["wall".to_string(), "wall_ui".to_string()],
+            exclude_states: vec!["wall_ui".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(matching(&walls_but_ui), vec!["wall"]);
+    }
+
+    #[test]
+    fn unmatched_targets_are_named() {
+        use dmi::icon::{Icon, IconState};
+
+        let input = InputIcon::Dmi(Icon {
+            states: vec![IconState {
+                name: "wall".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let mut targets = StateTargets {
+            target_states: vec!["wall".to_string(), "wal_ui".to_string()],
+            ..Default::default()
+        };
+        let Err(ProcessorError::UnmatchedTargets(unmatched)) = targets.check_matched(&input) else {
+            panic!("Expected the typo to be caught");
+        };
+        assert_eq!(unmatched, vec!["wal_ui"]);
+
+        targets.require_match = false;
+        assert!(targets.check_matched(&input).is_ok());
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 use crate::config::template_resolver::error::TemplateError;
@@ -10,6 +12,21 @@ pub enum ConfigError {
     Toml(#[from] toml::de::Error),
     #[error("error in config")]
     Config(String),
+    #[error("Failed to find {include:?}, included from {included_from:?}")]
+    IncludeNotFound {
+        include: PathBuf,
+        included_from: PathBuf,
+    },
+    #[error("{include:?} ends up including itself, by way of {included_from:?}")]
+    IncludeCycle {
+        include: PathBuf,
+        included_from: PathBuf,
+    },
+    #[error("No profile named {profile:?}, this config has {}", available.join(", "))]
+    UnknownProfile {
+        profile: String,
+        available: Vec<String>,
+    },
     #[error("Generic IO Error: {0}")]
     IO(#[from] std::io::Error),
 }
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::config::error::{ConfigError, ConfigResult};
+
+/// Lines starting with this are replaced by the contents of the file they
+/// name, ie `!include shared/walls.toml`. Paths are relative to the file the
+/// line is in
+const INCLUDE_DIRECTIVE: &str = "!include";
+
+/// Reads the config at `path`, pasting in the contents of any files it
+/// includes (and any they include, and so on) in place of their `!include`
+/// line. This happens before the config is parsed, so an include can hold any
+/// part of a config, not just whole tables.
+///
+/// # Errors
+/// Errors if a file can't be read, an included file doesn't exist, or a file
+/// ends up including itself
+pub fn expand_includes(path: &Path) -> ConfigResult<String> {
+    expand(path, &mut vec![])
+}
+
+/// `including` is every file we're currently in the middle of expanding,
+/// which is how cycles are spotted. Including the same file twice from
+/// different places is fine
+fn expand(path: &Path, including: &mut Vec<PathBuf>) -> ConfigResult<String> {
+    let text = fs::read_to_string(path)?;
+    including.push(fs::canonicalize(path)?);
+
+    let mut expanded = String::with_capacity(text.len());
+    for line in text.lines() {
+        let Some(include) = parse_include(line) else {
+            expanded.push_str(line);
+            expanded.push('\n');
+            continue;
+        };
+        let include_path = path.parent().unwrap_or(Path::new("")).join(include);
+        debug!(include = ?include_path, from = ?path, "Expanding include");
+        let Ok(canonical) = fs::canonicalize(&include_path) else {
+            return Err(ConfigError::IncludeNotFound {
+                include: include_path,
+                included_from: path.to_path_buf(),
+            });
+        };
+        if including.contains(&canonical) {
+            return Err(ConfigError::IncludeCycle {
+                include: include_path,
+                included_from: path.to_path_buf(),
+            });
+        }
+        expanded.push_str(&expand(&include_path, including)?);
+    }
+
+    including.pop();
+    Ok(expanded)
+}
+
+/// Pulls the path out of an include line, which may optionally be quoted
+fn parse_include(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix(INCLUDE_DIRECTIVE)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let include = rest.trim();
+    let include = include
+        .strip_prefix('"')
+        .and_then(|unquoted| unquoted.strip_suffix('"'))
+        .unwrap_or(include);
+    (!include.is_empty()).then_some(include)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_include_lines() {
+        assert_eq!(parse_include("!include shared.toml"), Some("shared.toml"));
+        assert_eq!(
+            parse_include("  !include \"walls/base.toml\"  "),
+            Some("walls/base.toml")
+        );
+        assert_eq!(parse_include("!include"), None);
+        assert_eq!(parse_include("!included = 1"), None);
+        assert_eq!(parse_include("# !include shared.toml"), None);
+        assert_eq!(parse_include("mode = \"Passthrough\""), None);
+    }
+}
@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::io::{read_to_string, Read, Seek};
+use std::path::Path;
 
 use serde::Deserialize;
 use template_resolver::TemplateResolver;
@@ -6,13 +8,16 @@ use toml::map::Map;
 use toml::Value;
 use tracing::{debug, trace};
 
-use crate::config::error::ConfigResult;
-use crate::config::template_resolver::error::TemplateResult;
+use crate::config::error::{ConfigError, ConfigResult};
+use crate::config::include::expand_includes;
+use crate::config::template_resolver::error::{TemplateError, TemplateResult};
+use crate::operations::pipeline::Pipeline;
 use crate::operations::IconOperation;
 use crate::util::deep_merge_toml;
 
 pub mod blocks;
 pub mod error;
+pub mod include;
 pub mod template_resolver;
 
 pub const LATEST_VERSION: &str = "1";
@@ -22,16 +27,91 @@ pub fn read_config<R: Read + Seek>(
     input: &mut R,
     resolver: impl TemplateResolver,
 ) -> ConfigResult<IconOperation> {
-    let reader_string = read_to_string(input)?;
-    let toml_value = toml::from_str(&reader_string)?;
-
-    let result_value = resolve_templates(toml_value, resolver)?;
+    let result_value = read_resolved(input, resolver)?;
 
     let out_icon_mode: IconOperation = IconOperation::deserialize(result_value)?;
     debug!(config = ?out_icon_mode, "Deserialized");
     Ok(out_icon_mode)
 }
 
+/// Like `read_config`, but also accepts configs with a list of operations,
+/// and picks out `profile` from the config's profiles, see [`apply_profile`]
+#[tracing::instrument(skip(resolver, input))]
+pub fn read_pipeline<R: Read + Seek>(
+    input: &mut R,
+    resolver: impl TemplateResolver,
+    profile: Option<&str>,
+) -> ConfigResult<Pipeline> {
+    let mut result_value = read_resolved(input, resolver)?;
+    apply_profile(&mut result_value, profile)?;
+
+    let pipeline = Pipeline::deserialize(result_value)?;
+    debug!(config = ?pipeline, "Deserialized");
+    Ok(pipeline)
+}
+
+/// Like `read_pipeline`, but reads the config from a file so that any
+/// `!include` lines in it can be expanded, see [`expand_includes`]
+#[tracing::instrument(skip(resolver))]
+pub fn read_pipeline_file(
+    path: &Path,
+    resolver: impl TemplateResolver,
+    profile: Option<&str>,
+) -> ConfigResult<Pipeline> {
+    let expanded = expand_includes(path)?;
+    let mut result_value = resolve_str(&expanded, resolver)?;
+    apply_profile(&mut result_value, profile)?;
+
+    let pipeline = Pipeline::deserialize(result_value)?;
+    debug!(config = ?pipeline, "Deserialized");
+    Ok(pipeline)
+}
+
+fn read_resolved<R: Read + Seek>(
+    input: &mut R,
+    resolver: impl TemplateResolver,
+) -> ConfigResult<Value> {
+    let reader_string = read_to_string(input)?;
+    resolve_str(&reader_string, resolver)
+}
+
+fn resolve_str(config: &str, resolver: impl TemplateResolver) -> ConfigResult<Value> {
+    let toml_value = toml::from_str(config)?;
+
+    Ok(resolve_templates(toml_value, resolver)?)
+}
+
+/// Takes the `profiles` table out of a resolved config, then merges the
+/// settings of the one named `profile` over the rest of it. Arrays, like
+/// `operations`, are replaced outright rather than merged.
+///
+/// Configs without any profiles ignore `profile`, so one can be picked for a
+/// whole folder of configs where only some of them have it
+/// # Errors
+/// Returns `ConfigError::UnknownProfile` if the config has profiles, but not
+/// one named `profile`
+pub fn apply_profile(config: &mut Value, profile: Option<&str>) -> ConfigResult<()> {
+    let Some(profiles) = config
+        .as_table_mut()
+        .and_then(|table| table.remove("profiles"))
+    else {
+        return Ok(());
+    };
+    let mut profiles = Map::deserialize(profiles)?;
+    let Some(profile) = profile else {
+        return Ok(());
+    };
+    let Some(overrides) = profiles.remove(profile) else {
+        return Err(ConfigError::UnknownProfile {
+            profile: profile.to_string(),
+            available: profiles.keys().cloned().collect(),
+        });
+    };
+    debug!(profile, overrides = ?overrides, "Applying profile");
+    deep_merge_toml(config, overrides);
+    Ok(())
+}
+
 /// Seeks out template string from a value and returns it as a `Some(String)`
 /// If not found, returns `None`
 /// SIDE EFFECT: removes it from the `Value` if it finds it!
@@ -48,42 +128,123 @@ fn extract_template_string(value: &mut Value) -> Option<String> {
     }
 }
 
-#[tracing::instrument(skip(resolver))]
-pub fn resolve_templates(first: Value, resolver: impl TemplateResolver) -> TemplateResult {
-    debug!(first = ?first, "Started resolving templates");
+/// The config followed by every template it inherits from, in order, each
+/// with the template string it was resolved from (none for the config itself)
+fn template_chain(
+    first: Value,
+    resolver: &impl TemplateResolver,
+) -> Result<Vec<(Option<String>, Value)>, TemplateError> {
     let mut current = first;
-    let mut stack: Vec<Value> = vec![];
+    let mut stack: Vec<(Option<String>, Value)> = vec![];
 
     let mut extracted_template = extract_template_string(&mut current);
     trace!(extracted = ?extracted_template, "extracted first template");
 
     // push the first on to the stack to be resolved
-    stack.push(current.clone());
+    stack.push((None, current));
     let mut recursion_cap = 0;
     // Drill in to templates and resolve until no new ones found
     while recursion_cap < 100 {
-        if let Some(template) = &extracted_template {
-            current = resolver.resolve(template.as_str())?;
+        if let Some(template) = extracted_template {
+            let mut current = resolver.resolve(template.as_str())?;
             extracted_template = extract_template_string(&mut current);
             trace!(value = ?current, "Resolved config");
-            stack.push(current.clone());
+            stack.push((Some(template), current));
             recursion_cap += 1;
         } else {
             break;
         }
     }
     trace!(num_in_chain = ?stack.len(), stack = ?stack, "Finished resolving templates");
+    Ok(stack)
+}
+
+#[tracing::instrument(skip(resolver))]
+pub fn resolve_templates(first: Value, resolver: impl TemplateResolver) -> TemplateResult {
+    debug!(first = ?first, "Started resolving templates");
+    let stack = template_chain(first, &resolver)?;
     // merge stack in to one hashmap
     let mut out: Value = Value::Table(Map::new());
-    for conf in stack.iter_mut().rev() {
-        deep_merge_toml(&mut out, conf.clone());
+    for (_, conf) in stack.into_iter().rev() {
         trace!(current = ?out, collapsing = ?conf, "Collapsing value step");
+        deep_merge_toml(&mut out, conf);
     }
 
     debug!(collapsed = ?out, "Collapsed value");
     Ok(out)
 }
 
+/// Which file set each setting of a config once its templates are merged, by
+/// dotted path from the root like `icon_size.x`. Files are named by
+/// `config_name` for the config itself, or [`TemplateResolver::source_name`]
+/// for the templates it inherits from
+pub type FieldSources = BTreeMap<String, String>;
+
+/// Merges the templates of a config the same way as `resolve_templates`, but
+/// keeps track of where each setting it ends up with came from instead
+/// # Errors
+/// Errors if a template can't be resolved
+#[tracing::instrument(skip(resolver))]
+pub fn trace_templates(
+    first: Value,
+    config_name: &str,
+    resolver: impl TemplateResolver,
+) -> Result<FieldSources, TemplateError> {
+    let stack = template_chain(first, &resolver)?;
+    let mut sources = FieldSources::new();
+    for (template, conf) in stack.iter().rev() {
+        let source = template.as_deref().map_or_else(
+            || config_name.to_string(),
+            |template| resolver.source_name(template),
+        );
+        record_sources("", conf, &source, &mut sources);
+    }
+    Ok(sources)
+}
+
+/// Like `trace_templates`, but reads the config from a file, with its
+/// `!include` lines expanded. Settings from included files count as the
+/// config's own
+/// # Errors
+/// Errors if the config can't be read or parsed, or a template can't be
+/// resolved
+pub fn trace_pipeline_file(
+    path: &Path,
+    resolver: impl TemplateResolver,
+) -> ConfigResult<FieldSources> {
+    let expanded = expand_includes(path)?;
+    let toml_value = toml::from_str(&expanded)?;
+    Ok(trace_templates(
+        toml_value,
+        &path.display().to_string(),
+        resolver,
+    )?)
+}
+
+/// Marks every setting in `value` as set by `source`, the way
+/// `deep_merge_toml` would merge it over the values already recorded: tables
+/// are merged key by key, anything else replaces whatever was there
+fn record_sources(path: &str, value: &Value, source: &str, sources: &mut FieldSources) {
+    if let Value::Table(table) = value {
+        for (key, value) in table {
+            let path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            record_sources(&path, value, source, sources);
+        }
+        return;
+    }
+    // Drop anything this replaces, which is whatever was under it before, or
+    // a setting above it that it's now going inside of
+    let inside = format!("{path}.");
+    sources.retain(|field, _| {
+        !field.starts_with(&inside) && !inside.starts_with(&format!("{field}."))
+    });
+    sources.insert(path.to_string(), source.to_string());
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -209,6 +370,102 @@ mod test {
             let expected_value: Value = toml::from_str(expected_string).unwrap();
             assert_eq!(result, expected_value);
         }
+
+        #[test]
+        fn tracing_names_the_template_each_setting_is_from() {
+            let input_string = r#"
+            template = "third"
+            first = 10
+            [inner]
+            inner_1 = 10
+            "#;
+
+            let input: Value = toml::from_str(input_string).unwrap();
+
+            let sources = trace_templates(input, "config", TestResolver).unwrap();
+
+            let expected = [
+                ("first", "config"),
+                ("inner.inner_1", "config"),
+                ("inner.inner_2", "third"),
+                ("inner.inner_3", "fourth"),
+                ("second", "third"),
+                ("third", "third"),
+            ];
+            let expected: FieldSources = expected
+                .iter()
+                .map(|(field, source)| ((*field).to_string(), (*source).to_string()))
+                .collect();
+            assert_eq!(sources, expected);
+        }
+
+        #[test]
+        fn tracing_replaces_what_a_setting_goes_over() {
+            let mut sources = FieldSources::new();
+            let table: Value = toml::from_str("[inner]\nfirst = 1").unwrap();
+            record_sources("", &table, "table", &mut sources);
+            let flat: Value = toml::from_str("inner = 2").unwrap();
+            record_sources("", &flat, "flat", &mut sources);
+            assert_eq!(sources.len(), 1);
+            assert_eq!(sources["inner"], "flat");
+
+            record_sources("", &table, "table", &mut sources);
+            assert_eq!(sources.len(), 1);
+            assert_eq!(sources["inner.first"], "table");
+        }
+    }
+
+    mod profiles {
+        use super::*;
+        use crate::operations::pipeline::Pipeline;
+
+        const CONFIG: &str = r#"
+        output_format = "Dmi"
+
+        [[operations]]
+        mode = "Passthrough"
+
+        [profiles.fast]
+        output_format = "PngSheet"
+
+        [[profiles.release.operations]]
+        mode = "Trim"
+
+        [[profiles.release.operations]]
+        mode = "Passthrough"
+        "#;
+
+        fn modes(profile: Option<&str>) -> ConfigResult<Vec<&'static str>> {
+            let mut config: Value = toml::from_str(CONFIG).unwrap();
+            apply_profile(&mut config, profile)?;
+            let pipeline = Pipeline::deserialize(config)?;
+            Ok(pipeline
+                .operations
+                .iter()
+                .map(|step| step.operation.mode_name())
+                .collect())
+        }
+
+        #[test]
+        fn profile_replaces_operations() {
+            assert_eq!(modes(None).unwrap(), vec!["Passthrough"]);
+            assert_eq!(modes(Some("fast")).unwrap(), vec!["Passthrough"]);
+            assert_eq!(modes(Some("release")).unwrap(), vec!["Trim", "Passthrough"]);
+        }
+
+        #[test]
+        fn unknown_profile_lists_the_others() {
+            let Err(ConfigError::UnknownProfile { profile, available }) = modes(Some("relase"))
+            else {
+                panic!("Expected an unknown profile");
+            };
+            assert_eq!(profile, "relase");
+            assert_eq!(available, vec!["fast", "release"]);
+
+            // Nothing to choose from, so nothing to get wrong
+            let mut config: Value = toml::from_str("mode = \"Passthrough\"").unwrap();
+            assert!(apply_profile(&mut config, Some("release")).is_ok());
+        }
     }
 
     mod config {
@@ -40,6 +40,11 @@ impl FileResolver {
             fs::canonicalize(path).map_err(|_e| NoTemplateDirError(path.to_path_buf()))?;
         Ok(FileResolver { path: pathbuf })
     }
+
+    /// Where the template `input` would be found
+    fn template_path(&self, input: &str) -> PathBuf {
+        self.path.join(Path::new(input)).with_extension("toml")
+    }
 }
 
 impl Default for FileResolver {
@@ -51,14 +56,11 @@ impl Default for FileResolver {
 impl TemplateResolver for FileResolver {
     #[tracing::instrument(skip(input))]
     fn resolve(&self, input: &str) -> TemplateResult {
-        let mut pathbuf = self.path.clone();
-        pathbuf.push(Path::new(input));
-
-        debug!(canon = ?pathbuf, "Full path parsed");
+        let toml_path = self.template_path(input);
 
-        let toml_path = pathbuf.with_extension("toml");
+        debug!(canon = ?toml_path, "Full path parsed");
 
-        pathbuf = if toml_path.exists() {
+        let pathbuf = if toml_path.exists() {
             toml_path
         } else {
             return Err(TemplateError::FailedToFindTemplate(
@@ -74,4 +76,8 @@ impl TemplateResolver for FileResolver {
         debug!(deserialized = ?deserialized, "Deserialized template");
         Ok(deserialized)
     }
+
+    fn source_name(&self, input: &str) -> String {
+        self.template_path(input).display().to_string()
+    }
 }
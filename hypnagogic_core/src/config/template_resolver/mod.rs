@@ -12,6 +12,12 @@ pub trait TemplateResolver {
     /// # Errors
     /// Throws an error if resolution fails
     fn resolve(&self, input: &str) -> TemplateResult;
+
+    /// Names where the template `input` comes from, for telling the user
+    /// which template set what
+    fn source_name(&self, input: &str) -> String {
+        input.to_string()
+    }
 }
 
 /// Simple resolver that always returns default templatedconfig
@@ -0,0 +1,115 @@
+//! Pins down what the operations expect from the `dmi` crate, by saving an
+//! icon that uses every field they depend on and checking it loads back the
+//! same. If an update to `dmi` changes how any of them are written or read,
+//! it should fail here rather than quietly corrupting outputs
+
+use std::io::Cursor;
+use std::num::NonZeroU32;
+
+use dmi::icon::{Hotspot, Icon, IconState, Looping};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::operations::InputIcon;
+
+/// A 3x2 frame filled with a color unique to `index`, with one pixel
+/// translucent so alpha gets checked too
+fn frame(index: u8) -> DynamicImage {
+    let mut image = RgbaImage::from_pixel(3, 2, Rgba([index, 255 - index, index / 2, 255]));
+    image.put_pixel(2, 1, Rgba([index, 0, 0, 100]));
+    DynamicImage::ImageRgba8(image)
+}
+
+fn representative_icon() -> Icon {
+    Icon {
+        width: 3,
+        height: 2,
+        states: vec![
+            IconState {
+                name: "still".to_string(),
+                images: vec![frame(0)],
+                ..Default::default()
+            },
+            IconState {
+                name: "walking".to_string(),
+                dirs: 4,
+                frames: 3,
+                images: (1..=12).map(frame).collect(),
+                delay: Some(vec![1.0, 2.5, 4.0]),
+                loop_flag: Looping::NTimes(NonZeroU32::new(2).unwrap()),
+                rewind: true,
+                movement: true,
+                hotspot: Some(Hotspot { x: 1, y: 0 }),
+                ..Default::default()
+            },
+            IconState {
+                name: "spinning".to_string(),
+                dirs: 8,
+                frames: 2,
+                images: (13..=28).map(frame).collect(),
+                delay: Some(vec![1.0, 1.0]),
+                ..Default::default()
+            },
+            // Byond allows a name twice as long as only one is a movement state
+            IconState {
+                name: "walking".to_string(),
+                images: vec![frame(29)],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+fn round_trip(icon: &Icon) -> Icon {
+    let mut bytes = vec![];
+    icon.save(&mut bytes).unwrap();
+    let InputIcon::Dmi(loaded) = InputIcon::from_reader(&mut Cursor::new(bytes), "dmi").unwrap()
+    else {
+        panic!("Expected a dmi");
+    };
+    loaded
+}
+
+#[test]
+fn every_field_survives_a_round_trip() {
+    let icon = representative_icon();
+    let loaded = round_trip(&icon);
+
+    assert_eq!((loaded.width, loaded.height), (3, 2));
+    assert_eq!(loaded.states.len(), icon.states.len());
+    for (expected, got) in icon.states.iter().zip(&loaded.states) {
+        let name = &expected.name;
+        assert_eq!(got.name, expected.name);
+        assert_eq!(got.dirs, expected.dirs, "dirs of {name}");
+        assert_eq!(got.frames, expected.frames, "frames of {name}");
+        assert_eq!(got.delay, expected.delay, "delay of {name}");
+        assert_eq!(got.loop_flag, expected.loop_flag, "loop of {name}");
+        assert_eq!(got.rewind, expected.rewind, "rewind of {name}");
+        assert_eq!(got.movement, expected.movement, "movement of {name}");
+        assert_eq!(got.hotspot, expected.hotspot, "hotspot of {name}");
+        // Operations index images as frame * dirs + dir, so order matters as
+        // much as content
+        assert_eq!(got.images.len(), expected.images.len(), "images of {name}");
+        for (index, (expected, got)) in expected.images.iter().zip(&got.images).enumerate() {
+            assert_eq!(got.dimensions(), (3, 2), "image {index} of {name}");
+            assert_eq!(
+                got.to_rgba8(),
+                expected.to_rgba8(),
+                "image {index} of {name}"
+            );
+        }
+    }
+}
+
+#[test]
+fn save_rejects_inconsistent_states() {
+    // Operations lean on save to catch images that don't add up to
+    // dirs * frames, and animations without delays
+    let mut icon = representative_icon();
+    icon.states[1].images.pop();
+    assert!(icon.save(&mut vec![]).is_err());
+
+    let mut icon = representative_icon();
+    icon.states[2].delay = None;
+    assert!(icon.save(&mut vec![]).is_err());
+}
@@ -20,4 +20,8 @@
 pub mod config;
 pub mod generation;
 pub mod operations;
+pub mod process;
 pub mod util;
+
+#[cfg(test)]
+mod dmi_assumptions;
@@ -0,0 +1,321 @@
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::{OutputImage, ProcessorPayload};
+use crate::util::color::Color;
+use crate::util::dirs::Dir;
+
+fn south() -> String {
+    "south".to_string()
+}
+
+/// Expectations about what a config produces, checked once the whole
+/// pipeline has run, so configs double as tests of themselves. Every check
+/// that's set has to hold for every output.
+///
+/// Written as an `assert` table next to the other pipeline wide settings, like
+/// `assert = { width = 32, height = 32, required_states = ["open"] }`
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Assertions {
+    /// The output is exactly this wide. For dmis that's the width of a single
+    /// icon state, not the whole sheet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// The output is exactly this tall, same as `width`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Icon states the output has to have. Pngs don't have any, so fail this
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_states: Vec<String>,
+    /// The most icon states the output can have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_states: Option<usize>,
+    /// Pixels the output has to have, for pinning down the parts that matter
+    /// without a whole golden file to compare against
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pixels: Vec<PixelSample>,
+}
+
+/// One pixel an output has to have, like
+/// `{ state = "wall_masked", dir = "south", frame = 0, x = 4, y = 4, color =
+/// "#00000000" }`
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PixelSample {
+    /// Icon state to look in. Pngs don't have any, so leave it out for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// Direction to look in, by name
+    #[serde(default = "south")]
+    pub dir: String,
+    /// Frame to look in, counting from 0
+    #[serde(default)]
+    pub frame: u32,
+    /// Where the pixel is, counting from the top left corner
+    pub x: u32,
+    pub y: u32,
+    /// The color the pixel has to be. Fully transparent colors match every
+    /// fully transparent pixel, whatever color they hold
+    pub color: Color,
+}
+
+impl PixelSample {
+    /// Why `output` doesn't have this pixel, if it doesn't
+    fn unmet(&self, output: &OutputImage) -> Option<String> {
+        let (image, place) = match (output, &self.state) {
+            (OutputImage::Png(image), None) => (image, String::new()),
+            (OutputImage::Png(_), Some(state)) => {
+                return Some(format!(
+                    "is a png, so has no icon state \"{state}\" to sample"
+                ));
+            }
+            (OutputImage::Dmi(_), None) => {
+                return Some(format!(
+                    "is a dmi, so sampling {},{} needs a state",
+                    self.x, self.y
+                ));
+            }
+            (OutputImage::Dmi(icon), Some(state)) => {
+                let Some(found) = icon.states.iter().find(|found| &found.name == state) else {
+                    return Some(format!("has no icon state named \"{state}\" to sample"));
+                };
+                let Some(dir) = Dir::from_name(&self.dir).and_then(|dir| dir.index(found.dirs))
+                else {
+                    return Some(format!(
+                        "has no {} direction in icon state \"{state}\" to sample",
+                        self.dir
+                    ));
+                };
+                if self.frame >= found.frames {
+                    return Some(format!(
+                        "has no frame {} in icon state \"{state}\" to sample, only {}",
+                        self.frame, found.frames
+                    ));
+                }
+                let index = self.frame as usize * usize::from(found.dirs) + dir;
+                let Some(image) = found.images.get(index) else {
+                    return Some(format!("is missing images for icon state \"{state}\""));
+                };
+                (
+                    image,
+                    format!(" of \"{state}\" ({}, frame {})", self.dir, self.frame),
+                )
+            }
+        };
+        let (width, height) = image.dimensions();
+        if self.x >= width || self.y >= height {
+            return Some(format!(
+                "is {width}x{height}, too small to sample {},{}{place}",
+                self.x, self.y
+            ));
+        }
+        let actual = Color::from(image.get_pixel(self.x, self.y));
+        let matches = if self.color.alpha == 0 {
+            actual.alpha == 0
+        } else {
+            actual == self.color
+        };
+        if matches {
+            return None;
+        }
+        let found = if actual.alpha == 0 {
+            "is transparent".to_string()
+        } else {
+            format!("has {}", actual.to_hex_str())
+        };
+        Some(format!(
+            "{found} at {},{}{place}, not {}",
+            self.x,
+            self.y,
+            self.color.to_hex_str()
+        ))
+    }
+}
+
+impl Assertions {
+    /// # Errors
+    /// Returns `ProcessorError::AssertionsFailed` listing every check that
+    /// didn't hold
+    pub fn check(&self, payload: &ProcessorPayload) -> ProcessorResult<()> {
+        let outputs = payload.images();
+        let several = outputs.len() > 1;
+        let mut unmet = vec![];
+        for (index, output) in outputs.into_iter().enumerate() {
+            // Only worth saying which output when there's a choice
+            let which = if several {
+                format!("Output {} ", index + 1)
+            } else {
+                "Output ".to_string()
+            };
+            for failure in self.unmet(output) {
+                unmet.push(format!("{which}{failure}"));
+            }
+        }
+        if unmet.is_empty() {
+            return Ok(());
+        }
+        Err(ProcessorError::AssertionsFailed(unmet))
+    }
+
+    fn unmet(&self, output: &OutputImage) -> Vec<String> {
+        let (width, height) = match output {
+            OutputImage::Png(image) => image.dimensions(),
+            OutputImage::Dmi(icon) => (icon.width, icon.height),
+        };
+        let states: Vec<&str> = match output {
+            OutputImage::Png(_) => vec![],
+            OutputImage::Dmi(icon) => {
+                icon.states
+                    .iter()
+                    .map(|state| state.name.as_str())
+                    .collect()
+            }
+        };
+
+        let mut unmet = vec![];
+        if let Some(expected) = self.width.filter(|expected| *expected != width) {
+            unmet.push(format!("is {width} pixels wide, not {expected}"));
+        }
+        if let Some(expected) = self.height.filter(|expected| *expected != height) {
+            unmet.push(format!("is {height} pixels tall, not {expected}"));
+        }
+        for required in &self.required_states {
+            if !states.contains(&required.as_str()) {
+                unmet.push(format!("has no icon state named \"{required}\""));
+            }
+        }
+        if let Some(max) = self.max_states.filter(|max| states.len() > *max) {
+            unmet.push(format!(
+                "has {} icon states, more than the most of {max}",
+                states.len()
+            ));
+        }
+        unmet.extend(self.pixels.iter().filter_map(|sample| sample.unmet(output)));
+        unmet
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn door() -> ProcessorPayload {
+        ProcessorPayload::from_icon(Icon {
+            width: 32,
+            height: 32,
+            states: ["open", "closed"]
+                .into_iter()
+                .map(|name| {
+                    IconState {
+                        name: name.to_string(),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn met_assertions_pass() {
+        let assertions: Assertions = toml::from_str(
+            r#"
+            width = 32
+            height = 32
+            required_states = ["open", "closed"]
+            max_states = 2
+            "#,
+        )
+        .unwrap();
+        assert!(assertions.check(&door()).is_ok());
+    }
+
+    #[test]
+    fn every_unmet_assertion_is_listed() {
+        let assertions = Assertions {
+            width: Some(64),
+            required_states: vec!["broken".to_string()],
+            max_states: Some(1),
+            ..Default::default()
+        };
+        let Err(ProcessorError::AssertionsFailed(unmet)) = assertions.check(&door()) else {
+            panic!("Expected the assertions to fail");
+        };
+        assert_eq!(
+            unmet,
+            vec![
+                "Output is 32 pixels wide, not 64",
+                "Output has no icon state named \"broken\"",
+                "Output has 2 icon states, more than the most of 1",
+            ]
+        );
+    }
+
+    /// A red wall with 4 dirs and 2 frames, with a hole masked out of the
+    /// first south frame only
+    fn masked_wall() -> ProcessorPayload {
+        let images = (0..8)
+            .map(|index| {
+                let mut image = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+                if index == 0 {
+                    image.put_pixel(1, 1, Rgba([0, 0, 0, 0]));
+                }
+                DynamicImage::ImageRgba8(image)
+            })
+            .collect();
+        ProcessorPayload::from_icon(Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "wall_masked".to_string(),
+                dirs: 4,
+                frames: 2,
+                images,
+                delay: Some(vec![1.0, 1.0]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn pixels_are_sampled_from_their_dir_and_frame() {
+        let passing: Assertions = toml::from_str(
+            r##"
+            pixels = [
+                { state = "wall_masked", x = 1, y = 1, color = "#00000000" },
+                { state = "wall_masked", dir = "north", frame = 1, x = 1, y = 1, color = "#FF0000" },
+            ]
+            "##,
+        )
+        .unwrap();
+        assert!(passing.check(&masked_wall()).is_ok());
+
+        let failing: Assertions = toml::from_str(
+            r##"
+            pixels = [
+                { state = "wall_masked", dir = "north", x = 1, y = 1, color = "#00000000" },
+                { state = "wall_masked", x = 1, y = 1, color = "#FF0000" },
+                { state = "wall_masked", dir = "southeast", x = 0, y = 0, color = "#FF0000" },
+                { state = "wall", x = 0, y = 0, color = "#FF0000" },
+            ]
+            "##,
+        )
+        .unwrap();
+        let Err(ProcessorError::AssertionsFailed(unmet)) = failing.check(&masked_wall()) else {
+            panic!("Expected the assertions to fail");
+        };
+        assert_eq!(
+            unmet,
+            vec![
+                "Output has #FF0000FF at 1,1 of \"wall_masked\" (north, frame 0), not #00000000",
+                "Output is transparent at 1,1 of \"wall_masked\" (south, frame 0), not #FF0000FF",
+                "Output has no southeast direction in icon state \"wall_masked\" to sample",
+                "Output has no icon state named \"wall\" to sample",
+            ]
+        );
+    }
+}
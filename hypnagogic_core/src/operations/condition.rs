@@ -0,0 +1,121 @@
+use std::fmt;
+
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::InputIcon;
+
+/// A width and height, written as `"32x32"` in configs
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Dimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TryFrom<String> for Dimensions {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let parsed = value
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+        let Some((width, height)) = parsed else {
+            return Err(format!(
+                "Expected dimensions like \"32x32\", got \"{value}\""
+            ));
+        };
+        Ok(Self { width, height })
+    }
+}
+
+impl From<Dimensions> for String {
+    fn from(dimensions: Dimensions) -> Self {
+        dimensions.to_string()
+    }
+}
+
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Decides whether an operation in a pipeline runs, going by the icon it
+/// would be given. Every check that's set has to pass, so one with nothing set
+/// always passes.
+///
+/// Written as a `when` table on the operation, like
+/// `when = { dims = "32x32", has_state = "walk" }`
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Condition {
+    /// The icon is exactly this size. For dmis that's the size of a single
+    /// icon state, not the whole sheet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dims: Option<Dimensions>,
+    /// The icon is a dmi with an icon state of this name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_state: Option<String>,
+}
+
+impl Condition {
+    #[must_use]
+    pub fn matches(&self, input: &InputIcon) -> bool {
+        let (width, height) = match input {
+            InputIcon::DynamicImage(image) => image.dimensions(),
+            InputIcon::Dmi(icon) => (icon.width, icon.height),
+        };
+        let dims_match = self
+            .dims
+            .is_none_or(|dims| (dims.width, dims.height) == (width, height));
+        let state_matches = self.has_state.as_ref().is_none_or(|name| {
+            match input {
+                InputIcon::DynamicImage(_) => false,
+                InputIcon::Dmi(icon) => icon.states.iter().any(|state| &state.name == name),
+            }
+        });
+        dims_match && state_matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+
+    use super::*;
+
+    fn icon() -> InputIcon {
+        InputIcon::Dmi(Icon {
+            width: 32,
+            height: 32,
+            states: vec![IconState {
+                name: "walk".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn every_check_has_to_pass() {
+        let condition: Condition = toml::from_str(r#"dims = "32x32""#).unwrap();
+        assert!(condition.matches(&icon()));
+
+        let condition: Condition = toml::from_str(
+            r#"
+            dims = "32x32"
+            has_state = "run"
+            "#,
+        )
+        .unwrap();
+        assert!(!condition.matches(&icon()));
+
+        assert!(Condition::default().matches(&icon()));
+    }
+
+    #[test]
+    fn bad_dimensions_are_rejected() {
+        let error = toml::from_str::<Condition>(r#"dims = "32 by 32""#).unwrap_err();
+        assert!(error.to_string().contains("\"32x32\""));
+    }
+}
@@ -0,0 +1,95 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::operations::error::ProcessorWarning;
+
+/// Scratch space shared by every operation in a single run of a pipeline, so
+/// one operation can leave something behind for a later one to pick up. `Trim`
+/// records the bounds it cut to, for `Pad` to restore them afterwards.
+///
+/// Values are keyed by their type. By convention an operation stores its own
+/// type (like [`TrimmedBounds`]) rather than a plain `u32` or `String`, so
+/// unrelated operations can't trip over each other's values, and anything that
+/// doesn't know about a type just never looks for it. An operation reading a
+/// value should give a config error if it's missing, since that means the
+/// operation that writes it wasn't run first.
+///
+/// A fresh context is made for every pipeline run, so nothing carries over
+/// between icons.
+///
+/// Operations can also raise warnings through it, for problems that only show
+/// up once they see their input. The pipeline passes them on as each
+/// operation finishes.
+///
+/// [`TrimmedBounds`]: crate::operations::modifiers::trim::TrimmedBounds
+#[derive(Debug, Default)]
+pub struct PipelineContext {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    warnings: Vec<ProcessorWarning>,
+}
+
+impl PipelineContext {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value`, handing back whatever value of the same type was
+    /// there before
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// The stored value of type `T`, if there is one
+    #[must_use]
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Takes the stored value of type `T` out, if there is one
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Raises a warning about the input, for the user to hear about
+    pub fn warn(&mut self, warning: ProcessorWarning) {
+        self.warnings.push(warning);
+    }
+
+    /// Takes every warning raised since this was last called
+    pub fn take_warnings(&mut self) -> Vec<ProcessorWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Marker(u32);
+
+    #[test]
+    fn values_are_keyed_by_type() {
+        let mut context = PipelineContext::new();
+        assert_eq!(context.get::<Marker>(), None);
+
+        assert_eq!(context.insert(Marker(1)), None);
+        assert_eq!(context.insert(7_u32), None);
+        assert_eq!(context.get::<Marker>(), Some(&Marker(1)));
+        assert_eq!(context.get::<u32>(), Some(&7));
+
+        assert_eq!(context.insert(Marker(2)), Some(Marker(1)));
+        assert_eq!(context.remove::<Marker>(), Some(Marker(2)));
+        assert_eq!(context.get::<Marker>(), None);
+        assert_eq!(context.get::<u32>(), Some(&7));
+    }
+}
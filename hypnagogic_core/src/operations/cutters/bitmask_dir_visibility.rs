@@ -11,7 +11,7 @@ use crate::operations::cutters::bitmask_slice::{
     SIZE_OF_CARDINALS,
     SIZE_OF_DIAGONALS,
 };
-use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
 use crate::operations::{
     IconOperationConfig,
     InputIcon,
@@ -190,9 +190,9 @@ impl IconOperationConfig for BitmaskDirectionalVis {
         }
     }
 
-    fn verify_config(&self) -> ProcessorResult<()> {
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
         // TODO: actually verify config
-        Ok(())
+        Ok(vec![])
     }
 }
 
@@ -19,7 +19,8 @@ use crate::config::blocks::cutters::{
 };
 use crate::config::blocks::generators::MapIcon;
 use crate::generation::icon::generate_map_icon;
-use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::metadata::{MetadataValue, StateMetadata};
 use crate::operations::{
     IconOperationConfig,
     InputIcon,
@@ -114,10 +115,7 @@ impl IconOperationConfig for BitmaskSlice {
             .clone()
             .map(|x| repeat_for(&x.delays, num_frames as usize));
 
-        let states_to_gen = (0..possible_states)
-            .map(|x| Adjacency::from_bits(x as u8).unwrap())
-            .filter(Adjacency::ref_has_no_orphaned_corner);
-        for adjacency in states_to_gen {
+        for adjacency in self.states_to_generate() {
             let mut icon_state_frames = vec![];
 
             for icon_state_dir in &icon_directions {
@@ -126,14 +124,8 @@ impl IconOperationConfig for BitmaskSlice {
                 icon_state_frames.extend(assembled[&rotated_sig].clone());
             }
 
-            let signature = adjacency.bits();
-            let name = if let Some(prefix_name) = &self.output_name {
-                format!("{prefix_name}-{signature}")
-            } else {
-                format!("{signature}")
-            };
             icon_states.push(dedupe_frames(IconState {
-                name,
+                name: self.state_name(adjacency),
                 dirs: icon_directions.len() as u8,
                 frames: num_frames,
                 images: icon_state_frames,
@@ -172,12 +164,43 @@ impl IconOperationConfig for BitmaskSlice {
         }
     }
 
-    fn verify_config(&self) -> ProcessorResult<()> {
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
         // TODO: Actual verification
-        Ok(())
+        Ok(vec![])
+    }
+
+    fn describe_states(&self, _output: &ProcessorPayload) -> Vec<StateMetadata> {
+        self.states_to_generate()
+            .map(|adjacency| {
+                let connected = NAMED_DIRECTIONS
+                    .iter()
+                    .filter(|(direction, _)| adjacency.contains(*direction))
+                    .map(|(_, name)| (*name).to_string())
+                    .collect();
+                StateMetadata::new(self.state_name(adjacency))
+                    .with(
+                        "adjacency",
+                        MetadataValue::Number(u32::from(adjacency.bits())),
+                    )
+                    .with("connected", MetadataValue::List(connected))
+            })
+            .collect()
     }
 }
 
+/// Each direction a smoothed state can connect in, with the name it's
+/// described by
+const NAMED_DIRECTIONS: [(Adjacency, &str); 8] = [
+    (Adjacency::N, "north"),
+    (Adjacency::S, "south"),
+    (Adjacency::E, "east"),
+    (Adjacency::W, "west"),
+    (Adjacency::NE, "northeast"),
+    (Adjacency::SE, "southeast"),
+    (Adjacency::SW, "southwest"),
+    (Adjacency::NW, "northwest"),
+];
+
 type CornerPayload = Map<CornerType, Map<Corner, Vec<DynamicImage>>>;
 type PrefabPayload = HashMap<Adjacency, Vec<DynamicImage>>;
 
@@ -187,6 +210,28 @@ pub const SIZE_OF_CARDINALS: usize = usize::pow(2, 4);
 pub const SIZE_OF_DIAGONALS: usize = usize::pow(2, 8);
 
 impl BitmaskSlice {
+    /// Every adjacency that gets an icon state, in the order they're made
+    fn states_to_generate(&self) -> impl Iterator<Item = Adjacency> {
+        let possible_states = if self.smooth_diagonally {
+            SIZE_OF_DIAGONALS
+        } else {
+            SIZE_OF_CARDINALS
+        };
+        (0..possible_states)
+            .map(|x| Adjacency::from_bits(x as u8).unwrap())
+            .filter(Adjacency::ref_has_no_orphaned_corner)
+    }
+
+    /// Name of the icon state for `adjacency`
+    fn state_name(&self, adjacency: Adjacency) -> String {
+        let signature = adjacency.bits();
+        if let Some(prefix_name) = &self.output_name {
+            format!("{prefix_name}-{signature}")
+        } else {
+            format!("{signature}")
+        }
+    }
+
     #[tracing::instrument(skip(img))]
     pub fn build_corner(
         &self,
@@ -14,7 +14,7 @@ use crate::config::blocks::cutters::{
     Positions,
 };
 use crate::operations::cutters::bitmask_slice::{BitmaskSlice, SIZE_OF_DIAGONALS};
-use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
 use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
 use crate::util::adjacency::Adjacency;
 use crate::util::corners::CornerType;
@@ -157,8 +157,8 @@ impl IconOperationConfig for BitmaskWindows {
         Ok(ProcessorPayload::from_icon(icon))
     }
 
-    fn verify_config(&self) -> ProcessorResult<()> {
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
         // TODO: Actually verify config
-        Ok(())
+        Ok(vec![])
     }
 }
@@ -1,12 +1,22 @@
+use std::path::PathBuf;
+
+use enum_iterator::{all, Sequence};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use user_error::UFE;
 
+use crate::operations::format_converter::error::InconsistentDelay;
+use crate::operations::InputError;
+use crate::util::delays::text_delays;
+
 #[derive(Debug, Error)]
 pub enum ProcessorError {
     #[error("Image Error")]
     ImageNotFound,
     #[error("DMI Error")]
     DMINotFound,
+    #[error("Empty DMI")]
+    EmptyIcon,
     #[error("Image Processing Error")]
     ImageError(#[from] image::error::ImageError),
     #[error("Restoration Error")]
@@ -15,10 +25,197 @@ pub enum ProcessorError {
     GenerationFailed(#[from] crate::generation::error::GenerationError),
     #[error("Error within image config:\n{0}")]
     ConfigError(String),
+    #[error("Too Many Frames")]
+    TooManyFrames {
+        state: String,
+        frames: u32,
+        limit: u32,
+    },
+    #[error("Unmatched Target States")]
+    UnmatchedTargets(Vec<String>),
+    #[error("Wrong Frame Size")]
+    DimensionViolation {
+        state: String,
+        frame: u32,
+        expected: (u32, u32),
+        got: (u32, u32),
+    },
+    #[error("Assertions Failed")]
+    AssertionsFailed(Vec<String>),
+    #[error("Mismatched Delays")]
+    DelayMismatch {
+        state: String,
+        frames: u32,
+        delays: Vec<f32>,
+    },
+    #[error("Inconsistent Delays")]
+    InconsistentDelays {
+        reference: String,
+        frames: u32,
+        problems: Vec<InconsistentDelay>,
+    },
+    #[error("Too Many Colors")]
+    TooManyColors {
+        limit: u32,
+        /// Each state over the limit, with how many colors it has
+        states: Vec<(String, usize)>,
+    },
+    #[error("Over Frame Budget")]
+    OverFrameBudget {
+        limit: u32,
+        /// Each state over the limit, with how many frames it has
+        states: Vec<(String, u32)>,
+    },
+    #[error("Output Too Large")]
+    OutputTooLarge {
+        bytes: u64,
+        states: usize,
+        limit: u64,
+    },
+    #[error("Frame Loading Failed")]
+    FrameLoadFailed {
+        state: String,
+        path: PathBuf,
+        error: InputError,
+    },
 }
 
 pub type ProcessorResult<T> = Result<T, ProcessorError>;
 
+/// A stable name for each kind of error, for programs wrapping hypnagogic to
+/// branch on instead of matching against messages.
+///
+/// Once released, a code keeps its meaning and its [`ErrorCode::as_str`] name
+/// for good. Codes are never renamed, reused or removed, but new ones can be
+/// added in any release, so matches need a wildcard arm
+#[non_exhaustive]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ErrorCode {
+    /// An operation that needs a png was given a dmi
+    ExpectedImage,
+    /// An operation that needs a dmi was given a png
+    ExpectedDmi,
+    /// A dmi has no icon states
+    EmptyIcon,
+    /// Decoding or encoding an image failed
+    ImageError,
+    /// Turning a dmi back in to a png failed
+    RestorationFailed,
+    /// Generating an icon from a config failed
+    GenerationFailed,
+    /// A config is malformed or has bad values
+    InvalidConfig,
+    /// A config has no operations
+    EmptyPipeline,
+    /// An operation failed in some other way
+    ProcessingFailed,
+    /// An icon state has more frames than allowed
+    TooManyFrames,
+    /// A frame isn't the size its dmi says
+    DimensionViolation,
+    /// An icon state doesn't have one delay per frame
+    DelayMismatch,
+    /// The input a config is for couldn't be found
+    InputNotFound,
+    /// A template a config uses couldn't be found
+    TemplateNotFound,
+    /// The templates folder doesn't exist
+    NoTemplateFolder,
+    /// An input couldn't be read as an image
+    InputParsingFailed,
+    /// An output couldn't be written
+    OutputWriteFailed,
+    /// A named icon state isn't in the dmi
+    StateNotFound,
+    /// Fetching something over the network failed
+    Network,
+    /// A dmi has no embedded config
+    NoEmbeddedConfig,
+    /// A warning was raised, and warnings are errors
+    StrictWarning,
+    /// Reading or writing a file failed
+    Io,
+    /// An output didn't match what its config's `assert` expects
+    AssertionFailed,
+    /// Icon states that should share delays have different frame counts
+    InconsistentDelays,
+    /// An icon state uses more colors than its budget allows
+    TooManyColors,
+    /// An output changed more pixels than allowed since it was last written
+    TooMuchChange,
+    /// A dmi has icon states that no operation in its config touches
+    UnreferencedStates,
+    /// An output would come out differently than its lockfile records
+    LockMismatch,
+    /// An output is bigger than allowed, once encoded
+    OutputTooLarge,
+}
+
+impl ErrorCode {
+    /// The code's name in `snake_case`, for printing and machine output
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::ExpectedImage => "expected_image",
+            ErrorCode::ExpectedDmi => "expected_dmi",
+            ErrorCode::EmptyIcon => "empty_icon",
+            ErrorCode::ImageError => "image_error",
+            ErrorCode::RestorationFailed => "restoration_failed",
+            ErrorCode::GenerationFailed => "generation_failed",
+            ErrorCode::InvalidConfig => "invalid_config",
+            ErrorCode::EmptyPipeline => "empty_pipeline",
+            ErrorCode::ProcessingFailed => "processing_failed",
+            ErrorCode::TooManyFrames => "too_many_frames",
+            ErrorCode::DimensionViolation => "dimension_violation",
+            ErrorCode::DelayMismatch => "delay_mismatch",
+            ErrorCode::InputNotFound => "input_not_found",
+            ErrorCode::TemplateNotFound => "template_not_found",
+            ErrorCode::NoTemplateFolder => "no_template_folder",
+            ErrorCode::InputParsingFailed => "input_parsing_failed",
+            ErrorCode::OutputWriteFailed => "output_write_failed",
+            ErrorCode::StateNotFound => "state_not_found",
+            ErrorCode::Network => "network",
+            ErrorCode::NoEmbeddedConfig => "no_embedded_config",
+            ErrorCode::StrictWarning => "strict_warning",
+            ErrorCode::Io => "io",
+            ErrorCode::AssertionFailed => "assertion_failed",
+            ErrorCode::InconsistentDelays => "inconsistent_delays",
+            ErrorCode::TooManyColors => "too_many_colors",
+            ErrorCode::TooMuchChange => "too_much_change",
+            ErrorCode::UnreferencedStates => "unreferenced_states",
+            ErrorCode::LockMismatch => "lock_mismatch",
+            ErrorCode::OutputTooLarge => "output_too_large",
+        }
+    }
+}
+
+impl ProcessorError {
+    /// Which kind of error this is, see [`ErrorCode`]
+    #[must_use]
+    pub const fn code(&self) -> ErrorCode {
+        match self {
+            ProcessorError::ImageNotFound => ErrorCode::ExpectedImage,
+            ProcessorError::DMINotFound => ErrorCode::ExpectedDmi,
+            ProcessorError::EmptyIcon => ErrorCode::EmptyIcon,
+            ProcessorError::ImageError(_) => ErrorCode::ImageError,
+            ProcessorError::RestorationFailed(_) => ErrorCode::RestorationFailed,
+            ProcessorError::GenerationFailed(_) => ErrorCode::GenerationFailed,
+            ProcessorError::ConfigError(_) => ErrorCode::InvalidConfig,
+            ProcessorError::TooManyFrames { .. } | ProcessorError::OverFrameBudget { .. } => {
+                ErrorCode::TooManyFrames
+            }
+            ProcessorError::DimensionViolation { .. } => ErrorCode::DimensionViolation,
+            ProcessorError::UnmatchedTargets(_) => ErrorCode::StateNotFound,
+            ProcessorError::AssertionsFailed(_) => ErrorCode::AssertionFailed,
+            ProcessorError::DelayMismatch { .. } => ErrorCode::DelayMismatch,
+            ProcessorError::InconsistentDelays { .. } => ErrorCode::InconsistentDelays,
+            ProcessorError::TooManyColors { .. } => ErrorCode::TooManyColors,
+            ProcessorError::OutputTooLarge { .. } => ErrorCode::OutputTooLarge,
+            ProcessorError::FrameLoadFailed { .. } => ErrorCode::InputParsingFailed,
+        }
+    }
+}
+
 impl UFE for ProcessorError {
     fn summary(&self) -> String {
         format!("{self}")
@@ -32,10 +229,111 @@ impl UFE for ProcessorError {
             ProcessorError::DMINotFound => {
                 Some(vec!["This operation only accepts DMIs".to_string()])
             }
+            ProcessorError::EmptyIcon => {
+                Some(vec!["This operation works on icon states, but the input \
+                           DMI doesn't have any"
+                    .to_string()])
+            }
             ProcessorError::ImageError(error) => Some(vec![format!("{}", error)]),
             ProcessorError::RestorationFailed(error) => error.reasons(),
             ProcessorError::GenerationFailed(error) => error.reasons(),
             ProcessorError::ConfigError(config) => Some(vec![format!("{}", config)]),
+            ProcessorError::TooManyFrames {
+                state,
+                frames,
+                limit,
+            } => {
+                Some(vec![format!(
+                    "Icon state \"{state}\" has {frames} frames, more than the limit of {limit}"
+                )])
+            }
+            ProcessorError::UnmatchedTargets(targets) => {
+                Some(
+                    targets
+                        .iter()
+                        .map(|target| format!("No icon state matches \"{target}\""))
+                        .collect(),
+                )
+            }
+            ProcessorError::DimensionViolation {
+                state,
+                frame,
+                expected: (expected_width, expected_height),
+                got: (width, height),
+            } => {
+                Some(vec![format!(
+                    "Frame {frame} of icon state \"{state}\" is {width}x{height}, but the DMI is \
+                     {expected_width}x{expected_height}"
+                )])
+            }
+            ProcessorError::AssertionsFailed(unmet) => Some(unmet.clone()),
+            ProcessorError::DelayMismatch {
+                state,
+                frames,
+                delays,
+            } => {
+                Some(vec![format!(
+                    "Icon state \"{state}\" has {frames} frames, but {} delays: {}",
+                    delays.len(),
+                    text_delays(delays, "ds")
+                )])
+            }
+            ProcessorError::InconsistentDelays {
+                reference,
+                frames,
+                problems,
+            } => {
+                let mut reasons = vec![format!(
+                    "The delays come from icon state \"{reference}\", with {frames} frames"
+                )];
+                for problem in problems {
+                    reasons.push(format!(
+                        "Icon state \"{}\" has {} frames, with delays {}",
+                        problem.state,
+                        problem.frames,
+                        text_delays(&problem.delays, "ds")
+                    ));
+                }
+                Some(reasons)
+            }
+            ProcessorError::TooManyColors { limit, states } => {
+                Some(
+                    states
+                        .iter()
+                        .map(|(state, colors)| {
+                            format!("Icon state \"{state}\" uses {colors} colors, over {limit}")
+                        })
+                        .collect(),
+                )
+            }
+            ProcessorError::OverFrameBudget { limit, states } => {
+                Some(
+                    states
+                        .iter()
+                        .map(|(state, frames)| {
+                            format!("Icon state \"{state}\" has {frames} frames, over {limit}")
+                        })
+                        .collect(),
+                )
+            }
+            ProcessorError::OutputTooLarge {
+                bytes,
+                states,
+                limit,
+            } => {
+                Some(vec![format!(
+                    "Output is {bytes} bytes with {states} icon states, more than the limit of \
+                     {limit} bytes"
+                )])
+            }
+            ProcessorError::FrameLoadFailed { state, path, error } => {
+                let mut reasons = vec![format!(
+                    "Couldn't load \"{}\" as a frame for icon state \"{state}\"",
+                    path.display()
+                )];
+                reasons.extend(error.reasons().unwrap_or_default());
+                Some(reasons)
+            }
         }
     }
 
@@ -53,12 +351,357 @@ impl UFE for ProcessorError {
                         .to_string(),
                 )
             }
+            ProcessorError::EmptyIcon => {
+                Some(
+                    "Check that the right DMI is being used, and that it saved properly"
+                        .to_string(),
+                )
+            }
             ProcessorError::ImageError(_) => None,
             ProcessorError::RestorationFailed(error) => error.helptext(),
             ProcessorError::GenerationFailed(error) => error.helptext(),
             ProcessorError::ConfigError(_config) => {
                 Some("TBH this needs to be its own error type".to_string())
             }
+            ProcessorError::TooManyFrames { .. } => {
+                Some(
+                    "Make sure this is the right input, or raise --max-frame-count if the \
+                     animation really is that long"
+                        .to_string(),
+                )
+            }
+            ProcessorError::UnmatchedTargets(_) => {
+                Some(
+                    "Check target_states for typos, or set require_match = false if some inputs \
+                     are expected to lack these states"
+                        .to_string(),
+                )
+            }
+            ProcessorError::DimensionViolation { .. } => {
+                Some(
+                    "The DMI is malformed. Re-save it from an editor, or restore it from a good \
+                     copy"
+                        .to_string(),
+                )
+            }
+            ProcessorError::AssertionsFailed(_) => {
+                Some(
+                    "Either the config no longer does what it was meant to, or its assert table \
+                     needs updating"
+                        .to_string(),
+                )
+            }
+            ProcessorError::DelayMismatch { .. } => {
+                Some(
+                    "Add a FixDelays operation to pad or cut the delays to fit, or re-save the \
+                     DMI from an editor"
+                        .to_string(),
+                )
+            }
+            ProcessorError::InconsistentDelays { .. } => {
+                Some(
+                    "Only states with the same number of frames can share delays. Leave the \
+                     others out of target_states, or fix their frame counts first"
+                        .to_string(),
+                )
+            }
+            ProcessorError::TooManyColors { .. } => {
+                Some(
+                    "Reduce the colors by hand, or set on_overflow = \"quantize\" to have them \
+                     reduced automatically"
+                        .to_string(),
+                )
+            }
+            ProcessorError::OverFrameBudget { .. } => {
+                Some(
+                    "Cut the animations down, or set on_overflow = \"warn\" to only warn about \
+                     them"
+                        .to_string(),
+                )
+            }
+            ProcessorError::OutputTooLarge { .. } => {
+                Some(
+                    "The output wasn't written. Check for an operation scaling or tiling by more \
+                     than intended, or raise --max-output-bytes"
+                        .to_string(),
+                )
+            }
+            ProcessorError::FrameLoadFailed { error, .. } => {
+                error.helptext().or_else(|| {
+                    Some(
+                        "Check the path is right. Relative paths start from the folder hypnagogic \
+                         is run in"
+                            .to_string(),
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// A stable name for each kind of warning, for configs and the command line to
+/// allow warnings by. Held to the same promises as [`ErrorCode`]
+#[non_exhaustive]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Sequence, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// Icon states that couldn't be decoded were left out
+    SkippedCorruptStates,
+    /// An icon state has more frames than the warning limit
+    LongAnimation,
+    /// An output is bigger than the warning limit
+    LargeOutput,
+    /// An icon state's name is longer than the warning limit
+    LongStateName,
+    /// A mask has pixels that are only partly transparent
+    SoftMask,
+    /// A config value is allowed, but is probably a mistake
+    SuspiciousConfig,
+    /// A config with no operations was let through
+    EmptyConfig,
+}
+
+impl WarningCode {
+    /// The name of this code, as configs write it
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            WarningCode::SkippedCorruptStates => "skipped_corrupt_states",
+            WarningCode::LongAnimation => "long_animation",
+            WarningCode::LargeOutput => "large_output",
+            WarningCode::LongStateName => "long_state_name",
+            WarningCode::SoftMask => "soft_mask",
+            WarningCode::SuspiciousConfig => "suspicious_config",
+            WarningCode::EmptyConfig => "empty_config",
+        }
+    }
+
+    /// The code called `name`, if there is one
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        all::<WarningCode>().find(|code| code.as_str() == name)
+    }
+}
+
+/// Problems that don't stop processing, but that the user should hear about
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProcessorWarning {
+    #[error("Skipped Corrupt Icon States")]
+    SkippedCorruptStates(Vec<String>),
+    #[error("Long Animation")]
+    LongAnimation {
+        state: String,
+        frames: u32,
+        limit: u32,
+    },
+    #[error("Large Output")]
+    LargeOutput {
+        bytes: u64,
+        states: usize,
+        limit: u64,
+    },
+    #[error("Long Icon State Name")]
+    LongStateName {
+        state: String,
+        length: usize,
+        limit: usize,
+    },
+    /// A mask with pixels that are only partly transparent
+    #[error("Soft Mask")]
+    SoftMask { state: String, pixels: usize },
+    /// A config value that's allowed, but is probably a mistake
+    #[error("Suspicious Config")]
+    SuspiciousConfig(String),
+    /// A config with no operations, that was let through anyway
+    #[error("Empty Config")]
+    EmptyConfig,
+    /// A warning from one of the operations in a pipeline
+    #[error("{warning}")]
+    InOperation {
+        /// Where the operation sits in the pipeline, starting from 0
+        index: usize,
+        mode: &'static str,
+        warning: Box<ProcessorWarning>,
+    },
+}
+
+impl ProcessorWarning {
+    /// Which kind of warning this is, see [`WarningCode`]. Warnings from an
+    /// operation have the code of what the operation raised
+    #[must_use]
+    pub fn code(&self) -> WarningCode {
+        match self {
+            ProcessorWarning::SkippedCorruptStates(_) => WarningCode::SkippedCorruptStates,
+            ProcessorWarning::LongAnimation { .. } => WarningCode::LongAnimation,
+            ProcessorWarning::LargeOutput { .. } => WarningCode::LargeOutput,
+            ProcessorWarning::LongStateName { .. } => WarningCode::LongStateName,
+            ProcessorWarning::SoftMask { .. } => WarningCode::SoftMask,
+            ProcessorWarning::SuspiciousConfig(_) => WarningCode::SuspiciousConfig,
+            ProcessorWarning::EmptyConfig => WarningCode::EmptyConfig,
+            ProcessorWarning::InOperation { warning, .. } => warning.code(),
+        }
+    }
+}
+
+impl UFE for ProcessorWarning {
+    fn summary(&self) -> String {
+        format!("{self}")
+    }
+
+    fn reasons(&self) -> Option<Vec<String>> {
+        match self {
+            ProcessorWarning::SkippedCorruptStates(states) => {
+                Some(vec![format!(
+                    "The pixel data for the following icon states could not be decoded, so they \
+                     were left out: [{}]",
+                    states.join(", ")
+                )])
+            }
+            ProcessorWarning::LongAnimation {
+                state,
+                frames,
+                limit,
+            } => {
+                Some(vec![format!(
+                    "Icon state \"{state}\" has {frames} frames, more than the warning limit of \
+                     {limit}"
+                )])
+            }
+            ProcessorWarning::LargeOutput {
+                bytes,
+                states,
+                limit,
+            } => {
+                Some(vec![format!(
+                    "Output is {} with {states} icon states, more than the warning limit of {}",
+                    megabytes(*bytes),
+                    megabytes(*limit)
+                )])
+            }
+            ProcessorWarning::LongStateName {
+                state,
+                length,
+                limit,
+            } => {
+                Some(vec![format!(
+                    "Icon state \"{state}\" has a {length} character name, more than the warning \
+                     limit of {limit}"
+                )])
+            }
+            ProcessorWarning::SoftMask { state, pixels } => {
+                Some(vec![format!(
+                    "Mask icon state \"{state}\" has {pixels} pixels that are neither fully \
+                     opaque nor fully transparent"
+                )])
+            }
+            ProcessorWarning::SuspiciousConfig(reason) => Some(vec![reason.clone()]),
+            ProcessorWarning::EmptyConfig => {
+                Some(vec!["The config has no operations, so the input was \
+                           copied through unchanged"
+                    .to_string()])
+            }
+            ProcessorWarning::InOperation {
+                index,
+                mode,
+                warning,
+            } => {
+                let mut reasons = vec![format!("Operation {} ({mode})", index + 1)];
+                for reason in warning.reasons().unwrap_or_default() {
+                    reasons.push(format!("    {reason}"));
+                }
+                Some(reasons)
+            }
+        }
+    }
+
+    fn helptext(&self) -> Option<String> {
+        match self {
+            ProcessorWarning::SkippedCorruptStates(_) => {
+                Some(
+                    "The input dmi is damaged. Restore it from a good copy if you need these \
+                     states"
+                        .to_string(),
+                )
+            }
+            ProcessorWarning::LongAnimation { .. } => {
+                Some(
+                    "Long animations are slow to process. Make sure this is the right input"
+                        .to_string(),
+                )
+            }
+            ProcessorWarning::LargeOutput { .. } => {
+                Some("Check for an operation scaling or tiling by more than intended".to_string())
+            }
+            ProcessorWarning::LongStateName { .. } => {
+                Some(
+                    "Shorten the input state's name, or the suffix the config adds to it"
+                        .to_string(),
+                )
+            }
+            ProcessorWarning::SoftMask { .. } => {
+                Some(
+                    "Masks select every pixel that isn't fully transparent, so these are masked \
+                     in as if they were opaque. Make them fully opaque or fully transparent"
+                        .to_string(),
+                )
+            }
+            ProcessorWarning::SuspiciousConfig(_) => {
+                Some("Double check the config does what you meant it to".to_string())
+            }
+            ProcessorWarning::EmptyConfig => {
+                Some(
+                    "Add some operations to the config, or delete it if it's not needed"
+                        .to_string(),
+                )
+            }
+            ProcessorWarning::InOperation { warning, .. } => warning.helptext(),
+        }
+    }
+}
+
+/// Formats a byte count for people to read
+#[allow(clippy::cast_precision_loss)]
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes_are_stable() {
+        // These names are promised to wrapping programs, so changing any of
+        // them is a breaking change
+        assert_eq!(
+            ProcessorError::ConfigError(String::new()).code().as_str(),
+            "invalid_config"
+        );
+        assert_eq!(ProcessorError::EmptyIcon.code().as_str(), "empty_icon");
+        assert_eq!(
+            ProcessorError::TooManyFrames {
+                state: String::new(),
+                frames: 2,
+                limit: 1,
+            }
+            .code(),
+            ErrorCode::TooManyFrames
+        );
+    }
+
+    #[test]
+    fn warning_codes_match_their_config_names() {
+        for code in all::<WarningCode>() {
+            let written = toml::Value::try_from(code).unwrap();
+            assert_eq!(written.as_str(), Some(code.as_str()));
+            assert_eq!(WarningCode::from_name(code.as_str()), Some(code));
         }
+        let nested = ProcessorWarning::InOperation {
+            index: 0,
+            mode: "Blur",
+            warning: Box::new(ProcessorWarning::SuspiciousConfig(String::new())),
+        };
+        assert_eq!(nested.code(), WarningCode::SuspiciousConfig);
+        assert_eq!(WarningCode::from_name("not_a_warning"), None);
     }
 }
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::config::blocks::cutters::StringMap;
-use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
 use crate::operations::format_converter::error::{InconsistentDelay, RestrorationError};
 use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
 use crate::util::delays::text_delays;
@@ -28,9 +28,7 @@ impl IconOperationConfig for BitmaskSliceReconstruct {
         mode: OperationMode,
     ) -> ProcessorResult<ProcessorPayload> {
         debug!("Starting bitmask slice reconstruction");
-        let InputIcon::Dmi(icon) = input else {
-            return Err(ProcessorError::DMINotFound);
-        };
+        let icon = input.require_states()?;
 
         // First, pull out icon states from DMI
         let states = icon.states.clone();
@@ -167,6 +165,7 @@ impl IconOperationConfig for BitmaskSliceReconstruct {
             if delays != state.delay {
                 problem_states.push(InconsistentDelay {
                     state: state.name,
+                    frames: state.frames,
                     delays: state.delay.unwrap_or_default(),
                 });
                 continue;
@@ -232,8 +231,8 @@ impl IconOperationConfig for BitmaskSliceReconstruct {
         ))
     }
 
-    fn verify_config(&self) -> ProcessorResult<()> {
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
         // TODO: Actual verification
-        Ok(())
+        Ok(vec![])
     }
 }
@@ -6,6 +6,7 @@ use crate::util::delays::text_delays;
 #[derive(Debug)]
 pub struct InconsistentDelay {
     pub state: String,
+    pub frames: u32,
     pub delays: Vec<f32>,
 }
 
@@ -0,0 +1,262 @@
+use dmi::icon::Icon;
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+
+/// Guards against running operations on absurdly long animations, which are
+/// slow, eat memory, and are usually a mistake
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct FrameLimits {
+    /// Warn about any icon state with more frames than this
+    pub warn_above: Option<u32>,
+    /// Refuse to process any icon state with more frames than this
+    pub max: Option<u32>,
+}
+
+impl FrameLimits {
+    /// Checks every icon state in `icon` against the limits
+    /// # Errors
+    /// Returns `ProcessorError::TooManyFrames` for the first icon state over
+    /// `max`
+    pub fn check(&self, icon: &Icon) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let mut warnings = vec![];
+        for state in &icon.states {
+            if let Some(max) = self.max.filter(|&max| state.frames > max) {
+                return Err(ProcessorError::TooManyFrames {
+                    state: state.name.clone(),
+                    frames: state.frames,
+                    limit: max,
+                });
+            }
+            if let Some(limit) = self.warn_above.filter(|&limit| state.frames > limit) {
+                warnings.push(ProcessorWarning::LongAnimation {
+                    state: state.name.clone(),
+                    frames: state.frames,
+                    limit,
+                });
+            }
+        }
+        Ok(warnings)
+    }
+}
+
+/// Default for [`OutputSizeLimit::warn_above`], 16MiB
+pub const DEFAULT_OUTPUT_SIZE_WARNING: u64 = 16 * 1024 * 1024;
+
+/// Catches outputs that came out far bigger than any icon should, usually
+/// from scaling or tiling something by the wrong amount, and keeps outputs
+/// inside a size budget
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct OutputSizeLimit {
+    /// Warn about any output bigger than this many bytes, once encoded
+    pub warn_above: u64,
+    /// Refuse to write any output bigger than this many bytes, once encoded
+    pub max: Option<u64>,
+}
+
+impl Default for OutputSizeLimit {
+    fn default() -> Self {
+        Self {
+            warn_above: DEFAULT_OUTPUT_SIZE_WARNING,
+            max: None,
+        }
+    }
+}
+
+impl OutputSizeLimit {
+    /// Checks the size of an encoded output with `states` icon states in it
+    /// # Errors
+    /// Returns `ProcessorError::OutputTooLarge` if it's over `max`
+    pub fn check(&self, bytes: u64, states: usize) -> ProcessorResult<Option<ProcessorWarning>> {
+        if let Some(max) = self.max.filter(|&max| bytes > max) {
+            return Err(ProcessorError::OutputTooLarge {
+                bytes,
+                states,
+                limit: max,
+            });
+        }
+        Ok(
+            (bytes > self.warn_above).then_some(ProcessorWarning::LargeOutput {
+                bytes,
+                states,
+                limit: self.warn_above,
+            }),
+        )
+    }
+}
+
+/// Default for [`StateNameLimit::warn_above`], in characters
+pub const DEFAULT_STATE_NAME_WARNING: usize = 100;
+
+/// Catches icon state names that grew too long to be practical in BYOND,
+/// usually from an operation adding a suffix to an already long name
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct StateNameLimit {
+    /// Warn about any icon state with a name longer than this many characters
+    pub warn_above: usize,
+}
+
+impl Default for StateNameLimit {
+    fn default() -> Self {
+        Self {
+            warn_above: DEFAULT_STATE_NAME_WARNING,
+        }
+    }
+}
+
+impl StateNameLimit {
+    /// Checks the name of every icon state in `icon`
+    #[must_use]
+    pub fn check(&self, icon: &Icon) -> Vec<ProcessorWarning> {
+        icon.states
+            .iter()
+            .filter_map(|state| {
+                let length = state.name.chars().count();
+                (length > self.warn_above).then(|| {
+                    ProcessorWarning::LongStateName {
+                        state: state.name.clone(),
+                        length,
+                        limit: self.warn_above,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::IconState;
+
+    use super::*;
+
+    fn icon(frames: &[u32]) -> Icon {
+        Icon {
+            states: frames
+                .iter()
+                .enumerate()
+                .map(|(index, &frames)| {
+                    IconState {
+                        name: format!("state{index}"),
+                        frames,
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn warns_past_threshold() {
+        let limits = FrameLimits {
+            warn_above: Some(10),
+            max: None,
+        };
+        let warnings = limits.check(&icon(&[1, 10, 11, 400])).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                ProcessorWarning::LongAnimation {
+                    state: "state2".to_string(),
+                    frames: 11,
+                    limit: 10,
+                },
+                ProcessorWarning::LongAnimation {
+                    state: "state3".to_string(),
+                    frames: 400,
+                    limit: 10,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_past_max() {
+        let limits = FrameLimits {
+            warn_above: Some(2),
+            max: Some(10),
+        };
+        assert_eq!(limits.check(&icon(&[1, 10])).unwrap().len(), 1);
+
+        let Err(ProcessorError::TooManyFrames {
+            state,
+            frames,
+            limit,
+        }) = limits.check(&icon(&[1, 11]))
+        else {
+            panic!("Expected the frame limit to be hit");
+        };
+        assert_eq!((state.as_str(), frames, limit), ("state1", 11, 10));
+    }
+
+    #[test]
+    fn warns_about_large_outputs() {
+        let limit = OutputSizeLimit {
+            warn_above: 1000,
+            max: None,
+        };
+        assert_eq!(limit.check(1000, 4).unwrap(), None);
+        assert_eq!(
+            limit.check(1001, 4).unwrap(),
+            Some(ProcessorWarning::LargeOutput {
+                bytes: 1001,
+                states: 4,
+                limit: 1000,
+            })
+        );
+        assert_eq!(
+            OutputSizeLimit::default().check(5_000_000, 400).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn errors_past_max_output_size() {
+        let limit = OutputSizeLimit {
+            warn_above: 1000,
+            max: Some(2000),
+        };
+        assert!(limit.check(2000, 4).unwrap().is_some());
+
+        let Err(ProcessorError::OutputTooLarge {
+            bytes,
+            states,
+            limit,
+        }) = limit.check(2001, 4)
+        else {
+            panic!("Expected the size limit to be hit");
+        };
+        assert_eq!((bytes, states, limit), (2001, 4, 2000));
+    }
+
+    #[test]
+    fn warns_about_long_generated_names() {
+        let limit = StateNameLimit { warn_above: 16 };
+        let named = |name: &str| {
+            Icon {
+                states: vec![IconState {
+                    name: name.to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        };
+        assert!(limit.check(&named("wall_emissive")).is_empty());
+        assert_eq!(
+            limit.check(&named("reinforced_wall_emissive")),
+            vec![ProcessorWarning::LongStateName {
+                state: "reinforced_wall_emissive".to_string(),
+                length: 24,
+                limit: 16,
+            }]
+        );
+    }
+
+    #[test]
+    fn no_limits_by_default() {
+        assert!(FrameLimits::default()
+            .check(&icon(&[10_000]))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use dmi::icon::Icon;
+
+/// A value describing an icon state, kept to what's easy to write out in any
+/// format
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MetadataValue {
+    Number(u32),
+    Text(String),
+    List(Vec<String>),
+}
+
+/// What an icon state made by an operation means, like which neighbours a
+/// smoothing state is for. Only operations whose state names don't say it all
+/// describe their states, see
+/// [`IconOperationConfig::describe_states`](crate::operations::IconOperationConfig::describe_states)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StateMetadata {
+    /// Name of the icon state
+    pub state: String,
+    /// Named facts about the state, in the order they were given
+    pub fields: Vec<(&'static str, MetadataValue)>,
+}
+
+impl StateMetadata {
+    #[must_use]
+    pub fn new(state: String) -> Self {
+        Self {
+            state,
+            fields: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn with(mut self, key: &'static str, value: MetadataValue) -> Self {
+        self.fields.push((key, value));
+        self
+    }
+}
+
+/// Everything the operations in one run of a pipeline had to say about their
+/// icon states. A state described by more than one operation keeps what the
+/// last of them said, since that's the one that made what ends up in the output
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Metadata {
+    pub states: Vec<StateMetadata>,
+}
+
+impl Metadata {
+    /// Adds the descriptions of one operation's states, replacing anything
+    /// said about them before
+    pub fn extend(&mut self, described: Vec<StateMetadata>) {
+        for metadata in described {
+            match self
+                .states
+                .iter_mut()
+                .find(|existing| existing.state == metadata.state)
+            {
+                Some(existing) => *existing = metadata,
+                None => self.states.push(metadata),
+            }
+        }
+    }
+
+    /// The descriptions of the states in `icon`, in the order they were
+    /// described
+    pub fn for_icon<'a>(&'a self, icon: &'a Icon) -> impl Iterator<Item = &'a StateMetadata> + 'a {
+        self.states
+            .iter()
+            .filter(|metadata| icon.states.iter().any(|state| state.name == metadata.state))
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::IconState;
+
+    use super::*;
+
+    #[test]
+    fn later_descriptions_win() {
+        let mut metadata = Metadata::default();
+        metadata.extend(vec![
+            StateMetadata::new("wall".to_string()).with("cell", MetadataValue::Number(1)),
+            StateMetadata::new("floor".to_string()).with("cell", MetadataValue::Number(2)),
+        ]);
+        metadata.extend(vec![StateMetadata::new("wall".to_string())
+            .with("side", MetadataValue::Text("north".to_string()))]);
+
+        let icon = Icon {
+            states: vec![IconState {
+                name: "wall".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let described: Vec<&StateMetadata> = metadata.for_icon(&icon).collect();
+        assert_eq!(
+            described,
+            vec![&StateMetadata::new("wall".to_string())
+                .with("side", MetadataValue::Text("north".to_string()))]
+        );
+    }
+}
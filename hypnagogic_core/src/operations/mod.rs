@@ -1,5 +1,5 @@
 use std::fmt::Debug;
-use std::io::{BufRead, Seek};
+use std::io::{BufRead, Seek, Write};
 use std::path::{Path, PathBuf};
 
 use cutters::bitmask_dir_visibility::BitmaskDirectionalVis;
@@ -7,19 +7,95 @@ use cutters::bitmask_slice::BitmaskSlice;
 use cutters::bitmask_windows::BitmaskWindows;
 use dmi::error::DmiError;
 use dmi::icon::Icon;
+use dmi::RawDmi;
 use enum_dispatch::enum_dispatch;
 use format_converter::bitmask_to_precut::BitmaskSliceReconstruct;
-use image::{DynamicImage, ImageError, ImageFormat};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, ImageError, ImageFormat, ImageOutputFormat};
+use modifiers::alpha_blur::AlphaBlur;
+use modifiers::apply_to_all::ApplyLayer;
+use modifiers::balance_directions::BalanceDirections;
+use modifiers::blur::Blur;
+use modifiers::cap_alpha::CapAlpha;
+use modifiers::center::Center;
+use modifiers::channel_merge::ChannelMerge;
+use modifiers::channel_split::ChannelSplit;
+use modifiers::checker::CheckerBake;
+use modifiers::clamp_frames::ClampFrames;
+use modifiers::color_budget::ColorBudget;
+use modifiers::concat::Concat;
+use modifiers::contact_state::ContactState;
+use modifiers::crop_hotspot::CropHotspot;
+use modifiers::curves::Curves;
+use modifiers::damage::Damage;
+use modifiers::dedup_states::DedupStates;
+use modifiers::defringe::Defringe;
+use modifiers::dir_contact::DirContact;
+use modifiers::dir_convention::DirConvention;
+use modifiers::dir_overlay::DirOverlay;
+use modifiers::dir_tint::DirTint;
+use modifiers::drop_frames::DropFrames;
+use modifiers::emissive::Emissive;
+use modifiers::enforce_aspect::EnforceAspect;
+use modifiers::fix_delays::FixDelays;
+use modifiers::freeze::Freeze;
+use modifiers::gamma::Gamma;
+use modifiers::glow::Glow;
+use modifiers::gradient_map::GradientMap;
+use modifiers::hsv::HsvShift;
+use modifiers::import_strip::ImportStrip;
+use modifiers::max_frames::MaxFrames;
+use modifiers::mirror_dirs::MirrorDirs;
+use modifiers::morphology::Morphology;
+use modifiers::normal_map::NormalFromHeight;
+use modifiers::normalize_header::NormalizeHeader;
+use modifiers::optimize_dirs::OptimizeDirs;
+use modifiers::overlay::Overlay;
+use modifiers::overlay_loop::OverlayLoop;
+use modifiers::pad::Pad;
+use modifiers::palette_cycle::PaletteCycle;
+use modifiers::passthrough::Passthrough;
+use modifiers::posterize::Posterize;
+use modifiers::region_adjust::RegionAdjust;
+use modifiers::relative_crop::RelativeCrop;
+use modifiers::renumber::Renumber;
+use modifiers::reorder_dirs::ReorderDirs;
+use modifiers::replace_frames::ReplaceFrames;
+use modifiers::scale_xy::ScaleXY;
+use modifiers::self_mask::SelfMask;
+use modifiers::shape_mask::ShapeMask;
+use modifiers::silhouette::Silhouette;
+use modifiers::snap_to_grid::SnapToGrid;
+use modifiers::split_emissive::SplitEmissive;
+use modifiers::subsample::Subsample;
+use modifiers::swap_channels::SwapChannels;
+use modifiers::sync_delays::SyncDelays;
+use modifiers::threshold_alpha::ThresholdAlpha;
+use modifiers::tile::Tile;
+use modifiers::trim::Trim;
+use modifiers::validate_dimensions::ValidateDimensions;
+use modifiers::validate_mask::ValidateMask;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::debug;
 use user_error::UFE;
 
-use crate::operations::error::ProcessorResult;
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::context::PipelineContext;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::metadata::StateMetadata;
+use crate::util::dmi_recovery::{chunks_in_bounds, load_recovering, RecoveredIcon};
 
+pub mod assertions;
+pub mod condition;
+pub mod context;
 pub mod cutters;
 pub mod error;
 pub mod format_converter;
+pub mod limits;
+pub mod metadata;
+pub mod modifiers;
+pub mod pipeline;
 
 #[derive(Debug, Error)]
 pub enum InputError {
@@ -28,7 +104,64 @@ pub enum InputError {
     #[error("Image Reading Error")]
     DynamicRead(#[from] ImageError),
     #[error("DMI Parsing Error")]
-    DmiRead(#[from] DmiError),
+    DmiRead { stage: DmiStage, error: DmiError },
+}
+
+/// How far loading a dmi got before it failed
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DmiStage {
+    /// The file couldn't be read at all
+    Reading,
+    /// It isn't a png, or its png data is cut short or corrupt
+    Png,
+    /// It's a readable png, but not a readable dmi
+    Icon,
+}
+
+impl DmiStage {
+    /// Works out which stage loading `bytes` as a dmi fails at, for a dmi
+    /// that's already failed to load
+    #[must_use]
+    pub fn of(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&PNG_SIGNATURE)
+            && chunks_in_bounds(bytes)
+            && RawDmi::load(bytes).is_ok()
+        {
+            DmiStage::Icon
+        } else {
+            DmiStage::Png
+        }
+    }
+
+    fn reason(self) -> &'static str {
+        match self {
+            DmiStage::Reading => "The file couldn't be read",
+            DmiStage::Png => "The file isn't a png, or its png data is cut short or corrupt",
+            DmiStage::Icon => {
+                "The file is a png, but its dmi metadata or pixel data couldn't be read"
+            }
+        }
+    }
+}
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Loads a dmi, saying how far it got if it can't be
+fn load_dmi(bytes: &[u8]) -> Result<Icon, InputError> {
+    // The dmi crate panics on chunks that run off the end of the file, so
+    // don't hand it truncated files
+    if !chunks_in_bounds(bytes) {
+        return Err(InputError::DmiRead {
+            stage: DmiStage::Png,
+            error: DmiError::Generic("Chunk data runs past the end of the file".to_string()),
+        });
+    }
+    Icon::load(bytes).map_err(|error| {
+        InputError::DmiRead {
+            stage: DmiStage::of(bytes),
+            error,
+        }
+    })
 }
 
 impl UFE for InputError {
@@ -42,7 +175,15 @@ impl UFE for InputError {
                 Some(vec![format!("The [{format}] image format is unsupported")])
             }
             InputError::DynamicRead(error) => Some(vec![format!("{}", error)]),
-            InputError::DmiRead(error) => Some(vec![format!("{}", error)]),
+            InputError::DmiRead { stage, error } => {
+                // The dmi crate's own messages are vague, the details are in
+                // what caused them
+                let detail = match std::error::Error::source(error) {
+                    Some(source) => format!("{error}: {source}"),
+                    None => error.to_string(),
+                };
+                Some(vec![stage.reason().to_string(), detail])
+            }
         }
     }
 
@@ -51,7 +192,14 @@ impl UFE for InputError {
             InputError::UnsupportedFormat(_) => {
                 Some("Are you using a valid image format?".to_string())
             }
-            InputError::DynamicRead(_) | InputError::DmiRead(_) => None,
+            InputError::DynamicRead(_) => None,
+            InputError::DmiRead { .. } => {
+                Some(
+                    "The file may be corrupt, or not a real dmi. Re-save it from an editor, or \
+                     restore it from a good copy"
+                        .to_string(),
+                )
+            }
         }
     }
 }
@@ -69,10 +217,68 @@ impl InputIcon {
     ) -> Result<Self, InputError> {
         match extension {
             "png" => Ok(Self::DynamicImage(image::load(reader, ImageFormat::Png)?)),
-            "dmi" => Ok(Self::Dmi(Icon::load(reader)?)),
+            "dmi" => {
+                let mut bytes = vec![];
+                reader.read_to_end(&mut bytes).map_err(|error| {
+                    InputError::DmiRead {
+                        stage: DmiStage::Reading,
+                        error: error.into(),
+                    }
+                })?;
+                Ok(Self::Dmi(load_dmi(&bytes)?))
+            }
             _ => Err(InputError::UnsupportedFormat(extension.to_string())),
         }
     }
+
+    /// Gets the dmi out of this input, for operations that work on its icon
+    /// states
+    /// # Errors
+    /// `ProcessorError::DMINotFound` if the input isn't a dmi, or
+    /// `ProcessorError::EmptyIcon` if it has no icon states to work on
+    pub fn require_states(&self) -> ProcessorResult<&Icon> {
+        let InputIcon::Dmi(icon) = self else {
+            return Err(ProcessorError::DMINotFound);
+        };
+        if icon.states.is_empty() {
+            return Err(ProcessorError::EmptyIcon);
+        }
+        Ok(icon)
+    }
+
+    /// Same as `from_reader`, but salvages DMIs whose pixel data is partially
+    /// corrupt instead of failing on them. Any icon states that had to be
+    /// dropped are reported back as warnings.
+    pub fn from_reader_recovering<R: BufRead + Seek>(
+        reader: &mut R,
+        extension: &str,
+    ) -> Result<(Self, Vec<ProcessorWarning>), InputError> {
+        if extension != "dmi" {
+            return Ok((Self::from_reader(reader, extension)?, vec![]));
+        }
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).map_err(|error| {
+            InputError::DmiRead {
+                stage: DmiStage::Reading,
+                error: error.into(),
+            }
+        })?;
+        let RecoveredIcon {
+            icon,
+            skipped_states,
+        } = load_recovering(&bytes).map_err(|error| {
+            InputError::DmiRead {
+                stage: DmiStage::of(&bytes),
+                error,
+            }
+        })?;
+
+        let mut warnings = vec![];
+        if !skipped_states.is_empty() {
+            warnings.push(ProcessorWarning::SkippedCorruptStates(skipped_states));
+        }
+        Ok((Self::Dmi(icon), warnings))
+    }
 }
 
 /// An output image, with a possible path hint and name hint.
@@ -93,6 +299,10 @@ pub struct NamedIcon {
     /// output file will be `foo/bar-baz.png` (or `foo/bar-baz.dmi` if the
     /// output format is dmi)
     pub name_hint: Option<String>,
+    /// Exactly where the resulting image goes, relative to the input file's
+    /// folder. Used over both hints when set, for outputs a config names
+    /// itself
+    pub path: Option<PathBuf>,
     /// The actual output image
     pub image: OutputImage,
 }
@@ -102,6 +312,7 @@ impl Debug for NamedIcon {
         f.debug_struct("NamedIcon")
             .field("path_hint", &self.path_hint)
             .field("name_hint", &self.name_hint)
+            .field("path", &self.path)
             .field("image", &"[OutputImage]")
             .finish()
     }
@@ -114,6 +325,7 @@ impl NamedIcon {
         Self {
             path_hint: Some(path_hint.to_string()),
             name_hint: Some(name_hint.to_string()),
+            path: None,
             image,
         }
     }
@@ -124,6 +336,7 @@ impl NamedIcon {
         Self {
             path_hint: None,
             name_hint: None,
+            path: None,
             image: OutputImage::Dmi(icon),
         }
     }
@@ -133,6 +346,9 @@ impl NamedIcon {
     #[tracing::instrument]
     pub fn build_path(&self, input_file: &Path) -> PathBuf {
         debug!(input_file = ?input_file, "Building path");
+        if let Some(path) = &self.path {
+            return path.clone();
+        }
         let file_name = input_file
             .with_extension("")
             .file_name()
@@ -217,6 +433,89 @@ impl OutputImage {
             OutputImage::Dmi(_) => "dmi",
         }
     }
+
+    /// Extension of the file this image gets written to in `format`
+    #[must_use]
+    pub const fn extension_for(&self, format: OutputFormat) -> &'static str {
+        match (self, format) {
+            (OutputImage::Png(_), _) | (OutputImage::Dmi(_), OutputFormat::PngSheet) => "png",
+            (OutputImage::Dmi(_), OutputFormat::Dmi) => "dmi",
+            (OutputImage::Dmi(_), OutputFormat::Gif) => "gif",
+        }
+    }
+
+    /// Writes the image out in `format`. Pngs are always written as pngs, the
+    /// format only decides what happens to dmis
+    /// # Errors
+    /// Returns an `OutputError` if encoding or writing fails
+    pub fn write_as<W: Write + Seek>(
+        &self,
+        format: OutputFormat,
+        writer: &mut W,
+    ) -> Result<(), OutputError> {
+        match (self, format) {
+            (OutputImage::Png(png), _) => png.write_to(writer, ImageOutputFormat::Png)?,
+            (OutputImage::Dmi(dmi), OutputFormat::Dmi) => {
+                dmi.save(writer)?;
+            }
+            (OutputImage::Dmi(dmi), OutputFormat::PngSheet) => {
+                // A dmi is already a png sheet, just one with metadata that
+                // would confuse tools expecting a plain image
+                let mut sheet = vec![];
+                dmi.save(&mut sheet)?;
+                image::load_from_memory_with_format(&sheet, ImageFormat::Png)?
+                    .write_to(writer, ImageOutputFormat::Png)?;
+            }
+            (OutputImage::Dmi(dmi), OutputFormat::Gif) => {
+                let mut encoder = GifEncoder::new(writer);
+                encoder.set_repeat(Repeat::Infinite)?;
+                encoder.encode_frames(gif_frames(dmi))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every state's south facing frames, one state after another, for previewing a
+/// dmi as a single animation
+fn gif_frames(dmi: &Icon) -> Vec<Frame> {
+    let mut frames = vec![];
+    for state in &dmi.states {
+        let dirs = usize::from(state.dirs.max(1));
+        // Images are stored frame by frame, with south first in each frame
+        for (index, image) in state.images.iter().step_by(dirs).enumerate() {
+            let delay = state
+                .delay
+                .as_ref()
+                .and_then(|delays| delays.get(index))
+                .copied()
+                .unwrap_or(1.0);
+            // Delays are in deciseconds
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let delay = Delay::from_numer_denom_ms((delay * 100.0).round() as u32, 1);
+            frames.push(Frame::from_parts(image.to_rgba8(), 0, 0, delay));
+        }
+    }
+    frames
+}
+
+/// Which kind of file dmi outputs get written as
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum OutputFormat {
+    /// A regular dmi
+    #[default]
+    Dmi,
+    /// The dmi's sprite sheet as a plain png, without any dmi metadata
+    PngSheet,
+    /// An animated gif playing the south facing frames of every icon state
+    Gif,
+}
+
+impl OutputFormat {
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 /// Represents the possible text outputs of an icon operation
@@ -263,6 +562,41 @@ impl ProcessorPayload {
         Self::Single(Box::new(OutputImage::Png(image)))
     }
 
+    /// Hands an input back as is
+    #[must_use]
+    pub fn from_input(input: InputIcon) -> Self {
+        match input {
+            InputIcon::DynamicImage(image) => Self::from_image(image),
+            InputIcon::Dmi(icon) => Self::from_icon(icon),
+        }
+    }
+
+    /// Every image in the payload, in the order they'd be written out
+    #[must_use]
+    pub fn images(&self) -> Vec<&OutputImage> {
+        match self {
+            Self::Single(image) => vec![image],
+            Self::SingleNamed(named) => vec![&named.image],
+            Self::MultipleNamed(named) => named.iter().map(|named| &named.image).collect(),
+            Self::ConfigWrapped(payload, _) => payload.images(),
+        }
+    }
+
+    /// Turns a payload back in to an input for another operation. Only
+    /// payloads holding a single unnamed image can be used this way
+    #[must_use]
+    pub fn into_input(self) -> Option<InputIcon> {
+        match self {
+            Self::Single(image) => {
+                match *image {
+                    OutputImage::Png(image) => Some(InputIcon::DynamicImage(image)),
+                    OutputImage::Dmi(icon) => Some(InputIcon::Dmi(icon)),
+                }
+            }
+            _ => None,
+        }
+    }
+
     #[must_use]
     pub fn wrap_png_config(payload: ProcessorPayload, text: String) -> Self {
         Self::ConfigWrapped(Box::new(payload), Box::new(OutputText::PngConfig(text)))
@@ -303,10 +637,60 @@ pub trait IconOperationConfig {
     ) -> ProcessorResult<ProcessorPayload>;
 
     /// Verifies that current config values are valid within the context of the
-    /// operation to be performed # Errors
+    /// operation to be performed. Values that are allowed but probably a
+    /// mistake come back as warnings, which don't stop the operation
+    /// # Errors
     /// Possible errors vary based on implementor; should be some kind of
     /// `ProcessorError::InvalidConfig`
-    fn verify_config(&self) -> ProcessorResult<()>;
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        Ok(vec![])
+    }
+
+    /// Which icon states this operation applies to, for operations that take
+    /// `target_states`. Lets the pipeline check them the same way for every
+    /// operation, see [`StateTargets::check_matched`]
+    fn state_targets(&self) -> Option<&StateTargets> {
+        None
+    }
+
+    /// Checks this operation's `target_states` against `input`, if it has any
+    /// # Errors
+    /// Returns `ProcessorError::UnmatchedTargets` for targets matching nothing
+    fn check_targets(&self, input: &InputIcon) -> ProcessorResult<()> {
+        self.state_targets()
+            .map_or(Ok(()), |targets| targets.check_matched(input))
+    }
+
+    /// Whether this operation touches the icon state with this name, for
+    /// pipelines that require every state to be referenced. Operations that
+    /// take `target_states` go by those, the ones working on named states
+    /// should say which, and the rest are taken to touch every state
+    fn references_state(&self, state_name: &str) -> bool {
+        self.state_targets()
+            .is_none_or(|targets| targets.matches(state_name))
+    }
+
+    /// Describes what the icon states this operation made in `output` mean,
+    /// for anything downstream that can't tell from their names. Most
+    /// operations have nothing to add
+    fn describe_states(&self, _output: &ProcessorPayload) -> Vec<StateMetadata> {
+        vec![]
+    }
+
+    /// `perform_operation`, with access to scratch space shared with the rest
+    /// of the pipeline, see [`PipelineContext`]. Only operations that work
+    /// together with others need to implement this, the rest ignore the
+    /// context
+    /// # Errors
+    /// Same as `perform_operation`
+    fn perform_operation_in_context(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        _context: &mut PipelineContext,
+    ) -> ProcessorResult<ProcessorPayload> {
+        self.perform_operation(input, mode)
+    }
 
     /// Helper function to call `verify_config` and `perform_operation` in
     /// sequence.
@@ -324,6 +708,7 @@ pub trait IconOperationConfig {
         mode: OperationMode,
     ) -> ProcessorResult<ProcessorPayload> {
         self.verify_config()?;
+        self.check_targets(input)?;
         self.perform_operation(input, mode)
     }
 }
@@ -336,4 +721,291 @@ pub enum IconOperation {
     BitmaskDirectionalVis,
     BitmaskWindows,
     BitmaskSliceReconstruct,
+    Blur,
+    DropFrames,
+    Overlay,
+    SnapToGrid,
+    Emissive,
+    Passthrough,
+    OverlayLoop,
+    CropHotspot,
+    HsvShift,
+    RelativeCrop,
+    BalanceDirections,
+    Defringe,
+    DirTint,
+    OptimizeDirs,
+    ScaleXY,
+    Tile,
+    Subsample,
+    ValidateDimensions,
+    Morphology,
+    Damage,
+    ApplyLayer,
+    MirrorDirs,
+    ReorderDirs,
+    Trim,
+    Pad,
+    CapAlpha,
+    Silhouette,
+    DedupStates,
+    ShapeMask,
+    PaletteCycle,
+    DirContact,
+    Gamma,
+    ClampFrames,
+    SplitEmissive,
+    EnforceAspect,
+    CheckerBake,
+    NormalFromHeight,
+    Posterize,
+    GradientMap,
+    Glow,
+    Renumber,
+    DirConvention,
+    ChannelSplit,
+    ChannelMerge,
+    Freeze,
+    FixDelays,
+    ThresholdAlpha,
+    SyncDelays,
+    ColorBudget,
+    Curves,
+    Center,
+    Concat,
+    ReplaceFrames,
+    MaxFrames,
+    SwapChannels,
+    ImportStrip,
+    AlphaBlur,
+    RegionAdjust,
+    ContactState,
+    ValidateMask,
+    SelfMask,
+    NormalizeHeader,
+    DirOverlay,
+}
+
+impl IconOperation {
+    /// The `mode` this operation is selected with in configs
+    #[must_use]
+    pub const fn mode_name(&self) -> &'static str {
+        match self {
+            IconOperation::BitmaskSlice(_) => "BitmaskSlice",
+            IconOperation::BitmaskDirectionalVis(_) => "BitmaskDirectionalVis",
+            IconOperation::BitmaskWindows(_) => "BitmaskWindows",
+            IconOperation::BitmaskSliceReconstruct(_) => "BitmaskSliceReconstruct",
+            IconOperation::Blur(_) => "Blur",
+            IconOperation::DropFrames(_) => "DropFrames",
+            IconOperation::Overlay(_) => "Overlay",
+            IconOperation::SnapToGrid(_) => "SnapToGrid",
+            IconOperation::Emissive(_) => "Emissive",
+            IconOperation::Passthrough(_) => "Passthrough",
+            IconOperation::OverlayLoop(_) => "OverlayLoop",
+            IconOperation::CropHotspot(_) => "CropHotspot",
+            IconOperation::HsvShift(_) => "HsvShift",
+            IconOperation::RelativeCrop(_) => "RelativeCrop",
+            IconOperation::BalanceDirections(_) => "BalanceDirections",
+            IconOperation::Defringe(_) => "Defringe",
+            IconOperation::DirTint(_) => "DirTint",
+            IconOperation::OptimizeDirs(_) => "OptimizeDirs",
+            IconOperation::ScaleXY(_) => "ScaleXY",
+            IconOperation::Tile(_) => "Tile",
+            IconOperation::Subsample(_) => "Subsample",
+            IconOperation::ValidateDimensions(_) => "ValidateDimensions",
+            IconOperation::Morphology(_) => "Morphology",
+            IconOperation::Damage(_) => "Damage",
+            IconOperation::ApplyLayer(_) => "ApplyLayer",
+            IconOperation::MirrorDirs(_) => "MirrorDirs",
+            IconOperation::ReorderDirs(_) => "ReorderDirs",
+            IconOperation::Trim(_) => "Trim",
+            IconOperation::Pad(_) => "Pad",
+            IconOperation::CapAlpha(_) => "CapAlpha",
+            IconOperation::Silhouette(_) => "Silhouette",
+            IconOperation::DedupStates(_) => "DedupStates",
+            IconOperation::ShapeMask(_) => "ShapeMask",
+            IconOperation::PaletteCycle(_) => "PaletteCycle",
+            IconOperation::DirContact(_) => "DirContact",
+            IconOperation::Gamma(_) => "Gamma",
+            IconOperation::ClampFrames(_) => "ClampFrames",
+            IconOperation::SplitEmissive(_) => "SplitEmissive",
+            IconOperation::EnforceAspect(_) => "EnforceAspect",
+            IconOperation::CheckerBake(_) => "CheckerBake",
+            IconOperation::NormalFromHeight(_) => "NormalFromHeight",
+            IconOperation::Posterize(_) => "Posterize",
+            IconOperation::GradientMap(_) => "GradientMap",
+            IconOperation::Glow(_) => "Glow",
+            IconOperation::Renumber(_) => "Renumber",
+            IconOperation::DirConvention(_) => "DirConvention",
+            IconOperation::ChannelSplit(_) => "ChannelSplit",
+            IconOperation::ChannelMerge(_) => "ChannelMerge",
+            IconOperation::Freeze(_) => "Freeze",
+            IconOperation::FixDelays(_) => "FixDelays",
+            IconOperation::ThresholdAlpha(_) => "ThresholdAlpha",
+            IconOperation::SyncDelays(_) => "SyncDelays",
+            IconOperation::ColorBudget(_) => "ColorBudget",
+            IconOperation::Curves(_) => "Curves",
+            IconOperation::Center(_) => "Center",
+            IconOperation::Concat(_) => "Concat",
+            IconOperation::ReplaceFrames(_) => "ReplaceFrames",
+            IconOperation::MaxFrames(_) => "MaxFrames",
+            IconOperation::SwapChannels(_) => "SwapChannels",
+            IconOperation::ImportStrip(_) => "ImportStrip",
+            IconOperation::AlphaBlur(_) => "AlphaBlur",
+            IconOperation::RegionAdjust(_) => "RegionAdjust",
+            IconOperation::ContactState(_) => "ContactState",
+            IconOperation::ValidateMask(_) => "ValidateMask",
+            IconOperation::SelfMask(_) => "SelfMask",
+            IconOperation::NormalizeHeader(_) => "NormalizeHeader",
+            IconOperation::DirOverlay(_) => "DirOverlay",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::operations::pipeline::{Pipeline, PipelineError};
+
+    /// Loads `bytes` as a dmi, expecting it to fail
+    fn dmi_error(bytes: Vec<u8>) -> InputError {
+        let Err(error) = InputIcon::from_reader(&mut std::io::Cursor::new(bytes), "dmi") else {
+            panic!("Expected the dmi to fail to load");
+        };
+        error
+    }
+
+    #[test]
+    fn malformed_dmis_say_where_they_failed() {
+        let garbage = dmi_error(b"definitely not a dmi, just some text".to_vec());
+        assert!(matches!(
+            garbage,
+            InputError::DmiRead {
+                stage: DmiStage::Png,
+                ..
+            }
+        ));
+        assert!(garbage.helptext().unwrap().contains("corrupt"));
+
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![dmi::icon::IconState {
+                name: "wall".to_string(),
+                images: vec![DynamicImage::new_rgba8(4, 4)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut bytes = vec![];
+        icon.save(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() / 2);
+        let truncated = dmi_error(bytes);
+        assert!(matches!(
+            truncated,
+            InputError::DmiRead {
+                stage: DmiStage::Png,
+                ..
+            }
+        ));
+
+        // A perfectly good png, but with no dmi metadata
+        let mut png = std::io::Cursor::new(vec![]);
+        DynamicImage::new_rgba8(4, 4)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+        let plain_png = dmi_error(png.into_inner());
+        assert!(matches!(
+            plain_png,
+            InputError::DmiRead {
+                stage: DmiStage::Icon,
+                ..
+            }
+        ));
+        let reasons = plain_png.reasons().unwrap();
+        assert_eq!(reasons[0], DmiStage::Icon.reason());
+        assert!(reasons[1].contains("zTXt"), "{reasons:?}");
+    }
+
+    #[test]
+    fn stateless_dmi_is_an_empty_icon() {
+        // dmi refuses to save an icon without states (the sheet would have no
+        // width), so build it directly
+        let input = InputIcon::Dmi(Icon {
+            width: 32,
+            height: 32,
+            ..Default::default()
+        });
+        assert!(matches!(
+            input.require_states(),
+            Err(ProcessorError::EmptyIcon)
+        ));
+
+        let pipeline: Pipeline = toml::from_str("mode = \"Blur\"\nradius = 1.0").unwrap();
+        let Err(PipelineError::OperationsFailed(failures)) =
+            pipeline.run(&input, OperationMode::Standard)
+        else {
+            panic!("Expected the pipeline to fail");
+        };
+        assert!(matches!(failures[0].error, ProcessorError::EmptyIcon));
+
+        // Operations that don't need states don't care
+        let pipeline: Pipeline = toml::from_str("mode = \"Passthrough\"").unwrap();
+        assert!(pipeline.run(&input, OperationMode::Standard).is_ok());
+    }
+
+    #[test]
+    fn every_output_format_decodes() {
+        let frame = |value: u8| {
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                4,
+                4,
+                image::Rgba([value, value, value, 255]),
+            ))
+        };
+        let output = OutputImage::Dmi(Icon {
+            width: 4,
+            height: 4,
+            states: vec![
+                dmi::icon::IconState {
+                    name: "still".to_string(),
+                    images: vec![frame(0)],
+                    ..Default::default()
+                },
+                dmi::icon::IconState {
+                    name: "anim".to_string(),
+                    frames: 2,
+                    images: vec![frame(100), frame(200)],
+                    delay: Some(vec![1.0, 2.0]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        });
+        let write = |format| {
+            let mut bytes = std::io::Cursor::new(vec![]);
+            output.write_as(format, &mut bytes).unwrap();
+            bytes.into_inner()
+        };
+
+        let dmi = Icon::load(write(OutputFormat::Dmi).as_slice()).unwrap();
+        assert_eq!(dmi.states.len(), 2);
+        assert_eq!(output.extension_for(OutputFormat::Dmi), "dmi");
+
+        let sheet =
+            image::load_from_memory_with_format(&write(OutputFormat::PngSheet), ImageFormat::Png)
+                .unwrap();
+        // Three frames, laid out the same way the dmi would be
+        assert!(sheet.width() * sheet.height() >= 3 * 4 * 4);
+        assert_eq!(output.extension_for(OutputFormat::PngSheet), "png");
+
+        let gif = write(OutputFormat::Gif);
+        let gif = image::codecs::gif::GifDecoder::new(gif.as_slice()).unwrap();
+        let frames = image::AnimationDecoder::into_frames(gif)
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].delay().numer_denom_ms(), (200, 1));
+        assert_eq!(output.extension_for(OutputFormat::Gif), "gif");
+    }
 }
@@ -0,0 +1,180 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::blur::{convolve_separable, Blur, BlurKind};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, with_alpha};
+
+/// Gaussian blurs just the alpha of every frame of the targeted icon states,
+/// leaving color as it was. Softens the edges of shadows and glows without
+/// smearing their color
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AlphaBlur {
+    /// Strength of the blur, in pixels. Used as the standard deviation
+    pub radius: f32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for AlphaBlur {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let kernel = Blur {
+            radius: self.radius,
+            kind: BlurKind::Gaussian,
+            targets: StateTargets::default(),
+        }
+        .kernel();
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| DynamicImage::ImageRgba8(blur_alpha(frame, &kernel)))
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !self.radius.is_finite() || self.radius <= 0.0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "AlphaBlur radius must be above 0, got {}",
+                self.radius
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+/// Blurs the alpha of a single frame with a separable kernel, clamping samples
+/// past the edge to the nearest edge pixel. Color channels are copied over
+/// untouched, including those of pixels that were fully transparent
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn blur_alpha(frame: &DynamicImage, kernel: &[f32]) -> RgbaImage {
+    let mut output = frame.to_rgba8();
+    let (width, height) = output.dimensions();
+    if width == 0 || height == 0 {
+        return output;
+    }
+
+    let alphas: Vec<[f32; 1]> = output
+        .pixels()
+        .map(|pixel| [f32::from(alpha(*pixel))])
+        .collect();
+    let blurred = convolve_separable(&alphas, width, height, kernel);
+
+    for (pixel, [blurred]) in output.pixels_mut().zip(blurred) {
+        *pixel = with_alpha(*pixel, blurred.round().clamp(0.0, 255.0) as u8);
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba};
+
+    use super::*;
+    use crate::operations::OutputImage;
+    use crate::util::color::rgb;
+
+    fn alpha_blur(radius: f32) -> AlphaBlur {
+        AlphaBlur {
+            radius,
+            targets: StateTargets::default(),
+        }
+    }
+
+    /// A 9x9 frame with an opaque 3x3 square of shifting color in the middle,
+    /// on transparent blue
+    fn square_frame() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(9, 9, |x, y| {
+            if (3..6).contains(&x) && (3..6).contains(&y) {
+                Rgba([(x * 40) as u8, (y * 40) as u8, 10, 255])
+            } else {
+                Rgba([0, 0, 255, 0])
+            }
+        }))
+    }
+
+    fn apply(config: &AlphaBlur, frame: DynamicImage) -> RgbaImage {
+        let icon = Icon {
+            width: 9,
+            height: 9,
+            states: vec![IconState {
+                name: "shadow".to_string(),
+                images: vec![frame],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].images[0].to_rgba8()
+    }
+
+    #[test]
+    fn color_is_untouched() {
+        let source = square_frame();
+        let blurred = apply(&alpha_blur(1.5), source.clone());
+        for (x, y, pixel) in blurred.enumerate_pixels() {
+            assert_eq!(rgb(*pixel), rgb(source.get_pixel(x, y)));
+        }
+    }
+
+    #[test]
+    fn alpha_is_smoothed_at_edges() {
+        let blurred = apply(&alpha_blur(1.0), square_frame());
+        let alpha_at = |x: u32, y: u32| alpha(*blurred.get_pixel(x, y));
+
+        // The opaque edge loses some alpha to the transparent pixels around it
+        assert!(alpha_at(3, 4) < 255);
+        assert!(alpha_at(2, 4) > 0);
+        assert!(alpha_at(3, 4) > alpha_at(2, 4));
+        assert!(alpha_at(2, 4) > alpha_at(1, 4));
+        assert_eq!(alpha_at(2, 4), alpha_at(6, 4));
+        assert_eq!(alpha_at(4, 2), alpha_at(4, 6));
+        // Far from the square stays transparent
+        assert_eq!(alpha_at(0, 0), 0);
+    }
+
+    #[test]
+    fn radius_must_be_positive() {
+        assert!(alpha_blur(0.0).verify_config().is_err());
+        assert!(alpha_blur(-2.0).verify_config().is_err());
+        assert!(alpha_blur(f32::NAN).verify_config().is_err());
+        assert!(alpha_blur(0.5).verify_config().unwrap().is_empty());
+    }
+}
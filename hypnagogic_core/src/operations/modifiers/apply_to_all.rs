@@ -0,0 +1,249 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::{blend_images, BlendMode};
+
+/// Which side of the other icon states the layer goes on
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerPosition {
+    /// Behind everything, like a shared background
+    #[default]
+    Under,
+    /// On top of everything, like a watermark
+    Over,
+}
+
+/// Composites one icon state of a dmi into every other icon state, under or
+/// over it.
+///
+/// Dirs are matched up by index, cycling the layer's if it has fewer. Frames
+/// are matched up by index too, but if the layer runs out of frames its last
+/// one is held for the rest of the animation
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ApplyLayer {
+    /// Name of the icon state to composite into the others
+    pub layer_state: String,
+    #[serde(default)]
+    pub position: LayerPosition,
+    /// Names of icon states to leave alone
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_states: Vec<String>,
+    /// Whether to drop the layer state from the output once it's been applied
+    #[serde(default)]
+    pub remove_layer: bool,
+}
+
+impl IconOperationConfig for ApplyLayer {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let Some(layer) = icon
+            .states
+            .iter()
+            .find(|state| state.name == self.layer_state)
+        else {
+            return Err(ProcessorError::ConfigError(format!(
+                "Layer icon state \"{}\" was not found in the input",
+                self.layer_state
+            )));
+        };
+        if layer.images.len() < usize::from(layer.dirs.max(1)) {
+            return Err(ProcessorError::ConfigError(format!(
+                "Layer icon state \"{}\" has no frames to apply",
+                self.layer_state
+            )));
+        }
+
+        let mut output = icon.clone();
+        for state in &mut output.states {
+            if state.name == self.layer_state || self.exclude_states.contains(&state.name) {
+                continue;
+            }
+            *state = self.apply_layer(state, layer);
+        }
+        if self.remove_layer {
+            output.states.retain(|state| state.name != self.layer_state);
+        }
+
+        Ok(ProcessorPayload::from_icon(output))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        state_name == self.layer_state || !self.exclude_states.iter().any(|x| x == state_name)
+    }
+}
+
+impl ApplyLayer {
+    fn apply_layer(&self, base: &IconState, layer: &IconState) -> IconState {
+        let base_dirs = usize::from(base.dirs.max(1));
+        let layer_dirs = usize::from(layer.dirs.max(1));
+        let layer_frames = layer.images.len() / layer_dirs;
+        let images = base
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                // Images are stored frame by frame, with every dir of a frame together
+                let (frame, dir) = (index / base_dirs, index % base_dirs);
+                let layer_image =
+                    &layer.images[frame.min(layer_frames - 1) * layer_dirs + dir % layer_dirs];
+                let blended = match self.position {
+                    LayerPosition::Under => blend_images(layer_image, image, BlendMode::Normal),
+                    LayerPosition::Over => blend_images(image, layer_image, BlendMode::Normal),
+                };
+                DynamicImage::ImageRgba8(blended)
+            })
+            .collect();
+        IconState {
+            images,
+            ..base.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+    /// A 2x2 state, each frame clear apart from its top left pixel
+    fn state(name: &str, corners: &[[u8; 4]]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            frames: corners.len() as u32,
+            images: corners
+                .iter()
+                .map(|&corner| {
+                    let mut image = RgbaImage::from_pixel(2, 2, Rgba(CLEAR));
+                    image.put_pixel(0, 0, Rgba(corner));
+                    DynamicImage::ImageRgba8(image)
+                })
+                .collect(),
+            delay: (corners.len() > 1).then(|| vec![1.0; corners.len()]),
+            ..Default::default()
+        }
+    }
+
+    /// A 2x2 state filled with one color per frame
+    fn solid(name: &str, colors: &[[u8; 4]]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            frames: colors.len() as u32,
+            images: colors
+                .iter()
+                .map(|&color| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba(color))))
+                .collect(),
+            delay: (colors.len() > 1).then(|| vec![1.0; colors.len()]),
+            ..Default::default()
+        }
+    }
+
+    fn run(config: &ApplyLayer, states: Vec<IconState>) -> Icon {
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    fn pixels(state: &IconState, x: u32, y: u32) -> Vec<Rgba<u8>> {
+        state
+            .images
+            .iter()
+            .map(|image| image.get_pixel(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn every_state_but_excluded_gets_the_layer() {
+        let config = ApplyLayer {
+            layer_state: "floor".to_string(),
+            position: LayerPosition::Under,
+            exclude_states: vec!["skip".to_string()],
+            remove_layer: false,
+        };
+        let output = run(
+            &config,
+            vec![
+                state("a", &[RED]),
+                state("b", &[BLUE, RED, BLUE]),
+                state("skip", &[RED]),
+                solid("floor", &[GREEN, BLUE]),
+            ],
+        );
+
+        // The states keep their own pixels, with the layer showing through around them
+        assert_eq!(pixels(&output.states[0], 0, 0), vec![Rgba(RED)]);
+        assert_eq!(pixels(&output.states[0], 1, 1), vec![Rgba(GREEN)]);
+        // The layer's last frame is held once it runs out
+        assert_eq!(
+            pixels(&output.states[1], 1, 1),
+            vec![Rgba(GREEN), Rgba(BLUE), Rgba(BLUE)]
+        );
+        assert_eq!(pixels(&output.states[2], 1, 1), vec![Rgba(CLEAR)]);
+        assert_eq!(output.states[3], solid("floor", &[GREEN, BLUE]));
+    }
+
+    #[test]
+    fn layer_over_and_removed() {
+        let config = ApplyLayer {
+            layer_state: "mark".to_string(),
+            position: LayerPosition::Over,
+            exclude_states: vec![],
+            remove_layer: true,
+        };
+        let output = run(&config, vec![solid("a", &[RED]), state("mark", &[BLUE])]);
+
+        assert_eq!(output.states.len(), 1);
+        assert_eq!(pixels(&output.states[0], 0, 0), vec![Rgba(BLUE)]);
+        assert_eq!(pixels(&output.states[0], 1, 1), vec![Rgba(RED)]);
+    }
+
+    #[test]
+    fn frameless_layer_errors() {
+        let config = ApplyLayer {
+            layer_state: "floor".to_string(),
+            position: LayerPosition::Under,
+            exclude_states: vec![],
+            remove_layer: false,
+        };
+        let icon = Icon {
+            states: vec![solid("a", &[RED]), solid("floor", &[])],
+            ..Default::default()
+        };
+        let Err(ProcessorError::ConfigError(reason)) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+        else {
+            panic!("Expected a config error");
+        };
+        assert!(reason.contains("no frames"), "{reason}");
+    }
+}
@@ -0,0 +1,145 @@
+use dmi::icon::IconState;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Repairs icon states where some directions have fewer frames than others.
+///
+/// Images are stored frame by frame with every dir of a frame together, so a
+/// short direction shows up as a state running out of images partway through
+/// its last frames. Short directions are padded by holding their last frame,
+/// and missing delays by holding the last delay, until everything matches the
+/// longest direction
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct BalanceDirections {}
+
+impl IconOperationConfig for BalanceDirections {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        for state in &mut icon.states {
+            balance_state(state);
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+}
+
+fn balance_state(state: &mut IconState) {
+    let dirs = usize::from(state.dirs.max(1));
+    let frames = state.images.len().div_ceil(dirs);
+    if frames == 0 {
+        return;
+    }
+
+    let present = state.images.len();
+    let mut images = Vec::with_capacity(frames * dirs);
+    for frame in 0..frames {
+        for dir in 0..dirs {
+            let index = frame * dirs + dir;
+            if index < present {
+                images.push(state.images[index].clone());
+                continue;
+            }
+            // Hold the frame before, which has already been padded if it
+            // needed to be
+            let Some(previous) = frame.checked_sub(1) else {
+                // A direction with no frames at all, the best we can do is
+                // borrow south's
+                images.push(images[0].clone());
+                continue;
+            };
+            info!(state = state.name, dir, frame, "Padded short direction");
+            images.push(images[previous * dirs + dir].clone());
+        }
+    }
+    state.images = images;
+    state.frames = frames as u32;
+
+    if let Some(delays) = &mut state.delay {
+        if delays.len() < frames {
+            let held = delays.last().copied().unwrap_or(1.0);
+            info!(
+                state = state.name,
+                missing = frames - delays.len(),
+                "Padded missing delays"
+            );
+            delays.resize(frames, held);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A frame filled with a color unique to its frame and dir
+    fn frame(frame: u8, dir: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([frame, dir, 0, 255])))
+    }
+
+    #[test]
+    fn short_directions_hold_their_last_frame() {
+        // South has three frames, but every other direction stops after two
+        let mut images: Vec<DynamicImage> = (0..2)
+            .flat_map(|frame_index| (0..4).map(move |dir| frame(frame_index, dir)))
+            .collect();
+        images.push(frame(2, 0));
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "walk".to_string(),
+                dirs: 4,
+                frames: 3,
+                images,
+                delay: Some(vec![1.0, 2.0]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let ProcessorPayload::Single(output) = BalanceDirections {}
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let state = &output.states[0];
+        assert_eq!(state.frames, 3);
+        assert_eq!(
+            state.images[8..],
+            [frame(2, 0), frame(1, 1), frame(1, 2), frame(1, 3)]
+        );
+        assert_eq!(state.delay, Some(vec![1.0, 2.0, 2.0]));
+    }
+
+    #[test]
+    fn balanced_states_are_untouched() {
+        let mut state = IconState {
+            name: "still".to_string(),
+            dirs: 4,
+            frames: 1,
+            images: (0..4).map(|dir| frame(0, dir)).collect(),
+            ..Default::default()
+        };
+        let before = state.clone();
+        balance_state(&mut state);
+        assert_eq!(state, before);
+    }
+}
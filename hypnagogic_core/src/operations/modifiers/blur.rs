@@ -0,0 +1,330 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::TRANSPARENT;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlurKind {
+    /// Every pixel within `radius` is weighted equally
+    Box,
+    /// Pixels are weighted by a normal distribution, with `radius` as the
+    /// standard deviation
+    #[default]
+    Gaussian,
+}
+
+/// Blurs every frame of the targeted icon states. Good for glows and other
+/// soft effects
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Blur {
+    /// Strength of the blur, in pixels
+    pub radius: f32,
+    #[serde(default)]
+    pub kind: BlurKind,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Blur {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let kernel = self.kernel();
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| DynamicImage::ImageRgba8(blur_frame(frame, &kernel)))
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !self.radius.is_finite() || self.radius < 0.0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Blur radius must be a positive number, got {}",
+                self.radius
+            )));
+        }
+        if self.radius == 0.0 {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "A blur radius of 0 leaves every pixel as it is".to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl Blur {
+    /// Builds the normalized 1d kernel to convolve with, centered on the
+    /// middle element
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn kernel(&self) -> Vec<f32> {
+        let weights: Vec<f32> = match self.kind {
+            BlurKind::Box => {
+                let reach = self.radius.round() as i32;
+                vec![1.0; (reach * 2 + 1) as usize]
+            }
+            BlurKind::Gaussian => {
+                if self.radius == 0.0 {
+                    return vec![1.0];
+                }
+                // Past 3 standard deviations the weights are too small to matter
+                let reach = (self.radius * 3.0).ceil() as i32;
+                let denominator = 2.0 * self.radius * self.radius;
+                (-reach..=reach)
+                    .map(|offset| (-((offset * offset) as f32) / denominator).exp())
+                    .collect()
+            }
+        };
+        let total: f32 = weights.iter().sum();
+        weights.into_iter().map(|weight| weight / total).collect()
+    }
+}
+
+/// Blurs a single frame with a separable kernel. Samples past the edge of the
+/// frame are clamped to the nearest edge pixel.
+///
+/// Color is premultiplied by alpha while blurring, so fully transparent pixels
+/// (which are usually black) don't bleed dark halos in to their neighbors
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn blur_frame(frame: &DynamicImage, kernel: &[f32]) -> RgbaImage {
+    let source = frame.to_rgba8();
+    let (width, height) = source.dimensions();
+    if width == 0 || height == 0 {
+        return source;
+    }
+
+    let premultiplied: Vec<[f32; 4]> = source
+        .pixels()
+        .map(|Rgba([r, g, b, a])| {
+            let alpha = f32::from(*a) / 255.0;
+            [
+                f32::from(*r) * alpha,
+                f32::from(*g) * alpha,
+                f32::from(*b) * alpha,
+                alpha,
+            ]
+        })
+        .collect();
+    let blurred = convolve_separable(&premultiplied, width, height, kernel);
+
+    let mut output = RgbaImage::new(width, height);
+    for (pixel, [r, g, b, alpha]) in output.pixels_mut().zip(blurred) {
+        let to_byte = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+        *pixel = if alpha > 0.0 {
+            Rgba([
+                to_byte(r / alpha),
+                to_byte(g / alpha),
+                to_byte(b / alpha),
+                to_byte(alpha * 255.0),
+            ])
+        } else {
+            TRANSPARENT
+        };
+    }
+    output
+}
+
+/// Runs a separable kernel over a frame's worth of pixels, `N` channels each,
+/// first across then down. Samples past the edge of the frame are clamped to
+/// the nearest edge pixel
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap
+)]
+pub(crate) fn convolve_separable<const N: usize>(
+    pixels: &[[f32; N]],
+    width: u32,
+    height: u32,
+    kernel: &[f32],
+) -> Vec<[f32; N]> {
+    let reach = (kernel.len() / 2) as i64;
+    let convolve = |input: &[[f32; N]], horizontal: bool| -> Vec<[f32; N]> {
+        let mut output = vec![[0.0; N]; input.len()];
+        for y in 0..i64::from(height) {
+            for x in 0..i64::from(width) {
+                let mut sum = [0.0; N];
+                for (index, weight) in kernel.iter().enumerate() {
+                    let offset = index as i64 - reach;
+                    let (sample_x, sample_y) = if horizontal {
+                        ((x + offset).clamp(0, i64::from(width) - 1), y)
+                    } else {
+                        (x, (y + offset).clamp(0, i64::from(height) - 1))
+                    };
+                    let sample = input[(sample_y * i64::from(width) + sample_x) as usize];
+                    for channel in 0..N {
+                        sum[channel] += sample[channel] * weight;
+                    }
+                }
+                output[(y * i64::from(width) + x) as usize] = sum;
+            }
+        }
+        output
+    };
+    convolve(&convolve(pixels, true), false)
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::GenericImageView;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn blur(kind: BlurKind, radius: f32) -> Blur {
+        Blur {
+            radius,
+            kind,
+            targets: StateTargets::default(),
+        }
+    }
+
+    fn single_pixel_frame() -> DynamicImage {
+        let mut image = RgbaImage::new(9, 9);
+        image.put_pixel(4, 4, Rgba([255, 0, 0, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn single_pixel_spreads_symmetrically() {
+        for kind in [BlurKind::Box, BlurKind::Gaussian] {
+            let blurred = blur_frame(&single_pixel_frame(), &blur(kind, 1.0).kernel());
+            let alpha_at = |x: u32, y: u32| blurred.get_pixel(x, y).0[3];
+
+            assert!(alpha_at(4, 4) < 255);
+            assert!(alpha_at(3, 4) > 0);
+            for offset in 1..=4 {
+                let left = alpha_at(4 - offset, 4);
+                assert_eq!(left, alpha_at(4 + offset, 4));
+                assert_eq!(left, alpha_at(4, 4 - offset));
+                assert_eq!(left, alpha_at(4, 4 + offset));
+                assert_eq!(
+                    alpha_at(4 - offset, 4 - offset),
+                    alpha_at(4 + offset, 4 + offset)
+                );
+            }
+            // Alpha weighting means the spread keeps its color instead of
+            // darkening towards the transparent black around it
+            assert_eq!(blurred.get_pixel(3, 4).0[..3], [255, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn opaque_frame_stays_opaque() {
+        let mut image = RgbaImage::new(8, 8);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            *pixel = Rgba([(x * 30) as u8, (y * 30) as u8, 100, 255]);
+        }
+        let frame = DynamicImage::ImageRgba8(image);
+
+        for kind in [BlurKind::Box, BlurKind::Gaussian] {
+            let blurred = blur_frame(&frame, &blur(kind, 2.0).kernel());
+            assert!(blurred.pixels().all(|pixel| pixel.0[3] == 255));
+        }
+    }
+
+    #[test]
+    fn only_targets_named_states() {
+        let icon = Icon {
+            width: 9,
+            height: 9,
+            states: ["glow", "plain"]
+                .into_iter()
+                .map(|name| {
+                    IconState {
+                        name: name.to_string(),
+                        images: vec![single_pixel_frame()],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let config = Blur {
+            targets: StateTargets {
+                target_states: vec!["glow".to_string()],
+                ..Default::default()
+            },
+            ..blur(BlurKind::Gaussian, 1.0)
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_ne!(output.states[0].images[0], single_pixel_frame());
+        assert_eq!(output.states[1].images[0], single_pixel_frame());
+        assert_eq!(output.states[0].images[0].dimensions(), (9, 9));
+    }
+
+    #[test]
+    fn parses_from_config() {
+        let config: crate::operations::IconOperation = toml::from_str(
+            r#"
+            mode = "Blur"
+            radius = 1.5
+            kind = "box"
+            target_states = ["glow"]
+            "#,
+        )
+        .unwrap();
+        let expected = Blur {
+            targets: StateTargets {
+                target_states: vec!["glow".to_string()],
+                ..Default::default()
+            },
+            ..blur(BlurKind::Box, 1.5)
+        };
+        assert_eq!(config, expected.into());
+    }
+
+    #[test]
+    fn negative_radius_is_rejected() {
+        assert!(blur(BlurKind::Box, -1.0).verify_config().is_err());
+    }
+
+    #[test]
+    fn zero_radius_warns() {
+        let warnings = blur(BlurKind::Box, 0.0).verify_config().unwrap();
+        assert!(matches!(
+            warnings.as_slice(),
+            [ProcessorWarning::SuspiciousConfig(_)]
+        ));
+        assert!(blur(BlurKind::Box, 1.0).verify_config().unwrap().is_empty());
+    }
+}
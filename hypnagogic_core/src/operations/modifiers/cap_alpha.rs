@@ -0,0 +1,118 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, with_alpha};
+
+/// Clamps the alpha of every pixel in the targeted icon states to at most
+/// `max_alpha`, for permanently translucent ghost variants. Pixels that are
+/// already more transparent than that are left alone, rather than everything
+/// being scaled down together
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CapAlpha {
+    /// The most opaque any pixel can be, from 0 to 255
+    pub max_alpha: u8,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for CapAlpha {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                *pixel = with_alpha(*pixel, alpha(*pixel).min(self.max_alpha));
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn clamps_opaque_and_keeps_translucent() {
+        let mut image = RgbaImage::new(3, 1);
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        image.put_pixel(1, 0, Rgba([10, 20, 30, 50]));
+        image.put_pixel(2, 0, Rgba([10, 20, 30, 128]));
+        let state = |name: &str| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(image.clone())],
+                ..Default::default()
+            }
+        };
+        let icon = Icon {
+            width: 3,
+            height: 1,
+            states: vec![state("ghost"), state("solid")],
+            ..Default::default()
+        };
+        let config = CapAlpha {
+            max_alpha: 128,
+            targets: StateTargets {
+                target_states: vec!["ghost".to_string()],
+                ..Default::default()
+            },
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let ghost = &output.states[0].images[0];
+        assert_eq!(ghost.get_pixel(0, 0), Rgba([10, 20, 30, 128]));
+        assert_eq!(ghost.get_pixel(1, 0), Rgba([10, 20, 30, 50]));
+        assert_eq!(ghost.get_pixel(2, 0), Rgba([10, 20, 30, 128]));
+        // Untargeted states keep full opacity
+        assert_eq!(
+            output.states[1].images[0].get_pixel(0, 0),
+            Rgba([10, 20, 30, 255])
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::icon_ops::{content_bounds, translate_image, Bounds};
+
+fn yes() -> bool {
+    true
+}
+
+/// How much of a state's content `Center` measures at once
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CenterEach {
+    /// Every frame and dir of a state moves by the same amount, measured from
+    /// the bounds of the content across all of them, so animations don't
+    /// jitter
+    #[default]
+    State,
+    /// Each frame and dir is centered on its own
+    Frame,
+}
+
+/// Moves the content of the targeted icon states to the middle of the icon,
+/// for sprites that drifted off-center while being drawn. The icon keeps its
+/// size. When the space either side can't be split evenly, the extra pixel
+/// goes after the content.
+///
+/// States with no content at all are left as they are
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Center {
+    /// Center the content left to right
+    #[serde(default = "yes")]
+    pub horizontal: bool,
+    /// Center the content top to bottom
+    #[serde(default = "yes")]
+    pub vertical: bool,
+    #[serde(default)]
+    pub each: CenterEach,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Center {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        for state in &mut icon.states {
+            if self.targets.matches(&state.name) {
+                *state = self.center_state(state);
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !self.horizontal && !self.vertical {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "horizontal and vertical are both off, so nothing is moved".to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl Center {
+    fn center_state(&self, state: &IconState) -> IconState {
+        let images = match self.each {
+            CenterEach::State => {
+                let Some(bounds) = state
+                    .images
+                    .iter()
+                    .filter_map(content_bounds)
+                    .reduce(Bounds::union)
+                else {
+                    // Nothing to center
+                    return state.clone();
+                };
+                state
+                    .images
+                    .iter()
+                    .map(|image| self.move_to_center(image, bounds))
+                    .collect()
+            }
+            CenterEach::Frame => {
+                state
+                    .images
+                    .iter()
+                    .map(|image| {
+                        match content_bounds(image) {
+                            Some(bounds) => self.move_to_center(image, bounds),
+                            None => image.clone(),
+                        }
+                    })
+                    .collect()
+            }
+        };
+        IconState {
+            images,
+            ..state.clone()
+        }
+    }
+
+    /// Moves `bounds` of `image` to its middle, along the axes being centered
+    fn move_to_center(&self, image: &DynamicImage, bounds: Bounds) -> DynamicImage {
+        let (width, height) = image.dimensions();
+        let offset = |enabled: bool, size: u32, start: u32, length: u32| -> i64 {
+            if !enabled {
+                return 0;
+            }
+            i64::from((size - length) / 2) - i64::from(start)
+        };
+        let offset_x = offset(self.horizontal, width, bounds.x, bounds.width);
+        let offset_y = offset(self.vertical, height, bounds.y, bounds.height);
+        translate_image(image, offset_x, offset_y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    /// An 8x8 frame with a `width` by `height` block of content with its top
+    /// left at `x`, `y`
+    fn block(x: u32, y: u32, width: u32, height: u32) -> DynamicImage {
+        let mut image = RgbaImage::new(8, 8);
+        for block_x in x..x + width {
+            for block_y in y..y + height {
+                image.put_pixel(block_x, block_y, RED);
+            }
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn center(config: &Center, images: Vec<DynamicImage>) -> Vec<DynamicImage> {
+        let icon = Icon {
+            width: 8,
+            height: 8,
+            states: vec![IconState {
+                name: "sprite".to_string(),
+                frames: images.len() as u32,
+                images,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states.into_iter().next().unwrap().images
+    }
+
+    fn centerer(each: CenterEach) -> Center {
+        Center {
+            horizontal: true,
+            vertical: true,
+            each,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn off_center_sprite_lands_centered() {
+        let output = center(&centerer(CenterEach::State), vec![block(0, 1, 2, 3)]);
+        // 6 columns spare split 3 and 3, 5 rows spare split 2 and 3
+        assert_eq!(output, vec![block(3, 2, 2, 3)]);
+
+        let only_across = Center {
+            vertical: false,
+            ..centerer(CenterEach::State)
+        };
+        let output = center(&only_across, vec![block(0, 1, 2, 3)]);
+        assert_eq!(output, vec![block(3, 1, 2, 3)]);
+    }
+
+    #[test]
+    fn animations_center_together_with_a_shared_box() {
+        // A block bobbing from the left edge over by two pixels
+        let frames = vec![block(0, 0, 2, 2), block(1, 0, 2, 2), block(2, 0, 2, 2)];
+
+        let output = center(&centerer(CenterEach::State), frames.clone());
+        // The bob spans 4 columns, which get centered as one
+        assert_eq!(
+            output,
+            vec![block(2, 3, 2, 2), block(3, 3, 2, 2), block(4, 3, 2, 2)]
+        );
+
+        let output = center(&centerer(CenterEach::Frame), frames);
+        assert_eq!(output, vec![block(3, 3, 2, 2); 3]);
+    }
+
+    #[test]
+    fn empty_states_are_left_alone() {
+        let empty = vec![DynamicImage::ImageRgba8(RgbaImage::new(8, 8))];
+        assert_eq!(center(&centerer(CenterEach::State), empty.clone()), empty);
+        assert_eq!(center(&centerer(CenterEach::Frame), empty.clone()), empty);
+    }
+}
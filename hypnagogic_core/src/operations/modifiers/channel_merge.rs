@@ -0,0 +1,294 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::split_emissive::Channel;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Merges grayscale icon states, one per color channel, back into a single
+/// color state. The other half of `ChannelSplit`.
+///
+/// Each source is read as grayscale, so how bright it is becomes how much of
+/// its channel the output has. Channels without a source are 0, apart from
+/// alpha which is fully opaque. The merged state replaces the sources, in the
+/// place of the first of them, and takes its dirs, frames and delays
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ChannelMerge {
+    /// Name of the icon state to create
+    pub output_state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub red: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub green: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<String>,
+}
+
+impl IconOperationConfig for ChannelMerge {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let find_state = |name: &str| {
+            icon.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or_else(|| {
+                    ProcessorError::ConfigError(format!(
+                        "Icon state \"{name}\" was not found in the input"
+                    ))
+                })
+        };
+
+        let mut indexes = vec![];
+        let mut sources: [Option<&IconState>; 4] = [None; 4];
+        for (source, name) in sources.iter_mut().zip(self.sources()) {
+            let Some(name) = name else {
+                continue;
+            };
+            let index = find_state(name)?;
+            indexes.push(index);
+            *source = Some(&icon.states[index]);
+        }
+        let first = &icon.states[indexes[0]];
+        for state in sources.iter().flatten() {
+            if state.dirs != first.dirs || state.images.len() != first.images.len() {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Icon states \"{}\" and \"{}\" need the same dirs and frames to be merged, \
+                     but have {} and {} images",
+                    first.name,
+                    state.name,
+                    first.images.len(),
+                    state.images.len()
+                )));
+            }
+        }
+        if icon
+            .states
+            .iter()
+            .enumerate()
+            .any(|(index, state)| state.name == self.output_state && !indexes.contains(&index))
+        {
+            return Err(ProcessorError::ConfigError(format!(
+                "Can't create icon state \"{}\", one with that name already exists",
+                self.output_state
+            )));
+        }
+
+        let images = (0..first.images.len())
+            .map(|image_index| {
+                let frames = sources.map(|source| source.map(|state| &state.images[image_index]));
+                DynamicImage::ImageRgba8(merge(icon.width, icon.height, frames))
+            })
+            .collect();
+        let merged = IconState {
+            name: self.output_state.clone(),
+            images,
+            ..first.clone()
+        };
+
+        let position = *indexes.iter().min().unwrap();
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| *index == position || !indexes.contains(index))
+            .map(|(index, state)| {
+                if index == position {
+                    merged.clone()
+                } else {
+                    state
+                }
+            })
+            .collect();
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        [&self.red, &self.green, &self.blue, &self.alpha]
+            .into_iter()
+            .flatten()
+            .any(|source| source == state_name)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let names: Vec<&String> = self.sources().into_iter().flatten().collect();
+        if names.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "At least one of red, green, blue or alpha needs a source icon state".to_string(),
+            ));
+        }
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "\"{name}\" is the source of more than one channel"
+                )));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+impl ChannelMerge {
+    /// Source state names in red, green, blue, alpha order
+    fn sources(&self) -> [Option<&String>; 4] {
+        [
+            self.red.as_ref(),
+            self.green.as_ref(),
+            self.blue.as_ref(),
+            self.alpha.as_ref(),
+        ]
+    }
+}
+
+/// One output frame from the grayscale frames for each channel, in red,
+/// green, blue, alpha order
+fn merge(width: u32, height: u32, frames: [Option<&DynamicImage>; 4]) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let mut pixel = Rgba([0, 0, 0, 255]);
+        for (channel, frame) in pixel.0.iter_mut().zip(frames) {
+            if let Some(frame) = frame {
+                *channel = Channel::Red.of(frame.get_pixel(x, y));
+            }
+        }
+        pixel
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+
+    use super::*;
+    use crate::config::blocks::modifiers::StateTargets;
+    use crate::operations::modifiers::channel_split::ChannelSplit;
+    use crate::operations::OutputImage;
+
+    fn run(config: &impl IconOperationConfig, icon: Icon) -> ProcessorResult<Icon> {
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    fn shield() -> Icon {
+        let frame = |shift: u8| {
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 2, |x, y| {
+                let x = x as u8 + shift;
+                let y = y as u8;
+                Rgba([30 * x, 200 - 40 * y, 7 * x + y, 255 - 50 * x])
+            }))
+        };
+        Icon {
+            width: 4,
+            height: 2,
+            states: vec![
+                IconState {
+                    name: "lamp".to_string(),
+                    images: vec![frame(0)],
+                    ..Default::default()
+                },
+                IconState {
+                    name: "shield".to_string(),
+                    frames: 2,
+                    images: vec![frame(0), frame(1)],
+                    delay: Some(vec![1.0, 2.0]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn merge_shield() -> ChannelMerge {
+        ChannelMerge {
+            output_state: "shield".to_string(),
+            red: Some("shield_red".to_string()),
+            green: Some("shield_green".to_string()),
+            blue: Some("shield_blue".to_string()),
+            alpha: Some("shield_alpha".to_string()),
+        }
+    }
+
+    fn split_red() -> ChannelSplit {
+        ChannelSplit {
+            channels: vec![Channel::Red],
+            targets: StateTargets {
+                target_states: vec!["shield".to_string()],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn merging_split_channels_round_trips() {
+        let split = ChannelSplit {
+            channels: vec![Channel::Alpha, Channel::Blue, Channel::Red, Channel::Green],
+            targets: StateTargets {
+                target_states: vec!["shield".to_string()],
+                ..Default::default()
+            },
+        };
+        let merged = run(&merge_shield(), run(&split, shield()).unwrap()).unwrap();
+        assert_eq!(merged, shield());
+    }
+
+    #[test]
+    fn missing_channels_default() {
+        let icon = run(&split_red(), shield()).unwrap();
+        let merge = ChannelMerge {
+            output_state: "red_only".to_string(),
+            red: Some("shield_red".to_string()),
+            green: None,
+            blue: None,
+            alpha: None,
+        };
+        let output = run(&merge, icon).unwrap();
+        let names: Vec<&str> = output
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["lamp", "red_only"]);
+        let pixel = output.states[1].images[1].get_pixel(2, 1);
+        assert_eq!(pixel, Rgba([90, 0, 0, 255]));
+    }
+
+    #[test]
+    fn sources_must_line_up() {
+        let merge = ChannelMerge {
+            output_state: "mixed".to_string(),
+            red: Some("lamp".to_string()),
+            green: None,
+            blue: Some("shield".to_string()),
+            alpha: None,
+        };
+        assert!(run(&merge, shield()).is_err());
+
+        let none = ChannelMerge {
+            output_state: "nothing".to_string(),
+            red: None,
+            green: None,
+            blue: None,
+            alpha: None,
+        };
+        assert!(none.verify_config().is_err());
+        let repeated = ChannelMerge {
+            green: Some("shield_red".to_string()),
+            ..merge_shield()
+        };
+        assert!(repeated.verify_config().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::split_emissive::Channel;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+fn every_channel() -> Vec<Channel> {
+    vec![Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha]
+}
+
+/// Splits the targeted icon states up by color channel, for authoring
+/// shaders. Each channel becomes its own grayscale state, fully opaque, where
+/// white is the most of that channel and black none of it.
+///
+/// The channel states replace the source, named after it with `_red`,
+/// `_green`, `_blue` or `_alpha` on the end
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ChannelSplit {
+    /// Which channels to make states for, in the order they're added
+    #[serde(default = "every_channel")]
+    pub channels: Vec<Channel>,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ChannelSplit {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut states = vec![];
+        for state in &icon.states {
+            if !self.targets.matches(&state.name) {
+                states.push(state.clone());
+                continue;
+            }
+            for &channel in &self.channels {
+                let name = format!("{}{}", state.name, suffix(channel));
+                if icon.states.iter().any(|existing| existing.name == name) {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "Can't add channel state \"{name}\", an icon state with that name already \
+                         exists"
+                    )));
+                }
+                states.push(IconState {
+                    name,
+                    images: state
+                        .images
+                        .iter()
+                        .map(|image| DynamicImage::ImageRgba8(grayscale(image, channel)))
+                        .collect(),
+                    ..state.clone()
+                });
+            }
+        }
+
+        let mut icon = icon.clone();
+        icon.states = states;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.channels.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "channels can't be empty, or split states would be removed outright".to_string(),
+            ));
+        }
+        for (index, channel) in self.channels.iter().enumerate() {
+            if self.channels[..index].contains(channel) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "{channel:?} is listed in channels more than once"
+                )));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+fn suffix(channel: Channel) -> &'static str {
+    match channel {
+        Channel::Red => "_red",
+        Channel::Green => "_green",
+        Channel::Blue => "_blue",
+        Channel::Alpha => "_alpha",
+    }
+}
+
+/// One channel of `image`, as an opaque grayscale image
+fn grayscale(image: &DynamicImage, channel: Channel) -> RgbaImage {
+    let mut output = image.to_rgba8();
+    for pixel in output.pixels_mut() {
+        let value = channel.of(*pixel);
+        *pixel = Rgba([value, value, value, 255]);
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::GenericImageView;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A 4x1 state with every pixel a different color and alpha
+    fn split(channels: Vec<Channel>) -> Icon {
+        let image = RgbaImage::from_fn(4, 1, |x, _| {
+            let x = x as u8;
+            Rgba([200 - x, 10 + x, 90, 40 * x + 15])
+        });
+        let icon = Icon {
+            width: 4,
+            height: 1,
+            states: vec![IconState {
+                name: "shield".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = ChannelSplit {
+            channels,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    #[test]
+    fn every_channel_gets_a_state() {
+        let icon = split(every_channel());
+        let names: Vec<&str> = icon
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["shield_red", "shield_green", "shield_blue", "shield_alpha"]
+        );
+        let red = &icon.states[0].images[0];
+        assert_eq!(red.get_pixel(1, 0), Rgba([199, 199, 199, 255]));
+    }
+
+    #[test]
+    fn alpha_state_holds_the_source_alpha() {
+        let icon = split(vec![Channel::Alpha]);
+        assert_eq!(icon.states.len(), 1);
+        for (x, _, pixel) in icon.states[0].images[0].pixels() {
+            let alpha = 40 * x as u8 + 15;
+            assert_eq!(pixel, Rgba([alpha, alpha, alpha, 255]));
+        }
+    }
+
+    #[test]
+    fn channels_must_be_listed_once() {
+        let empty = ChannelSplit {
+            channels: vec![],
+            targets: StateTargets::default(),
+        };
+        assert!(empty.verify_config().is_err());
+        let repeated = ChannelSplit {
+            channels: vec![Channel::Red, Channel::Red],
+            targets: StateTargets::default(),
+        };
+        assert!(repeated.verify_config().is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::{blend_pixel, BlendMode};
+use crate::util::color::Color;
+
+fn four() -> u32 {
+    4
+}
+
+fn light_gray() -> Color {
+    Color::new_rgb(204, 204, 204)
+}
+
+fn dark_gray() -> Color {
+    Color::new_rgb(153, 153, 153)
+}
+
+/// Bakes a checkerboard in behind the targeted icon states, the same way image
+/// editors show transparency, so screenshots and previews make it obvious
+/// which parts of a sprite are see through. The output is fully opaque.
+///
+/// The checkerboard starts with `light` in the top left corner of every frame
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CheckerBake {
+    /// Width and height of each square, in pixels
+    #[serde(default = "four")]
+    pub cell_size: u32,
+    #[serde(default = "light_gray")]
+    pub light: Color,
+    #[serde(default = "dark_gray")]
+    pub dark: Color,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for CheckerBake {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        // The result has to be opaque, so the checker colors are too
+        let [light, dark] = [self.light, self.dark].map(|color| {
+            Rgba::from(Color {
+                alpha: 255,
+                ..color
+            })
+        });
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for (x, y, pixel) in frame.enumerate_pixels_mut() {
+                                let cell = x / self.cell_size + y / self.cell_size;
+                                let backdrop = if cell.is_multiple_of(2) { light } else { dark };
+                                *pixel = blend_pixel(backdrop, *pixel, BlendMode::Normal);
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.cell_size == 0 {
+            return Err(ProcessorError::ConfigError(
+                "cell_size must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+    use crate::util::color::{alpha, TRANSPARENT};
+
+    const SPRITE: Rgba<u8> = Rgba([0, 200, 0, 255]);
+
+    #[test]
+    fn checker_shows_through_transparency_only() {
+        // Transparent, with an opaque pixel in the second cell of the top row
+        let mut image = RgbaImage::from_pixel(4, 4, TRANSPARENT);
+        image.put_pixel(2, 0, SPRITE);
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "ghost".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = CheckerBake {
+            cell_size: 2,
+            light: Color::new_rgb(255, 255, 255),
+            dark: Color::new_rgb(0, 0, 0),
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let baked = &output.states[0].images[0];
+
+        assert_eq!(baked.get_pixel(2, 0), SPRITE);
+        assert_eq!(baked.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(baked.get_pixel(1, 1), Rgba([255, 255, 255, 255]));
+        assert_eq!(baked.get_pixel(3, 1), Rgba([0, 0, 0, 255]));
+        assert_eq!(baked.get_pixel(0, 2), Rgba([0, 0, 0, 255]));
+        assert_eq!(baked.get_pixel(2, 2), Rgba([255, 255, 255, 255]));
+        assert!(baked.pixels().all(|(_, _, pixel)| alpha(pixel) == 255));
+    }
+}
@@ -0,0 +1,164 @@
+use dmi::icon::IconState;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::drop_frames::DroppedDelay;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+fn discard() -> DroppedDelay {
+    DroppedDelay::Discard
+}
+
+/// Cuts the animations of the targeted icon states down to at most
+/// `max_frames` frames, dropping every dir of the frames past it. States that
+/// are already short enough are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ClampFrames {
+    pub max_frames: u32,
+    /// What happens to the delay of the frames cut off. `redistribute` adds
+    /// it all on to the last frame kept
+    #[serde(default = "discard")]
+    pub dropped_delay: DroppedDelay,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ClampFrames {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if self.targets.matches(&state.name) && state.frames > self.max_frames {
+                    self.clamp(state)
+                } else {
+                    state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.max_frames == 0 {
+            return Err(ProcessorError::ConfigError(
+                "max_frames must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl ClampFrames {
+    fn clamp(&self, state: IconState) -> IconState {
+        let kept = self.max_frames as usize;
+        // Images are stored frame by frame, with every dir of a frame together
+        let images = state.images[..kept * usize::from(state.dirs)].to_vec();
+        let delay = state.delay.as_ref().map(|delays| {
+            let mut kept_delays = delays[..kept.min(delays.len())].to_vec();
+            if self.dropped_delay == DroppedDelay::Redistribute {
+                let dropped: f32 = delays.iter().skip(kept).sum();
+                if let Some(last) = kept_delays.last_mut() {
+                    *last += dropped;
+                }
+            }
+            kept_delays
+        });
+
+        IconState {
+            frames: self.max_frames,
+            images,
+            delay,
+            ..state
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn clamp(dropped_delay: DroppedDelay) -> IconState {
+        let frames = 10;
+        let dirs = 4;
+        let state = IconState {
+            name: "flicker".to_string(),
+            dirs,
+            frames,
+            images: (0..frames)
+                .flat_map(|frame| {
+                    (0..dirs).map(move |dir| {
+                        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                            1,
+                            1,
+                            Rgba([frame as u8, dir, 0, 255]),
+                        ))
+                    })
+                })
+                .collect(),
+            delay: Some(vec![1.0; frames as usize]),
+            ..Default::default()
+        };
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![state],
+            ..Default::default()
+        };
+        let config = ClampFrames {
+            max_frames: 4,
+            dropped_delay,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].clone()
+    }
+
+    #[test]
+    fn keeps_first_frames_of_every_dir() {
+        let state = clamp(DroppedDelay::Discard);
+        assert_eq!((state.frames, state.dirs), (4, 4));
+        assert_eq!(state.images.len(), 16);
+        for (index, image) in state.images.iter().enumerate() {
+            let (frame, dir) = (index / 4, index % 4);
+            assert_eq!(
+                image.get_pixel(0, 0),
+                Rgba([frame as u8, dir as u8, 0, 255])
+            );
+        }
+        assert_eq!(state.delay, Some(vec![1.0; 4]));
+    }
+
+    #[test]
+    fn redistribute_folds_in_to_last_frame() {
+        let state = clamp(DroppedDelay::Redistribute);
+        assert_eq!(state.delay, Some(vec![1.0, 1.0, 1.0, 7.0]));
+    }
+}
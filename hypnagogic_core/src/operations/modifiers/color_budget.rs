@@ -0,0 +1,220 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, is_transparent, rgb};
+use crate::util::quantize::{color_counts, nearest, reduce_palette};
+
+/// What `ColorBudget` does with a state that has too many colors
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Fail, listing every state over budget
+    #[default]
+    Error,
+    /// Reduce the state to a palette of the most it's allowed
+    Quantize,
+}
+
+/// Holds the targeted icon states to a limit on how many distinct colors each
+/// can use, across all of its dirs and frames, for targets with strict palette
+/// budgets. Fully transparent pixels don't count, and neither does alpha.
+///
+/// How many colors each state uses is logged, so artists can see how close
+/// they are. States within budget are never changed
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ColorBudget {
+    /// The most colors a state can use, at least 1
+    pub max_colors: u32,
+    #[serde(default)]
+    pub on_overflow: Overflow,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ColorBudget {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let max_colors = self.max_colors as usize;
+
+        let mut over_budget = vec![];
+        let mut states = vec![];
+        for state in &icon.states {
+            if !self.targets.matches(&state.name) {
+                states.push(state.clone());
+                continue;
+            }
+            let counts = color_counts(&state.images);
+            info!(
+                state = ?state.name,
+                "Uses {} of {} colors",
+                counts.len(),
+                self.max_colors
+            );
+            if counts.len() <= max_colors {
+                states.push(state.clone());
+                continue;
+            }
+            match self.on_overflow {
+                Overflow::Error => over_budget.push((state.name.clone(), counts.len())),
+                Overflow::Quantize => {
+                    let palette = reduce_palette(&counts, max_colors);
+                    states.push(IconState {
+                        images: state
+                            .images
+                            .iter()
+                            .map(|frame| {
+                                let mut frame = frame.to_rgba8();
+                                for pixel in frame.pixels_mut() {
+                                    if !is_transparent(*pixel) {
+                                        let [red, green, blue] = nearest(&palette, rgb(*pixel));
+                                        *pixel = Rgba([red, green, blue, alpha(*pixel)]);
+                                    }
+                                }
+                                DynamicImage::ImageRgba8(frame)
+                            })
+                            .collect(),
+                        ..state.clone()
+                    });
+                }
+            }
+        }
+        if !over_budget.is_empty() {
+            return Err(ProcessorError::TooManyColors {
+                limit: self.max_colors,
+                states: over_budget,
+            });
+        }
+
+        let mut icon = icon.clone();
+        icon.states = states;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.max_colors == 0 {
+            return Err(ProcessorError::ConfigError(
+                "max_colors must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A state with a pixel of each color in `colors`, plus a transparent one
+    fn state(name: &str, colors: &[[u8; 4]]) -> IconState {
+        let mut image = RgbaImage::new(colors.len() as u32 + 1, 1);
+        for (x, &color) in colors.iter().enumerate() {
+            image.put_pixel(x as u32, 0, Rgba(color));
+        }
+        IconState {
+            name: name.to_string(),
+            images: vec![DynamicImage::ImageRgba8(image)],
+            ..Default::default()
+        }
+    }
+
+    fn icon() -> Icon {
+        Icon {
+            width: 7,
+            height: 1,
+            states: vec![
+                state("small", &[[0, 0, 0, 255], [255, 0, 0, 255], [0, 0, 0, 40]]),
+                state(
+                    "big",
+                    &[
+                        [0, 0, 0, 255],
+                        [8, 0, 0, 255],
+                        [200, 0, 0, 255],
+                        [210, 0, 0, 120],
+                        [0, 0, 250, 255],
+                        [0, 0, 255, 255],
+                    ],
+                ),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn budget(icon: Icon, on_overflow: Overflow) -> ProcessorResult<Icon> {
+        let config = ColorBudget {
+            max_colors: 3,
+            on_overflow,
+            targets: StateTargets::default(),
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    #[test]
+    fn within_budget_is_unchanged() {
+        let mut icon = icon();
+        icon.states.truncate(1);
+        assert_eq!(budget(icon.clone(), Overflow::Error).unwrap(), icon);
+        assert_eq!(budget(icon.clone(), Overflow::Quantize).unwrap(), icon);
+    }
+
+    #[test]
+    fn over_budget_errors() {
+        let Err(ProcessorError::TooManyColors { limit, states }) = budget(icon(), Overflow::Error)
+        else {
+            panic!("Expected too many colors");
+        };
+        assert_eq!(limit, 3);
+        assert_eq!(states, vec![("big".to_string(), 6)]);
+    }
+
+    #[test]
+    fn over_budget_quantizes() {
+        let output = budget(icon(), Overflow::Quantize).unwrap();
+        assert_eq!(output.states[0], icon().states[0]);
+        let big = &output.states[1];
+        assert_eq!(color_counts(&big.images).len(), 3);
+        let pixels: Vec<[u8; 4]> = big.images[0]
+            .to_rgba8()
+            .pixels()
+            .map(|pixel| pixel.0)
+            .collect();
+        assert_eq!(
+            pixels,
+            vec![
+                [4, 0, 0, 255],
+                [4, 0, 0, 255],
+                [205, 0, 0, 255],
+                // Alpha is kept
+                [205, 0, 0, 120],
+                [0, 0, 253, 255],
+                [0, 0, 253, 255],
+                [0, 0, 0, 0],
+            ]
+        );
+    }
+}
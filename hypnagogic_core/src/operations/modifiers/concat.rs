@@ -0,0 +1,236 @@
+use dmi::icon::IconState;
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Plays icon states one after another as a single animation, adding it as a
+/// new icon state right after the first of them, for joining up sequences
+/// like a door opening and then staying open. The sources are kept.
+///
+/// Every frame keeps its own delay, with frames that had none taking 1 tick.
+/// The sources need the same dirs and frame size, and the result takes the
+/// rest of its settings, like looping, from the first of them
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Concat {
+    /// Names of the icon states to join, in the order they play
+    pub states: Vec<String>,
+    /// Name of the icon state to create
+    pub output_state: String,
+}
+
+impl IconOperationConfig for Concat {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let find_state = |name: &str| {
+            icon.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or_else(|| {
+                    ProcessorError::ConfigError(format!(
+                        "Icon state \"{name}\" was not found in the input"
+                    ))
+                })
+        };
+        let indexes = self
+            .states
+            .iter()
+            .map(|name| find_state(name))
+            .collect::<ProcessorResult<Vec<usize>>>()?;
+        if find_state(&self.output_state).is_ok() {
+            return Err(ProcessorError::ConfigError(format!(
+                "Can't create icon state \"{}\", one with that name already exists",
+                self.output_state
+            )));
+        }
+
+        let sources: Vec<&IconState> = indexes.iter().map(|&index| &icon.states[index]).collect();
+        let joined = self.join(&sources)?;
+
+        let mut icon = icon.clone();
+        icon.states.insert(indexes[0] + 1, joined);
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        self.states.iter().any(|state| state == state_name)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.states.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "states needs at least one icon state to join".to_string(),
+            ));
+        }
+        if self.states.len() == 1 {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(format!(
+                "Only \"{}\" is listed in states, so the result is just a copy of it",
+                self.states[0]
+            ))]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl Concat {
+    fn join(&self, sources: &[&IconState]) -> ProcessorResult<IconState> {
+        let first = sources[0];
+        let size = |state: &IconState| state.images.first().map(GenericImageView::dimensions);
+        for state in &sources[1..] {
+            if state.dirs != first.dirs {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Icon states \"{}\" and \"{}\" need the same dirs to be joined, but have {} \
+                     and {}",
+                    first.name, state.name, first.dirs, state.dirs
+                )));
+            }
+            if let (Some(first_size), Some(size)) = (size(first), size(state)) {
+                if first_size != size {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "Icon states \"{}\" and \"{}\" need the same frame size to be joined, but \
+                         are {}x{} and {}x{}",
+                        first.name, state.name, first_size.0, first_size.1, size.0, size.1
+                    )));
+                }
+            }
+        }
+
+        // Images are stored frame by frame, with every dir of a frame
+        // together, so each source's frames can follow on as they are
+        let images = sources
+            .iter()
+            .flat_map(|state| state.images.iter().cloned())
+            .collect();
+        let delays: Vec<f32> = sources
+            .iter()
+            .flat_map(|state| {
+                state
+                    .delay
+                    .clone()
+                    .unwrap_or_else(|| vec![1.0; state.frames as usize])
+            })
+            .collect();
+        let frames = sources.iter().map(|state| state.frames).sum();
+        Ok(IconState {
+            name: self.output_state.clone(),
+            frames,
+            images,
+            delay: (frames > 1).then_some(delays),
+            ..first.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A 1x1 image with its red channel set to `marker`, to tell them apart
+    fn marked(marker: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([marker, 0, 0, 255])))
+    }
+
+    fn markers(state: &IconState) -> Vec<u8> {
+        state
+            .images
+            .iter()
+            .map(|image| image.get_pixel(0, 0).0[0])
+            .collect()
+    }
+
+    fn animation(name: &str, dirs: u8, markers: &[u8], delay: Option<Vec<f32>>) -> IconState {
+        IconState {
+            name: name.to_string(),
+            dirs,
+            frames: markers.len() as u32 / u32::from(dirs),
+            images: markers.iter().copied().map(marked).collect(),
+            delay,
+            ..Default::default()
+        }
+    }
+
+    fn concat(names: &[&str], states: Vec<IconState>) -> ProcessorResult<Icon> {
+        let config = Concat {
+            states: names.iter().map(ToString::to_string).collect(),
+            output_state: "door_sequence".to_string(),
+        };
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    #[test]
+    fn frames_follow_on_within_each_dir() {
+        let output = concat(
+            &["opening", "open"],
+            vec![
+                // Frame 1 is 10-11, frame 2 is 20-21
+                animation("opening", 2, &[10, 11, 20, 21], Some(vec![1.0, 2.0])),
+                animation("open", 2, &[30, 31], None),
+            ],
+        )
+        .unwrap();
+
+        let names: Vec<&str> = output
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["opening", "door_sequence", "open"]);
+        let joined = &output.states[1];
+        assert_eq!((joined.dirs, joined.frames), (2, 3));
+        assert_eq!(markers(joined), vec![10, 11, 20, 21, 30, 31]);
+        assert_eq!(joined.delay, Some(vec![1.0, 2.0, 1.0]));
+    }
+
+    #[test]
+    fn mismatched_sources_are_refused() {
+        let result = concat(
+            &["opening", "open"],
+            vec![
+                animation("opening", 2, &[10, 11, 20, 21], Some(vec![1.0, 2.0])),
+                animation("open", 1, &[30], None),
+            ],
+        );
+        let Err(ProcessorError::ConfigError(message)) = result else {
+            panic!("Expected a config error");
+        };
+        assert!(message.contains("same dirs"), "{message}");
+
+        let mut big = animation("open", 1, &[30], None);
+        big.images = vec![DynamicImage::ImageRgba8(RgbaImage::new(2, 2))];
+        let result = concat(
+            &["opening", "open"],
+            vec![
+                animation("opening", 1, &[10, 20], Some(vec![1.0, 2.0])),
+                big,
+            ],
+        );
+        let Err(ProcessorError::ConfigError(message)) = result else {
+            panic!("Expected a config error");
+        };
+        assert!(message.contains("same frame size"), "{message}");
+    }
+}
@@ -0,0 +1,223 @@
+use dmi::icon::{Icon, IconState};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{
+    IconOperationConfig,
+    InputIcon,
+    NamedIcon,
+    OperationMode,
+    OutputImage,
+    ProcessorPayload,
+};
+use crate::util::contact_sheet::ContactSheet;
+
+/// Name of the icon state the contact sheet is baked into
+pub const CONTACT_STATE: &str = "__contact__";
+
+fn default_columns() -> u32 {
+    ContactSheet::default().columns
+}
+
+fn default_padding() -> u32 {
+    ContactSheet::default().padding
+}
+
+fn yes() -> bool {
+    true
+}
+
+/// Bakes a contact sheet of the targeted icon states into a dmi, for
+/// reviewing a whole file at a glance: the first frame (south facing) of each
+/// state laid out in a labeled grid, as a single `__contact__` state.
+///
+/// The grid is bigger than the input's icon states, so it goes in a dmi of its
+/// own named `{input}-contact`, sized to fit it. The input is passed through
+/// untouched alongside it. Since that makes two outputs, this has to be the
+/// last operation
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ContactState {
+    /// How many cells go in each row
+    #[serde(default = "default_columns")]
+    pub columns: u32,
+    /// Gap around and between cells, in pixels
+    #[serde(default = "default_padding")]
+    pub padding: u32,
+    /// Whether animated states get a cell, showing their first frame
+    #[serde(default = "yes")]
+    pub include_animated: bool,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ContactState {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let shown = Icon {
+            states: icon
+                .states
+                .iter()
+                .filter(|state| {
+                    self.targets.matches(&state.name)
+                        && (self.include_animated || state.frames <= 1)
+                })
+                .cloned()
+                .collect(),
+            ..icon.clone()
+        };
+        let sheet = ContactSheet {
+            columns: self.columns,
+            states_per_page: usize::MAX,
+            padding: self.padding,
+        };
+        let Some(page) = sheet.render(&shown).pop() else {
+            return Err(ProcessorError::ConfigError(
+                "None of the input's icon states are shown on the contact sheet".to_string(),
+            ));
+        };
+
+        let contact = Icon {
+            width: page.width(),
+            height: page.height(),
+            states: vec![IconState {
+                name: CONTACT_STATE.to_string(),
+                dirs: 1,
+                frames: 1,
+                images: vec![DynamicImage::ImageRgba8(page)],
+                ..Default::default()
+            }],
+            ..icon.clone()
+        };
+
+        Ok(ProcessorPayload::MultipleNamed(vec![
+            NamedIcon::from_icon(icon.clone()),
+            NamedIcon {
+                path_hint: None,
+                name_hint: Some("contact".to_string()),
+                path: None,
+                image: OutputImage::Dmi(contact),
+            },
+        ]))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.columns == 0 {
+            return Err(ProcessorError::ConfigError(
+                "columns must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::RgbaImage;
+
+    use super::*;
+
+    fn icon() -> Icon {
+        let state = |name: &str, frames: u32| {
+            IconState {
+                name: name.to_string(),
+                frames,
+                images: (0..frames)
+                    .map(|_| DynamicImage::ImageRgba8(RgbaImage::new(4, 4)))
+                    .collect(),
+                ..Default::default()
+            }
+        };
+        Icon {
+            width: 4,
+            height: 4,
+            states: vec![state("wall", 1), state("door", 1), state("door_opening", 6)],
+            ..Default::default()
+        }
+    }
+
+    fn contact(config: &ContactState) -> Icon {
+        let ProcessorPayload::MultipleNamed(outputs) = config
+            .do_operation(&InputIcon::Dmi(icon()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected named icons");
+        };
+        let [original, contact] = &outputs[..] else {
+            panic!("Expected two outputs");
+        };
+        let OutputImage::Dmi(original) = &original.image else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(original, &icon());
+        assert_eq!(contact.name_hint.as_deref(), Some("contact"));
+        let OutputImage::Dmi(contact) = &contact.image else {
+            panic!("Expected a dmi");
+        };
+        contact.clone()
+    }
+
+    fn config(columns: u32, padding: u32, include_animated: bool) -> ContactState {
+        ContactState {
+            columns,
+            padding,
+            include_animated,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn contact_state_fills_the_grid() {
+        let contact = contact(&config(2, 3, true));
+        let (cell_width, cell_height) = ContactSheet::cell_size(&icon());
+
+        // Three states over two columns makes two rows
+        let width = 2 * (cell_width + 3) + 3;
+        let height = 2 * (cell_height + 3) + 3;
+        assert_eq!((contact.width, contact.height), (width, height));
+        let [state] = &contact.states[..] else {
+            panic!("Expected a single state");
+        };
+        assert_eq!(state.name, CONTACT_STATE);
+        assert_eq!((state.dirs, state.frames), (1, 1));
+        assert_eq!(state.images[0].width(), width);
+        assert_eq!(state.images[0].height(), height);
+    }
+
+    #[test]
+    fn animated_states_can_be_left_out() {
+        let contact = contact(&config(8, 2, false));
+        let (cell_width, cell_height) = ContactSheet::cell_size(&icon());
+
+        // Only the two still states, in one row
+        assert_eq!(
+            (contact.width, contact.height),
+            (2 * (cell_width + 2) + 2, cell_height + 4)
+        );
+    }
+
+    #[test]
+    fn nothing_to_show_is_an_error() {
+        let everything_excluded = ContactState {
+            targets: StateTargets {
+                exclude_states: vec!["wall".to_string(), "door".to_string()],
+                ..Default::default()
+            },
+            ..config(8, 2, false)
+        };
+        assert!(everything_excluded
+            .do_operation(&InputIcon::Dmi(icon()), OperationMode::Standard)
+            .is_err());
+        assert!(config(0, 2, true).verify_config().is_err());
+    }
+}
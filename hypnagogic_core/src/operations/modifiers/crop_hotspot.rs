@@ -0,0 +1,189 @@
+use dmi::icon::{Hotspot, IconState};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::validate_dimensions::check_dimensions;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Crops every frame of the targeted icon states to `width` by `height`,
+/// centered on the state's hotspot, for things like item-in-hand sprites
+/// drawn on a bigger canvas. Wherever the crop runs past the edge of a frame
+/// is left transparent. For an even size, the hotspot goes just right of
+/// and below the middle.
+///
+/// Each cropped state's hotspot is moved along with its pixels, and the dmi
+/// takes on the new size, so any state left out has to be that size already
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CropHotspot {
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for CropHotspot {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        for state in &mut icon.states {
+            if self.targets.matches(&state.name) {
+                *state = self.crop_state(state, icon.height)?;
+            }
+        }
+        (icon.width, icon.height) = (self.width, self.height);
+        check_dimensions(&icon)?;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ProcessorError::ConfigError(
+                "width and height have to be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl CropHotspot {
+    fn crop_state(&self, state: &IconState, frame_height: u32) -> ProcessorResult<IconState> {
+        let Some(hotspot) = state.hotspot else {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" has no hotspot to crop around",
+                state.name
+            )));
+        };
+        // Hotspots count up from the bottom left, images down from the top left
+        let hotspot_x = i64::from(hotspot.x);
+        let hotspot_y = i64::from(frame_height) - 1 - i64::from(hotspot.y);
+        let (center_x, center_y) = (self.width / 2, self.height / 2);
+        let left = hotspot_x - i64::from(center_x);
+        let top = hotspot_y - i64::from(center_y);
+
+        let images = state
+            .images
+            .iter()
+            .map(|image| crop_padded(image, left, top, self.width, self.height))
+            .collect();
+        Ok(IconState {
+            images,
+            hotspot: Some(Hotspot {
+                x: center_x,
+                y: self.height - 1 - center_y,
+            }),
+            ..state.clone()
+        })
+    }
+}
+
+/// The `width` by `height` region of `image` with its top left at `left` and
+/// `top`, which can be outside of it. Anything outside the image is
+/// transparent
+fn crop_padded(image: &DynamicImage, left: i64, top: i64, width: u32, height: u32) -> DynamicImage {
+    let (image_width, image_height) = image.dimensions();
+    let output = RgbaImage::from_fn(width, height, |x, y| {
+        let source_x = left + i64::from(x);
+        let source_y = top + i64::from(y);
+        if (0..i64::from(image_width)).contains(&source_x)
+            && (0..i64::from(image_height)).contains(&source_y)
+        {
+            image.get_pixel(source_x as u32, source_y as u32)
+        } else {
+            image::Rgba([0, 0, 0, 0])
+        }
+    });
+    DynamicImage::ImageRgba8(output)
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::Rgba;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const MARK: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const FILL: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// A 16x16 state filled in blue, with the pixel its hotspot is on marked
+    /// red. `hotspot` is counted from the top left, like the image
+    fn held_item(hotspot: Option<(u32, u32)>) -> IconState {
+        let mut image = RgbaImage::from_pixel(16, 16, FILL);
+        if let Some((x, y)) = hotspot {
+            image.put_pixel(x, y, MARK);
+        }
+        IconState {
+            name: "inhand".to_string(),
+            dirs: 4,
+            images: vec![DynamicImage::ImageRgba8(image); 4],
+            hotspot: hotspot.map(|(x, y)| Hotspot { x, y: 15 - y }),
+            ..Default::default()
+        }
+    }
+
+    fn crop(width: u32, height: u32, state: IconState) -> ProcessorResult<Icon> {
+        let icon = Icon {
+            width: 16,
+            height: 16,
+            states: vec![state],
+            ..Default::default()
+        };
+        let config = CropHotspot {
+            width,
+            height,
+            targets: StateTargets::default(),
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    #[test]
+    fn hotspot_ends_up_in_the_middle() {
+        let icon = crop(5, 7, held_item(Some((6, 9)))).unwrap();
+        assert_eq!((icon.width, icon.height), (5, 7));
+        let state = &icon.states[0];
+        assert_eq!(state.hotspot, Some(Hotspot { x: 2, y: 3 }));
+        for image in &state.images {
+            assert_eq!(image.dimensions(), (5, 7));
+            assert_eq!(image.get_pixel(2, 3), MARK);
+            assert_eq!(image.get_pixel(0, 0), FILL);
+        }
+    }
+
+    #[test]
+    fn crops_past_the_edge_are_padded() {
+        let icon = crop(6, 6, held_item(Some((0, 15)))).unwrap();
+        let image = &icon.states[0].images[0];
+        assert_eq!(image.get_pixel(3, 3), MARK);
+        // Left of and below the frame
+        assert_eq!(image.get_pixel(2, 3), Rgba([0, 0, 0, 0]));
+        assert_eq!(image.get_pixel(3, 4), Rgba([0, 0, 0, 0]));
+        // Up and to the right is still in it
+        assert_eq!(image.get_pixel(5, 0), FILL);
+    }
+
+    #[test]
+    fn states_need_a_hotspot() {
+        assert!(crop(8, 8, held_item(None)).is_err());
+    }
+}
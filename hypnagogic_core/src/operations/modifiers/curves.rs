@@ -0,0 +1,256 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// What one channel's values turn in to
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Curve {
+    /// What each value from 0 to 255 becomes, all 256 of them
+    Table(Vec<u8>),
+    /// `[from, to]` points the curve passes through, joined by straight
+    /// lines. Values before the first point or after the last are held level
+    Points(Vec<[u8; 2]>),
+}
+
+impl Curve {
+    /// What every channel value maps to
+    fn lookup_table(&self) -> [u8; 256] {
+        match self {
+            Curve::Table(table) => std::array::from_fn(|value| table[value]),
+            Curve::Points(points) => {
+                let mut points = points.clone();
+                points.sort_unstable();
+                std::array::from_fn(|value| {
+                    let value = value as u8;
+                    let after = points
+                        .iter()
+                        .position(|&[from, _]| from >= value)
+                        .unwrap_or(points.len() - 1);
+                    let [to_from, to_to] = points[after];
+                    let [from_from, from_to] = points[after.saturating_sub(1)];
+                    if to_from <= value || from_from >= value {
+                        return if value < from_from { from_to } else { to_to };
+                    }
+                    let progress = f32::from(value - from_from) / f32::from(to_from - from_from);
+                    (f32::from(from_to) + (f32::from(to_to) - f32::from(from_to)) * progress)
+                        .round() as u8
+                })
+            }
+        }
+    }
+
+    fn verify(&self, channel: &str) -> ProcessorResult<()> {
+        match self {
+            Curve::Table(table) if table.len() != 256 => {
+                Err(ProcessorError::ConfigError(format!(
+                    "The {channel} curve needs a value for each of 0 to 255, so 256 in all, but \
+                     has {}",
+                    table.len()
+                )))
+            }
+            Curve::Points(points) if points.is_empty() => {
+                Err(ProcessorError::ConfigError(format!(
+                    "The {channel} curve needs at least one point"
+                )))
+            }
+            Curve::Points(points) => {
+                let mut froms: Vec<u8> = points.iter().map(|&[from, _]| from).collect();
+                froms.sort_unstable();
+                if let Some(&[from, _]) = froms.windows(2).find(|pair| pair[0] == pair[1]) {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "The {channel} curve has more than one point for {from}"
+                    )));
+                }
+                Ok(())
+            }
+            Curve::Table(_) => Ok(()),
+        }
+    }
+}
+
+/// Runs each channel of every pixel in the targeted icon states through a
+/// curve, for tone adjustments. This is the general form of brightness,
+/// contrast and gamma. Channels without a curve are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Curves {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub red: Option<Curve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub green: Option<Curve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blue: Option<Curve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<Curve>,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Curves {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let tables: Vec<(usize, [u8; 256])> = self
+            .channels()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(channel, (_, curve))| Some((channel, curve?.lookup_table())))
+            .collect();
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                for (channel, table) in &tables {
+                                    pixel.0[*channel] = table[usize::from(pixel.0[*channel])];
+                                }
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let mut given = 0;
+        for (channel, curve) in self.channels() {
+            if let Some(curve) = curve {
+                curve.verify(channel)?;
+                given += 1;
+            }
+        }
+        if given == 0 {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "No curves are given, so every pixel is left as it is".to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl Curves {
+    /// Each channel's curve, in RGBA order
+    fn channels(&self) -> [(&'static str, Option<&Curve>); 4] {
+        [
+            ("red", self.red.as_ref()),
+            ("green", self.green.as_ref()),
+            ("blue", self.blue.as_ref()),
+            ("alpha", self.alpha.as_ref()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn curves(red: Option<Curve>, alpha: Option<Curve>) -> Curves {
+        Curves {
+            red,
+            green: None,
+            blue: None,
+            alpha,
+            targets: StateTargets::default(),
+        }
+    }
+
+    fn apply(config: &Curves, pixel: Rgba<u8>) -> Rgba<u8> {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "lamp".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, pixel))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].images[0].get_pixel(0, 0)
+    }
+
+    #[test]
+    fn inverting_curves_flip_values() {
+        let table = Curve::Table((0..=255).rev().collect());
+        let points = Curve::Points(vec![[0, 255], [255, 0]]);
+        assert_eq!(table.lookup_table(), points.lookup_table());
+
+        let pixel = apply(&curves(Some(table), Some(points)), Rgba([10, 20, 30, 200]));
+        // Only the channels with curves change
+        assert_eq!(pixel, Rgba([245, 20, 30, 55]));
+    }
+
+    #[test]
+    fn points_are_joined_and_held_past_the_ends() {
+        let table = Curve::Points(vec![[200, 255], [100, 50], [150, 50]]).lookup_table();
+        assert_eq!(table[0], 50);
+        assert_eq!(table[100], 50);
+        assert_eq!(table[125], 50);
+        assert_eq!(table[175], 153);
+        assert_eq!(table[200], 255);
+        assert_eq!(table[255], 255);
+
+        let table = Curve::Points(vec![[64, 128]]).lookup_table();
+        assert!(table.iter().all(|&value| value == 128));
+    }
+
+    #[test]
+    fn bad_curves_are_rejected() {
+        let short = curves(Some(Curve::Table(vec![0; 255])), None);
+        assert!(short.verify_config().is_err());
+        let empty = curves(None, Some(Curve::Points(vec![])));
+        assert!(empty.verify_config().is_err());
+        let doubled = curves(Some(Curve::Points(vec![[10, 0], [10, 255]])), None);
+        assert!(doubled.verify_config().is_err());
+
+        assert_eq!(curves(None, None).verify_config().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn tables_and_points_both_deserialize() {
+        let config: Curves =
+            toml::from_str("red = [[0, 255], [255, 0]]\ngreen = [0, 1, 2]\n").unwrap();
+        assert_eq!(config.red, Some(Curve::Points(vec![[0, 255], [255, 0]])));
+        assert_eq!(config.green, Some(Curve::Table(vec![0, 1, 2])));
+    }
+}
@@ -0,0 +1,236 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::overlay::Overlay;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::BlendMode;
+use crate::util::color::{alpha, with_alpha};
+
+/// Generates a ramp of damaged variants of the targeted icon states, by
+/// overlaying crack decals at escalating intensity. A state named `wall` gets
+/// `wall_damage1` through `wall_damageN` added right after it.
+///
+/// Decals should be listed from lightest to heaviest damage, and are spread
+/// evenly over the levels. When several levels share a decal, it fades in
+/// over them, reaching full strength at the last one. Frames and dirs are
+/// matched up the same way as [`Overlay`]. The decal states are left as is
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Damage {
+    /// Names of the decal icon states, lightest first
+    pub decal_states: Vec<String>,
+    /// How many damage levels to make. Defaults to one per decal
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub levels: Option<u32>,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Damage {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let decals = self
+            .decal_states
+            .iter()
+            .map(|name| {
+                icon.states
+                    .iter()
+                    .find(|state| state.name == *name)
+                    .ok_or_else(|| {
+                        ProcessorError::ConfigError(format!(
+                            "Decal icon state \"{name}\" was not found in the input"
+                        ))
+                    })
+            })
+            .collect::<ProcessorResult<Vec<&IconState>>>()?;
+        let ramp: Vec<IconState> = self
+            .ramp()
+            .into_iter()
+            .map(|(decal, strength)| fade(decals[decal], strength))
+            .collect();
+
+        let mut output = icon.clone();
+        output.states = vec![];
+        for state in &icon.states {
+            output.states.push(state.clone());
+            if self.decal_states.contains(&state.name) || !self.targets.matches(&state.name) {
+                continue;
+            }
+            for (level, decal) in (1..).zip(&ramp) {
+                let overlay = Overlay {
+                    overlay_state: decal.name.clone(),
+                    blend_mode: self.blend_mode,
+                    targets: StateTargets::default(),
+                };
+                output.states.push(IconState {
+                    name: format!("{}_damage{level}", state.name),
+                    ..overlay.overlay_state_onto(state, decal)?
+                });
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(output))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.decal_states.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "At least one decal state is needed".to_string(),
+            ));
+        }
+        if self.levels == Some(0) {
+            return Err(ProcessorError::ConfigError(
+                "levels must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Damage {
+    /// The decal (by index) and how strongly to apply it, for every level
+    fn ramp(&self) -> Vec<(usize, f32)> {
+        let decal_count = self.decal_states.len();
+        let levels = self.levels.map_or(decal_count, |levels| levels as usize);
+        let decal_for = |level: usize| (level * decal_count).div_ceil(levels) - 1;
+        (1..=levels)
+            .map(|level| {
+                let decal = decal_for(level);
+                let sharing: Vec<usize> = (1..=levels)
+                    .filter(|&other| decal_for(other) == decal)
+                    .collect();
+                let position = sharing.iter().position(|&other| other == level).unwrap() + 1;
+                (decal, position as f32 / sharing.len() as f32)
+            })
+            .collect()
+    }
+}
+
+/// Scales the alpha of every pixel in `decal` by `strength`
+fn fade(decal: &IconState, strength: f32) -> IconState {
+    if strength >= 1.0 {
+        return decal.clone();
+    }
+    IconState {
+        images: decal
+            .images
+            .iter()
+            .map(|image| {
+                let mut image = image.to_rgba8();
+                for pixel in image.pixels_mut() {
+                    let faded = (f32::from(alpha(*pixel)) * strength).round() as u8;
+                    *pixel = with_alpha(*pixel, faded);
+                }
+                DynamicImage::ImageRgba8(image)
+            })
+            .collect(),
+        ..decal.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// A 4x4 state filled with `color`, plus `marks` pixels of `mark`
+    fn state(name: &str, color: Rgba<u8>, mark: Rgba<u8>, marks: &[(u32, u32)]) -> IconState {
+        let mut image = RgbaImage::from_pixel(4, 4, color);
+        for &(x, y) in marks {
+            image.put_pixel(x, y, mark);
+        }
+        IconState {
+            name: name.to_string(),
+            images: vec![DynamicImage::ImageRgba8(image)],
+            ..Default::default()
+        }
+    }
+
+    fn config(levels: Option<u32>) -> Damage {
+        Damage {
+            decal_states: vec!["cracks_light".to_string(), "cracks_heavy".to_string()],
+            levels,
+            blend_mode: BlendMode::Normal,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn each_level_gets_its_decal() {
+        let grey = Rgba([128, 128, 128, 255]);
+        let black = Rgba([0, 0, 0, 255]);
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![
+                state("wall", grey, grey, &[]),
+                state("cracks_light", CLEAR, black, &[(0, 0)]),
+                state("cracks_heavy", CLEAR, black, &[(0, 0), (1, 1), (2, 2)]),
+            ],
+            ..Default::default()
+        };
+
+        let ProcessorPayload::Single(output) = config(None)
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let names: Vec<&str> = output
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "wall",
+                "wall_damage1",
+                "wall_damage2",
+                "cracks_light",
+                "cracks_heavy"
+            ]
+        );
+
+        let light = &output.states[1].images[0];
+        assert_eq!(light.get_pixel(0, 0), black);
+        assert_eq!(light.get_pixel(1, 1), grey);
+        let heavy = &output.states[2].images[0];
+        assert_eq!(heavy.get_pixel(1, 1), black);
+        // The wall still shows everywhere the cracks don't cover
+        assert_eq!(heavy.get_pixel(3, 0), grey);
+        assert_eq!(output.states[0].images[0].get_pixel(0, 0), grey);
+    }
+
+    #[test]
+    fn shared_decals_fade_in() {
+        assert_eq!(config(None).ramp(), vec![(0, 1.0), (1, 1.0)]);
+        assert_eq!(
+            config(Some(4)).ramp(),
+            vec![(0, 0.5), (0, 1.0), (1, 0.5), (1, 1.0)]
+        );
+        assert_eq!(config(Some(1)).ramp(), vec![(1, 1.0)]);
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::state_diff::find_duplicate_states;
+
+/// Removes icon states that are exact copies of an earlier state, keeping the
+/// first of each. States only count as copies if their dirs, frames, delays,
+/// and pixels all match, names aside
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct DedupStates {}
+
+impl IconOperationConfig for DedupStates {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let duplicates = find_duplicate_states(icon);
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !duplicates.iter().any(|duplicate| duplicate.index == *index))
+            .map(|(_, state)| state)
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn state(name: &str, image: &RgbaImage) -> IconState {
+        IconState {
+            name: name.to_string(),
+            images: vec![DynamicImage::ImageRgba8(image.clone())],
+            ..Default::default()
+        }
+    }
+
+    fn dedup(states: Vec<IconState>) -> Vec<String> {
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = DedupStates {}
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states.into_iter().map(|state| state.name).collect()
+    }
+
+    #[test]
+    fn merges_identical_states() {
+        let image = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let names = dedup(vec![
+            state("crate", &image),
+            state("crate_copy", &image),
+            state("crate_copy_again", &image),
+        ]);
+        assert_eq!(names, vec!["crate"]);
+    }
+
+    #[test]
+    fn keeps_states_one_pixel_apart() {
+        let image = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let mut nearly = image.clone();
+        nearly.put_pixel(1, 1, Rgba([254, 0, 0, 255]));
+        let names = dedup(vec![state("crate", &image), state("crate_nearly", &nearly)]);
+        assert_eq!(names, vec!["crate", "crate_nearly"]);
+    }
+}
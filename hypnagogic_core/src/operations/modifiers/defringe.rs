@@ -0,0 +1,166 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, rgb, Color};
+
+fn default_radius() -> u32 {
+    1
+}
+
+/// Removes dark halos from soft edges, the kind left behind when straight
+/// alpha gets treated as premultiplied somewhere along the way.
+///
+/// Each partly transparent pixel takes on the average color of the fully
+/// opaque pixels within `radius` of it, keeping its own alpha. Only pixels
+/// darker than that color are touched, so deliberately light edges like glows
+/// are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Defringe {
+    /// How far away, in pixels, to look for opaque color to bleed in
+    #[serde(default = "default_radius")]
+    pub radius: u32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Defringe {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| DynamicImage::ImageRgba8(self.defringe_frame(frame)))
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.radius == 0 {
+            return Err(ProcessorError::ConfigError(
+                "radius must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Defringe {
+    fn defringe_frame(&self, frame: &DynamicImage) -> RgbaImage {
+        let source = frame.to_rgba8();
+        let mut output = source.clone();
+        for (x, y, pixel) in output.enumerate_pixels_mut() {
+            let pixel_alpha = alpha(*pixel);
+            if pixel_alpha == 0 || pixel_alpha == 255 {
+                continue;
+            }
+            let Some(interior) = self.interior_color(&source, x, y) else {
+                continue;
+            };
+            if Color::from(*pixel).luminance() >= Color::from(interior).luminance() {
+                continue;
+            }
+            let [red, green, blue] = rgb(interior);
+            *pixel = Rgba([red, green, blue, pixel_alpha]);
+        }
+        output
+    }
+
+    /// Average color of the fully opaque pixels within `radius` of `x`, `y`
+    #[allow(clippy::cast_possible_truncation)]
+    fn interior_color(&self, image: &RgbaImage, x: u32, y: u32) -> Option<Rgba<u8>> {
+        let x_range = x.saturating_sub(self.radius)..=(x + self.radius).min(image.width() - 1);
+        let y_range = y.saturating_sub(self.radius)..=(y + self.radius).min(image.height() - 1);
+
+        let mut total = [0_u32; 3];
+        let mut count = 0;
+        for near_y in y_range {
+            for near_x in x_range.clone() {
+                let near = *image.get_pixel(near_x, near_y);
+                if alpha(near) != 255 {
+                    continue;
+                }
+                for (total, channel) in total.iter_mut().zip(rgb(near)) {
+                    *total += u32::from(channel);
+                }
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return None;
+        }
+        let [red, green, blue] = total.map(|total| ((total + count / 2) / count) as u8);
+        Some(Rgba([red, green, blue, 255]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INTERIOR: Rgba<u8> = Rgba([200, 60, 40, 255]);
+
+    fn defringe(row: &[Rgba<u8>]) -> Vec<Rgba<u8>> {
+        let image = RgbaImage::from_fn(row.len() as u32, 1, |x, _| row[x as usize]);
+        let config = Defringe {
+            radius: 1,
+            targets: StateTargets::default(),
+        };
+        config
+            .defringe_frame(&DynamicImage::ImageRgba8(image))
+            .pixels()
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn dark_fringe_takes_interior_color() {
+        let output = defringe(&[
+            INTERIOR,
+            INTERIOR,
+            Rgba([20, 6, 4, 128]),
+            Rgba([0, 0, 0, 0]),
+        ]);
+        // Color comes from the interior, alpha stays put
+        assert_eq!(output[2], Rgba([200, 60, 40, 128]));
+        // Everything else is left alone
+        assert_eq!(output[..2], [INTERIOR, INTERIOR]);
+        assert_eq!(output[3], Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn light_and_isolated_edges_are_kept() {
+        let glow = Rgba([255, 240, 200, 100]);
+        let output = defringe(&[INTERIOR, glow, Rgba([0, 0, 0, 0]), Rgba([10, 10, 10, 100])]);
+        assert_eq!(output[1], glow);
+        // Nothing opaque nearby to take color from
+        assert_eq!(output[3], Rgba([10, 10, 10, 100]));
+    }
+}
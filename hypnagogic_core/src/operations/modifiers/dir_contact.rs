@@ -0,0 +1,184 @@
+use dmi::icon::{Icon, IconState};
+use image::{imageops, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::{
+    IconOperationConfig,
+    InputIcon,
+    NamedIcon,
+    OperationMode,
+    OutputImage,
+    ProcessorPayload,
+};
+
+/// Lays the dirs of one frame of each targeted icon state out side by side,
+/// in a single frame, single dir state named `{name}_dirs`. A preview of
+/// directional sprites, for docs and the like.
+///
+/// Those states are wider than the input's, so they go in a dmi of their own
+/// named `{input}-dirs`, as wide as the state with the most dirs needs. The
+/// input is passed through untouched alongside it. Since that makes two
+/// outputs, this has to be the last operation
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct DirContact {
+    /// Which frame to show, starting from 0. States with fewer frames show
+    /// their last one
+    #[serde(default)]
+    pub frame: u32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for DirContact {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let targeted: Vec<&IconState> = icon
+            .states
+            .iter()
+            .filter(|state| self.targets.matches(&state.name))
+            .collect();
+        if targeted.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "None of the target_states are in the input".to_string(),
+            ));
+        }
+        let widest = targeted
+            .iter()
+            .map(|state| u32::from(state.dirs.max(1)))
+            .max()
+            .unwrap_or(1);
+
+        let contact = Icon {
+            width: icon.width * widest,
+            states: targeted
+                .into_iter()
+                .map(|state| self.contact_state(state, icon, widest))
+                .collect(),
+            ..icon.clone()
+        };
+
+        Ok(ProcessorPayload::MultipleNamed(vec![
+            NamedIcon::from_icon(icon.clone()),
+            NamedIcon {
+                path_hint: None,
+                name_hint: Some("dirs".to_string()),
+                path: None,
+                image: OutputImage::Dmi(contact),
+            },
+        ]))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+impl DirContact {
+    fn contact_state(&self, state: &IconState, icon: &Icon, widest: u32) -> IconState {
+        let dirs = usize::from(state.dirs.max(1));
+        let frame = self.frame.min(state.frames.max(1) - 1) as usize;
+        let mut sheet = RgbaImage::new(icon.width * widest, icon.height);
+        for (dir, image) in state.images[frame * dirs..(frame + 1) * dirs]
+            .iter()
+            .enumerate()
+        {
+            imageops::replace(
+                &mut sheet,
+                &image.to_rgba8(),
+                i64::from(icon.width * dir as u32),
+                0,
+            );
+        }
+
+        IconState {
+            name: format!("{}_dirs", state.name),
+            dirs: 1,
+            frames: 1,
+            images: vec![DynamicImage::ImageRgba8(sheet)],
+            delay: None,
+            ..state.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{GenericImageView, Rgba};
+
+    use super::*;
+
+    #[test]
+    fn four_dirs_side_by_side() {
+        let frame = |value: u8| {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([value, 0, 0, 255])))
+        };
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states: vec![
+                IconState {
+                    name: "walking".to_string(),
+                    dirs: 4,
+                    frames: 2,
+                    images: (1..=8).map(frame).collect(),
+                    delay: Some(vec![1.0, 1.0]),
+                    ..Default::default()
+                },
+                IconState {
+                    name: "sign".to_string(),
+                    images: vec![frame(20)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let config = DirContact {
+            frame: 1,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::MultipleNamed(outputs) = config
+            .do_operation(&InputIcon::Dmi(icon.clone()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected named icons");
+        };
+        let [original, contact] = &outputs[..] else {
+            panic!("Expected two outputs");
+        };
+        let OutputImage::Dmi(original) = &original.image else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(original, &icon);
+        assert_eq!(contact.name_hint.as_deref(), Some("dirs"));
+        let OutputImage::Dmi(contact) = &contact.image else {
+            panic!("Expected a dmi");
+        };
+
+        assert_eq!((contact.width, contact.height), (8, 2));
+        let walking = &contact.states[0];
+        assert_eq!(walking.name, "walking_dirs");
+        assert_eq!((walking.dirs, walking.frames), (1, 1));
+        assert_eq!(walking.delay, None);
+        // The second frame's dirs, in order
+        for (dir, value) in [5, 6, 7, 8].into_iter().enumerate() {
+            assert_eq!(
+                walking.images[0].get_pixel(dir as u32 * 2 + 1, 1),
+                Rgba([value, 0, 0, 255])
+            );
+        }
+
+        // Single dir states only fill the first slot, and fall back to their
+        // only frame
+        let sign = &contact.states[1];
+        assert_eq!(sign.images[0].get_pixel(0, 0), Rgba([20, 0, 0, 255]));
+        assert_eq!(sign.images[0].get_pixel(2, 0), Rgba([0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::reorder_dirs::ReorderDirs;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::dirs::{facing, Dir};
+
+/// An order tools store the directions of a sprite in
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Convention {
+    /// How dmis store them. South, north, east, west, then southeast,
+    /// southwest, northeast, northwest
+    Byond,
+    /// Clockwise from north. North, east, south, west, or with diagonals
+    /// north, northeast, east, southeast, south, southwest, west, northwest
+    Clockwise,
+    /// The row order of RPG Maker character sheets. South, west, east, north.
+    /// Only has four directions
+    RpgMaker,
+}
+
+impl Convention {
+    /// Every direction in the order this convention stores them. `None` if it
+    /// has no order for that many directions
+    fn order(self, dirs: u8) -> Option<&'static [Dir]> {
+        match (self, dirs) {
+            (Convention::Byond, 4 | 8) => Some(facing(dirs)),
+            (Convention::Clockwise, 4) => Some(&[Dir::North, Dir::East, Dir::South, Dir::West]),
+            (Convention::Clockwise, 8) => {
+                Some(&[
+                    Dir::North,
+                    Dir::NorthEast,
+                    Dir::East,
+                    Dir::SouthEast,
+                    Dir::South,
+                    Dir::SouthWest,
+                    Dir::West,
+                    Dir::NorthWest,
+                ])
+            }
+            (Convention::RpgMaker, 4) => Some(&[Dir::South, Dir::West, Dir::East, Dir::North]),
+            _ => None,
+        }
+    }
+}
+
+/// Converts the directions of the targeted icon states from the order one
+/// tool exports them in to another's, so imported sheets line up. Usually
+/// `to` is `byond`, for sheets made elsewhere that are being brought in to a
+/// dmi.
+///
+/// Like `ReorderDirs`, but with the orders built in rather than written out
+/// by hand. Single direction states are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DirConvention {
+    /// The order the directions are in now
+    pub from: Convention,
+    /// The order to put them in
+    pub to: Convention,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for DirConvention {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if state.dirs <= 1 || !self.targets.matches(&state.name) {
+                    return Ok(state);
+                }
+                let reorder = ReorderDirs {
+                    permutation: self.permutation(&state.name, state.dirs)?,
+                    targets: StateTargets::default(),
+                };
+                reorder.reorder_state(state)
+            })
+            .collect::<ProcessorResult<_>>()?;
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.from == self.to {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(format!(
+                "from and to are both {:?}, so nothing will move",
+                self.from
+            ))]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl DirConvention {
+    /// Where each direction of a state with `dirs` directions moves to, in the
+    /// form `ReorderDirs` takes
+    fn permutation(&self, state: &str, dirs: u8) -> ProcessorResult<Vec<usize>> {
+        let unsupported = |convention: Convention| {
+            ProcessorError::ConfigError(format!(
+                "Icon state \"{state}\" has {dirs} directions, which the {convention:?} \
+                 convention has no order for"
+            ))
+        };
+        let from = self
+            .from
+            .order(dirs)
+            .ok_or_else(|| unsupported(self.from))?;
+        let to = self.to.order(dirs).ok_or_else(|| unsupported(self.to))?;
+        Ok(from
+            .iter()
+            .map(|dir| to.iter().position(|other| other == dir).unwrap())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const NORTH: u8 = Dir::North.byond_dir();
+    const SOUTH: u8 = Dir::South.byond_dir();
+    const EAST: u8 = Dir::East.byond_dir();
+    const WEST: u8 = Dir::West.byond_dir();
+    const NORTHEAST: u8 = Dir::NorthEast.byond_dir();
+    const NORTHWEST: u8 = Dir::NorthWest.byond_dir();
+    const SOUTHEAST: u8 = Dir::SouthEast.byond_dir();
+    const SOUTHWEST: u8 = Dir::SouthWest.byond_dir();
+
+    /// A 1x1 image with its red channel set to `dir`, to tell them apart
+    fn marked(dir: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([dir, 0, 0, 255])))
+    }
+
+    /// Converts a state whose frames are marked with the direction they face,
+    /// laid out in `from`'s order
+    fn convert(from: Convention, to: Convention, dirs: u8) -> ProcessorResult<Vec<u8>> {
+        let state = IconState {
+            name: "mob".to_string(),
+            dirs,
+            frames: 1,
+            images: from
+                .order(dirs)
+                .unwrap()
+                .iter()
+                .map(|dir| marked(dir.byond_dir()))
+                .collect(),
+            ..Default::default()
+        };
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![state],
+            ..Default::default()
+        };
+        let config = DirConvention {
+            from,
+            to,
+            targets: StateTargets::default(),
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states[0]
+            .images
+            .iter()
+            .map(|image| image.get_pixel(0, 0).0[0])
+            .collect())
+    }
+
+    #[test]
+    fn rpg_maker_to_byond() {
+        let dirs = convert(Convention::RpgMaker, Convention::Byond, 4).unwrap();
+        assert_eq!(dirs, vec![SOUTH, NORTH, EAST, WEST]);
+    }
+
+    #[test]
+    fn clockwise_to_byond_with_diagonals() {
+        let dirs = convert(Convention::Clockwise, Convention::Byond, 8).unwrap();
+        assert_eq!(
+            dirs,
+            vec![SOUTH, NORTH, EAST, WEST, SOUTHEAST, SOUTHWEST, NORTHEAST, NORTHWEST]
+        );
+    }
+
+    #[test]
+    fn byond_back_out() {
+        let dirs = convert(Convention::Byond, Convention::Clockwise, 4).unwrap();
+        assert_eq!(dirs, vec![NORTH, EAST, SOUTH, WEST]);
+    }
+
+    #[test]
+    fn missing_order_is_an_error() {
+        assert!(convert(Convention::Byond, Convention::RpgMaker, 8).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::BTreeMap;
+
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::{blend_images, BlendMode};
+use crate::util::dirs::Dir;
+
+/// Composites a different icon state over each direction of the targeted
+/// icon states, for things like a flashlight glow that follows the way a
+/// sprite faces. Each overlay only goes on the frames facing its direction,
+/// and directions without one are left alone.
+///
+/// Frames are matched up by index, with the overlay's last frame held if it
+/// runs out before the state it's on. An overlay with directions of its own
+/// uses the one it's put on, or its first if it doesn't have that one. The
+/// overlay states themselves are left as is
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DirOverlay {
+    /// Direction to the name of the icon state laid over frames facing it
+    pub overlays: BTreeMap<Dir, String>,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for DirOverlay {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let overlays = self
+            .overlays
+            .iter()
+            .map(|(&dir, name)| {
+                let overlay = icon
+                    .states
+                    .iter()
+                    .find(|state| &state.name == name)
+                    .ok_or_else(|| {
+                        ProcessorError::ConfigError(format!(
+                            "Overlay icon state \"{name}\" for {dir} was not found in the input"
+                        ))
+                    })?;
+                if overlay.images.len() < usize::from(overlay.dirs.max(1)) {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "Overlay icon state \"{name}\" for {dir} has no frames to lay on top"
+                    )));
+                }
+                Ok((dir, overlay))
+            })
+            .collect::<ProcessorResult<BTreeMap<Dir, &IconState>>>()?;
+
+        let mut output = icon.clone();
+        for state in &mut output.states {
+            if self.is_overlay(&state.name) || !self.targets.matches(&state.name) {
+                continue;
+            }
+            *state = self.overlay_dirs(state, &overlays)?;
+        }
+
+        Ok(ProcessorPayload::from_icon(output))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        self.is_overlay(state_name) || self.targets.matches(state_name)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.overlays.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "At least one direction must be given an overlay".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl DirOverlay {
+    fn is_overlay(&self, state_name: &str) -> bool {
+        self.overlays.values().any(|name| name == state_name)
+    }
+
+    fn overlay_dirs(
+        &self,
+        base: &IconState,
+        overlays: &BTreeMap<Dir, &IconState>,
+    ) -> ProcessorResult<IconState> {
+        if base.dirs <= 1 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" only faces one direction, so it can't be given an overlay per \
+                 direction",
+                base.name
+            )));
+        }
+        if let Some(missing) = overlays.keys().find(|dir| dir.index(base.dirs).is_none()) {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" can't be given an overlay facing {missing}, it only has {} \
+                 directions",
+                base.name, base.dirs
+            )));
+        }
+        let base_dirs = usize::from(base.dirs);
+
+        let images = base
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                // Images are stored frame by frame, with every dir of a frame together
+                let (frame, dir) = (index / base_dirs, index % base_dirs);
+                let Some((dir, overlay)) = Dir::at_index(dir, base.dirs)
+                    .and_then(|dir| overlays.get(&dir).map(|overlay| (dir, overlay)))
+                else {
+                    return image.clone();
+                };
+                let overlay_dirs = usize::from(overlay.dirs.max(1));
+                let overlay_frames = overlay.images.len() / overlay_dirs;
+                let overlay_dir = dir.index(overlay.dirs).unwrap_or(0);
+                let overlay_image =
+                    &overlay.images[frame.min(overlay_frames - 1) * overlay_dirs + overlay_dir];
+                DynamicImage::ImageRgba8(blend_images(image, overlay_image, self.blend_mode))
+            })
+            .collect();
+        Ok(IconState {
+            images,
+            ..base.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// A 2x1 image, clear apart from `pixel` at `x`
+    fn image(x: u32, pixel: Rgba<u8>) -> DynamicImage {
+        let mut image = RgbaImage::from_pixel(2, 1, CLEAR);
+        image.put_pixel(x, 0, pixel);
+        DynamicImage::ImageRgba8(image)
+    }
+
+    /// A state facing `dirs` ways, with a frame per entry of `frames`. Each
+    /// frame of each dir is clear apart from its left pixel
+    fn state(name: &str, dirs: u8, frames: &[Rgba<u8>]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            dirs,
+            frames: frames.len() as u32,
+            images: frames
+                .iter()
+                .flat_map(|&pixel| (0..dirs).map(move |_| image(0, pixel)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// A single direction overlay, clear apart from its right pixel
+    fn glow(name: &str, frames: &[Rgba<u8>]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            frames: frames.len() as u32,
+            images: frames.iter().map(|&pixel| image(1, pixel)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn dir_overlay(overlays: &[(Dir, &str)]) -> DirOverlay {
+        DirOverlay {
+            overlays: overlays
+                .iter()
+                .map(|&(dir, name)| (dir, name.to_string()))
+                .collect(),
+            blend_mode: BlendMode::Normal,
+            targets: StateTargets::default(),
+        }
+    }
+
+    fn apply(config: &DirOverlay, states: Vec<IconState>) -> ProcessorResult<Vec<IconState>> {
+        let icon = Icon {
+            width: 2,
+            height: 1,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states)
+    }
+
+    fn right_pixel(image: &DynamicImage) -> Rgba<u8> {
+        *image.to_rgba8().get_pixel(1, 0)
+    }
+
+    #[test]
+    fn each_dir_gets_only_its_overlay() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        let base = Rgba([9, 9, 9, 255]);
+        let config: DirOverlay = toml::from_str(
+            r#"
+            target_states = ["guard"]
+            [overlays]
+            north = "glow_north"
+            west = "glow_west"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.overlays,
+            dir_overlay(&[(Dir::North, "glow_north"), (Dir::West, "glow_west")]).overlays
+        );
+
+        let states = apply(
+            &config,
+            vec![
+                state("guard", 4, &[base, base]),
+                glow("glow_north", &[red]),
+                glow("glow_west", &[blue, blue]),
+            ],
+        )
+        .unwrap();
+        let guard = &states[0];
+        for frame in 0..2 {
+            let facing = |dir: Dir| &guard.images[frame * 4 + dir.index(4).unwrap()];
+            assert_eq!(right_pixel(facing(Dir::North)), red);
+            assert_eq!(right_pixel(facing(Dir::West)), blue);
+            assert_eq!(right_pixel(facing(Dir::South)), CLEAR);
+            assert_eq!(right_pixel(facing(Dir::East)), CLEAR);
+            // The base still shows through
+            assert_eq!(*facing(Dir::North).to_rgba8().get_pixel(0, 0), base);
+        }
+        // The overlays themselves are left alone
+        assert_eq!(right_pixel(&states[1].images[0]), red);
+    }
+
+    #[test]
+    fn last_overlay_frame_is_held() {
+        let states = apply(
+            &dir_overlay(&[(Dir::South, "glow")]),
+            vec![
+                state("guard", 4, &[CLEAR; 3]),
+                glow("glow", &[Rgba([1, 1, 1, 255]), Rgba([2, 2, 2, 255])]),
+            ],
+        )
+        .unwrap();
+        let south: Vec<u8> = (0..3)
+            .map(|frame| right_pixel(&states[0].images[frame * 4]).0[0])
+            .collect();
+        assert_eq!(south, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn bad_targets_and_overlays_are_rejected() {
+        let glow = || glow("glow", &[Rgba([1, 1, 1, 255])]);
+        // Not directional
+        assert!(apply(
+            &dir_overlay(&[(Dir::South, "glow")]),
+            vec![state("guard", 1, &[CLEAR]), glow()]
+        )
+        .is_err());
+        // Faces a way the target doesn't
+        assert!(apply(
+            &dir_overlay(&[(Dir::NorthEast, "glow")]),
+            vec![state("guard", 4, &[CLEAR]), glow()]
+        )
+        .is_err());
+        // Missing overlay
+        assert!(apply(
+            &dir_overlay(&[(Dir::South, "missing")]),
+            vec![state("guard", 4, &[CLEAR])]
+        )
+        .is_err());
+        // Overlay without any frames
+        assert!(apply(
+            &dir_overlay(&[(Dir::South, "glow")]),
+            vec![
+                state("guard", 4, &[CLEAR]),
+                IconState {
+                    name: "glow".to_string(),
+                    ..Default::default()
+                }
+            ]
+        )
+        .is_err());
+        assert!(dir_overlay(&[]).verify_config().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, Color};
+use crate::util::corners::Side;
+use crate::util::dirs::Dir;
+
+/// Tints each direction of the targeted icon states a different color, by
+/// multiplying the tint into the RGB of every frame facing that way. Alpha is
+/// left alone. Directions without a tint are untouched
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DirTint {
+    /// Direction to the color its frames get multiplied by
+    pub tints: BTreeMap<Side, Color>,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for DirTint {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if self.targets.matches(&state.name) {
+                    self.tint_state(state)
+                } else {
+                    Ok(state)
+                }
+            })
+            .collect::<ProcessorResult<_>>()?;
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.tints.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "At least one direction must be given a tint".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl DirTint {
+    fn tint_state(&self, state: IconState) -> ProcessorResult<IconState> {
+        if let Some(missing) = self
+            .tints
+            .keys()
+            .find(|&&side| Dir::from(side).index(state.dirs).is_none())
+        {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" can't be tinted facing {missing}, it only has {} direction(s)",
+                state.name, state.dirs
+            )));
+        }
+        let dirs = usize::from(state.dirs.max(1));
+
+        // Images are stored frame by frame, with every dir of a frame together
+        let images = state
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let side = Dir::at_index(index % dirs, state.dirs).and_then(Dir::side);
+                match side.and_then(|side| self.tints.get(&side)) {
+                    Some(&tint) => DynamicImage::ImageRgba8(tint_frame(frame, tint)),
+                    None => frame.clone(),
+                }
+            })
+            .collect();
+
+        Ok(IconState { images, ..state })
+    }
+}
+
+fn tint_frame(frame: &DynamicImage, tint: Color) -> RgbaImage {
+    let multiply = |channel: u8, by: u8| (u16::from(channel) * u16::from(by) / 255) as u8;
+    let mut frame = frame.to_rgba8();
+    for pixel in frame.pixels_mut() {
+        let Rgba([red, green, blue, _]) = *pixel;
+        *pixel = Rgba([
+            multiply(red, tint.red),
+            multiply(green, tint.green),
+            multiply(blue, tint.blue),
+            alpha(*pixel),
+        ]);
+    }
+    frame
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn white_state(dirs: u8, frames: u32) -> IconState {
+        IconState {
+            name: "indicator".to_string(),
+            dirs,
+            frames,
+            images: (0..u32::from(dirs) * frames)
+                .map(|_| {
+                    DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                        2,
+                        2,
+                        Rgba([255, 255, 255, 200]),
+                    ))
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn color_of(frame: &DynamicImage) -> Rgba<u8> {
+        *frame.to_rgba8().get_pixel(0, 0)
+    }
+
+    #[test]
+    fn only_tinted_dirs_change() {
+        let config = DirTint {
+            tints: BTreeMap::from([
+                (Side::North, Color::new_rgb(255, 0, 0)),
+                (Side::South, Color::new_rgb(0, 0, 255)),
+            ]),
+            targets: StateTargets::default(),
+        };
+        let state = config.tint_state(white_state(4, 2)).unwrap();
+
+        // Dirs go south, north, east, west within each frame
+        for frame in state.images.chunks(4) {
+            assert_eq!(color_of(&frame[0]), Rgba([0, 0, 255, 200]));
+            assert_eq!(color_of(&frame[1]), Rgba([255, 0, 0, 200]));
+            assert_eq!(color_of(&frame[2]), Rgba([255, 255, 255, 200]));
+            assert_eq!(color_of(&frame[3]), Rgba([255, 255, 255, 200]));
+        }
+    }
+
+    #[test]
+    fn diagonals_are_left_alone() {
+        let config = DirTint {
+            tints: BTreeMap::from([(Side::East, Color::new_rgb(0, 255, 0))]),
+            targets: StateTargets::default(),
+        };
+        let state = config.tint_state(white_state(8, 1)).unwrap();
+
+        let colors: Vec<Rgba<u8>> = state.images.iter().map(color_of).collect();
+        let mut expected = vec![Rgba([255, 255, 255, 200]); 8];
+        expected[2] = Rgba([0, 255, 0, 200]);
+        assert_eq!(colors, expected);
+    }
+
+    #[test]
+    fn missing_dirs_are_rejected() {
+        let config = DirTint {
+            tints: BTreeMap::from([(Side::West, Color::new_rgb(255, 0, 0))]),
+            targets: StateTargets::default(),
+        };
+        assert!(config.tint_state(white_state(1, 1)).is_err());
+
+        let config = DirTint {
+            tints: BTreeMap::from([(Side::South, Color::new_rgb(255, 0, 0))]),
+            targets: StateTargets::default(),
+        };
+        assert!(config.tint_state(white_state(1, 1)).is_ok());
+    }
+}
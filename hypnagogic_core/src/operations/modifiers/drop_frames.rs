@@ -0,0 +1,240 @@
+use dmi::icon::IconState;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// What happens to the delay of a frame that gets dropped
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DroppedDelay {
+    /// Added on to the previous kept frame (or the next one, if there isn't
+    /// one), so the animation takes just as long to play
+    #[default]
+    Redistribute,
+    /// Thrown away, so the animation plays faster
+    Discard,
+}
+
+/// Removes frames from the targeted icon states, either by index or by only
+/// keeping every `stride`th frame. Every direction of a dropped frame is
+/// removed
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DropFrames {
+    /// Indexes of the frames to drop, starting from 0
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<u32>,
+    /// If set, keep only every `stride`th frame (starting with the first) and
+    /// drop the rest
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stride: Option<u32>,
+    #[serde(default)]
+    pub dropped_delay: DroppedDelay,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for DropFrames {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if self.targets.matches(&state.name) {
+                    self.drop_frames(state)
+                } else {
+                    Ok(state)
+                }
+            })
+            .collect::<ProcessorResult<_>>()?;
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        match (self.frames.is_empty(), self.stride) {
+            (true, None) => {
+                Err(ProcessorError::ConfigError(
+                    "Either frames or stride must be set".to_string(),
+                ))
+            }
+            (false, Some(_)) => {
+                Err(ProcessorError::ConfigError(
+                    "Only one of frames or stride can be set".to_string(),
+                ))
+            }
+            (true, Some(0)) => {
+                Err(ProcessorError::ConfigError(
+                    "stride must be at least 1".to_string(),
+                ))
+            }
+            _ => Ok(vec![]),
+        }
+    }
+}
+
+impl DropFrames {
+    fn should_drop(&self, frame: u32) -> bool {
+        match self.stride {
+            Some(stride) => !frame.is_multiple_of(stride),
+            None => self.frames.contains(&frame),
+        }
+    }
+
+    fn drop_frames(&self, state: IconState) -> ProcessorResult<IconState> {
+        if let Some(out_of_range) = self.frames.iter().find(|&&frame| frame >= state.frames) {
+            return Err(ProcessorError::ConfigError(format!(
+                "Frame {out_of_range} can't be dropped from icon state \"{}\", it only has {} \
+                 frames",
+                state.name, state.frames
+            )));
+        }
+        let kept_frames = (0..state.frames)
+            .filter(|&frame| !self.should_drop(frame))
+            .count() as u32;
+        if kept_frames == 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Every frame of icon state \"{}\" would be dropped",
+                state.name
+            )));
+        }
+
+        let mut images = vec![];
+        let mut delays: Vec<f32> = vec![];
+        // Delay from dropped frames that haven't had a kept frame to go to yet
+        let mut orphaned_delay = 0.0;
+        // Images are stored frame by frame, with every dir of a frame together
+        for (frame, frame_images) in (0..state.frames).zip(state.images.chunks(state.dirs.into())) {
+            let delay = state
+                .delay
+                .as_ref()
+                .and_then(|delays| delays.get(frame as usize))
+                .copied()
+                .unwrap_or(1.0);
+            if self.should_drop(frame) {
+                if self.dropped_delay == DroppedDelay::Redistribute {
+                    match delays.last_mut() {
+                        Some(previous) => *previous += delay,
+                        None => orphaned_delay += delay,
+                    }
+                }
+                continue;
+            }
+            images.extend_from_slice(frame_images);
+            delays.push(delay + orphaned_delay);
+            orphaned_delay = 0.0;
+        }
+
+        Ok(IconState {
+            frames: kept_frames,
+            images,
+            delay: state.delay.as_ref().map(|_| delays),
+            ..state
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    /// A frame filled with a color unique to its frame and dir
+    fn frame(frame: u32, dir: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            2,
+            2,
+            Rgba([frame as u8, dir as u8, 0, 255]),
+        ))
+    }
+
+    fn animated_state(frames: u32, dirs: u8) -> IconState {
+        IconState {
+            name: "anim".to_string(),
+            dirs,
+            frames,
+            images: (0..frames)
+                .flat_map(|frame_index| {
+                    (0..u32::from(dirs)).map(move |dir| frame(frame_index, dir))
+                })
+                .collect(),
+            delay: Some((1..=frames).map(|delay| delay as f32).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn stride(stride: u32, dropped_delay: DroppedDelay) -> DropFrames {
+        DropFrames {
+            frames: vec![],
+            stride: Some(stride),
+            dropped_delay,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn stride_keeps_every_nth_frame() {
+        let config = stride(2, DroppedDelay::Redistribute);
+        let state = config.drop_frames(animated_state(5, 4)).unwrap();
+
+        assert_eq!(state.frames, 3);
+        let expected: Vec<DynamicImage> = [0, 2, 4]
+            .into_iter()
+            .flat_map(|frame_index| (0..4).map(move |dir| frame(frame_index, dir)))
+            .collect();
+        assert_eq!(state.images, expected);
+        // Frame 1's delay goes to frame 0, and frame 3's to frame 2
+        assert_eq!(state.delay, Some(vec![3.0, 7.0, 5.0]));
+    }
+
+    #[test]
+    fn stride_can_discard_delays() {
+        let config = stride(3, DroppedDelay::Discard);
+        let state = config.drop_frames(animated_state(6, 1)).unwrap();
+
+        assert_eq!(state.frames, 2);
+        assert_eq!(state.images, vec![frame(0, 0), frame(3, 0)]);
+        assert_eq!(state.delay, Some(vec![1.0, 4.0]));
+    }
+
+    #[test]
+    fn dropping_first_frame_moves_delay_forward() {
+        let config = DropFrames {
+            frames: vec![0],
+            stride: None,
+            dropped_delay: DroppedDelay::Redistribute,
+            targets: StateTargets::default(),
+        };
+        let state = config.drop_frames(animated_state(3, 1)).unwrap();
+
+        assert_eq!(state.images, vec![frame(1, 0), frame(2, 0)]);
+        assert_eq!(state.delay, Some(vec![3.0, 3.0]));
+    }
+
+    #[test]
+    fn out_of_range_frames_are_rejected() {
+        let config = DropFrames {
+            frames: vec![1, 7],
+            stride: None,
+            dropped_delay: DroppedDelay::Redistribute,
+            targets: StateTargets::default(),
+        };
+        assert!(config.drop_frames(animated_state(3, 1)).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, is_transparent, with_alpha, Color, TRANSPARENT};
+
+fn white() -> Color {
+    Color::new(255, 255, 255, 255)
+}
+
+fn default_suffix() -> String {
+    "_emissive".to_string()
+}
+
+/// Adds an emissive copy of each targeted icon state, right after it.
+///
+/// The copy is the silhouette of the original in one flat color, with the
+/// original's alpha kept as is so soft edges stay soft
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Emissive {
+    /// Color to fill the silhouette with. Its alpha scales the original alpha
+    #[serde(default = "white")]
+    pub color: Color,
+    /// Appended to the name of a state to name its emissive copy
+    #[serde(default = "default_suffix")]
+    pub suffix: String,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Emissive {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut states = vec![];
+        for state in &icon.states {
+            states.push(state.clone());
+            if !self.targets.matches(&state.name) {
+                continue;
+            }
+            let name = format!("{}{}", state.name, self.suffix);
+            if icon.states.iter().any(|existing| existing.name == name) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Can't add emissive state \"{name}\", an icon state with that name already \
+                     exists"
+                )));
+            }
+            states.push(IconState {
+                name,
+                images: state
+                    .images
+                    .iter()
+                    .map(|image| DynamicImage::ImageRgba8(self.silhouette(image)))
+                    .collect(),
+                ..state.clone()
+            });
+        }
+
+        let mut icon = icon.clone();
+        icon.states = states;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.suffix.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "suffix can't be empty, emissive states need their own names".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Emissive {
+    fn silhouette(&self, image: &DynamicImage) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        RgbaImage::from_fn(width, height, |x, y| {
+            let pixel = image.get_pixel(x, y);
+            if is_transparent(pixel) {
+                return TRANSPARENT;
+            }
+            let alpha = (u16::from(alpha(pixel)) * u16::from(self.color.alpha) + 127) / 255;
+            with_alpha(self.color.into(), alpha as u8)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::Rgba;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn run(config: &Emissive, icon: Icon) -> ProcessorResult<Icon> {
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    fn test_icon() -> Icon {
+        let mut image = RgbaImage::new(3, 1);
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        image.put_pixel(1, 0, Rgba([200, 100, 0, 100]));
+        Icon {
+            width: 3,
+            height: 1,
+            states: vec![IconState {
+                name: "lamp".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_alpha_and_replaces_color() {
+        let config = Emissive {
+            color: Color::new_rgb(255, 200, 0),
+            suffix: default_suffix(),
+            targets: StateTargets::default(),
+        };
+        let output = run(&config, test_icon()).unwrap();
+
+        assert_eq!(output.states.len(), 2);
+        assert_eq!(output.states[0], test_icon().states[0]);
+        assert_eq!(output.states[1].name, "lamp_emissive");
+        let emissive = &output.states[1].images[0];
+        assert_eq!(emissive.get_pixel(0, 0), Rgba([255, 200, 0, 255]));
+        assert_eq!(emissive.get_pixel(1, 0), Rgba([255, 200, 0, 100]));
+        assert_eq!(emissive.get_pixel(2, 0), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn name_collisions_are_rejected() {
+        let config = Emissive {
+            color: white(),
+            suffix: String::new(),
+            targets: StateTargets::default(),
+        };
+        assert!(run(&config, test_icon()).is_err());
+
+        let mut icon = test_icon();
+        let mut existing = icon.states[0].clone();
+        existing.name = "lamp_glow".to_string();
+        icon.states.push(existing);
+        let config = Emissive {
+            suffix: "_glow".to_string(),
+            ..config
+        };
+        assert!(run(&config, icon).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use image::{imageops, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How the icon is brought to the ratio
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectFit {
+    /// Cuts the long side down
+    #[default]
+    Crop,
+    /// Adds transparent space to the short side
+    Pad,
+}
+
+/// Which part of the icon stays put. When cropping this is the part kept,
+/// when padding it's where the original ends up
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl AspectAnchor {
+    /// How far along each axis the anchor is, in halves
+    fn halves(self) -> (u32, u32) {
+        match self {
+            AspectAnchor::TopLeft => (0, 0),
+            AspectAnchor::Top => (1, 0),
+            AspectAnchor::TopRight => (2, 0),
+            AspectAnchor::Left => (0, 1),
+            AspectAnchor::Center => (1, 1),
+            AspectAnchor::Right => (2, 1),
+            AspectAnchor::BottomLeft => (0, 2),
+            AspectAnchor::Bottom => (1, 2),
+            AspectAnchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+/// Crops or pads every frame of every icon state so the icon has a set aspect
+/// ratio, for fitting icons of mixed sizes in to uniform UI slots.
+///
+/// Every state in a dmi shares its size, so the whole icon is resized at once.
+/// Icons that already have the ratio are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EnforceAspect {
+    pub ratio: AspectRatio,
+    #[serde(default)]
+    pub fit: AspectFit,
+    #[serde(default)]
+    pub anchor: AspectAnchor,
+}
+
+impl IconOperationConfig for EnforceAspect {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let (width, height) = self.fitted_size(icon.width, icon.height);
+        if (width, height) == (icon.width, icon.height) {
+            return Ok(ProcessorPayload::from_icon(icon.clone()));
+        }
+
+        let (along_x, along_y) = self.anchor.halves();
+        let mut icon = icon.clone();
+        // Crop and pad both move the image by the size difference, weighted by
+        // the anchor, just in opposite directions
+        let x = icon.width.abs_diff(width) * along_x / 2;
+        let y = icon.height.abs_diff(height) * along_y / 2;
+        for state in &mut icon.states {
+            for image in &mut state.images {
+                let frame = image.to_rgba8();
+                let resized = match self.fit {
+                    AspectFit::Crop => imageops::crop_imm(&frame, x, y, width, height).to_image(),
+                    AspectFit::Pad => {
+                        let mut padded = RgbaImage::new(width, height);
+                        imageops::replace(&mut padded, &frame, i64::from(x), i64::from(y));
+                        padded
+                    }
+                };
+                *image = DynamicImage::ImageRgba8(resized);
+            }
+        }
+        icon.width = width;
+        icon.height = height;
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.ratio.width == 0 || self.ratio.height == 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Aspect ratio {}:{} needs both sides above 0",
+                self.ratio.width, self.ratio.height
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+impl EnforceAspect {
+    /// The size the icon ends up, never smaller than a pixel
+    fn fitted_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (ratio_width, ratio_height) =
+            (u64::from(self.ratio.width), u64::from(self.ratio.height));
+        let (wide, tall) = (u64::from(width), u64::from(height));
+        // Comparing width / height against the ratio without dividing
+        let too_wide = wide * ratio_height > tall * ratio_width;
+        let too_tall = wide * ratio_height < tall * ratio_width;
+        let fitted = match (self.fit, too_wide, too_tall) {
+            (AspectFit::Crop, true, _) => ((tall * ratio_width / ratio_height).max(1), tall),
+            (AspectFit::Crop, _, true) => (wide, (wide * ratio_height / ratio_width).max(1)),
+            (AspectFit::Pad, true, _) => (wide, (wide * ratio_height).div_ceil(ratio_width)),
+            (AspectFit::Pad, _, true) => ((tall * ratio_width).div_ceil(ratio_height), tall),
+            _ => (wide, tall),
+        };
+        (
+            u32::try_from(fitted.0).unwrap_or(u32::MAX),
+            u32::try_from(fitted.1).unwrap_or(u32::MAX),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{GenericImageView, Rgba};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const MARK: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    /// A 32x16 icon with a single marked pixel in the middle
+    fn enforce(fit: AspectFit, anchor: AspectAnchor) -> Icon {
+        let mut image = RgbaImage::from_pixel(32, 16, Rgba([0, 0, 255, 255]));
+        image.put_pixel(16, 8, MARK);
+        let icon = Icon {
+            width: 32,
+            height: 16,
+            states: vec![IconState {
+                name: "wide".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = EnforceAspect {
+            ratio: AspectRatio {
+                width: 1,
+                height: 1,
+            },
+            fit,
+            anchor,
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    #[test]
+    fn crop_keeps_the_middle() {
+        let icon = enforce(AspectFit::Crop, AspectAnchor::Center);
+        assert_eq!((icon.width, icon.height), (16, 16));
+        let image = &icon.states[0].images[0];
+        assert_eq!(image.dimensions(), (16, 16));
+        assert_eq!(image.get_pixel(8, 8), MARK);
+    }
+
+    #[test]
+    fn crop_anchored_right() {
+        let icon = enforce(AspectFit::Crop, AspectAnchor::Right);
+        // The right half, so the mark lands on the left edge
+        assert_eq!(icon.states[0].images[0].get_pixel(0, 8), MARK);
+    }
+
+    #[test]
+    fn pad_centers_vertically() {
+        let icon = enforce(AspectFit::Pad, AspectAnchor::Center);
+        assert_eq!((icon.width, icon.height), (32, 32));
+        let image = &icon.states[0].images[0];
+        assert_eq!(image.get_pixel(16, 16), MARK);
+        assert_eq!(image.get_pixel(0, 7), Rgba([0, 0, 0, 0]));
+        assert_eq!(image.get_pixel(0, 8), Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(0, 24), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn pad_anchored_top() {
+        let icon = enforce(AspectFit::Pad, AspectAnchor::TopLeft);
+        let image = &icon.states[0].images[0];
+        assert_eq!(image.get_pixel(16, 8), MARK);
+        assert_eq!(image.get_pixel(0, 16), Rgba([0, 0, 0, 0]));
+    }
+}
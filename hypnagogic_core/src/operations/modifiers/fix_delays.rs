@@ -0,0 +1,176 @@
+use dmi::icon::{Icon, IconState};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::delays::text_delays;
+
+/// Fixes up icon states that don't have exactly one delay per frame, which
+/// BYOND won't play properly. Missing delays copy the last one there is (or
+/// are 1 if there are none), and extra delays are cut off. Every change is
+/// logged as a warning, since it's guesswork.
+///
+/// With `strict` set nothing is changed, and a mismatch fails instead
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct FixDelays {
+    /// Fail on mismatched delays instead of fixing them
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for FixDelays {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        for state in &mut icon.states {
+            if !self.targets.matches(&state.name) || delays_fit(state) {
+                continue;
+            }
+            if self.strict {
+                return Err(mismatch(state));
+            }
+            let before = state.delay.clone().unwrap_or_default();
+            state.delay = fitted_delays(&before, state.frames);
+            warn!(
+                state = ?state.name,
+                "Changed delays from {} to {} to fit {} frames",
+                text_delays(&before, "ds"),
+                text_delays(state.delay.as_deref().unwrap_or_default(), "ds"),
+                state.frames
+            );
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+/// Checks every icon state in `icon` has a delay for each of its frames
+/// # Errors
+/// Returns `ProcessorError::DelayMismatch` for the first state that doesn't
+pub fn check_delays(icon: &Icon) -> ProcessorResult<()> {
+    match icon.states.iter().find(|state| !delays_fit(state)) {
+        Some(state) => Err(mismatch(state)),
+        None => Ok(()),
+    }
+}
+
+/// Whether `state` has a delay per frame. States that aren't animated don't
+/// need any
+fn delays_fit(state: &IconState) -> bool {
+    match &state.delay {
+        None => state.frames <= 1,
+        Some(delays) => delays.len() == state.frames.max(1) as usize,
+    }
+}
+
+fn mismatch(state: &IconState) -> ProcessorError {
+    ProcessorError::DelayMismatch {
+        state: state.name.clone(),
+        frames: state.frames,
+        delays: state.delay.clone().unwrap_or_default(),
+    }
+}
+
+/// `delays` cut or padded out to `frames` entries
+fn fitted_delays(delays: &[f32], frames: u32) -> Option<Vec<f32>> {
+    if frames <= 1 {
+        return None;
+    }
+    let last = delays.last().copied().unwrap_or(1.0);
+    let mut fitted: Vec<f32> = delays.iter().copied().take(frames as usize).collect();
+    fitted.resize(frames as usize, last);
+    Some(fitted)
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn animation(frames: u32, delay: Option<Vec<f32>>) -> Icon {
+        Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "fan".to_string(),
+                frames,
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(1, 1)); frames as usize],
+                delay,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn fix(config: &FixDelays, icon: Icon) -> ProcessorResult<Option<Vec<f32>>> {
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(mut output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states.remove(0).delay)
+    }
+
+    #[test]
+    fn missing_delays_are_padded() {
+        let icon = animation(4, Some(vec![1.0, 2.0, 3.0]));
+        assert!(check_delays(&icon).is_err());
+        let delays = fix(&FixDelays::default(), icon).unwrap();
+        assert_eq!(delays, Some(vec![1.0, 2.0, 3.0, 3.0]));
+
+        let delays = fix(&FixDelays::default(), animation(2, None)).unwrap();
+        assert_eq!(delays, Some(vec![1.0, 1.0]));
+    }
+
+    #[test]
+    fn extra_delays_are_cut() {
+        let icon = animation(2, Some(vec![1.0, 2.0, 3.0]));
+        assert!(check_delays(&icon).is_err());
+        let delays = fix(&FixDelays::default(), icon).unwrap();
+        assert_eq!(delays, Some(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn strict_fails_on_mismatch() {
+        let strict = FixDelays {
+            strict: true,
+            ..Default::default()
+        };
+        let Err(ProcessorError::DelayMismatch {
+            state,
+            frames,
+            delays,
+        }) = fix(&strict, animation(4, Some(vec![1.0, 2.0, 3.0])))
+        else {
+            panic!("Expected a delay mismatch");
+        };
+        assert_eq!(state, "fan");
+        assert_eq!(frames, 4);
+        assert_eq!(delays, vec![1.0, 2.0, 3.0]);
+
+        // Fine states pass either way
+        let fine = animation(2, Some(vec![1.0, 2.0]));
+        assert!(check_delays(&fine).is_ok());
+        assert_eq!(fix(&strict, fine).unwrap(), Some(vec![1.0, 2.0]));
+        assert!(check_delays(&animation(1, None)).is_ok());
+    }
+}
@@ -0,0 +1,179 @@
+use dmi::icon::{IconState, Looping};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+fn default_suffix() -> String {
+    "_still".to_string()
+}
+
+/// Adds a still copy of each targeted animated icon state, right after it,
+/// for paused or preview versions. The still is a single frame of the
+/// animation, with every dir. The animation itself is left alone, and states
+/// that aren't animated are skipped
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Freeze {
+    /// Which frame to keep, starting from 0. States with fewer frames keep
+    /// their last one
+    #[serde(default)]
+    pub frame: u32,
+    /// Appended to the name of a state to name its still
+    #[serde(default = "default_suffix")]
+    pub suffix: String,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Freeze {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut states = vec![];
+        for state in &icon.states {
+            states.push(state.clone());
+            if state.frames <= 1 || !self.targets.matches(&state.name) {
+                continue;
+            }
+            let name = format!("{}{}", state.name, self.suffix);
+            if icon.states.iter().any(|existing| existing.name == name) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Can't add still state \"{name}\", an icon state with that name already exists"
+                )));
+            }
+            states.push(self.still(state, name));
+        }
+
+        let mut icon = icon.clone();
+        icon.states = states;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.suffix.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "suffix can't be empty, still states need their own names".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Freeze {
+    fn still(&self, state: &IconState, name: String) -> IconState {
+        let dirs = usize::from(state.dirs.max(1));
+        let frame = self.frame.min(state.frames - 1) as usize;
+        IconState {
+            name,
+            frames: 1,
+            // Images are stored frame by frame, with every dir of a frame
+            // together
+            images: state.images[frame * dirs..(frame + 1) * dirs].to_vec(),
+            delay: None,
+            loop_flag: Looping::Indefinitely,
+            rewind: false,
+            ..state.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A 1x1 image with its red channel set to `marker`, to tell them apart
+    fn marked(marker: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([marker, 0, 0, 255])))
+    }
+
+    fn markers(state: &IconState) -> Vec<u8> {
+        state
+            .images
+            .iter()
+            .map(|image| image.get_pixel(0, 0).0[0])
+            .collect()
+    }
+
+    fn animation() -> IconState {
+        IconState {
+            name: "fan".to_string(),
+            dirs: 2,
+            frames: 3,
+            // Frame 1 is 10-11, frame 2 is 20-21, frame 3 is 30-31
+            images: [10, 11, 20, 21, 30, 31].into_iter().map(marked).collect(),
+            delay: Some(vec![1.0, 2.0, 3.0]),
+            ..Default::default()
+        }
+    }
+
+    fn freeze(frame: u32) -> Icon {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![
+                animation(),
+                IconState {
+                    name: "off".to_string(),
+                    images: vec![marked(1)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let config = Freeze {
+            frame,
+            suffix: default_suffix(),
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    #[test]
+    fn animation_is_kept_beside_its_still() {
+        let icon = freeze(0);
+        let names: Vec<&str> = icon
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        // Unanimated states don't get one
+        assert_eq!(names, vec!["fan", "fan_still", "off"]);
+        assert_eq!(icon.states[0], animation());
+    }
+
+    #[test]
+    fn still_is_the_chosen_frame() {
+        let icon = freeze(1);
+        let still = &icon.states[1];
+        assert_eq!(markers(still), vec![20, 21]);
+        assert_eq!((still.dirs, still.frames), (2, 1));
+        assert_eq!(still.delay, None);
+
+        // Past the end keeps the last frame
+        assert_eq!(markers(&freeze(7).states[1]), vec![30, 31]);
+    }
+}
@@ -0,0 +1,161 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Applies gamma correction to the color of every pixel in the targeted icon
+/// states, raising each channel (as a fraction of full) to the power of
+/// `gamma`. Above 1 darkens mid-tones, below 1 brightens them. Pure black,
+/// pure white and alpha are left alone
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Gamma {
+    pub gamma: f32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Gamma {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let table = self.lookup_table();
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                for channel in &mut pixel.0[..3] {
+                                    *channel = table[usize::from(*channel)];
+                                }
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !(self.gamma.is_finite() && self.gamma > 0.0) {
+            return Err(ProcessorError::ConfigError(format!(
+                "gamma must be above 0, got {}",
+                self.gamma
+            )));
+        }
+        #[allow(clippy::float_cmp)] // anything else changes something
+        if self.gamma == 1.0 {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "A gamma of 1 leaves every pixel as it is".to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl Gamma {
+    /// What every channel value maps to, worked out once rather than per
+    /// pixel
+    fn lookup_table(&self) -> [u8; 256] {
+        std::array::from_fn(|value| self.correct(value as u8))
+    }
+
+    fn correct(&self, value: u8) -> u8 {
+        ((f32::from(value) / 255.0).powf(self.gamma) * 255.0).round() as u8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn gamma(gamma: f32) -> Gamma {
+        Gamma {
+            gamma,
+            targets: StateTargets::default(),
+        }
+    }
+
+    fn apply(config: &Gamma, pixel: Rgba<u8>) -> Rgba<u8> {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "lamp".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, pixel))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].images[0].get_pixel(0, 0)
+    }
+
+    #[test]
+    fn one_is_identity() {
+        let table = gamma(1.0).lookup_table();
+        for (value, corrected) in table.into_iter().enumerate() {
+            assert_eq!(usize::from(corrected), value);
+        }
+    }
+
+    #[test]
+    fn darkens_mid_tones_and_keeps_alpha() {
+        let pixel = apply(&gamma(2.2), Rgba([128, 0, 255, 77]));
+        // 0.502 ^ 2.2 is about 0.22
+        assert_eq!(pixel, Rgba([56, 0, 255, 77]));
+    }
+
+    #[test]
+    fn table_matches_direct_computation() {
+        for value in [0.45, 1.8, 2.2] {
+            let config = gamma(value);
+            let table = config.lookup_table();
+            for channel in 0..=255u8 {
+                let direct = (f64::from(channel) / 255.0).powf(f64::from(value)) * 255.0;
+                let from_table = f64::from(table[usize::from(channel)]);
+                assert!(
+                    (from_table - direct).abs() <= 0.5 + 1e-3,
+                    "gamma {value}, channel {channel}: {from_table} vs {direct}"
+                );
+            }
+        }
+    }
+}
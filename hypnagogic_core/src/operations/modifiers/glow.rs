@@ -0,0 +1,235 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::blur::{blur_frame, Blur, BlurKind};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::{blend_pixel, BlendMode};
+use crate::util::color::{alpha, with_alpha, Color, TRANSPARENT};
+
+fn default_threshold() -> f32 {
+    0.7
+}
+
+fn two() -> f32 {
+    2.0
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn default_suffix() -> String {
+    "_glow".to_string()
+}
+
+/// Adds a glowing copy of each targeted icon state, right after it.
+///
+/// The bright parts of the original are picked out, blurred, and added back
+/// over it, so they bleed soft light on to their surroundings. Anything darker
+/// than `threshold` gives off no light of its own
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Glow {
+    /// How bright a pixel has to be to glow, from 0 to 1
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+    /// How far the glow spreads, in pixels
+    #[serde(default = "two")]
+    pub radius: f32,
+    /// How strong the glow is. Above 1 makes it more solid
+    #[serde(default = "one")]
+    pub intensity: f32,
+    /// Appended to the name of a state to name its glowing copy
+    #[serde(default = "default_suffix")]
+    pub suffix: String,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Glow {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let kernel = Blur {
+            radius: self.radius,
+            kind: BlurKind::Gaussian,
+            targets: StateTargets::default(),
+        }
+        .kernel();
+
+        let mut states = vec![];
+        for state in &icon.states {
+            states.push(state.clone());
+            if !self.targets.matches(&state.name) {
+                continue;
+            }
+            let name = format!("{}{}", state.name, self.suffix);
+            if icon.states.iter().any(|existing| existing.name == name) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Can't add glow state \"{name}\", an icon state with that name already exists"
+                )));
+            }
+            states.push(IconState {
+                name,
+                images: state
+                    .images
+                    .iter()
+                    .map(|image| DynamicImage::ImageRgba8(self.glow(image, &kernel)))
+                    .collect(),
+                ..state.clone()
+            });
+        }
+
+        let mut icon = icon.clone();
+        icon.states = states;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(ProcessorError::ConfigError(format!(
+                "threshold must be between 0 and 1, got {}",
+                self.threshold
+            )));
+        }
+        if !self.radius.is_finite() || self.radius < 0.0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Glow radius must be a positive number, got {}",
+                self.radius
+            )));
+        }
+        if !self.intensity.is_finite() || self.intensity < 0.0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "intensity must be a positive number, got {}",
+                self.intensity
+            )));
+        }
+        if self.suffix.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "suffix can't be empty, glow states need their own names".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Glow {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn glow(&self, image: &DynamicImage, kernel: &[f32]) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let bright = RgbaImage::from_fn(width, height, |x, y| {
+            let pixel = image.get_pixel(x, y);
+            if Color::from(pixel).luminance() >= self.threshold {
+                pixel
+            } else {
+                TRANSPARENT
+            }
+        });
+        let light = blur_frame(&DynamicImage::ImageRgba8(bright), kernel);
+
+        let mut output = image.to_rgba8();
+        for (pixel, light) in output.pixels_mut().zip(light.pixels()) {
+            let strength = (f32::from(alpha(*light)) * self.intensity)
+                .round()
+                .min(255.0) as u8;
+            *pixel = blend_pixel(*pixel, with_alpha(*light, strength), BlendMode::Add);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::Rgba;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const LAMP: Rgba<u8> = Rgba([255, 240, 200, 255]);
+    const SHADOW: Rgba<u8> = Rgba([40, 30, 30, 255]);
+
+    /// A 16x8 icon with a lit pixel on the left, and a dark one on the right
+    fn glow() -> Icon {
+        let mut image = RgbaImage::new(16, 8);
+        image.put_pixel(3, 4, LAMP);
+        image.put_pixel(12, 4, SHADOW);
+        let icon = Icon {
+            width: 16,
+            height: 8,
+            states: vec![IconState {
+                name: "lamp".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = Glow {
+            threshold: 0.7,
+            radius: 1.0,
+            intensity: 1.0,
+            suffix: default_suffix(),
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    #[test]
+    fn glow_is_added_as_its_own_state() {
+        let icon = glow();
+        let names: Vec<&str> = icon
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["lamp", "lamp_glow"]);
+        // The original is left alone
+        assert_eq!(icon.states[0].images[0].get_pixel(4, 4), TRANSPARENT);
+    }
+
+    #[test]
+    fn bright_regions_glow() {
+        let image = &glow().states[1].images[0];
+        for (x, y) in [(2, 4), (4, 4), (3, 3), (3, 5)] {
+            let pixel = image.get_pixel(x, y);
+            assert!(alpha(pixel) > 0, "({x}, {y}) didn't glow");
+            assert!(pixel.0[0] > 200, "({x}, {y}) is {pixel:?}");
+        }
+        // Still at least as bright as it started
+        let center = image.get_pixel(3, 4);
+        assert_eq!(alpha(center), 255);
+        assert!(center.0[..3]
+            .iter()
+            .zip(LAMP.0)
+            .all(|(&out, start)| out >= start));
+    }
+
+    #[test]
+    fn dark_regions_are_unaffected() {
+        let image = &glow().states[1].images[0];
+        assert_eq!(image.get_pixel(12, 4), SHADOW);
+        for (x, y) in [(11, 4), (13, 4), (12, 3), (12, 5)] {
+            assert_eq!(image.get_pixel(x, y), TRANSPARENT, "({x}, {y})");
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, Color};
+
+/// A color at a point along a gradient
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GradientStop {
+    /// Where the stop sits, from 0 (black) to 1 (white)
+    pub position: f32,
+    /// Alpha is ignored
+    pub color: Color,
+}
+
+/// Recolors the targeted icon states through a gradient, looking up each
+/// pixel's luminance along it and blending between the stops either side, for
+/// duotone and heatmap looks. Pixels keep their own alpha.
+///
+/// The stops have to be in order, starting at 0 and ending at 1
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GradientMap {
+    pub stops: Vec<GradientStop>,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for GradientMap {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                let [red, green, blue] =
+                                    self.sample(Color::from(*pixel).luminance());
+                                *pixel = Rgba([red, green, blue, alpha(*pixel)]);
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return Err(ProcessorError::ConfigError(
+                "stops needs at least one color".to_string(),
+            ));
+        };
+        if self.stops.iter().any(|stop| !stop.position.is_finite()) {
+            return Err(ProcessorError::ConfigError(
+                "Every stop position has to be a number".to_string(),
+            ));
+        }
+        if self
+            .stops
+            .windows(2)
+            .any(|pair| pair[0].position > pair[1].position)
+        {
+            return Err(ProcessorError::ConfigError(
+                "stops have to be in order of position".to_string(),
+            ));
+        }
+        #[allow(clippy::float_cmp)] // the ends have to be set exactly
+        if first.position != 0.0 || last.position != 1.0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "stops have to cover 0 to 1, but run from {} to {}",
+                first.position, last.position
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+impl GradientMap {
+    /// The color of the gradient at `position`, from 0 to 1
+    fn sample(&self, position: f32) -> [u8; 3] {
+        let to = self
+            .stops
+            .iter()
+            .position(|stop| stop.position >= position)
+            .unwrap_or(self.stops.len() - 1);
+        let from = to.saturating_sub(1);
+        let (from, to) = (self.stops[from], self.stops[to]);
+        let span = to.position - from.position;
+        let progress = if span > 0.0 {
+            ((position - from.position) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let channels = |color: Color| [color.red, color.green, color.blue].map(f32::from);
+        let (from, to) = (channels(from.color), channels(to.color));
+        std::array::from_fn(|channel| {
+            (from[channel] + (to[channel] - from[channel]) * progress).round() as u8
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn stop(position: f32, color: Color) -> GradientStop {
+        GradientStop { position, color }
+    }
+
+    /// Navy through orange to yellow
+    fn heatmap() -> GradientMap {
+        GradientMap {
+            stops: vec![
+                stop(0.0, Color::new_rgb(0, 0, 128)),
+                stop(0.5, Color::new_rgb(255, 128, 0)),
+                stop(1.0, Color::new_rgb(255, 255, 0)),
+            ],
+            targets: StateTargets::default(),
+        }
+    }
+
+    fn apply(config: &GradientMap, pixel: Rgba<u8>) -> Rgba<u8> {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "heat".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, pixel))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].images[0].get_pixel(0, 0)
+    }
+
+    #[test]
+    fn black_and_white_map_to_the_ends() {
+        let config = heatmap();
+        assert_eq!(apply(&config, Rgba([0, 0, 0, 255])), Rgba([0, 0, 128, 255]));
+        assert_eq!(
+            apply(&config, Rgba([255, 255, 255, 90])),
+            Rgba([255, 255, 0, 90])
+        );
+    }
+
+    #[test]
+    fn blends_between_stops() {
+        assert_eq!(heatmap().sample(0.75), [255, 192, 0]);
+        assert_eq!(heatmap().sample(0.5), [255, 128, 0]);
+    }
+
+    #[test]
+    fn stops_must_be_sorted_and_cover_everything() {
+        let mut config = heatmap();
+        config.stops.swap(0, 1);
+        assert!(config.verify_config().is_err());
+
+        let mut config = heatmap();
+        config.stops[2].position = 0.9;
+        assert!(config.verify_config().is_err());
+
+        assert!(heatmap().verify_config().is_ok());
+    }
+}
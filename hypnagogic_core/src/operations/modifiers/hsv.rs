@@ -0,0 +1,208 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, hsv_to_rgb, rgb, rgb_to_hsv};
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Shifts the hue of every pixel in the targeted icon states, and scales their
+/// saturation and value. The easy way to make differently colored variants of
+/// a sprite
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HsvShift {
+    /// Degrees to rotate the hue by, wrapping around the color wheel
+    #[serde(default)]
+    pub hue: f32,
+    /// Multiplier for saturation
+    #[serde(default = "one")]
+    pub saturation: f32,
+    /// Multiplier for value (brightness)
+    #[serde(default = "one")]
+    pub value: f32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for HsvShift {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| DynamicImage::ImageRgba8(self.shift_frame(frame)))
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !self.hue.is_finite() {
+            return Err(ProcessorError::ConfigError(format!(
+                "hue must be a number of degrees, got {}",
+                self.hue
+            )));
+        }
+        for (name, multiplier) in [("saturation", self.saturation), ("value", self.value)] {
+            if !multiplier.is_finite() || multiplier < 0.0 {
+                return Err(ProcessorError::ConfigError(format!(
+                    "{name} must be a positive multiplier, got {multiplier}"
+                )));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+impl HsvShift {
+    fn shift_pixel(&self, pixel: Rgba<u8>) -> Rgba<u8> {
+        let [hue, saturation, value] = rgb_to_hsv(rgb(pixel));
+        let [red, green, blue] = hsv_to_rgb([
+            (hue + self.hue).rem_euclid(360.0),
+            (saturation * self.saturation).min(1.0),
+            (value * self.value).min(1.0),
+        ]);
+        Rgba([red, green, blue, alpha(pixel)])
+    }
+
+    fn shift_frame(&self, frame: &DynamicImage) -> RgbaImage {
+        let mut frame = frame.to_rgba8();
+        for pixel in frame.pixels_mut() {
+            *pixel = self.shift_pixel(*pixel);
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::GenericImageView;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn shift(hue: f32) -> HsvShift {
+        HsvShift {
+            hue,
+            saturation: 1.0,
+            value: 1.0,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn half_turn_gives_complement() {
+        let config = shift(180.0);
+        assert_eq!(
+            config.shift_pixel(Rgba([255, 0, 0, 255])),
+            Rgba([0, 255, 255, 255])
+        );
+        assert_eq!(
+            config.shift_pixel(Rgba([0, 0, 255, 128])),
+            Rgba([255, 255, 0, 128])
+        );
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        // Red is at 0, so going back 120 degrees lands on blue
+        assert_eq!(
+            shift(-120.0).shift_pixel(Rgba([255, 0, 0, 255])),
+            Rgba([0, 0, 255, 255])
+        );
+        assert_eq!(
+            shift(480.0).shift_pixel(Rgba([255, 0, 0, 255])),
+            Rgba([0, 255, 0, 255])
+        );
+    }
+
+    #[test]
+    fn saturation_and_value_scale() {
+        let config = HsvShift {
+            hue: 0.0,
+            saturation: 0.0,
+            value: 0.5,
+            targets: StateTargets::default(),
+        };
+        // No saturation leaves a grey at half the brightness
+        assert_eq!(
+            config.shift_pixel(Rgba([255, 0, 0, 255])),
+            Rgba([128, 128, 128, 255])
+        );
+    }
+
+    #[test]
+    fn only_targets_are_shifted() {
+        let state = |name: &str| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    1,
+                    1,
+                    Rgba([255, 0, 0, 255]),
+                ))],
+                ..Default::default()
+            }
+        };
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![state("shifted"), state("left_alone")],
+            ..Default::default()
+        };
+        let config = HsvShift {
+            targets: StateTargets {
+                target_states: vec!["shifted".to_string()],
+                ..Default::default()
+            },
+            ..shift(180.0)
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(
+            output.states[0].images[0].get_pixel(0, 0),
+            Rgba([0, 255, 255, 255])
+        );
+        assert_eq!(
+            output.states[1].images[0].get_pixel(0, 0),
+            Rgba([255, 0, 0, 255])
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use dmi::icon::{Icon, IconState};
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::dir_convention::{Convention, DirConvention};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+fn byond() -> Convention {
+    Convention::Byond
+}
+
+/// Slices a sprite strip, stored as the single image of an icon state, in to
+/// an animated icon state with directions, for bringing in art made outside
+/// of dmis.
+///
+/// The strip is read a cell at a time, left to right and then top to bottom,
+/// so sheets wrapping on to more than one row work too. It holds every frame
+/// of one direction before moving on to the next, with the directions in
+/// `dir_order`. Only the last row can have spare cells, which are ignored.
+///
+/// The output dmi holds just the imported state, sized to one cell
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ImportStrip {
+    /// Name of the icon state holding the strip
+    pub source_state: String,
+    /// Name to give the imported icon state
+    pub output_state: String,
+    /// Size of one cell of the strip, in pixels
+    pub cell_width: u32,
+    pub cell_height: u32,
+    /// How many directions the strip holds, 1, 4 or 8
+    pub dirs: u8,
+    /// How many frames each direction has
+    pub frames: u32,
+    /// The order the directions come in along the strip
+    #[serde(default = "byond")]
+    pub dir_order: Convention,
+    /// Delay of each frame, in ticks. Every frame gets 1 tick if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<Vec<f32>>,
+}
+
+impl IconOperationConfig for ImportStrip {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let Some(source) = icon
+            .states
+            .iter()
+            .find(|state| state.name == self.source_state)
+        else {
+            return Err(ProcessorError::ConfigError(format!(
+                "No icon state named \"{}\" to import a strip from",
+                self.source_state
+            )));
+        };
+        let [strip] = source.images.as_slice() else {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" has {} images, but a strip has to be a single image",
+                self.source_state,
+                source.images.len()
+            )));
+        };
+        let cells = self.cells(strip)?;
+
+        let dirs = usize::from(self.dirs);
+        let frames = self.frames as usize;
+        // Dmis store every dir of a frame together, where the strip has every
+        // frame of a dir together
+        let images = (0..frames * dirs)
+            .map(|index| {
+                let (frame, dir) = (index / dirs, index % dirs);
+                let (x, y) = cells[dir * frames + frame];
+                DynamicImage::ImageRgba8(
+                    strip
+                        .view(x, y, self.cell_width, self.cell_height)
+                        .to_image(),
+                )
+            })
+            .collect();
+        let delay = match &self.delay {
+            Some(delay) => Some(delay.clone()),
+            None if self.frames > 1 => Some(vec![1.0; frames]),
+            None => None,
+        };
+        let imported = Icon {
+            width: self.cell_width,
+            height: self.cell_height,
+            states: vec![IconState {
+                name: self.output_state.clone(),
+                dirs: self.dirs,
+                frames: self.frames,
+                images,
+                delay,
+                ..IconState::default()
+            }],
+            ..icon.clone()
+        };
+
+        if self.dir_order == Convention::Byond {
+            return Ok(ProcessorPayload::from_icon(imported));
+        }
+        DirConvention {
+            from: self.dir_order,
+            to: Convention::Byond,
+            targets: StateTargets::default(),
+        }
+        .perform_operation(&InputIcon::Dmi(imported), mode)
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        state_name == self.source_state
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.cell_width == 0 || self.cell_height == 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Cells can't be {}x{}, both sides need to be at least 1 pixel",
+                self.cell_width, self.cell_height
+            )));
+        }
+        if ![1, 4, 8].contains(&self.dirs) {
+            return Err(ProcessorError::ConfigError(format!(
+                "dirs must be 1, 4 or 8, not {}",
+                self.dirs
+            )));
+        }
+        if self.frames == 0 {
+            return Err(ProcessorError::ConfigError(
+                "frames must be at least 1".to_string(),
+            ));
+        }
+        if let Some(delay) = &self.delay {
+            if delay.len() != self.frames as usize {
+                return Err(ProcessorError::ConfigError(format!(
+                    "There are {} frames, but {} delays are given",
+                    self.frames,
+                    delay.len()
+                )));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+impl ImportStrip {
+    /// The top left corner of every cell in `strip`, in the order they're
+    /// read
+    fn cells(&self, strip: &DynamicImage) -> ProcessorResult<Vec<(u32, u32)>> {
+        let (width, height) = strip.dimensions();
+        if width % self.cell_width != 0 || height % self.cell_height != 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "The strip is {width}x{height}, which doesn't split in to {}x{} cells",
+                self.cell_width, self.cell_height
+            )));
+        }
+        let columns = width / self.cell_width;
+        let rows = height / self.cell_height;
+        let needed = u32::from(self.dirs) * self.frames;
+        let total = columns * rows;
+        if needed > total || total - needed >= columns {
+            return Err(ProcessorError::ConfigError(format!(
+                "The strip holds {total} cells in {rows} rows of {columns}, but {} frames of {} \
+                 dirs need {needed}",
+                self.frames, self.dirs
+            )));
+        }
+        Ok((0..needed)
+            .map(|cell| {
+                (
+                    cell % columns * self.cell_width,
+                    cell / columns * self.cell_height,
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A strip of 2x2 cells with the red channel of each set to its place
+    /// along the strip, laid out `columns` cells wide
+    fn strip(cells: u8, columns: u8) -> Icon {
+        let rows = cells.div_ceil(columns);
+        let image = RgbaImage::from_fn(u32::from(columns) * 2, u32::from(rows) * 2, |x, y| {
+            let cell = (y / 2) as u8 * columns + (x / 2) as u8;
+            Rgba([cell, 0, 0, 255])
+        });
+        Icon {
+            width: image.width(),
+            height: image.height(),
+            states: vec![IconState {
+                name: "strip".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn importer(dirs: u8, frames: u32) -> ImportStrip {
+        ImportStrip {
+            source_state: "strip".to_string(),
+            output_state: "walk".to_string(),
+            cell_width: 2,
+            cell_height: 2,
+            dirs,
+            frames,
+            dir_order: Convention::Byond,
+            delay: None,
+        }
+    }
+
+    fn import(config: &ImportStrip, icon: Icon) -> ProcessorResult<Icon> {
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    /// Which cell of the strip each image of the imported state came from
+    fn cells(icon: &Icon) -> Vec<u8> {
+        icon.states[0]
+            .images
+            .iter()
+            .map(|image| {
+                assert_eq!(image.dimensions(), (2, 2));
+                image.get_pixel(1, 1).0[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cells_land_in_their_dir_and_frame() {
+        let output = import(&importer(4, 2), strip(8, 8)).unwrap();
+        assert_eq!((output.width, output.height), (2, 2));
+        let state = &output.states[0];
+        assert_eq!(state.name, "walk");
+        assert_eq!((state.dirs, state.frames), (4, 2));
+        assert_eq!(state.delay, Some(vec![1.0, 1.0]));
+        // Frame 1 of south, north, east, west, then frame 2 of each
+        assert_eq!(cells(&output), vec![0, 2, 4, 6, 1, 3, 5, 7]);
+    }
+
+    #[test]
+    fn dirs_are_put_in_byond_order() {
+        let config = ImportStrip {
+            dir_order: Convention::RpgMaker,
+            delay: Some(vec![2.0, 3.0]),
+            ..importer(4, 2)
+        };
+        // Wrapped on to two rows, with a spare cell at the end
+        let output = import(&config, strip(8, 3)).unwrap();
+        // The strip goes south, west, east, north
+        assert_eq!(cells(&output), vec![0, 6, 4, 2, 1, 7, 5, 3]);
+        assert_eq!(output.states[0].delay, Some(vec![2.0, 3.0]));
+    }
+
+    #[test]
+    fn strips_have_to_fit_the_layout() {
+        assert!(import(&importer(4, 2), strip(6, 6)).is_err());
+        // A whole spare row is more likely a wrong layout than padding
+        assert!(import(&importer(1, 2), strip(4, 2)).is_err());
+        let uneven = ImportStrip {
+            cell_width: 3,
+            ..importer(1, 1)
+        };
+        assert!(import(&uneven, strip(2, 2)).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::context::PipelineContext;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// What `MaxFrames` does about a state with too many frames
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameOverflow {
+    /// Fail, listing every state over budget
+    #[default]
+    Error,
+    /// Raise a warning for each state over budget, which only fails under
+    /// `--strict`
+    Warn,
+}
+
+/// Holds the targeted icon states to a budget on how many frames each can
+/// have, since long animations are costly in BYOND. Unlike `ClampFrames` it
+/// never cuts anything, and the icon always comes out unchanged
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MaxFrames {
+    /// The most frames a state can have, at least 1
+    pub max_frames: u32,
+    #[serde(default)]
+    pub on_overflow: FrameOverflow,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for MaxFrames {
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        self.perform_operation_in_context(input, mode, &mut PipelineContext::new())
+    }
+
+    #[tracing::instrument(skip(input, context))]
+    fn perform_operation_in_context(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        context: &mut PipelineContext,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let over_budget: Vec<(String, u32)> = icon
+            .states
+            .iter()
+            .filter(|state| self.targets.matches(&state.name))
+            .inspect(|state| {
+                info!(
+                    state = ?state.name,
+                    "Has {} of {} frames",
+                    state.frames,
+                    self.max_frames
+                );
+            })
+            .filter(|state| state.frames > self.max_frames)
+            .map(|state| (state.name.clone(), state.frames))
+            .collect();
+        if !over_budget.is_empty() {
+            match self.on_overflow {
+                FrameOverflow::Error => {
+                    return Err(ProcessorError::OverFrameBudget {
+                        limit: self.max_frames,
+                        states: over_budget,
+                    });
+                }
+                FrameOverflow::Warn => {
+                    for (state, frames) in over_budget {
+                        context.warn(ProcessorWarning::LongAnimation {
+                            state,
+                            frames,
+                            limit: self.max_frames,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(icon.clone()))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.max_frames == 0 {
+            return Err(ProcessorError::ConfigError(
+                "max_frames must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+
+    use super::*;
+
+    fn icon() -> Icon {
+        let state = |name: &str, frames: u32| {
+            IconState {
+                name: name.to_string(),
+                frames,
+                ..Default::default()
+            }
+        };
+        Icon {
+            states: vec![state("idle", 2), state("spin", 12), state("explode", 30)],
+            ..Default::default()
+        }
+    }
+
+    fn budget(on_overflow: FrameOverflow) -> MaxFrames {
+        MaxFrames {
+            max_frames: 10,
+            on_overflow,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn states_over_budget_are_listed() {
+        let result = budget(FrameOverflow::Error)
+            .do_operation(&InputIcon::Dmi(icon()), OperationMode::Standard);
+        let Err(ProcessorError::OverFrameBudget { limit, states }) = result else {
+            panic!("Expected the frame budget to be exceeded");
+        };
+        assert_eq!(limit, 10);
+        assert_eq!(
+            states,
+            vec![("spin".to_string(), 12), ("explode".to_string(), 30)]
+        );
+    }
+
+    #[test]
+    fn warn_raises_warnings_instead() {
+        let mut context = PipelineContext::new();
+        budget(FrameOverflow::Warn)
+            .perform_operation_in_context(
+                &InputIcon::Dmi(icon()),
+                OperationMode::Standard,
+                &mut context,
+            )
+            .unwrap();
+        assert_eq!(
+            context.take_warnings(),
+            vec![
+                ProcessorWarning::LongAnimation {
+                    state: "spin".to_string(),
+                    frames: 12,
+                    limit: 10,
+                },
+                ProcessorWarning::LongAnimation {
+                    state: "explode".to_string(),
+                    frames: 30,
+                    limit: 10,
+                },
+            ]
+        );
+
+        // Only the states being checked count
+        let within = MaxFrames {
+            targets: StateTargets {
+                exclude_states: vec!["spin".to_string(), "explode".to_string()],
+                ..StateTargets::default()
+            },
+            ..budget(FrameOverflow::Error)
+        };
+        assert!(within
+            .do_operation(&InputIcon::Dmi(icon()), OperationMode::Standard)
+            .is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::dirs::{facing, Dir};
+
+/// How to make a side facing direction out of the south facing sprite
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirTransform {
+    /// Use the south sprite as is
+    Copy,
+    /// Mirror the south sprite left to right
+    FlipH,
+}
+
+impl DirTransform {
+    fn apply(self, image: &DynamicImage) -> DynamicImage {
+        match self {
+            DirTransform::Copy => image.clone(),
+            DirTransform::FlipH => image.fliph(),
+        }
+    }
+}
+
+/// What to face north with, since there's no way to work out the back of a
+/// sprite from its front
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NorthFallback {
+    /// Use the south sprite as is
+    CopySouth,
+    /// Leave north fully transparent
+    Empty,
+}
+
+fn flip_h() -> DirTransform {
+    DirTransform::FlipH
+}
+
+fn copy() -> DirTransform {
+    DirTransform::Copy
+}
+
+/// Turns single direction icon states in to four direction ones, by copying
+/// or mirroring the south facing sprite. For simple symmetric icons that don't
+/// need anything hand drawn per direction. Icon states that already have more
+/// than one direction are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MirrorDirs {
+    /// How to make the east facing sprite
+    #[serde(default = "flip_h")]
+    pub east: DirTransform,
+    /// How to make the west facing sprite
+    #[serde(default = "copy")]
+    pub west: DirTransform,
+    /// What to face north with
+    pub north: NorthFallback,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for MirrorDirs {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let mut output = icon.clone();
+        for state in &mut output.states {
+            if state.dirs > 1 || !self.targets.matches(&state.name) {
+                continue;
+            }
+            *state = self.mirror(state);
+        }
+
+        Ok(ProcessorPayload::from_icon(output))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+impl MirrorDirs {
+    fn mirror(&self, state: &IconState) -> IconState {
+        // Every frame becomes four images, one for each cardinal in dmi order
+        let images = state
+            .images
+            .iter()
+            .flat_map(|south| facing(4).iter().map(|&dir| self.face(south, dir)))
+            .collect();
+        IconState {
+            dirs: 4,
+            images,
+            ..state.clone()
+        }
+    }
+
+    /// The sprite facing `dir`, made from the south facing one
+    fn face(&self, south: &DynamicImage, dir: Dir) -> DynamicImage {
+        match dir {
+            Dir::North => {
+                match self.north {
+                    NorthFallback::CopySouth => south.clone(),
+                    NorthFallback::Empty => {
+                        DynamicImage::ImageRgba8(RgbaImage::new(south.width(), south.height()))
+                    }
+                }
+            }
+            Dir::East => self.east.apply(south),
+            Dir::West => self.west.apply(south),
+            _ => south.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// A 2x1 frame, `left` on the left and `right` on the right
+    fn frame(left: Rgba<u8>, right: Rgba<u8>) -> DynamicImage {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, left);
+        image.put_pixel(1, 0, right);
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn run(config: &MirrorDirs, states: Vec<IconState>) -> Icon {
+        let icon = Icon {
+            width: 2,
+            height: 1,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    fn pixels(image: &DynamicImage) -> (Rgba<u8>, Rgba<u8>) {
+        (image.get_pixel(0, 0), image.get_pixel(1, 0))
+    }
+
+    #[test]
+    fn east_is_south_flipped() {
+        let config = MirrorDirs {
+            east: DirTransform::FlipH,
+            west: DirTransform::Copy,
+            north: NorthFallback::Empty,
+            targets: StateTargets::default(),
+        };
+        let output = run(
+            &config,
+            vec![IconState {
+                name: "arrow".to_string(),
+                frames: 2,
+                images: vec![frame(RED, BLUE), frame(BLUE, BLUE)],
+                delay: Some(vec![1.0, 2.0]),
+                ..Default::default()
+            }],
+        );
+
+        let state = &output.states[0];
+        assert_eq!((state.dirs, state.frames), (4, 2));
+        assert_eq!(state.delay, Some(vec![1.0, 2.0]));
+        let pixels: Vec<_> = state.images.iter().map(pixels).collect();
+        assert_eq!(
+            pixels,
+            vec![
+                // Frame 1: south, north, east, west
+                (RED, BLUE),
+                (CLEAR, CLEAR),
+                (BLUE, RED),
+                (RED, BLUE),
+                // Frame 2
+                (BLUE, BLUE),
+                (CLEAR, CLEAR),
+                (BLUE, BLUE),
+                (BLUE, BLUE),
+            ]
+        );
+    }
+
+    #[test]
+    fn north_copies_south_and_multi_dir_states_are_skipped() {
+        let config = MirrorDirs {
+            east: DirTransform::Copy,
+            west: DirTransform::FlipH,
+            north: NorthFallback::CopySouth,
+            targets: StateTargets::default(),
+        };
+        let directional = IconState {
+            name: "done".to_string(),
+            dirs: 4,
+            images: vec![frame(RED, RED); 4],
+            ..Default::default()
+        };
+        let output = run(
+            &config,
+            vec![
+                IconState {
+                    name: "arrow".to_string(),
+                    images: vec![frame(RED, BLUE)],
+                    ..Default::default()
+                },
+                directional.clone(),
+            ],
+        );
+
+        let pixels: Vec<_> = output.states[0].images.iter().map(pixels).collect();
+        assert_eq!(
+            pixels,
+            vec![(RED, BLUE), (RED, BLUE), (RED, BLUE), (BLUE, RED)]
+        );
+        assert_eq!(output.states[1], directional);
+    }
+}
@@ -0,0 +1,63 @@
+pub mod alpha_blur;
+pub mod apply_to_all;
+pub mod balance_directions;
+pub mod blur;
+pub mod cap_alpha;
+pub mod center;
+pub mod channel_merge;
+pub mod channel_split;
+pub mod checker;
+pub mod clamp_frames;
+pub mod color_budget;
+pub mod concat;
+pub mod contact_state;
+pub mod crop_hotspot;
+pub mod curves;
+pub mod damage;
+pub mod dedup_states;
+pub mod defringe;
+pub mod dir_contact;
+pub mod dir_convention;
+pub mod dir_overlay;
+pub mod dir_tint;
+pub mod drop_frames;
+pub mod emissive;
+pub mod enforce_aspect;
+pub mod fix_delays;
+pub mod freeze;
+pub mod gamma;
+pub mod glow;
+pub mod gradient_map;
+pub mod hsv;
+pub mod import_strip;
+pub mod max_frames;
+pub mod mirror_dirs;
+pub mod morphology;
+pub mod normal_map;
+pub mod normalize_header;
+pub mod optimize_dirs;
+pub mod overlay;
+pub mod overlay_loop;
+pub mod pad;
+pub mod palette_cycle;
+pub mod passthrough;
+pub mod posterize;
+pub mod region_adjust;
+pub mod relative_crop;
+pub mod renumber;
+pub mod reorder_dirs;
+pub mod replace_frames;
+pub mod scale_xy;
+pub mod self_mask;
+pub mod shape_mask;
+pub mod silhouette;
+pub mod snap_to_grid;
+pub mod split_emissive;
+pub mod subsample;
+pub mod swap_channels;
+pub mod sync_delays;
+pub mod threshold_alpha;
+pub mod tile;
+pub mod trim;
+pub mod validate_dimensions;
+pub mod validate_mask;
@@ -0,0 +1,196 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, with_alpha};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MorphOp {
+    /// Grows silhouettes, each pixel taking the highest alpha around it
+    Dilate,
+    /// Shrinks silhouettes, each pixel taking the lowest alpha around it
+    Erode,
+}
+
+/// Which pixels count as "around" a pixel
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MorphShape {
+    /// Everything within `radius` on both axes, which keeps corners sharp
+    #[default]
+    Square,
+    /// Everything within `radius` in a straight line, which rounds corners
+    Disk,
+}
+
+/// Grows or shrinks the silhouettes of the targeted icon states by `radius`
+/// pixels, working on the alpha channel. Pixels that become visible when
+/// dilating take the color of the neighbour that made them visible
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Morphology {
+    pub op: MorphOp,
+    pub radius: u32,
+    #[serde(default)]
+    pub shape: MorphShape,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Morphology {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| DynamicImage::ImageRgba8(self.apply(frame)))
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.radius == 0 {
+            return Err(ProcessorError::ConfigError(
+                "radius must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Morphology {
+    /// Offsets of every pixel in the structuring element
+    fn offsets(&self) -> Vec<(i64, i64)> {
+        let radius = i64::from(self.radius);
+        (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| {
+                self.shape == MorphShape::Square || dx * dx + dy * dy <= radius * radius
+            })
+            .collect()
+    }
+
+    fn apply(&self, frame: &DynamicImage) -> RgbaImage {
+        let source = frame.to_rgba8();
+        let offsets = self.offsets();
+        let (width, height) = source.dimensions();
+        RgbaImage::from_fn(width, height, |x, y| {
+            // Parts of the element hanging off the edge are ignored
+            let neighbours = offsets.iter().filter_map(|&(dx, dy)| {
+                let x = u32::try_from(i64::from(x) + dx).ok()?;
+                let y = u32::try_from(i64::from(y) + dy).ok()?;
+                (x < width && y < height).then(|| *source.get_pixel(x, y))
+            });
+            let own = *source.get_pixel(x, y);
+            match self.op {
+                MorphOp::Dilate => {
+                    let strongest = neighbours.max_by_key(|&pixel| alpha(pixel)).unwrap_or(own);
+                    if alpha(strongest) > alpha(own) {
+                        strongest
+                    } else {
+                        own
+                    }
+                }
+                MorphOp::Erode => {
+                    let weakest = neighbours.map(alpha).min().unwrap_or(alpha(own));
+                    with_alpha(own, weakest.min(alpha(own)))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Rgba;
+
+    use super::*;
+    use crate::util::color::is_transparent;
+
+    /// A 12x12 frame with a 4x4 opaque red square in the middle
+    fn square_frame() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(12, 12, |x, y| {
+            if (4..8).contains(&x) && (4..8).contains(&y) {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        }))
+    }
+
+    fn morphology(op: MorphOp, shape: MorphShape) -> Morphology {
+        Morphology {
+            op,
+            radius: 1,
+            shape,
+            targets: StateTargets::default(),
+        }
+    }
+
+    /// Width and height of the visible part of a frame
+    fn visible_size(frame: &RgbaImage) -> (u32, u32) {
+        let visible: Vec<(u32, u32)> = frame
+            .enumerate_pixels()
+            .filter(|(_, _, &pixel)| !is_transparent(pixel))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let width = visible.iter().map(|&(x, _)| x).max().unwrap() + 1
+            - visible.iter().map(|&(x, _)| x).min().unwrap();
+        let height = visible.iter().map(|&(_, y)| y).max().unwrap() + 1
+            - visible.iter().map(|&(_, y)| y).min().unwrap();
+        (width, height)
+    }
+
+    #[test]
+    fn dilation_grows_by_radius() {
+        let dilated = morphology(MorphOp::Dilate, MorphShape::Square).apply(&square_frame());
+        assert_eq!(visible_size(&dilated), (6, 6));
+        // New pixels pick up the color of the silhouette
+        assert_eq!(*dilated.get_pixel(3, 3), Rgba([255, 0, 0, 255]));
+        assert_eq!(*dilated.get_pixel(2, 2), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn erosion_shrinks_by_radius() {
+        let eroded = morphology(MorphOp::Erode, MorphShape::Square).apply(&square_frame());
+        assert_eq!(visible_size(&eroded), (2, 2));
+        assert_eq!(*eroded.get_pixel(5, 5), Rgba([255, 0, 0, 255]));
+        assert_eq!(alpha(*eroded.get_pixel(4, 4)), 0);
+    }
+
+    #[test]
+    fn disk_rounds_corners() {
+        let dilated = morphology(MorphOp::Dilate, MorphShape::Disk).apply(&square_frame());
+        assert_eq!(visible_size(&dilated), (6, 6));
+        // The corner diagonal from the square is too far away to reach
+        assert!(is_transparent(*dilated.get_pixel(3, 3)));
+        assert!(!is_transparent(*dilated.get_pixel(3, 4)));
+    }
+}
@@ -0,0 +1,218 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::split_emissive::Channel;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+fn red() -> Channel {
+    Channel::Red
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Builds a tangent space normal map from a height field, using Sobel
+/// gradients, and adds it as a new icon state right after the source. Good
+/// enough as a placeholder until a proper one gets painted.
+///
+/// Normals are encoded OpenGL style, with red pointing right and green
+/// pointing up, so flat areas come out `(128, 128, 255)`. Edges of the icon
+/// are treated as continuing outwards, so they don't slope off
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct NormalFromHeight {
+    /// Icon state to build a normal map for
+    pub state: String,
+    /// Icon state holding the heights, if not `state` itself. Needs either a
+    /// single frame and dir, or the same frames and dirs as `state`
+    #[serde(default)]
+    pub height_state: Option<String>,
+    /// Which channel of the height pixels is the height, with 255 the highest
+    #[serde(default = "red")]
+    pub channel: Channel,
+    /// How steep slopes come out. Higher is bumpier
+    #[serde(default = "one")]
+    pub strength: f32,
+    /// What to name the normal map. Defaults to `{state}_normal`
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl IconOperationConfig for NormalFromHeight {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let find = |name: &str| {
+            icon.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or_else(|| {
+                    ProcessorError::ConfigError(format!(
+                        "Icon state \"{name}\" was not found in the input"
+                    ))
+                })
+        };
+        let index = find(&self.state)?;
+        let source = &icon.states[index];
+        let heights = match &self.height_state {
+            Some(name) => &icon.states[find(name)?],
+            None => source,
+        };
+        if heights.images.len() != 1 && heights.images.len() != source.images.len() {
+            return Err(ProcessorError::ConfigError(format!(
+                "Height icon state \"{}\" needs either a single frame and dir, or the same frames \
+                 and dirs as \"{}\"",
+                heights.name, self.state
+            )));
+        }
+        let name = self
+            .output
+            .clone()
+            .unwrap_or_else(|| format!("{}_normal", self.state));
+        if icon.states.iter().any(|state| state.name == name) {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{name}\" already exists, set output to name the normal map \
+                 something else"
+            )));
+        }
+
+        let normal = IconState {
+            name,
+            images: (0..source.images.len())
+                .map(|image_index| {
+                    let height = &heights.images[image_index % heights.images.len()];
+                    DynamicImage::ImageRgba8(self.normals(height))
+                })
+                .collect(),
+            ..source.clone()
+        };
+        let mut icon = icon.clone();
+        icon.states.insert(index + 1, normal);
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if !self.strength.is_finite() {
+            return Err(ProcessorError::ConfigError(format!(
+                "strength has to be a number, got {}",
+                self.strength
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+impl NormalFromHeight {
+    fn height(&self, pixel: Rgba<u8>) -> f32 {
+        f32::from(self.channel.of(pixel)) / 255.0
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn normals(&self, heights: &DynamicImage) -> RgbaImage {
+        let (width, height) = heights.dimensions();
+        let at = |x: i64, y: i64| {
+            let x = x.clamp(0, i64::from(width) - 1) as u32;
+            let y = y.clamp(0, i64::from(height) - 1) as u32;
+            self.height(heights.get_pixel(x, y))
+        };
+        let encode = |value: f32| ((value * 0.5 + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8;
+
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (x, y) = (i64::from(x), i64::from(y));
+            // Sobel kernels, scaled so a one pixel slope of 1 comes out as 1
+            let across = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1))
+                / 8.0;
+            let down = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1))
+                / 8.0;
+            // Image rows run down while green points up, hence the flip
+            let (normal_x, normal_y, normal_z) =
+                (-across * self.strength, down * self.strength, 1.0);
+            let length = (normal_x * normal_x + normal_y * normal_y + normal_z * normal_z).sqrt();
+            Rgba([
+                encode(normal_x / length),
+                encode(normal_y / length),
+                encode(normal_z / length),
+                255,
+            ])
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn normal_map(height: RgbaImage) -> IconState {
+        let (width, tall) = height.dimensions();
+        let icon = Icon {
+            width,
+            height: tall,
+            states: vec![IconState {
+                name: "rock".to_string(),
+                images: vec![DynamicImage::ImageRgba8(height)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = NormalFromHeight {
+            state: "rock".to_string(),
+            height_state: None,
+            channel: Channel::Red,
+            strength: 1.0,
+            output: None,
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output.states[1].name, "rock_normal");
+        output.states[1].clone()
+    }
+
+    #[test]
+    fn flat_points_straight_out() {
+        let state = normal_map(RgbaImage::from_pixel(4, 4, Rgba([90, 0, 0, 255])));
+        for (_, _, pixel) in state.images[0].pixels() {
+            assert_eq!(pixel, Rgba([128, 128, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn ramp_tilts_away_from_the_slope() {
+        // Rising to the right, so the surface faces left
+        let ramp = RgbaImage::from_fn(5, 5, |x, _| Rgba([x as u8 * 50, 0, 0, 255]));
+        let state = normal_map(ramp);
+        let pixel = state.images[0].get_pixel(2, 2);
+        let [red, green, blue, _] = pixel.0;
+        assert!(red < 128, "{pixel:?}");
+        assert_eq!(green, 128);
+        assert!(blue > 128);
+
+        // Rising downwards, so the surface faces up
+        let ramp = RgbaImage::from_fn(5, 5, |_, y| Rgba([y as u8 * 50, 0, 0, 255]));
+        let pixel = normal_map(ramp).images[0].get_pixel(2, 2);
+        assert_eq!(pixel.0[0], 128);
+        assert!(pixel.0[1] > 128, "{pixel:?}");
+    }
+}
@@ -0,0 +1,104 @@
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::ProcessorResult;
+use crate::operations::modifiers::validate_dimensions::check_dimensions;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Sets the width and height a dmi declares to the size its frames actually
+/// are, for the end of pipelines with operations that resize frames. Every
+/// frame has to be the same size as the first one, since byond rejects dmis
+/// that don't match their declared size
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct NormalizeHeader {}
+
+impl IconOperationConfig for NormalizeHeader {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        let first = icon.states.iter().find_map(|state| state.images.first());
+        if let Some(first) = first {
+            (icon.width, icon.height) = first.dimensions();
+        }
+        check_dimensions(&icon)?;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+
+    use super::*;
+    use crate::operations::error::ProcessorError;
+    use crate::operations::OutputImage;
+
+    fn state(name: &str, sizes: &[(u32, u32)]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            frames: sizes.len() as u32,
+            images: sizes
+                .iter()
+                .map(|&(width, height)| DynamicImage::ImageRgba8(RgbaImage::new(width, height)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn normalize(states: Vec<IconState>) -> ProcessorResult<Icon> {
+        let icon = Icon {
+            width: 32,
+            height: 32,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) =
+            NormalizeHeader {}.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    #[test]
+    fn header_follows_uniformly_resized_frames() {
+        let icon = normalize(vec![
+            state("wall", &[(64, 48); 2]),
+            state("floor", &[(64, 48)]),
+        ])
+        .unwrap();
+        assert_eq!((icon.width, icon.height), (64, 48));
+        assert_eq!(icon.states.len(), 2);
+    }
+
+    #[test]
+    fn inconsistent_frames_name_the_first_offender() {
+        let Err(ProcessorError::DimensionViolation {
+            state,
+            frame,
+            expected,
+            got,
+        }) = normalize(vec![
+            state("wall", &[(64, 64)]),
+            state("door", &[(64, 64), (32, 32)]),
+            state("window", &[(16, 16)]),
+        ])
+        else {
+            panic!("Expected a dimension violation");
+        };
+        assert_eq!(state, "door");
+        assert_eq!(frame, 1);
+        assert_eq!(expected, (64, 64));
+        assert_eq!(got, (32, 32));
+    }
+}
@@ -0,0 +1,161 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::is_transparent;
+
+/// How sure we have to be that a state's extra directions aren't needed
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirCollapse {
+    /// Only collapse states where every direction of every frame is pixel for
+    /// pixel the same, so the result looks exactly like the original
+    #[default]
+    Strict,
+    /// Also collapse states where every direction but south is fully
+    /// transparent. Those states will show south's frames facing every way
+    /// afterwards, rather than nothing
+    Lossy,
+}
+
+/// Shrinks directional icon states down to a single direction when the extra
+/// directions don't add anything
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct OptimizeDirs {
+    #[serde(default)]
+    pub collapse: DirCollapse,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for OptimizeDirs {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if self.targets.matches(&state.name) && self.can_collapse(&state) {
+                    info!(state = state.name, dirs = state.dirs, "Collapsed to 1 dir");
+                    collapse(state)
+                } else {
+                    state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+impl OptimizeDirs {
+    fn can_collapse(&self, state: &IconState) -> bool {
+        let dirs = usize::from(state.dirs);
+        // Anything with missing images isn't safe to guess about
+        if dirs <= 1 || state.images.len() != state.frames as usize * dirs {
+            return false;
+        }
+        // Images are stored frame by frame, with south first in each
+        state.images.chunks(dirs).all(|frame| {
+            let south = frame[0].to_rgba8();
+            frame[1..].iter().all(|dir| {
+                let dir = dir.to_rgba8();
+                dir == south
+                    || (self.collapse == DirCollapse::Lossy
+                        && dir.pixels().all(|&pixel| is_transparent(pixel)))
+            })
+        })
+    }
+}
+
+/// Keeps only the south facing image of every frame
+fn collapse(state: IconState) -> IconState {
+    let images: Vec<DynamicImage> = state
+        .images
+        .chunks(state.dirs.into())
+        .map(|frame| frame[0].clone())
+        .collect();
+    IconState {
+        dirs: 1,
+        images,
+        ..state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn image(color: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba(color)))
+    }
+
+    /// A two frame state with the given image for each of its 4 dirs
+    fn state(dirs: [[u8; 4]; 4]) -> IconState {
+        IconState {
+            name: "thing".to_string(),
+            dirs: 4,
+            frames: 2,
+            images: (0..2).flat_map(|_| dirs.map(image)).collect(),
+            delay: Some(vec![1.0, 2.0]),
+            ..Default::default()
+        }
+    }
+
+    fn config(collapse: DirCollapse) -> OptimizeDirs {
+        OptimizeDirs {
+            collapse,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn identical_dirs_collapse() {
+        let red = [255, 0, 0, 255];
+        let state = state([red; 4]);
+        assert!(config(DirCollapse::Strict).can_collapse(&state));
+
+        let collapsed = collapse(state);
+        assert_eq!(collapsed.dirs, 1);
+        assert_eq!(collapsed.frames, 2);
+        assert_eq!(collapsed.images, vec![image(red), image(red)]);
+        assert_eq!(collapsed.delay, Some(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn different_dirs_are_kept() {
+        let state = state([
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [255, 0, 0, 255],
+            [255, 0, 0, 255],
+        ]);
+        assert!(!config(DirCollapse::Strict).can_collapse(&state));
+        assert!(!config(DirCollapse::Lossy).can_collapse(&state));
+    }
+
+    #[test]
+    fn only_lossy_collapses_empty_dirs() {
+        let empty = [0, 0, 0, 0];
+        let state = state([[255, 0, 0, 255], empty, empty, empty]);
+        assert!(!config(DirCollapse::Strict).can_collapse(&state));
+        assert!(config(DirCollapse::Lossy).can_collapse(&state));
+    }
+}
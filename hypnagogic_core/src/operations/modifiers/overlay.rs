@@ -0,0 +1,204 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::{blend_images, BlendMode};
+
+/// Composites one icon state of a dmi on top of the targeted icon states.
+///
+/// Frames and dirs are matched up by index, with the overlay's cycling if it
+/// has fewer of either than the state it's being put on. The overlay state
+/// itself is left as is
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Overlay {
+    /// Name of the icon state to lay on top
+    pub overlay_state: String,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Overlay {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let Some(overlay) = icon
+            .states
+            .iter()
+            .find(|state| state.name == self.overlay_state)
+        else {
+            return Err(ProcessorError::ConfigError(format!(
+                "Overlay icon state \"{}\" was not found in the input",
+                self.overlay_state
+            )));
+        };
+
+        let mut output = icon.clone();
+        for state in &mut output.states {
+            if state.name == self.overlay_state || !self.targets.matches(&state.name) {
+                continue;
+            }
+            *state = self.overlay_state_onto(state, overlay)?;
+        }
+
+        Ok(ProcessorPayload::from_icon(output))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+impl Overlay {
+    pub(crate) fn overlay_state_onto(
+        &self,
+        base: &IconState,
+        overlay: &IconState,
+    ) -> ProcessorResult<IconState> {
+        let base_dirs = usize::from(base.dirs.max(1));
+        let overlay_dirs = usize::from(overlay.dirs.max(1));
+        let overlay_frames = overlay.images.len() / overlay_dirs;
+        if overlay_frames == 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Overlay icon state \"{}\" has no frames to lay on top",
+                overlay.name
+            )));
+        }
+        let images = base
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                // Images are stored frame by frame, with every dir of a frame together
+                let (frame, dir) = (index / base_dirs, index % base_dirs);
+                let overlay_index = (frame % overlay_frames) * overlay_dirs + dir % overlay_dirs;
+                DynamicImage::ImageRgba8(blend_images(
+                    image,
+                    &overlay.images[overlay_index],
+                    self.blend_mode,
+                ))
+            })
+            .collect();
+        Ok(IconState {
+            images,
+            ..base.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn solid_state(name: &str, colors: &[[u8; 4]]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            frames: colors.len() as u32,
+            images: colors
+                .iter()
+                .map(|&color| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba(color))))
+                .collect(),
+            delay: (colors.len() > 1).then(|| vec![1.0; colors.len()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlay_cycles_over_animated_base() {
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states: vec![
+                solid_state(
+                    "base",
+                    &[[200, 100, 50, 255], [0, 0, 0, 255], [10, 10, 10, 255]],
+                ),
+                solid_state("light", &[[100, 200, 250, 255]]),
+            ],
+            ..Default::default()
+        };
+        let config = Overlay {
+            overlay_state: "light".to_string(),
+            blend_mode: BlendMode::Add,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let first_pixels: Vec<Rgba<u8>> = output.states[0]
+            .images
+            .iter()
+            .map(|image| image.get_pixel(0, 0))
+            .collect();
+        assert_eq!(
+            first_pixels,
+            vec![
+                Rgba([255, 255, 255, 255]),
+                Rgba([100, 200, 250, 255]),
+                Rgba([110, 210, 255, 255]),
+            ]
+        );
+        // The overlay itself isn't touched
+        assert_eq!(
+            output.states[1].images[0].get_pixel(0, 0),
+            Rgba([100, 200, 250, 255])
+        );
+    }
+
+    #[test]
+    fn missing_overlay_state_errors() {
+        let icon = Icon {
+            states: vec![solid_state("base", &[[0, 0, 0, 255]])],
+            ..Default::default()
+        };
+        let config = Overlay {
+            overlay_state: "nope".to_string(),
+            blend_mode: BlendMode::Normal,
+            targets: StateTargets::default(),
+        };
+        assert!(config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .is_err());
+    }
+
+    #[test]
+    fn frameless_overlay_state_errors() {
+        let icon = Icon {
+            states: vec![
+                solid_state("base", &[[0, 0, 0, 255]]),
+                solid_state("light", &[]),
+            ],
+            ..Default::default()
+        };
+        let config = Overlay {
+            overlay_state: "light".to_string(),
+            blend_mode: BlendMode::Normal,
+            targets: StateTargets::default(),
+        };
+        let Err(ProcessorError::ConfigError(reason)) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+        else {
+            panic!("Expected a config error");
+        };
+        assert!(reason.contains("no frames"), "{reason}");
+    }
+}
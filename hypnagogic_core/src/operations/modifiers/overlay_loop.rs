@@ -0,0 +1,259 @@
+use std::num::NonZeroU32;
+
+use dmi::icon::{IconState, Looping};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::Offset;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::blend::{blend_images, BlendMode};
+use crate::util::icon_ops::translate_image;
+
+/// Loops an animated overlay (fire, sparks, and so on) over a base icon state,
+/// adding the result as a new icon state right after the base.
+///
+/// The result has as many frames as the overlay, with the overlay's delays.
+/// If the base is animated too, its frames cycle underneath
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct OverlayLoop {
+    /// Name of the icon state to go underneath
+    pub base_state: String,
+    /// Name of the animated icon state to loop on top
+    pub overlay_state: String,
+    /// Name of the icon state to create. Defaults to
+    /// `{base_state}-{overlay_state}`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_state: Option<String>,
+    /// How many times the animation plays. 0 loops forever
+    #[serde(default)]
+    pub loops: u32,
+    /// Where to put the overlay, relative to the base
+    #[serde(default)]
+    pub offset: Offset,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+}
+
+impl IconOperationConfig for OverlayLoop {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let find_state = |name: &str| {
+            icon.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or_else(|| {
+                    ProcessorError::ConfigError(format!(
+                        "Icon state \"{name}\" was not found in the input"
+                    ))
+                })
+        };
+        let base_index = find_state(&self.base_state)?;
+        let overlay = &icon.states[find_state(&self.overlay_state)?];
+
+        let output_name = self.output_name();
+        if find_state(&output_name).is_ok() {
+            return Err(ProcessorError::ConfigError(format!(
+                "Can't create icon state \"{output_name}\", one with that name already exists"
+            )));
+        }
+        let combined = IconState {
+            name: output_name,
+            ..self.combine(&icon.states[base_index], overlay)?
+        };
+
+        let mut icon = icon.clone();
+        icon.states.insert(base_index + 1, combined);
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        state_name == self.base_state || state_name == self.overlay_state
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.base_state == self.overlay_state {
+            return Err(ProcessorError::ConfigError(
+                "base_state and overlay_state must be different icon states".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl OverlayLoop {
+    fn output_name(&self) -> String {
+        self.output_state
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}", self.base_state, self.overlay_state))
+    }
+
+    fn combine(&self, base: &IconState, overlay: &IconState) -> ProcessorResult<IconState> {
+        let base_dirs = usize::from(base.dirs.max(1));
+        let base_frames = base.images.len() / base_dirs;
+        let overlay_dirs = usize::from(overlay.dirs.max(1));
+        let overlay_frames = overlay.images.len() / overlay_dirs;
+        for (state, frames) in [(base, base_frames), (overlay, overlay_frames)] {
+            if frames == 0 {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Icon state \"{}\" has no frames to loop",
+                    state.name
+                )));
+            }
+        }
+
+        // Images are stored frame by frame, with every dir of a frame together
+        let mut images = vec![];
+        for frame in 0..overlay_frames {
+            for dir in 0..base_dirs {
+                let base_image = &base.images[(frame % base_frames) * base_dirs + dir];
+                let overlay_image = &overlay.images[frame * overlay_dirs + dir % overlay_dirs];
+                let overlay_image = translate_image(
+                    overlay_image,
+                    i64::from(self.offset.x),
+                    i64::from(self.offset.y),
+                );
+                images.push(DynamicImage::ImageRgba8(blend_images(
+                    base_image,
+                    &overlay_image,
+                    self.blend_mode,
+                )));
+            }
+        }
+
+        Ok(IconState {
+            dirs: base.dirs,
+            frames: overlay_frames as u32,
+            images,
+            delay: overlay
+                .delay
+                .clone()
+                .or_else(|| (overlay_frames > 1).then(|| vec![1.0; overlay_frames])),
+            loop_flag: NonZeroU32::new(self.loops).map_or(Looping::Indefinitely, Looping::NTimes),
+            ..base.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn state(name: &str, images: Vec<DynamicImage>) -> IconState {
+        let frames = images.len();
+        IconState {
+            name: name.to_string(),
+            frames: frames as u32,
+            images,
+            delay: (frames > 1).then(|| (1..=frames).map(|delay| delay as f32).collect()),
+            ..Default::default()
+        }
+    }
+
+    /// 2x1 frame, left pixel is the base color, right pixel is transparent
+    fn base_frame() -> DynamicImage {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, Rgba([0, 0, 255, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    /// 2x1 frame with only the right pixel filled, in a color unique to `frame`
+    fn flame_frame(frame: u8) -> DynamicImage {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(1, 0, Rgba([255, frame * 50, 0, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn static_base_gets_every_overlay_frame() {
+        let icon = Icon {
+            width: 2,
+            height: 1,
+            states: vec![
+                state("crate", vec![base_frame()]),
+                state("fire", (0..4).map(flame_frame).collect()),
+            ],
+            ..Default::default()
+        };
+        let config = OverlayLoop {
+            base_state: "crate".to_string(),
+            overlay_state: "fire".to_string(),
+            output_state: None,
+            loops: 0,
+            offset: Offset::default(),
+            blend_mode: BlendMode::Normal,
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+
+        let names: Vec<&str> = output.states.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["crate", "crate-fire", "fire"]);
+        let combined = &output.states[1];
+        assert_eq!(combined.frames, 4);
+        assert_eq!(combined.delay, Some(vec![1.0, 2.0, 3.0, 4.0]));
+        for (frame, image) in combined.images.iter().enumerate() {
+            assert_eq!(image.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+            assert_eq!(
+                image.get_pixel(1, 0),
+                flame_frame(frame as u8).get_pixel(1, 0)
+            );
+        }
+    }
+
+    #[test]
+    fn offset_and_loops_are_applied() {
+        let config = OverlayLoop {
+            base_state: "crate".to_string(),
+            overlay_state: "fire".to_string(),
+            output_state: Some("burning".to_string()),
+            loops: 3,
+            offset: Offset { x: -1, y: 0 },
+            blend_mode: BlendMode::Normal,
+        };
+        let combined = config
+            .combine(
+                &state("crate", vec![base_frame()]),
+                &state("fire", (0..2).map(flame_frame).collect()),
+            )
+            .unwrap();
+
+        assert_eq!(combined.loop_flag, Looping::new(3));
+        // Flame moved over the base pixel, and off of the empty one
+        assert_eq!(combined.images[1].get_pixel(0, 0), Rgba([255, 50, 0, 255]));
+        assert_eq!(combined.images[1].get_pixel(1, 0), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn frameless_states_error() {
+        let config = OverlayLoop {
+            base_state: "crate".to_string(),
+            overlay_state: "fire".to_string(),
+            output_state: None,
+            loops: 0,
+            offset: Offset::default(),
+            blend_mode: BlendMode::Normal,
+        };
+        let base = state("crate", vec![base_frame()]);
+        let fire = state("fire", (0..2).map(flame_frame).collect());
+        assert!(config.combine(&state("crate", vec![]), &fire).is_err());
+        assert!(config.combine(&base, &state("fire", vec![])).is_err());
+    }
+}
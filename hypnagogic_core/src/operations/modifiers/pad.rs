@@ -0,0 +1,142 @@
+use image::{imageops, DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::context::PipelineContext;
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::modifiers::trim::TrimmedBounds;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Adds transparent space around every frame of every icon state, growing the
+/// icon to fit.
+///
+/// With `restore_trim`, puts back the space an earlier `Trim` in the same
+/// pipeline cut away, so the content lands where it started. Any other padding
+/// is added on top of that
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Pad {
+    #[serde(default)]
+    pub left: u32,
+    #[serde(default)]
+    pub right: u32,
+    #[serde(default)]
+    pub top: u32,
+    #[serde(default)]
+    pub bottom: u32,
+    /// Undo the last `Trim` before this in the pipeline
+    #[serde(default)]
+    pub restore_trim: bool,
+}
+
+impl IconOperationConfig for Pad {
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        self.perform_operation_in_context(input, mode, &mut PipelineContext::new())
+    }
+
+    #[tracing::instrument(skip(input, context))]
+    fn perform_operation_in_context(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        context: &mut PipelineContext,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let (mut left, mut right, mut top, mut bottom) =
+            (self.left, self.right, self.top, self.bottom);
+        if self.restore_trim {
+            let Some(trimmed) = context.get::<TrimmedBounds>() else {
+                return Err(ProcessorError::ConfigError(
+                    "restore_trim needs a Trim operation earlier in the pipeline".to_string(),
+                ));
+            };
+            // Anything between might have resized the icon, so work from its
+            // size now rather than the size Trim left it at
+            left += trimmed.kept.x;
+            top += trimmed.kept.y;
+            right += trimmed
+                .original_width
+                .saturating_sub(trimmed.kept.x + icon.width);
+            bottom += trimmed
+                .original_height
+                .saturating_sub(trimmed.kept.y + icon.height);
+        }
+
+        let mut icon = icon.clone();
+        icon.width += left + right;
+        icon.height += top + bottom;
+        for state in &mut icon.states {
+            for image in &mut state.images {
+                let mut padded = RgbaImage::new(icon.width, icon.height);
+                imageops::replace(
+                    &mut padded,
+                    &image.to_rgba8(),
+                    i64::from(left),
+                    i64::from(top),
+                );
+                *image = DynamicImage::ImageRgba8(padded);
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{GenericImageView, Rgba};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn pads_each_side() {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "dot".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    1,
+                    1,
+                    Rgba([255, 0, 0, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = Pad {
+            left: 1,
+            right: 2,
+            top: 3,
+            bottom: 0,
+            restore_trim: false,
+        };
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon.clone()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!((output.width, output.height), (4, 4));
+        let image = &output.states[0].images[0];
+        assert_eq!(image.dimensions(), (4, 4));
+        assert_eq!(image.get_pixel(1, 3), Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+
+        // Without a Trim there's nothing to restore
+        let restore = Pad {
+            restore_trim: true,
+            ..Pad::default()
+        };
+        assert!(restore
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, rgb, Color};
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Animates a single frame icon state by cycling one of its colors through a
+/// list of others, for glows and pulses. Every pixel of `color` is swapped
+/// for the current color of the cycle, keeping its own alpha.
+///
+/// With `frames` left out each color gets a frame of its own. Otherwise the
+/// colors are treated as a looping gradient and sampled evenly, so the last
+/// color blends back in to the first
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PaletteCycle {
+    /// Icon state to animate
+    pub state: String,
+    /// The color to replace. Alpha is ignored
+    pub color: Color,
+    /// Colors to cycle through, in order
+    pub colors: Vec<Color>,
+    /// How many frames the animation has
+    #[serde(default)]
+    pub frames: Option<u32>,
+    /// How long each frame lasts, in ticks
+    #[serde(default = "one")]
+    pub delay: f32,
+}
+
+impl IconOperationConfig for PaletteCycle {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let Some(index) = icon
+            .states
+            .iter()
+            .position(|state| state.name == self.state)
+        else {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" was not found in the input",
+                self.state
+            )));
+        };
+        let source = &icon.states[index];
+        if source.frames > 1 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" is already animated, palette cycling needs a single frame",
+                self.state
+            )));
+        }
+
+        let mut icon = icon.clone();
+        icon.states[index] = self.animate(source);
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        state_name == self.state
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.colors.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "colors needs at least one color to cycle through".to_string(),
+            ));
+        }
+        if self.frames == Some(0) {
+            return Err(ProcessorError::ConfigError(
+                "frames must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl PaletteCycle {
+    fn animate(&self, source: &IconState) -> IconState {
+        let target = [self.color.red, self.color.green, self.color.blue];
+        let cycle = self.cycle();
+        let mut images = vec![];
+        for color in &cycle {
+            for dir in &source.images {
+                let mut frame = dir.to_rgba8();
+                for pixel in frame.pixels_mut() {
+                    if rgb(*pixel) == target {
+                        let [red, green, blue] = *color;
+                        *pixel = Rgba([red, green, blue, alpha(*pixel)]);
+                    }
+                }
+                images.push(DynamicImage::ImageRgba8(frame));
+            }
+        }
+
+        IconState {
+            frames: cycle.len() as u32,
+            images,
+            delay: Some(vec![self.delay; cycle.len()]),
+            ..source.clone()
+        }
+    }
+
+    /// The color of each frame of the animation
+    fn cycle(&self) -> Vec<[u8; 3]> {
+        let stops: Vec<[u8; 3]> = self
+            .colors
+            .iter()
+            .map(|color| [color.red, color.green, color.blue])
+            .collect();
+        let Some(frames) = self.frames else {
+            return stops;
+        };
+        (0..frames)
+            .map(|frame| {
+                let position = frame as f32 * stops.len() as f32 / frames as f32;
+                let from = position.floor() as usize;
+                let to = (from + 1) % stops.len();
+                let progress = position.fract();
+                std::array::from_fn(|channel| {
+                    let (from, to) = (
+                        f32::from(stops[from][channel]),
+                        f32::from(stops[to][channel]),
+                    );
+                    (from + (to - from) * progress).round() as u8
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn cycle(frames: Option<u32>) -> IconState {
+        // Left pixel gets cycled, right pixel is left alone
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 128]));
+        image.put_pixel(1, 0, Rgba([0, 0, 255, 255]));
+        let icon = Icon {
+            width: 2,
+            height: 1,
+            states: vec![IconState {
+                name: "light".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = PaletteCycle {
+            state: "light".to_string(),
+            color: Color::new_rgb(255, 0, 0),
+            colors: vec![Color::new_rgb(0, 0, 0), Color::new_rgb(100, 200, 0)],
+            frames,
+            delay: 2.0,
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].clone()
+    }
+
+    #[test]
+    fn one_frame_per_color() {
+        let state = cycle(None);
+        assert_eq!(state.frames, 2);
+        assert_eq!(state.delay, Some(vec![2.0, 2.0]));
+        assert_eq!(state.images[0].get_pixel(0, 0), Rgba([0, 0, 0, 128]));
+        assert_eq!(state.images[1].get_pixel(0, 0), Rgba([100, 200, 0, 128]));
+        for image in &state.images {
+            assert_eq!(image.get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn blends_between_colors() {
+        let state = cycle(Some(4));
+        assert_eq!(state.frames, 4);
+        assert_eq!(state.delay, Some(vec![2.0; 4]));
+        let colors: Vec<Rgba<u8>> = state
+            .images
+            .iter()
+            .map(|image| image.get_pixel(0, 0))
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                Rgba([0, 0, 0, 128]),
+                Rgba([50, 100, 0, 128]),
+                Rgba([100, 200, 0, 128]),
+                // Heading back to the start
+                Rgba([50, 100, 0, 128]),
+            ]
+        );
+    }
+}
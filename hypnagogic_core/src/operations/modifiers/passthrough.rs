@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Does nothing, handing back the input as is. Handy as a placeholder in
+/// templates and pipelines, and for testing
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Passthrough {}
+
+impl IconOperationConfig for Passthrough {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        Ok(ProcessorPayload::from_input(input.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+
+    use super::*;
+    use crate::operations::IconOperation;
+
+    #[test]
+    fn hands_back_input() {
+        let config: IconOperation = toml::from_str("mode = \"Passthrough\"").unwrap();
+        let icon = Icon {
+            states: vec![IconState {
+                name: "untouched".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let output = config
+            .do_operation(&InputIcon::Dmi(icon.clone()), OperationMode::Standard)
+            .unwrap();
+        let Some(InputIcon::Dmi(output)) = output.into_input() else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output.states, icon.states);
+    }
+}
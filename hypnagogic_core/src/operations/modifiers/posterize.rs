@@ -0,0 +1,169 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Posterizes the targeted icon states, snapping each color channel to the
+/// nearest of `levels` evenly spaced values, for a deliberately flat look.
+/// The lowest level is always 0 and the highest 255. Alpha is left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Posterize {
+    /// How many values each channel is allowed, at least 2
+    pub levels: u8,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Posterize {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let table = self.lookup_table();
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                for channel in &mut pixel.0[..3] {
+                                    *channel = table[usize::from(*channel)];
+                                }
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.levels < 2 {
+            return Err(ProcessorError::ConfigError(format!(
+                "levels must be at least 2, got {}",
+                self.levels
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Posterize {
+    /// What every channel value maps to, worked out once rather than per
+    /// pixel
+    fn lookup_table(&self) -> [u8; 256] {
+        std::array::from_fn(|value| self.snap(value as u8))
+    }
+
+    fn snap(&self, value: u8) -> u8 {
+        let steps = f32::from(self.levels - 1);
+        let level = (f32::from(value) / 255.0 * steps).round();
+        (level / steps * 255.0).round() as u8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// Posterizes a gradient running through every channel value, with the
+    /// alpha running backwards
+    fn posterize(levels: u8) -> Vec<Rgba<u8>> {
+        let gradient = RgbaImage::from_fn(256, 1, |x, _| {
+            let value = x as u8;
+            Rgba([value, value / 2, 255 - value, 255 - value])
+        });
+        let icon = Icon {
+            width: 256,
+            height: 1,
+            states: vec![IconState {
+                name: "sky".to_string(),
+                images: vec![DynamicImage::ImageRgba8(gradient)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = Posterize {
+            levels,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states[0].images[0]
+            .pixels()
+            .map(|(_, _, pixel)| pixel)
+            .collect()
+    }
+
+    #[test]
+    fn two_levels_snap_to_the_extremes() {
+        for pixel in posterize(2) {
+            for channel in &pixel.0[..3] {
+                assert!(matches!(channel, 0 | 255), "{pixel:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn four_levels_make_four_bands() {
+        let pixels = posterize(4);
+        let reds: BTreeSet<u8> = pixels.iter().map(|pixel| pixel.0[0]).collect();
+        assert_eq!(reds.into_iter().collect::<Vec<_>>(), vec![0, 85, 170, 255]);
+        // Banded in order, so never going back down
+        assert!(pixels.windows(2).all(|pair| pair[0].0[0] <= pair[1].0[0]));
+    }
+
+    #[test]
+    fn alpha_passes_through() {
+        for (value, pixel) in posterize(2).into_iter().enumerate() {
+            assert_eq!(usize::from(pixel.0[3]), 255 - value);
+        }
+    }
+
+    #[test]
+    fn fewer_than_two_levels_is_rejected() {
+        let config = Posterize {
+            levels: 1,
+            targets: StateTargets::default(),
+        };
+        assert!(config.verify_config().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, GenericImageView, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::split_emissive::{check_mask, mask_selects};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, Color};
+
+fn one() -> f32 {
+    1.0
+}
+
+/// Adjusts the color of just the pixels of an icon state that another state
+/// masks, leaving the rest exactly as they were. Brightness is applied first,
+/// then contrast, then the tint is multiplied in. Alpha is never changed.
+///
+/// The source is adjusted in place, unless a `suffix` is given, in which case
+/// the adjusted copy is added after it, named with the suffix on the end
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RegionAdjust {
+    /// Icon state to adjust
+    pub state: String,
+    /// Icon state picking the pixels to adjust, wherever it isn't transparent.
+    /// A mask with a single frame and dir is used for every frame and dir of
+    /// the source
+    pub mask: String,
+    /// Multiplier for the color of masked pixels
+    #[serde(default = "one")]
+    pub brightness: f32,
+    /// Multiplier for how far the color of masked pixels is from middle grey
+    #[serde(default = "one")]
+    pub contrast: f32,
+    /// Color multiplied into masked pixels
+    #[serde(default)]
+    pub tint: Option<Color>,
+    #[serde(default)]
+    pub suffix: Option<String>,
+}
+
+impl IconOperationConfig for RegionAdjust {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let find = |name: &str| {
+            icon.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or_else(|| {
+                    ProcessorError::ConfigError(format!(
+                        "Icon state \"{name}\" was not found in the input"
+                    ))
+                })
+        };
+        let index = find(&self.state)?;
+        let source = &icon.states[index];
+        let mask = &icon.states[find(&self.mask)?];
+        check_mask(mask, source)?;
+
+        let images = source
+            .images
+            .iter()
+            .enumerate()
+            .map(|(image_index, image)| {
+                let mut adjusted = image.to_rgba8();
+                for (x, y, pixel) in image.pixels() {
+                    if mask_selects(mask, image_index, x, y) {
+                        adjusted.put_pixel(x, y, self.adjust_pixel(pixel));
+                    }
+                }
+                DynamicImage::ImageRgba8(adjusted)
+            })
+            .collect();
+
+        let mut icon = icon.clone();
+        match &self.suffix {
+            Some(suffix) => {
+                let name = format!("{}{suffix}", self.state);
+                if icon.states.iter().any(|existing| existing.name == name) {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "Can't add adjusted state \"{name}\", an icon state with that name \
+                         already exists"
+                    )));
+                }
+                let adjusted = IconState {
+                    name,
+                    images,
+                    ..icon.states[index].clone()
+                };
+                icon.states.insert(index + 1, adjusted);
+            }
+            None => icon.states[index].images = images,
+        }
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        state_name == self.state || state_name == self.mask
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        for (name, multiplier) in [("brightness", self.brightness), ("contrast", self.contrast)] {
+            if !multiplier.is_finite() || multiplier < 0.0 {
+                return Err(ProcessorError::ConfigError(format!(
+                    "{name} must be a positive multiplier, got {multiplier}"
+                )));
+            }
+        }
+        if self.suffix.as_deref() == Some("") {
+            return Err(ProcessorError::ConfigError(
+                "suffix can't be empty, leave it out to adjust the state in place".to_string(),
+            ));
+        }
+        if self.state == self.mask {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(format!(
+                "\"{}\" is used as its own mask, so every pixel that isn't transparent is adjusted",
+                self.state
+            ))]);
+        }
+        #[allow(clippy::float_cmp)]
+        if self.brightness == 1.0 && self.contrast == 1.0 && self.tint.is_none() {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "No brightness, contrast or tint is given, so nothing is adjusted".to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl RegionAdjust {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn adjust_pixel(&self, pixel: Rgba<u8>) -> Rgba<u8> {
+        let tint = self.tint.unwrap_or(Color::new_rgb(255, 255, 255));
+        let adjust = |channel: u8, tint: u8| {
+            let value = f32::from(channel) * self.brightness;
+            let value = (value - 127.5) * self.contrast + 127.5;
+            let value = value.clamp(0.0, 255.0) * f32::from(tint) / 255.0;
+            value.round() as u8
+        };
+        let Rgba([red, green, blue, _]) = pixel;
+        Rgba([
+            adjust(red, tint.red),
+            adjust(green, tint.green),
+            adjust(blue, tint.blue),
+            alpha(pixel),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const PANEL: Rgba<u8> = Rgba([100, 80, 60, 255]);
+
+    /// A 4x1 panel over two frames, masked on its first two pixels
+    fn panel() -> Icon {
+        let state = |name: &str, images: Vec<RgbaImage>| {
+            IconState {
+                name: name.to_string(),
+                frames: images.len() as u32,
+                images: images.into_iter().map(DynamicImage::ImageRgba8).collect(),
+                ..Default::default()
+            }
+        };
+        let mut mask = RgbaImage::new(4, 1);
+        mask.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        mask.put_pixel(1, 0, Rgba([255, 255, 255, 1]));
+        Icon {
+            width: 4,
+            height: 1,
+            states: vec![
+                state(
+                    "panel",
+                    vec![
+                        RgbaImage::from_pixel(4, 1, PANEL),
+                        RgbaImage::from_pixel(4, 1, Rgba([10, 20, 30, 128])),
+                    ],
+                ),
+                state("panel_mask", vec![mask]),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn adjuster() -> RegionAdjust {
+        RegionAdjust {
+            state: "panel".to_string(),
+            mask: "panel_mask".to_string(),
+            brightness: 2.0,
+            contrast: 1.0,
+            tint: None,
+            suffix: None,
+        }
+    }
+
+    fn apply(config: &RegionAdjust) -> Vec<IconState> {
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(panel()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states
+    }
+
+    #[test]
+    fn only_masked_pixels_are_adjusted() {
+        let source = panel();
+        let states = apply(&adjuster());
+        let adjusted = &states[0];
+
+        for (frame, expected) in [Rgba([200, 160, 120, 255]), Rgba([20, 40, 60, 128])]
+            .into_iter()
+            .enumerate()
+        {
+            let image = adjusted.images[frame].to_rgba8();
+            let original = source.states[0].images[frame].to_rgba8();
+            assert_eq!(*image.get_pixel(0, 0), expected);
+            assert_eq!(*image.get_pixel(1, 0), expected);
+            // Unmasked pixels keep their exact bytes
+            for x in 2..4 {
+                assert_eq!(image.get_pixel(x, 0), original.get_pixel(x, 0));
+            }
+        }
+        assert_eq!(states[1], source.states[1]);
+    }
+
+    #[test]
+    fn suffix_keeps_the_source() {
+        let states = apply(&RegionAdjust {
+            tint: Some(Color::new_rgb(255, 0, 255)),
+            brightness: 1.0,
+            suffix: Some("_lit".to_string()),
+            ..adjuster()
+        });
+        let names: Vec<&str> = states.iter().map(|state| state.name.as_str()).collect();
+        assert_eq!(names, vec!["panel", "panel_lit", "panel_mask"]);
+        assert_eq!(states[0], panel().states[0]);
+
+        let lit = states[1].images[0].to_rgba8();
+        assert_eq!(*lit.get_pixel(0, 0), Rgba([100, 0, 60, 255]));
+        assert_eq!(*lit.get_pixel(3, 0), PANEL);
+    }
+
+    #[test]
+    fn contrast_pushes_away_from_grey() {
+        let config = RegionAdjust {
+            brightness: 1.0,
+            contrast: 2.0,
+            ..adjuster()
+        };
+        assert_eq!(
+            config.adjust_pixel(Rgba([100, 200, 128, 7])),
+            Rgba([73, 255, 129, 7])
+        );
+    }
+
+    #[test]
+    fn mismatched_mask_is_rejected() {
+        let mut icon = panel();
+        icon.states[1].images = vec![icon.states[1].images[0].clone(); 3];
+        assert!(adjuster()
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use dmi::icon::IconState;
+use image::{imageops, DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::icon_ops::{content_bounds, Bounds};
+
+/// A region of a state's content, with every value a fraction of the size of
+/// the content. `x = 0.0, y = 0.0, width = 1.0, height = 0.5` is the top half
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RelativeRegion {
+    #[serde(default)]
+    pub x: f32,
+    #[serde(default)]
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl RelativeRegion {
+    /// Finds the pixels this region covers within `bounds`. Always at least a
+    /// pixel wide and tall, so thin content doesn't vanish entirely
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn within(&self, bounds: Bounds) -> Bounds {
+        let scale = |fraction: f32, size: u32| (fraction * size as f32).round() as u32;
+        let left = scale(self.x, bounds.width);
+        let top = scale(self.y, bounds.height);
+        let right = scale(self.x + self.width, bounds.width).max(left + 1);
+        let bottom = scale(self.y + self.height, bounds.height).max(top + 1);
+        Bounds {
+            x: bounds.x + left,
+            y: bounds.y + top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+/// Crops every icon state to a region of its own content, rather than a fixed
+/// rectangle. Useful when states are different sizes but the same part of
+/// each is wanted.
+///
+/// The content bounds of a state cover all of its frames and dirs, so every
+/// frame is cropped the same way. Crops sit in the top left of the output, and
+/// the icon shrinks to fit the largest one. States without any content come
+/// out empty
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RelativeCrop {
+    pub region: RelativeRegion,
+}
+
+impl IconOperationConfig for RelativeCrop {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let crops: Vec<Option<Bounds>> = icon
+            .states
+            .iter()
+            .map(|state| {
+                state
+                    .images
+                    .iter()
+                    .filter_map(content_bounds)
+                    .reduce(Bounds::union)
+                    .map(|bounds| self.region.within(bounds))
+            })
+            .collect();
+        let width = crops.iter().flatten().map(|crop| crop.width).max();
+        let height = crops.iter().flatten().map(|crop| crop.height).max();
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(ProcessorError::ConfigError(
+                "Nothing to crop, every icon state is fully transparent".to_string(),
+            ));
+        };
+
+        let mut icon = icon.clone();
+        icon.width = width;
+        icon.height = height;
+        for (state, crop) in icon.states.iter_mut().zip(crops) {
+            *state = crop_state(state, crop, width, height);
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let RelativeRegion {
+            x,
+            y,
+            width,
+            height,
+        } = self.region;
+        let in_range = |value: f32| (0.0..=1.0).contains(&value);
+        if ![x, y, width, height].into_iter().all(in_range) {
+            return Err(ProcessorError::ConfigError(
+                "Every part of region must be a fraction between 0.0 and 1.0".to_string(),
+            ));
+        }
+        if width <= 0.0 || height <= 0.0 {
+            return Err(ProcessorError::ConfigError(
+                "region must have a width and height above 0.0".to_string(),
+            ));
+        }
+        if x + width > 1.0 || y + height > 1.0 {
+            return Err(ProcessorError::ConfigError(
+                "region can't extend past the edge of the content".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+/// Crops every frame of `state` to `crop`, placed in the top left of a
+/// `width` by `height` frame
+fn crop_state(state: &IconState, crop: Option<Bounds>, width: u32, height: u32) -> IconState {
+    let images = state
+        .images
+        .iter()
+        .map(|image| {
+            let mut frame = RgbaImage::new(width, height);
+            if let Some(crop) = crop {
+                let cropped = image.view(crop.x, crop.y, crop.width, crop.height);
+                imageops::replace(&mut frame, &cropped.to_image(), 0, 0);
+            }
+            DynamicImage::ImageRgba8(frame)
+        })
+        .collect();
+    IconState {
+        images,
+        ..state.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::Rgba;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    const TOP: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BOTTOM: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// An 8x8 frame with a `size` square sprite in the middle, red on top and
+    /// blue on the bottom
+    fn centered_sprite(name: &str, size: u32) -> IconState {
+        let start = (8 - size) / 2;
+        let image = RgbaImage::from_fn(8, 8, |x, y| {
+            let inside = start..start + size;
+            if !inside.contains(&x) || !inside.contains(&y) {
+                Rgba([0, 0, 0, 0])
+            } else if y < start + size / 2 {
+                TOP
+            } else {
+                BOTTOM
+            }
+        });
+        IconState {
+            name: name.to_string(),
+            images: vec![DynamicImage::ImageRgba8(image)],
+            ..Default::default()
+        }
+    }
+
+    fn crop(region: RelativeRegion, states: Vec<IconState>) -> Icon {
+        let icon = Icon {
+            width: 8,
+            height: 8,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) = RelativeCrop { region }
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    #[test]
+    fn top_half_of_differently_sized_sprites() {
+        let top_half = RelativeRegion {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 0.5,
+        };
+        let output = crop(
+            top_half,
+            vec![centered_sprite("big", 6), centered_sprite("small", 2)],
+        );
+
+        // Sized to fit the biggest crop, the top half of the 6x6 sprite
+        assert_eq!((output.width, output.height), (6, 3));
+        let big = &output.states[0].images[0];
+        assert!(big.pixels().all(|(_, _, pixel)| pixel == TOP));
+
+        // The 2x2 sprite's top half is its single top row
+        let small = &output.states[1].images[0];
+        for (x, y, pixel) in small.pixels() {
+            let expected = if x < 2 && y == 0 {
+                TOP
+            } else {
+                Rgba([0, 0, 0, 0])
+            };
+            assert_eq!(pixel, expected, "{x}, {y}");
+        }
+    }
+
+    #[test]
+    fn bad_regions_are_rejected() {
+        let region = |x, width| {
+            RelativeCrop {
+                region: RelativeRegion {
+                    x,
+                    y: 0.0,
+                    width,
+                    height: 1.0,
+                },
+            }
+        };
+        assert!(region(0.5, 0.5).verify_config().is_ok());
+        assert!(region(0.6, 0.5).verify_config().is_err());
+        assert!(region(0.0, 0.0).verify_config().is_err());
+        assert!(region(-0.1, 0.5).verify_config().is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+fn one() -> u32 {
+    1
+}
+
+/// Renumbers icon states named `prefix` followed by a number, like
+/// `explosion_1` through `explosion_12`, so their numbers run without gaps.
+///
+/// States keep their place in the icon and their relative order by number,
+/// only their names change. So `explosion_2`, `explosion_5` and `explosion_9`
+/// become `explosion_1`, `explosion_2` and `explosion_3`
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Renumber {
+    /// Everything in the name before the number, like `explosion_`
+    pub prefix: String,
+    /// Number given to the lowest numbered state
+    #[serde(default = "one")]
+    pub start: u32,
+    /// How much each number goes up by from the last
+    #[serde(default = "one")]
+    pub step: u32,
+}
+
+impl IconOperationConfig for Renumber {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut numbered: Vec<(u64, usize)> = icon
+            .states
+            .iter()
+            .enumerate()
+            .filter_map(|(index, state)| Some((self.number_of(&state.name)?, index)))
+            .collect();
+        if numbered.is_empty() {
+            return Err(ProcessorError::ConfigError(format!(
+                "No icon states are named \"{}\" followed by a number",
+                self.prefix
+            )));
+        }
+        // Stable, so states sharing a number (like `_1` and `_01`) keep their
+        // order in the icon
+        numbered.sort_by_key(|(number, _)| *number);
+
+        let mut icon = icon.clone();
+        for (position, (_, index)) in numbered.iter().enumerate() {
+            let number = u64::from(self.start) + position as u64 * u64::from(self.step);
+            icon.states[*index].name = format!("{}{number}", self.prefix);
+        }
+        let renamed: Vec<usize> = numbered.iter().map(|(_, index)| *index).collect();
+        for (index, state) in icon.states.iter().enumerate() {
+            if renamed.contains(&index) {
+                continue;
+            }
+            if renamed
+                .iter()
+                .any(|&other| icon.states[other].name == state.name)
+            {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Renumbering would give two icon states the name \"{}\"",
+                    state.name
+                )));
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.step == 0 {
+            return Err(ProcessorError::ConfigError(
+                "step must be at least 1, or every state would get the same number".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Renumber {
+    /// The number after the prefix, if `name` is the prefix and then only
+    /// digits
+    fn number_of(&self, name: &str) -> Option<u64> {
+        let digits = name.strip_prefix(&self.prefix)?;
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn renumber(config: &Renumber, names: &[&str]) -> ProcessorResult<Vec<String>> {
+        let icon = Icon {
+            states: names
+                .iter()
+                .map(|name| {
+                    IconState {
+                        name: (*name).to_string(),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states.into_iter().map(|state| state.name).collect())
+    }
+
+    fn explosions() -> Renumber {
+        Renumber {
+            prefix: "explosion_".to_string(),
+            start: 1,
+            step: 1,
+        }
+    }
+
+    #[test]
+    fn gaps_are_closed() {
+        let names = renumber(
+            &explosions(),
+            &[
+                "explosion_9",
+                "smoke",
+                "explosion_2",
+                "explosion_12",
+                "explosion_5",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            names,
+            vec![
+                "explosion_3",
+                "smoke",
+                "explosion_1",
+                "explosion_4",
+                "explosion_2"
+            ]
+        );
+    }
+
+    #[test]
+    fn start_and_step() {
+        let config = Renumber {
+            start: 0,
+            step: 10,
+            ..explosions()
+        };
+        let names = renumber(&config, &["explosion_3", "explosion_7"]).unwrap();
+        assert_eq!(names, vec!["explosion_0", "explosion_10"]);
+    }
+
+    #[test]
+    fn only_prefix_and_digits_match() {
+        let names = renumber(
+            &explosions(),
+            &[
+                "explosion_4",
+                "explosion_4b",
+                "explosion_",
+                "big_explosion_2",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            names,
+            vec![
+                "explosion_1",
+                "explosion_4b",
+                "explosion_",
+                "big_explosion_2"
+            ]
+        );
+        assert!(renumber(&explosions(), &["smoke"]).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use dmi::icon::IconState;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Moves the directions of the targeted icon states around, for fixing up
+/// dmis that were put together with their directions in the wrong order.
+///
+/// `permutation` has one entry per direction in the state, in the order
+/// they're currently in, giving the index each should be moved to. Dmis store
+/// directions in `util::dirs::DMI_ORDER`: south, north, east, west (then
+/// southeast, southwest, northeast, northwest), so `[1, 0, 2, 3]` swaps north
+/// and south
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ReorderDirs {
+    /// Where each current direction should end up, by index
+    pub permutation: Vec<usize>,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ReorderDirs {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if self.targets.matches(&state.name) {
+                    self.reorder_state(state)
+                } else {
+                    Ok(state)
+                }
+            })
+            .collect::<ProcessorResult<_>>()?;
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let mut sorted = self.permutation.clone();
+        sorted.sort_unstable();
+        if sorted.is_empty() || sorted.iter().enumerate().any(|(index, &to)| index != to) {
+            return Err(ProcessorError::ConfigError(format!(
+                "permutation must contain each index from 0 to one less than its length exactly \
+                 once, got {:?}",
+                self.permutation
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+impl ReorderDirs {
+    pub(crate) fn reorder_state(&self, mut state: IconState) -> ProcessorResult<IconState> {
+        let dirs = usize::from(state.dirs.max(1));
+        if dirs != self.permutation.len() {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" has {dirs} directions, but the permutation is for {}",
+                state.name,
+                self.permutation.len()
+            )));
+        }
+        // Images are stored frame by frame, with every dir of a frame together
+        for frame in state.images.chunks_mut(dirs) {
+            let current = frame.to_vec();
+            for (image, &to) in current.into_iter().zip(&self.permutation) {
+                frame[to] = image;
+            }
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A 1x1 image with its red channel set to `marker`, to tell them apart
+    fn marked(marker: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([marker, 0, 0, 255])))
+    }
+
+    fn markers(state: &IconState) -> Vec<u8> {
+        state
+            .images
+            .iter()
+            .map(|image| image.get_pixel(0, 0).0[0])
+            .collect()
+    }
+
+    fn run(config: &ReorderDirs, state: IconState) -> ProcessorResult<IconState> {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![state],
+            ..Default::default()
+        };
+        config.verify_config()?;
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(mut output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states.remove(0))
+    }
+
+    fn four_dirs() -> IconState {
+        IconState {
+            name: "mob".to_string(),
+            dirs: 4,
+            frames: 2,
+            // Frame 1 is 10-13, frame 2 is 20-23, each as south, north, east, west
+            images: [10, 11, 12, 13, 20, 21, 22, 23]
+                .into_iter()
+                .map(marked)
+                .collect(),
+            delay: Some(vec![1.0, 1.0]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn swaps_north_and_south() {
+        let config = ReorderDirs {
+            permutation: vec![1, 0, 2, 3],
+            targets: StateTargets::default(),
+        };
+        let output = run(&config, four_dirs()).unwrap();
+        assert_eq!(markers(&output), vec![11, 10, 12, 13, 21, 20, 22, 23]);
+        assert_eq!((output.dirs, output.frames), (4, 2));
+    }
+
+    #[test]
+    fn permutation_moves_to_the_given_index() {
+        let config = ReorderDirs {
+            permutation: vec![3, 0, 1, 2],
+            targets: StateTargets::default(),
+        };
+        let output = run(&config, four_dirs()).unwrap();
+        assert_eq!(markers(&output), vec![11, 12, 13, 10, 21, 22, 23, 20]);
+    }
+
+    #[test]
+    fn permutation_must_fit_the_state() {
+        let wrong_length = ReorderDirs {
+            permutation: vec![1, 0],
+            targets: StateTargets::default(),
+        };
+        assert!(run(&wrong_length, four_dirs()).is_err());
+
+        let repeated = ReorderDirs {
+            permutation: vec![0, 0, 2, 3],
+            targets: StateTargets::default(),
+        };
+        assert!(repeated.verify_config().is_err());
+        let out_of_range = ReorderDirs {
+            permutation: vec![0, 1, 2, 4],
+            targets: StateTargets::default(),
+        };
+        assert!(out_of_range.verify_config().is_err());
+    }
+}
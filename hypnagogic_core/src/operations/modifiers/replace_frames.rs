@@ -0,0 +1,394 @@
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use dmi::icon::{IconState, Looping};
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{
+    IconOperationConfig,
+    InputError,
+    InputIcon,
+    OperationMode,
+    ProcessorPayload,
+};
+
+/// Swaps the frames of one icon state for images loaded from pngs, for
+/// dropping in fixed up frames without redrawing the rest of the dmi.
+///
+/// The images go in the order the dmi stores them, frame by frame with every
+/// dir of a frame together. The state keeps its other settings, and its
+/// delays too unless they're given or the frame count changes. Pngs can't
+/// carry delays or flags themselves, so they can come from a [`FrameMeta`]
+/// file kept next to them instead
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ReplaceFrames {
+    /// Icon state to replace the frames of
+    pub state: String,
+    /// Paths of the pngs to load, relative to the folder hypnagogic is run in
+    pub images: Vec<PathBuf>,
+    /// How many dirs the images hold. Defaults to the state's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirs: Option<u8>,
+    /// How many frames the images hold. Defaults to the state's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<u32>,
+    /// Delays to give the frames, in place of the state's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<Vec<f32>>,
+    /// Path of a toml file holding the delays and flags of the frames,
+    /// relative to the folder hypnagogic is run in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<PathBuf>,
+}
+
+/// The animation settings of a set of png frames, that a dmi would have
+/// stored alongside them. Anything left out is kept from the icon state
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrameMeta {
+    /// Delay of each frame, in ticks. How many are given is how many frames
+    /// there are, unless `frames` is set on the operation
+    #[serde(default)]
+    pub delay: Option<Vec<f32>>,
+    /// How many times the animation plays, 0 to loop forever
+    #[serde(default)]
+    pub loops: Option<u32>,
+    #[serde(default)]
+    pub rewind: Option<bool>,
+    #[serde(default)]
+    pub movement: Option<bool>,
+}
+
+impl FrameMeta {
+    /// Reads the meta file at `path`
+    ///
+    /// # Errors
+    /// If the file can't be read, or isn't valid
+    pub fn load(path: &Path) -> ProcessorResult<Self> {
+        let text = fs::read_to_string(path).map_err(|error| {
+            ProcessorError::ConfigError(format!(
+                "Failed to read frame meta \"{}\": {error}",
+                path.display()
+            ))
+        })?;
+        toml::from_str(&text).map_err(|error| {
+            ProcessorError::ConfigError(format!(
+                "Frame meta \"{}\" is invalid: {error}",
+                path.display()
+            ))
+        })
+    }
+}
+
+impl IconOperationConfig for ReplaceFrames {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let index = icon
+            .states
+            .iter()
+            .position(|state| state.name == self.state)
+            .ok_or_else(|| {
+                ProcessorError::ConfigError(format!(
+                    "Icon state \"{}\" was not found in the input",
+                    self.state
+                ))
+            })?;
+        let state = &icon.states[index];
+        let meta = match &self.meta {
+            Some(path) => FrameMeta::load(path)?,
+            None => FrameMeta::default(),
+        };
+        let given_delay = match (&self.delay, meta.delay) {
+            (Some(_), Some(_)) => {
+                return Err(ProcessorError::ConfigError(
+                    "Delays can be given in the config or in the frame meta, not both".to_string(),
+                ));
+            }
+            (Some(delay), None) => Some(delay.clone()),
+            (None, delay) => delay,
+        };
+
+        let dirs = self.dirs.unwrap_or(state.dirs);
+        let frames = self
+            .frames
+            .or_else(|| given_delay.as_ref().map(|delay| delay.len() as u32))
+            .unwrap_or(state.frames);
+        let expected = u32::from(dirs) * frames;
+        if self.images.len() != expected as usize {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" needs {expected} images for {frames} frames of {dirs} dirs, \
+                 but {} are given",
+                self.state,
+                self.images.len()
+            )));
+        }
+        let delay = match given_delay {
+            Some(delay) if delay.len() != frames as usize => {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Icon state \"{}\" has {frames} frames, but {} delays are given",
+                    self.state,
+                    delay.len()
+                )));
+            }
+            Some(delay) => Some(delay),
+            None if frames == state.frames => state.delay.clone(),
+            None if frames <= 1 => None,
+            None => {
+                return Err(ProcessorError::ConfigError(format!(
+                    "Icon state \"{}\" goes from {} frames to {frames}, so it needs new delays",
+                    self.state, state.frames
+                )));
+            }
+        };
+
+        let images = self
+            .images
+            .iter()
+            .map(|path| {
+                let image = image::open(path).map_err(|error| {
+                    ProcessorError::FrameLoadFailed {
+                        state: self.state.clone(),
+                        path: path.clone(),
+                        error: InputError::from(error),
+                    }
+                })?;
+                let (width, height) = image.dimensions();
+                if (width, height) != (icon.width, icon.height) {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "\"{}\" is {width}x{height}, but the icon is {}x{}",
+                        path.display(),
+                        icon.width,
+                        icon.height
+                    )));
+                }
+                Ok(image)
+            })
+            .collect::<ProcessorResult<Vec<_>>>()?;
+
+        let mut icon = icon.clone();
+        icon.states[index] = IconState {
+            dirs,
+            frames,
+            images,
+            delay,
+            loop_flag: meta.loops.map_or(state.loop_flag, |loops| {
+                NonZeroU32::new(loops).map_or(Looping::Indefinitely, Looping::NTimes)
+            }),
+            rewind: meta.rewind.unwrap_or(state.rewind),
+            movement: meta.movement.unwrap_or(state.movement),
+            ..state.clone()
+        };
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        state_name == self.state
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.images.is_empty() {
+            return Err(ProcessorError::ConfigError(
+                "images needs at least one png to load".to_string(),
+            ));
+        }
+        if let Some(dirs) = self.dirs {
+            if ![1, 4, 8].contains(&dirs) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "dirs must be 1, 4 or 8, not {dirs}"
+                )));
+            }
+        }
+        if self.frames == Some(0) {
+            return Err(ProcessorError::ConfigError(
+                "frames must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use dmi::icon::Icon;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn solid(color: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba(color)))
+    }
+
+    fn blinking_light() -> Icon {
+        Icon {
+            width: 2,
+            height: 2,
+            states: vec![IconState {
+                name: "light".to_string(),
+                frames: 2,
+                images: vec![solid([0, 0, 0, 255]), solid([0, 0, 0, 255])],
+                delay: Some(vec![1.0, 3.0]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn replacer(images: Vec<PathBuf>) -> ReplaceFrames {
+        ReplaceFrames {
+            state: "light".to_string(),
+            images,
+            dirs: None,
+            frames: None,
+            delay: None,
+            meta: None,
+        }
+    }
+
+    fn save(dir: &Path, name: &str, image: &DynamicImage) -> PathBuf {
+        let path = dir.join(name);
+        image.save(&path).unwrap();
+        path
+    }
+
+    fn replace(config: &ReplaceFrames, icon: Icon) -> ProcessorResult<IconState> {
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states.into_iter().next().unwrap())
+    }
+
+    #[test]
+    fn frames_come_from_the_pngs() {
+        let dir = tempfile::tempdir().unwrap();
+        let on = solid([255, 220, 0, 255]);
+        let off = solid([40, 30, 0, 255]);
+        let images = vec![
+            save(dir.path(), "on.png", &on),
+            save(dir.path(), "off.png", &off),
+        ];
+
+        let state = replace(&replacer(images), blinking_light()).unwrap();
+        assert_eq!(state.images, vec![on, off]);
+        assert_eq!((state.dirs, state.frames), (1, 2));
+        assert_eq!(state.delay, Some(vec![1.0, 3.0]));
+    }
+
+    #[test]
+    fn a_new_layout_needs_matching_images_and_delays() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = save(dir.path(), "frame.png", &solid([255, 0, 0, 255]));
+
+        let too_few = replace(&replacer(vec![image.clone()]), blinking_light());
+        assert!(matches!(too_few, Err(ProcessorError::ConfigError(_))));
+
+        let longer = ReplaceFrames {
+            frames: Some(3),
+            ..replacer(vec![image.clone(); 3])
+        };
+        assert!(matches!(
+            replace(&longer, blinking_light()),
+            Err(ProcessorError::ConfigError(_))
+        ));
+        let longer = ReplaceFrames {
+            delay: Some(vec![1.0, 1.0, 2.0]),
+            ..longer
+        };
+        let state = replace(&longer, blinking_light()).unwrap();
+        assert_eq!(state.frames, 3);
+        assert_eq!(state.delay, Some(vec![1.0, 1.0, 2.0]));
+    }
+
+    #[test]
+    fn bad_pngs_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let big = save(
+            dir.path(),
+            "big.png",
+            &DynamicImage::ImageRgba8(RgbaImage::new(4, 4)),
+        );
+        let missing = dir.path().join("missing.png");
+
+        let result = replace(&replacer(vec![big.clone(), big]), blinking_light());
+        let Err(ProcessorError::ConfigError(message)) = result else {
+            panic!("Expected a config error");
+        };
+        assert!(message.contains("is 4x4"), "{message}");
+
+        let result = replace(&replacer(vec![missing.clone(), missing]), blinking_light());
+        assert!(matches!(
+            result,
+            Err(ProcessorError::FrameLoadFailed {
+                error: InputError::DynamicRead(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn delays_and_flags_come_from_the_meta() {
+        let dir = tempfile::tempdir().unwrap();
+        let images: Vec<PathBuf> = (0..3)
+            .map(|frame| {
+                save(
+                    dir.path(),
+                    &format!("{frame}.png"),
+                    &solid([frame * 80, 0, 0, 255]),
+                )
+            })
+            .collect();
+        let meta = dir.path().join("light.meta.toml");
+        fs::write(&meta, "delay = [1, 2, 4]\nloops = 2\nrewind = true\n").unwrap();
+
+        let config = ReplaceFrames {
+            meta: Some(meta.clone()),
+            ..replacer(images.clone())
+        };
+        let state = replace(&config, blinking_light()).unwrap();
+        assert_eq!(state.frames, 3);
+        assert_eq!(state.delay, Some(vec![1.0, 2.0, 4.0]));
+        assert_eq!(state.loop_flag, Looping::new(2));
+        assert!(state.rewind);
+        assert!(!state.movement);
+
+        // The delays say three frames, so two pngs aren't enough
+        let result = replace(
+            &ReplaceFrames {
+                meta: Some(meta.clone()),
+                ..replacer(images[..2].to_vec())
+            },
+            blinking_light(),
+        );
+        let Err(ProcessorError::ConfigError(message)) = result else {
+            panic!("Expected a config error");
+        };
+        assert!(
+            message.contains("needs 3 images for 3 frames of 1 dirs, but 2 are given"),
+            "{message}"
+        );
+
+        let both = ReplaceFrames {
+            delay: Some(vec![1.0, 1.0, 1.0]),
+            ..config
+        };
+        assert!(matches!(
+            replace(&both, blinking_light()),
+            Err(ProcessorError::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use dmi::icon::IconState;
+use image::imageops::{self, FilterType};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// How new pixels are worked out when scaling
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleFilter {
+    /// Copies the closest pixel, keeping hard pixel art edges
+    #[default]
+    Nearest,
+    /// Linear blend of the surrounding pixels
+    Triangle,
+    /// Cubic blend of the surrounding pixels, sharper than triangle
+    CatmullRom,
+    /// Smooth and a little blurry
+    Gaussian,
+    /// Sharpest of the smooth filters
+    Lanczos3,
+}
+
+impl From<ScaleFilter> for FilterType {
+    fn from(filter: ScaleFilter) -> Self {
+        match filter {
+            ScaleFilter::Nearest => FilterType::Nearest,
+            ScaleFilter::Triangle => FilterType::Triangle,
+            ScaleFilter::CatmullRom => FilterType::CatmullRom,
+            ScaleFilter::Gaussian => FilterType::Gaussian,
+            ScaleFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Scales every icon state by separate horizontal and vertical factors, for
+/// stretching things like UI bars. The icon is resized to match, with sizes
+/// rounded to the nearest pixel
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ScaleXY {
+    pub x_factor: f32,
+    pub y_factor: f32,
+    #[serde(default)]
+    pub filter: ScaleFilter,
+}
+
+impl IconOperationConfig for ScaleXY {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let (width, height) = self.scaled_size(icon.width, icon.height);
+        if width == 0 || height == 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Scaling a {}x{} icon by {}x{} leaves nothing behind",
+                icon.width, icon.height, self.x_factor, self.y_factor
+            )));
+        }
+
+        let mut icon = icon.clone();
+        icon.width = width;
+        icon.height = height;
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            DynamicImage::ImageRgba8(imageops::resize(
+                                frame,
+                                width,
+                                height,
+                                self.filter.into(),
+                            ))
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        for (name, factor) in [("x_factor", self.x_factor), ("y_factor", self.y_factor)] {
+            if !factor.is_finite() || factor <= 0.0 {
+                return Err(ProcessorError::ConfigError(format!(
+                    "{name} must be greater than 0, got {factor}"
+                )));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+impl ScaleXY {
+    fn scaled_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = |size: u32, factor: f32| (size as f32 * factor).round() as u32;
+        (scale(width, self.x_factor), scale(height, self.y_factor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn double_width_keeps_height() {
+        // Left column red, right column blue
+        let frame = RgbaImage::from_fn(2, 2, |x, _| {
+            if x == 0 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states: vec![IconState {
+                name: "bar".to_string(),
+                images: vec![DynamicImage::ImageRgba8(frame)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = ScaleXY {
+            x_factor: 2.0,
+            y_factor: 1.0,
+            filter: ScaleFilter::Nearest,
+        };
+
+        let payload = config
+            .perform_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap();
+        let ProcessorPayload::Single(output) = payload else {
+            panic!("expected a single output");
+        };
+        let OutputImage::Dmi(icon) = *output else {
+            panic!("expected a dmi");
+        };
+        assert_eq!((icon.width, icon.height), (4, 2));
+        let frame = &icon.states[0].images[0];
+        assert_eq!(frame.dimensions(), (4, 2));
+        for y in 0..2 {
+            assert_eq!(frame.get_pixel(1, y), Rgba([255, 0, 0, 255]));
+            assert_eq!(frame.get_pixel(2, y), Rgba([0, 0, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn zero_factors_are_rejected() {
+        let config = ScaleXY {
+            x_factor: 0.0,
+            y_factor: 1.0,
+            filter: ScaleFilter::Nearest,
+        };
+        assert!(config.verify_config().is_err());
+        let config = ScaleXY {
+            x_factor: 1.0,
+            y_factor: -2.0,
+            filter: ScaleFilter::Nearest,
+        };
+        assert!(config.verify_config().is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::split_emissive::split_state;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::alpha;
+
+fn opaque() -> u8 {
+    255
+}
+
+fn core_suffix() -> String {
+    "_core".to_string()
+}
+
+fn edge_suffix() -> String {
+    "_edge".to_string()
+}
+
+/// Splits each targeted icon state in two by its own alpha, for rendering the
+/// solid part of a sprite separately from its soft edges: a core state
+/// holding the pixels with at least `threshold` alpha, and an edge state
+/// holding the rest. Each pixel ends up in exactly one of them, and is
+/// transparent in the other.
+///
+/// The two states replace the source, core first, named after it with
+/// `core_suffix` and `edge_suffix` on the end
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SelfMask {
+    /// Least alpha a pixel needs to be part of the core
+    #[serde(default = "opaque")]
+    pub threshold: u8,
+    #[serde(default = "core_suffix")]
+    pub core_suffix: String,
+    #[serde(default = "edge_suffix")]
+    pub edge_suffix: String,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for SelfMask {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut states = Vec::with_capacity(icon.states.len());
+        for state in &icon.states {
+            if !self.targets.matches(&state.name) {
+                states.push(state.clone());
+                continue;
+            }
+            let (edge, core) = split_state(
+                state,
+                (&self.edge_suffix, &self.core_suffix),
+                |_, _, _, pixel| alpha(pixel) >= self.threshold,
+            );
+            for split in [&core, &edge] {
+                if icon
+                    .states
+                    .iter()
+                    .any(|existing| existing.name == split.name)
+                {
+                    return Err(ProcessorError::ConfigError(format!(
+                        "Can't split \"{}\" in to \"{}\", an icon state with that name already \
+                         exists",
+                        state.name, split.name
+                    )));
+                }
+            }
+            states.push(core);
+            states.push(edge);
+        }
+
+        let mut icon = icon.clone();
+        icon.states = states;
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.core_suffix == self.edge_suffix {
+            return Err(ProcessorError::ConfigError(
+                "core_suffix and edge_suffix have to be different".to_string(),
+            ));
+        }
+        if self.threshold == 0 {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "threshold is 0, so every pixel is part of the core and the edge states are \
+                 always empty"
+                    .to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A 4x1 sprite fading out from opaque on the left, plus an untargeted
+    /// state
+    fn sprite() -> Icon {
+        let fade = RgbaImage::from_fn(4, 1, |x, _| {
+            Rgba([200, 100, 50, [255, 200, 100, 0][x as usize]])
+        });
+        let state = |name: &str, image: RgbaImage| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }
+        };
+        Icon {
+            width: 4,
+            height: 1,
+            states: vec![
+                state("glow", fade),
+                state("other", RgbaImage::from_pixel(4, 1, Rgba([1, 2, 3, 50]))),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn self_mask(threshold: u8) -> SelfMask {
+        SelfMask {
+            threshold,
+            core_suffix: core_suffix(),
+            edge_suffix: edge_suffix(),
+            targets: StateTargets {
+                target_states: vec!["glow".to_string()],
+                ..Default::default()
+            },
+        }
+    }
+
+    fn apply(config: &SelfMask, icon: Icon) -> ProcessorResult<Vec<IconState>> {
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output.states)
+    }
+
+    #[test]
+    fn gradient_splits_at_the_threshold() {
+        let source = sprite();
+        let states = apply(&self_mask(200), sprite()).unwrap();
+        let names: Vec<&str> = states.iter().map(|state| state.name.as_str()).collect();
+        assert_eq!(names, vec!["glow_core", "glow_edge", "other"]);
+
+        let alphas = |state: &IconState| -> Vec<u8> {
+            state.images[0]
+                .to_rgba8()
+                .pixels()
+                .map(|&pixel| alpha(pixel))
+                .collect()
+        };
+        assert_eq!(alphas(&states[0]), vec![255, 200, 0, 0]);
+        assert_eq!(alphas(&states[1]), vec![0, 0, 100, 0]);
+        // Pixels keep their color in whichever half they land in
+        let source_image = source.states[0].images[0].to_rgba8();
+        let edge_image = states[1].images[0].to_rgba8();
+        assert_eq!(edge_image.get_pixel(2, 0), source_image.get_pixel(2, 0));
+        assert_eq!(states[2], source.states[1]);
+    }
+
+    #[test]
+    fn splits_cant_replace_existing_states() {
+        let mut icon = sprite();
+        icon.states[1].name = "glow_edge".to_string();
+        assert!(apply(&self_mask(128), icon).is_err());
+    }
+
+    #[test]
+    fn zero_threshold_is_suspicious() {
+        assert!(self_mask(128).verify_config().unwrap().is_empty());
+        assert_eq!(self_mask(0).verify_config().unwrap().len(), 1);
+        let same_suffixes = SelfMask {
+            edge_suffix: core_suffix(),
+            ..self_mask(128)
+        };
+        assert!(same_suffixes.verify_config().is_err());
+    }
+}
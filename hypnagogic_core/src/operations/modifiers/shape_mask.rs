@@ -0,0 +1,274 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{with_alpha, Color};
+
+/// A shape to mask with, in pixels from the top left of the icon
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Shape {
+    Rectangle {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    /// Every pixel whose distance from the center pixel is at most `radius`
+    Circle {
+        center_x: u32,
+        center_y: u32,
+        radius: u32,
+    },
+    /// A rectangle with its corners rounded off by circles of `radius`
+    RoundedRect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        radius: u32,
+    },
+}
+
+impl Shape {
+    /// Whether the pixel at `x`, `y` is inside the shape
+    #[must_use]
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        match *self {
+            Shape::Rectangle {
+                x: left,
+                y: top,
+                width,
+                height,
+            } => (left..left + width).contains(&x) && (top..top + height).contains(&y),
+            Shape::Circle {
+                center_x,
+                center_y,
+                radius,
+            } => {
+                let dx = i64::from(x) - i64::from(center_x);
+                let dy = i64::from(y) - i64::from(center_y);
+                dx * dx + dy * dy <= i64::from(radius).pow(2)
+            }
+            Shape::RoundedRect {
+                x: left,
+                y: top,
+                width,
+                height,
+                radius,
+            } => {
+                let rectangle = Shape::Rectangle {
+                    x: left,
+                    y: top,
+                    width,
+                    height,
+                };
+                if !rectangle.contains(x, y) {
+                    return false;
+                }
+                // Measured between pixel centers, so the corners come out
+                // the same on every side. Only pixels out in the corners are
+                // far enough from the inner rectangle to be cut
+                let radius = f64::from(radius);
+                let nearest_x = (f64::from(x) + 0.5)
+                    .clamp(f64::from(left) + radius, f64::from(left + width) - radius);
+                let nearest_y = (f64::from(y) + 0.5)
+                    .clamp(f64::from(top) + radius, f64::from(top + height) - radius);
+                let (dx, dy) = (
+                    f64::from(x) + 0.5 - nearest_x,
+                    f64::from(y) + 0.5 - nearest_y,
+                );
+                dx * dx + dy * dy <= radius * radius
+            }
+        }
+    }
+}
+
+/// Cuts every frame of the targeted icon states down to a shape, clearing
+/// every pixel outside it. Saves drawing a mask state for simple cutouts
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShapeMask {
+    pub shape: Shape,
+    /// Clear the pixels inside the shape instead, punching a hole in it
+    #[serde(default)]
+    pub invert: bool,
+    /// The color cleared pixels are left as. They're always fully
+    /// transparent, so only its red, green and blue matter, but some tools
+    /// treat transparent black badly. Defaults to transparent black
+    #[serde(default)]
+    pub transparent_fill: Color,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ShapeMask {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let fill = with_alpha(self.transparent_fill.into(), 0);
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for (x, y, pixel) in frame.enumerate_pixels_mut() {
+                                if self.shape.contains(x, y) == self.invert {
+                                    *pixel = fill;
+                                }
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if let Shape::RoundedRect {
+            width,
+            height,
+            radius,
+            ..
+        } = self.shape
+        {
+            if radius * 2 > width.min(height) {
+                return Err(ProcessorError::ConfigError(format!(
+                    "A rounded_rect radius of {radius} is too big for a {width}x{height} rectangle"
+                )));
+            }
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+    use crate::util::color::TRANSPARENT;
+
+    #[test]
+    fn circle_keeps_inside_and_clears_outside() {
+        let red = Rgba([255, 0, 0, 255]);
+        let icon = Icon {
+            width: 9,
+            height: 9,
+            states: vec![IconState {
+                name: "porthole".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(9, 9, red))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = ShapeMask {
+            shape: Shape::Circle {
+                center_x: 4,
+                center_y: 4,
+                radius: 3,
+            },
+            invert: false,
+            transparent_fill: Color::default(),
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let image = &output.states[0].images[0];
+        assert_eq!(image.get_pixel(4, 4), red);
+        assert_eq!(image.get_pixel(7, 4), red);
+        assert_eq!(image.get_pixel(7, 6), TRANSPARENT);
+        assert_eq!(image.get_pixel(0, 0), TRANSPARENT);
+        assert_eq!(image.get_pixel(8, 4), TRANSPARENT);
+    }
+
+    #[test]
+    fn rounded_rect_trims_corners() {
+        let shape = Shape::RoundedRect {
+            x: 1,
+            y: 1,
+            width: 6,
+            height: 4,
+            radius: 2,
+        };
+        assert!(!shape.contains(1, 1));
+        assert!(shape.contains(3, 1));
+        assert!(shape.contains(1, 3));
+        assert!(shape.contains(4, 2));
+        assert!(!shape.contains(6, 4));
+        assert!(!shape.contains(0, 2));
+    }
+
+    #[test]
+    fn cleared_pixels_take_the_fill_color() {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "screen".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([0, 0, 255, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config: ShapeMask = toml::from_str(
+            r##"
+            transparent_fill = "#FFFFFF"
+            shape = { kind = "rectangle", x = 0, y = 0, width = 2, height = 4 }
+            "##,
+        )
+        .unwrap();
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let image = &output.states[0].images[0];
+        assert_eq!(image.get_pixel(1, 2), Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(2, 2), Rgba([255, 255, 255, 0]));
+        assert_eq!(image.get_pixel(3, 0), Rgba([255, 255, 255, 0]));
+    }
+}
@@ -0,0 +1,197 @@
+use dmi::icon::IconState;
+use image::{DynamicImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::ProcessorResult;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, is_transparent, with_alpha, Color, TRANSPARENT};
+
+fn magenta() -> Color {
+    Color::new_rgb(255, 0, 255)
+}
+
+/// Adds a flat color silhouette of each targeted icon state, named
+/// `{name}_debug` and placed right after it, for checking collision shapes
+/// and spotting stray pixels. Every pixel that isn't fully transparent
+/// becomes the color at full alpha, however faint it was.
+///
+/// With `replace` and `keep_alpha`, each state is swapped for a silhouette
+/// that keeps its soft edges instead, for making shadow sprites
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Silhouette {
+    /// What to draw the silhouettes in
+    #[serde(default = "magenta")]
+    pub color: Color,
+    /// Replace each targeted state with its silhouette, keeping its name,
+    /// instead of adding a `_debug` state after it
+    #[serde(default)]
+    pub replace: bool,
+    /// Give each pixel of a silhouette the alpha it had, instead of drawing
+    /// every one fully opaque
+    #[serde(default)]
+    pub keep_alpha: bool,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Silhouette {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let color = Rgba::from(Color {
+            alpha: 255,
+            ..self.color
+        });
+
+        let mut output = icon.clone();
+        output.states = vec![];
+        for state in &icon.states {
+            if !self.targets.matches(&state.name) {
+                output.states.push(state.clone());
+                continue;
+            }
+            let name = if self.replace {
+                state.name.clone()
+            } else {
+                output.states.push(state.clone());
+                format!("{}_debug", state.name)
+            };
+            output.states.push(IconState {
+                name,
+                images: state
+                    .images
+                    .iter()
+                    .map(|frame| {
+                        let mut frame = frame.to_rgba8();
+                        for pixel in frame.pixels_mut() {
+                            *pixel = if is_transparent(*pixel) {
+                                TRANSPARENT
+                            } else if self.keep_alpha {
+                                with_alpha(color, alpha(*pixel))
+                            } else {
+                                color
+                            };
+                        }
+                        DynamicImage::ImageRgba8(frame)
+                    })
+                    .collect(),
+                ..state.clone()
+            });
+        }
+
+        Ok(ProcessorPayload::from_icon(output))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn faint_edges_are_fully_colored() {
+        let mut image = RgbaImage::new(3, 1);
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        // A soft, barely visible edge
+        image.put_pixel(1, 0, Rgba([10, 20, 30, 3]));
+        let icon = Icon {
+            width: 3,
+            height: 1,
+            states: vec![IconState {
+                name: "crate".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image.clone())],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = Silhouette {
+            color: magenta(),
+            replace: false,
+            keep_alpha: false,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output.states.len(), 2);
+        assert_eq!(output.states[0].images[0].to_rgba8(), image);
+
+        let debug = &output.states[1];
+        assert_eq!(debug.name, "crate_debug");
+        let magenta = Rgba([255, 0, 255, 255]);
+        assert_eq!(debug.images[0].get_pixel(0, 0), magenta);
+        assert_eq!(debug.images[0].get_pixel(1, 0), magenta);
+        assert!(is_transparent(debug.images[0].get_pixel(2, 0)));
+    }
+
+    #[test]
+    fn replaced_shadows_keep_alpha() {
+        let mut image = RgbaImage::new(3, 1);
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        image.put_pixel(1, 0, Rgba([200, 100, 50, 90]));
+        let state = |name: &str, frames: u32| {
+            IconState {
+                name: name.to_string(),
+                dirs: 4,
+                frames,
+                images: vec![DynamicImage::ImageRgba8(image.clone()); 4 * frames as usize],
+                delay: (frames > 1).then(|| vec![2.0; frames as usize]),
+                ..Default::default()
+            }
+        };
+        let icon = Icon {
+            width: 3,
+            height: 1,
+            states: vec![state("crate", 1), state("crate_open", 2)],
+            ..Default::default()
+        };
+        let config = Silhouette {
+            color: Color::new_rgb(0, 0, 0),
+            replace: true,
+            keep_alpha: true,
+            targets: StateTargets::default(),
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon.clone()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output.states.len(), 2);
+        for (shadow, original) in output.states.iter().zip(&icon.states) {
+            assert_eq!(shadow.name, original.name);
+            assert_eq!(
+                (shadow.dirs, shadow.frames, &shadow.delay),
+                (original.dirs, original.frames, &original.delay)
+            );
+            for frame in &shadow.images {
+                assert_eq!(frame.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+                assert_eq!(frame.get_pixel(1, 0), Rgba([0, 0, 0, 90]));
+                assert_eq!(frame.get_pixel(2, 0), TRANSPARENT);
+            }
+        }
+    }
+}
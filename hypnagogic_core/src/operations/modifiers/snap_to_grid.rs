@@ -0,0 +1,173 @@
+use dmi::icon::IconState;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::icon_ops::{content_bounds, translate_image, Bounds};
+
+/// Which point of a state's content gets lined up with the grid
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapAnchor {
+    #[default]
+    TopLeft,
+    Center,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct GridOrigin {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Moves the content of the targeted icon states so it lines up with a pixel
+/// grid. Fixes sprites that were imported a pixel or two off.
+///
+/// Every frame and dir of a state is moved by the same amount, measured from
+/// the bounds of the content across all of them, so animations don't jitter
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SnapToGrid {
+    /// Spacing of the grid, in pixels
+    pub grid_size: u32,
+    /// Where the grid starts. Defaults to the top left corner of the icon
+    #[serde(default)]
+    pub origin: GridOrigin,
+    #[serde(default)]
+    pub anchor: SnapAnchor,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for SnapToGrid {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        for state in &mut icon.states {
+            if self.targets.matches(&state.name) {
+                *state = self.snap_state(state);
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.grid_size == 0 {
+            return Err(ProcessorError::ConfigError(
+                "grid_size must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl SnapToGrid {
+    fn snap_state(&self, state: &IconState) -> IconState {
+        let Some(bounds) = state
+            .images
+            .iter()
+            .filter_map(content_bounds)
+            .reduce(Bounds::union)
+        else {
+            // Nothing to line up
+            return state.clone();
+        };
+
+        let (anchor_x, anchor_y) = match self.anchor {
+            SnapAnchor::TopLeft => (bounds.x, bounds.y),
+            SnapAnchor::Center => (bounds.x + bounds.width / 2, bounds.y + bounds.height / 2),
+        };
+        let offset_x = self.snap(anchor_x, self.origin.x) - i64::from(anchor_x);
+        let offset_y = self.snap(anchor_y, self.origin.y) - i64::from(anchor_y);
+        if (offset_x, offset_y) == (0, 0) {
+            return state.clone();
+        }
+
+        IconState {
+            images: state
+                .images
+                .iter()
+                .map(|image| translate_image(image, offset_x, offset_y))
+                .collect(),
+            ..state.clone()
+        }
+    }
+
+    /// Finds the grid line nearest to `position`
+    fn snap(&self, position: u32, origin: u32) -> i64 {
+        let grid_size = i64::from(self.grid_size);
+        let relative = i64::from(position) - i64::from(origin);
+        let cells = (relative as f64 / grid_size as f64).round() as i64;
+        i64::from(origin) + cells * grid_size
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+
+    /// An 8x8 frame with a 2x2 block of content with its top left at `x`, `y`
+    fn block_at(x: u32, y: u32) -> IconState {
+        let mut image = RgbaImage::new(8, 8);
+        for (block_x, block_y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            image.put_pixel(x + block_x, y + block_y, Rgba([255, 0, 0, 255]));
+        }
+        IconState {
+            name: "sprite".to_string(),
+            images: vec![DynamicImage::ImageRgba8(image)],
+            ..Default::default()
+        }
+    }
+
+    fn snapper(anchor: SnapAnchor) -> SnapToGrid {
+        SnapToGrid {
+            grid_size: 4,
+            origin: GridOrigin::default(),
+            anchor,
+            targets: StateTargets::default(),
+        }
+    }
+
+    #[test]
+    fn offset_sprite_snaps_to_origin() {
+        let snapped = snapper(SnapAnchor::TopLeft).snap_state(&block_at(1, 1));
+        assert_eq!(snapped.images, block_at(0, 0).images);
+        assert_eq!(snapped.images[0].dimensions(), (8, 8));
+    }
+
+    #[test]
+    fn aligned_sprite_is_unchanged() {
+        let snapped = snapper(SnapAnchor::TopLeft).snap_state(&block_at(4, 4));
+        assert_eq!(snapped.images, block_at(4, 4).images);
+    }
+
+    #[test]
+    fn center_anchor_snaps_middle_of_content() {
+        // Block center is at (3, 4), so only x needs to move
+        let snapped = snapper(SnapAnchor::Center).snap_state(&block_at(2, 3));
+        assert_eq!(snapped.images, block_at(3, 3).images);
+    }
+
+    #[test]
+    fn custom_origin_offsets_grid() {
+        let config = SnapToGrid {
+            origin: GridOrigin { x: 1, y: 1 },
+            ..snapper(SnapAnchor::TopLeft)
+        };
+        let snapped = config.snap_state(&block_at(4, 6));
+        assert_eq!(snapped.images, block_at(5, 5).images);
+    }
+}
@@ -0,0 +1,349 @@
+use dmi::icon::{Icon, IconState};
+use image::{DynamicImage, GenericImageView, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{
+    IconOperationConfig,
+    InputIcon,
+    NamedIcon,
+    OperationMode,
+    OutputImage,
+    ProcessorPayload,
+};
+use crate::util::color::{alpha, is_transparent, rgb, TRANSPARENT};
+
+fn half() -> u8 {
+    128
+}
+
+fn diffuse_suffix() -> String {
+    "_diffuse".to_string()
+}
+
+fn emissive_suffix() -> String {
+    "_emissive".to_string()
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    /// This channel of `pixel`
+    #[must_use]
+    pub fn of(self, pixel: Rgba<u8>) -> u8 {
+        let [red, green, blue] = rgb(pixel);
+        match self {
+            Channel::Red => red,
+            Channel::Green => green,
+            Channel::Blue => blue,
+            Channel::Alpha => alpha(pixel),
+        }
+    }
+}
+
+/// How `SplitEmissive` decides which pixels glow
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum EmissiveSelector {
+    /// Pixels with at least `threshold` in one of their own channels
+    Channel {
+        channel: Channel,
+        #[serde(default = "half")]
+        threshold: u8,
+    },
+    /// Pixels where another icon state isn't transparent. A mask with a
+    /// single frame and dir is used for every frame and dir of the source
+    Mask { state: String },
+}
+
+/// Splits an icon state in two for engines with a lighting pass: a diffuse
+/// state holding the pixels that are lit normally, and an emissive state
+/// holding the ones that glow. Each pixel ends up in exactly one of them, and
+/// is transparent in the other.
+///
+/// The two states replace the source, named after it with `diffuse_suffix`
+/// and `emissive_suffix` on the end. With `separate_outputs`, they're written
+/// to two dmis of their own instead, with nothing else in them
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SplitEmissive {
+    /// Icon state to split
+    pub state: String,
+    pub emissive: EmissiveSelector,
+    #[serde(default = "diffuse_suffix")]
+    pub diffuse_suffix: String,
+    #[serde(default = "emissive_suffix")]
+    pub emissive_suffix: String,
+    /// Write the diffuse and emissive states to dmis of their own, named
+    /// after the input with `-diffuse` and `-emissive` on the end, and leave
+    /// out the rest of the input
+    #[serde(default)]
+    pub separate_outputs: bool,
+}
+
+impl IconOperationConfig for SplitEmissive {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let find = |name: &str| {
+            icon.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or_else(|| {
+                    ProcessorError::ConfigError(format!(
+                        "Icon state \"{name}\" was not found in the input"
+                    ))
+                })
+        };
+        let index = find(&self.state)?;
+        let source = &icon.states[index];
+        let mask = match &self.emissive {
+            EmissiveSelector::Mask { state } => {
+                let mask = &icon.states[find(state)?];
+                check_mask(mask, source)?;
+                Some(mask)
+            }
+            EmissiveSelector::Channel { .. } => None,
+        };
+
+        let (diffuse, emissive) = split_state(
+            source,
+            (&self.diffuse_suffix, &self.emissive_suffix),
+            |image_index, x, y, pixel| {
+                match (&self.emissive, mask) {
+                    (EmissiveSelector::Channel { channel, threshold }, _) => {
+                        channel.of(pixel) >= *threshold
+                    }
+                    (EmissiveSelector::Mask { .. }, Some(mask)) => {
+                        mask_selects(mask, image_index, x, y)
+                    }
+                    (EmissiveSelector::Mask { .. }, None) => unreachable!("mask was looked up"),
+                }
+            },
+        );
+
+        if self.separate_outputs {
+            let alone = |name_hint: &str, state: IconState| {
+                NamedIcon {
+                    path_hint: None,
+                    name_hint: Some(name_hint.to_string()),
+                    path: None,
+                    image: OutputImage::Dmi(Icon {
+                        states: vec![state],
+                        ..icon.clone()
+                    }),
+                }
+            };
+            return Ok(ProcessorPayload::MultipleNamed(vec![
+                alone("diffuse", diffuse),
+                alone("emissive", emissive),
+            ]));
+        }
+        let mut icon = icon.clone();
+        icon.states.splice(index..=index, [diffuse, emissive]);
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn references_state(&self, state_name: &str) -> bool {
+        let mask = match &self.emissive {
+            EmissiveSelector::Mask { state } => Some(state),
+            EmissiveSelector::Channel { .. } => None,
+        };
+        state_name == self.state || mask.is_some_and(|mask| mask == state_name)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.diffuse_suffix == self.emissive_suffix {
+            return Err(ProcessorError::ConfigError(
+                "diffuse_suffix and emissive_suffix have to be different".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+/// Checks `mask` can be laid over `source`, which needs it to have either a
+/// single frame and dir, or the same frames and dirs as `source`
+pub(crate) fn check_mask(mask: &IconState, source: &IconState) -> ProcessorResult<()> {
+    if mask.images.len() != 1 && mask.images.len() != source.images.len() {
+        return Err(ProcessorError::ConfigError(format!(
+            "Mask icon state \"{}\" needs either a single frame and dir, or the same frames and \
+             dirs as \"{}\"",
+            mask.name, source.name
+        )));
+    }
+    Ok(())
+}
+
+/// Splits `source` in two, named after it with each of `suffixes` on the end.
+/// Pixels `selected` picks go in the second state and the rest in the first,
+/// with each transparent in the state it isn't in. `selected` is given the
+/// index of the image within the state, the pixel's position, and its color
+pub(crate) fn split_state(
+    source: &IconState,
+    suffixes: (&str, &str),
+    selected: impl Fn(usize, u32, u32, Rgba<u8>) -> bool,
+) -> (IconState, IconState) {
+    let mut unselected_state = source.clone();
+    let mut selected_state = source.clone();
+    unselected_state.name = format!("{}{}", source.name, suffixes.0);
+    selected_state.name = format!("{}{}", source.name, suffixes.1);
+    unselected_state.images.clear();
+    selected_state.images.clear();
+    for (image_index, image) in source.images.iter().enumerate() {
+        let mut unselected_image = image.to_rgba8();
+        let mut selected_image = image.to_rgba8();
+        for (x, y, pixel) in image.pixels() {
+            if selected(image_index, x, y, pixel) {
+                unselected_image.put_pixel(x, y, TRANSPARENT);
+            } else {
+                selected_image.put_pixel(x, y, TRANSPARENT);
+            }
+        }
+        unselected_state
+            .images
+            .push(DynamicImage::ImageRgba8(unselected_image));
+        selected_state
+            .images
+            .push(DynamicImage::ImageRgba8(selected_image));
+    }
+    (unselected_state, selected_state)
+}
+
+/// Whether `mask` selects pixel `x`,`y` of the image at `image_index` in the
+/// state it's laid over, which it does wherever it isn't transparent
+pub(crate) fn mask_selects(mask: &IconState, image_index: usize, x: u32, y: u32) -> bool {
+    let mask_image = &mask.images[image_index % mask.images.len()];
+    !is_transparent(mask_image.get_pixel(x, y))
+}
+
+#[cfg(test)]
+mod test {
+    use image::RgbaImage;
+
+    use super::*;
+
+    const BULB: Rgba<u8> = Rgba([255, 240, 100, 255]);
+    const CASING: Rgba<u8> = Rgba([60, 60, 60, 255]);
+
+    fn lamp() -> Icon {
+        let mut lamp = RgbaImage::new(3, 1);
+        lamp.put_pixel(0, 0, BULB);
+        lamp.put_pixel(1, 0, CASING);
+        let mut mask = RgbaImage::new(3, 1);
+        mask.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        let state = |name: &str, image: RgbaImage| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }
+        };
+        Icon {
+            width: 3,
+            height: 1,
+            states: vec![state("lamp", lamp), state("lamp_mask", mask)],
+            ..Default::default()
+        }
+    }
+
+    fn splitter(emissive: EmissiveSelector) -> SplitEmissive {
+        SplitEmissive {
+            state: "lamp".to_string(),
+            emissive,
+            diffuse_suffix: diffuse_suffix(),
+            emissive_suffix: emissive_suffix(),
+            separate_outputs: false,
+        }
+    }
+
+    fn split(emissive: EmissiveSelector) -> Vec<IconState> {
+        let ProcessorPayload::Single(output) = splitter(emissive)
+            .do_operation(&InputIcon::Dmi(lamp()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output.states
+    }
+
+    fn assert_split(states: &[IconState]) {
+        let names: Vec<&str> = states.iter().map(|state| state.name.as_str()).collect();
+        assert_eq!(names, vec!["lamp_diffuse", "lamp_emissive", "lamp_mask"]);
+        let diffuse = &states[0].images[0];
+        let emissive = &states[1].images[0];
+        assert_eq!(diffuse.get_pixel(0, 0), TRANSPARENT);
+        assert_eq!(emissive.get_pixel(0, 0), BULB);
+        assert_eq!(diffuse.get_pixel(1, 0), CASING);
+        assert_eq!(emissive.get_pixel(1, 0), TRANSPARENT);
+        assert_eq!(diffuse.get_pixel(2, 0), TRANSPARENT);
+        assert_eq!(emissive.get_pixel(2, 0), TRANSPARENT);
+    }
+
+    #[test]
+    fn splits_by_channel() {
+        assert_split(&split(EmissiveSelector::Channel {
+            channel: Channel::Red,
+            threshold: half(),
+        }));
+    }
+
+    #[test]
+    fn splits_by_mask() {
+        assert_split(&split(EmissiveSelector::Mask {
+            state: "lamp_mask".to_string(),
+        }));
+    }
+
+    #[test]
+    fn separate_outputs_hold_only_their_half() {
+        let config = SplitEmissive {
+            separate_outputs: true,
+            ..splitter(EmissiveSelector::Channel {
+                channel: Channel::Red,
+                threshold: half(),
+            })
+        };
+        let ProcessorPayload::MultipleNamed(outputs) = config
+            .do_operation(&InputIcon::Dmi(lamp()), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected named icons");
+        };
+        let outputs: Vec<(&str, Vec<&str>)> = outputs
+            .iter()
+            .map(|output| {
+                let OutputImage::Dmi(icon) = &output.image else {
+                    panic!("Expected a dmi");
+                };
+                (
+                    output.name_hint.as_deref().unwrap(),
+                    icon.states
+                        .iter()
+                        .map(|state| state.name.as_str())
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                ("diffuse", vec!["lamp_diffuse"]),
+                ("emissive", vec!["lamp_emissive"]),
+            ]
+        );
+    }
+}
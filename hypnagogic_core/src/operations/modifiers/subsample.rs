@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::drop_frames::{DropFrames, DroppedDelay};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Lowers the frame rate of the targeted icon states by keeping only every
+/// `keep_every`th frame (starting with the first). Dropped frames' delays go
+/// to the kept frame before them, so animations take just as long to play.
+///
+/// Shorthand for [`DropFrames`] with a stride
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Subsample {
+    pub keep_every: u32,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for Subsample {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        self.as_drop_frames().perform_operation(input, mode)
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.keep_every == 0 {
+            return Err(ProcessorError::ConfigError(
+                "keep_every must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Subsample {
+    fn as_drop_frames(&self) -> DropFrames {
+        DropFrames {
+            frames: vec![],
+            stride: Some(self.keep_every),
+            dropped_delay: DroppedDelay::Redistribute,
+            targets: self.targets.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn halving_doubles_delays() {
+        // 6 frames of 2 dirs, each image colored by its frame and dir
+        let images = (0..6u8)
+            .flat_map(|frame| {
+                (0..2u8).map(move |dir| {
+                    DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                        1,
+                        1,
+                        Rgba([frame, dir, 0, 255]),
+                    ))
+                })
+            })
+            .collect();
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "anim".to_string(),
+                dirs: 2,
+                frames: 6,
+                images,
+                delay: Some(vec![1.0; 6]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let config = Subsample {
+            keep_every: 2,
+            targets: StateTargets::default(),
+        };
+
+        let payload = config
+            .perform_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap();
+        let ProcessorPayload::Single(output) = payload else {
+            panic!("expected a single output");
+        };
+        let OutputImage::Dmi(icon) = *output else {
+            panic!("expected a dmi");
+        };
+        let state = &icon.states[0];
+        assert_eq!(state.frames, 3);
+        assert_eq!(state.delay, Some(vec![2.0, 2.0, 2.0]));
+        // Both dirs of frames 0, 2 and 4 are kept, in order
+        let kept: Vec<[u8; 2]> = state
+            .images
+            .iter()
+            .map(|image| {
+                let pixel = image.to_rgba8().get_pixel(0, 0).0;
+                [pixel[0], pixel[1]]
+            })
+            .collect();
+        assert_eq!(kept, vec![[0, 0], [0, 1], [2, 0], [2, 1], [4, 0], [4, 1]]);
+    }
+
+    #[test]
+    fn zero_is_rejected() {
+        let config = Subsample {
+            keep_every: 0,
+            targets: StateTargets::default(),
+        };
+        assert!(config.verify_config().is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::modifiers::split_emissive::Channel;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Common channel swaps, so they don't need spelling out
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapPreset {
+    /// Swaps red and blue, for icons saved as BGR by mistake (or the other
+    /// way round)
+    RgbToBgr,
+}
+
+impl SwapPreset {
+    fn channels(self) -> (Channel, Channel) {
+        match self {
+            SwapPreset::RgbToBgr => (Channel::Red, Channel::Blue),
+        }
+    }
+}
+
+/// Swaps two color channels across every frame of the targeted icon states,
+/// to fix icons imported with them mixed up. Takes either a `from`/`to` pair
+/// or a `preset`. Alpha is only touched if it's named, so presets never change
+/// transparency
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SwapChannels {
+    #[serde(default)]
+    pub from: Option<Channel>,
+    #[serde(default)]
+    pub to: Option<Channel>,
+    #[serde(default)]
+    pub preset: Option<SwapPreset>,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for SwapChannels {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let (from, to) = self.channels()?;
+        let (from, to) = (index(from), index(to));
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                pixel.0.swap(from, to);
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        let (from, to) = self.channels()?;
+        if from == to {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "Swapping a channel with itself leaves every pixel as it is".to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+impl SwapChannels {
+    /// The two channels being swapped, from whichever way they were given
+    fn channels(&self) -> ProcessorResult<(Channel, Channel)> {
+        match (self.from, self.to, self.preset) {
+            (Some(from), Some(to), None) => Ok((from, to)),
+            (None, None, Some(preset)) => Ok(preset.channels()),
+            (_, _, Some(_)) => {
+                Err(ProcessorError::ConfigError(
+                    "Give either a preset or from and to, not both".to_string(),
+                ))
+            }
+            _ => {
+                Err(ProcessorError::ConfigError(
+                    "Both from and to are needed, unless a preset is given".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Where `channel` is in an rgba pixel
+fn index(channel: Channel) -> usize {
+    match channel {
+        Channel::Red => 0,
+        Channel::Green => 1,
+        Channel::Blue => 2,
+        Channel::Alpha => 3,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn swap(
+        from: Option<Channel>,
+        to: Option<Channel>,
+        preset: Option<SwapPreset>,
+    ) -> SwapChannels {
+        SwapChannels {
+            from,
+            to,
+            preset,
+            targets: StateTargets::default(),
+        }
+    }
+
+    fn icon(pixels: &[Rgba<u8>]) -> Icon {
+        Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "crate".to_string(),
+                frames: pixels.len() as u32,
+                images: pixels
+                    .iter()
+                    .map(|&pixel| DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, pixel)))
+                    .collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn apply(config: &SwapChannels, icon: Icon) -> Icon {
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        output
+    }
+
+    fn pixels(icon: &Icon) -> Vec<Rgba<u8>> {
+        icon.states[0]
+            .images
+            .iter()
+            .map(|frame| frame.get_pixel(0, 0))
+            .collect()
+    }
+
+    #[test]
+    fn rgb_to_bgr_swaps_red_and_blue_and_undoes_itself() {
+        let original = icon(&[Rgba([255, 40, 0, 200]), Rgba([10, 20, 30, 0])]);
+        let config = swap(None, None, Some(SwapPreset::RgbToBgr));
+
+        let swapped = apply(&config, original.clone());
+        assert_eq!(
+            pixels(&swapped),
+            vec![Rgba([0, 40, 255, 200]), Rgba([30, 20, 10, 0])]
+        );
+
+        let restored = apply(&config, swapped);
+        assert_eq!(pixels(&restored), pixels(&original));
+    }
+
+    #[test]
+    fn named_pair_can_swap_alpha() {
+        let swapped = apply(
+            &swap(Some(Channel::Green), Some(Channel::Alpha), None),
+            icon(&[Rgba([1, 2, 3, 4])]),
+        );
+        assert_eq!(pixels(&swapped), vec![Rgba([1, 4, 3, 2])]);
+    }
+
+    #[test]
+    fn needs_exactly_one_way_of_naming_channels() {
+        assert!(swap(Some(Channel::Red), None, None)
+            .verify_config()
+            .is_err());
+        assert!(swap(
+            Some(Channel::Red),
+            Some(Channel::Blue),
+            Some(SwapPreset::RgbToBgr)
+        )
+        .verify_config()
+        .is_err());
+        assert_eq!(
+            swap(Some(Channel::Red), Some(Channel::Red), None)
+                .verify_config()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
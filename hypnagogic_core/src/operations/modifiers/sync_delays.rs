@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::format_converter::error::InconsistentDelay;
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Copies the delays of one icon state onto the targeted states, so a family
+/// of animations that should play in step all share the same timing.
+///
+/// Every targeted state needs as many frames as the reference, or nothing is
+/// changed and the ones that don't are listed in the error
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SyncDelays {
+    /// Name of the icon state to copy delays from
+    pub reference: String,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for SyncDelays {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let Some(reference) = icon
+            .states
+            .iter()
+            .find(|state| state.name == self.reference)
+        else {
+            return Err(ProcessorError::ConfigError(format!(
+                "Icon state \"{}\" was not found in the input",
+                self.reference
+            )));
+        };
+
+        let targeted = |name: &str| name != self.reference && self.targets.matches(name);
+        let problems: Vec<InconsistentDelay> = icon
+            .states
+            .iter()
+            .filter(|state| targeted(&state.name) && state.frames != reference.frames)
+            .map(|state| {
+                InconsistentDelay {
+                    state: state.name.clone(),
+                    frames: state.frames,
+                    delays: state.delay.clone().unwrap_or_default(),
+                }
+            })
+            .collect();
+        if !problems.is_empty() {
+            return Err(ProcessorError::InconsistentDelays {
+                reference: self.reference.clone(),
+                frames: reference.frames,
+                problems,
+            });
+        }
+
+        let delay = reference.delay.clone();
+        let mut icon = icon.clone();
+        for state in &mut icon.states {
+            if targeted(&state.name) {
+                state.delay.clone_from(&delay);
+            }
+        }
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn animation(name: &str, frames: u32, delay: Vec<f32>) -> IconState {
+        IconState {
+            name: name.to_string(),
+            frames,
+            images: vec![DynamicImage::ImageRgba8(RgbaImage::new(1, 1)); frames as usize],
+            delay: Some(delay),
+            ..Default::default()
+        }
+    }
+
+    fn sync(config: &SyncDelays, states: Vec<IconState>) -> ProcessorResult<Icon> {
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            states,
+            ..Default::default()
+        };
+        let ProcessorPayload::Single(output) =
+            config.do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)?
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        Ok(output)
+    }
+
+    fn sync_to_door() -> SyncDelays {
+        SyncDelays {
+            reference: "door".to_string(),
+            targets: StateTargets {
+                target_states: vec![
+                    "door_mask".to_string(),
+                    "door_lights".to_string(),
+                    "door_glass".to_string(),
+                ],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn targets_copy_the_reference() {
+        let output = sync(
+            &sync_to_door(),
+            vec![
+                animation("door", 3, vec![1.0, 2.0, 5.0]),
+                animation("door_mask", 3, vec![1.0, 1.0, 1.0]),
+                animation("door_lights", 3, vec![4.0, 4.0, 4.0]),
+                animation("door_glass", 3, vec![1.0, 2.0, 3.0]),
+                animation("sign", 3, vec![9.0, 9.0, 9.0]),
+            ],
+        )
+        .unwrap();
+        let delays: Vec<(&str, &[f32])> = output
+            .states
+            .iter()
+            .map(|state| (state.name.as_str(), state.delay.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                ("door", &[1.0, 2.0, 5.0][..]),
+                ("door_mask", &[1.0, 2.0, 5.0][..]),
+                ("door_lights", &[1.0, 2.0, 5.0][..]),
+                ("door_glass", &[1.0, 2.0, 5.0][..]),
+                // Not targeted
+                ("sign", &[9.0, 9.0, 9.0][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_count_mismatches_are_listed() {
+        let Err(ProcessorError::InconsistentDelays {
+            reference,
+            frames,
+            problems,
+        }) = sync(
+            &sync_to_door(),
+            vec![
+                animation("door", 3, vec![1.0, 2.0, 5.0]),
+                animation("door_mask", 2, vec![1.0, 1.0]),
+                animation("door_lights", 3, vec![4.0, 4.0, 4.0]),
+                animation("door_glass", 4, vec![1.0, 2.0, 3.0, 4.0]),
+            ],
+        )
+        else {
+            panic!("Expected inconsistent delays");
+        };
+        assert_eq!((reference.as_str(), frames), ("door", 3));
+        let problems: Vec<(&str, u32)> = problems
+            .iter()
+            .map(|problem| (problem.state.as_str(), problem.frames))
+            .collect();
+        assert_eq!(problems, vec![("door_mask", 2), ("door_glass", 4)]);
+
+        let missing = SyncDelays {
+            reference: "window".to_string(),
+            targets: StateTargets::default(),
+        };
+        assert!(matches!(
+            sync(&missing, vec![animation("door", 3, vec![1.0, 2.0, 5.0])]),
+            Err(ProcessorError::ConfigError(_))
+        ));
+    }
+}
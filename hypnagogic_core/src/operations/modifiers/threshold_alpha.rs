@@ -0,0 +1,155 @@
+use dmi::icon::IconState;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::error::{ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::{alpha, with_alpha};
+
+fn default_cutoff() -> u8 {
+    128
+}
+
+/// Snaps the alpha of every pixel in the targeted icon states to either fully
+/// transparent or fully opaque, for sprites that can't have partial
+/// transparency. Pixels with an alpha under `cutoff` are cleared, and the rest
+/// are made opaque. Colors are left alone
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdAlpha {
+    /// The lowest alpha that's kept, from 0 to 255
+    #[serde(default = "default_cutoff")]
+    pub cutoff: u8,
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ThresholdAlpha {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let mut icon = icon.clone();
+        icon.states = icon
+            .states
+            .into_iter()
+            .map(|state| {
+                if !self.targets.matches(&state.name) {
+                    return state;
+                }
+                IconState {
+                    images: state
+                        .images
+                        .iter()
+                        .map(|frame| {
+                            let mut frame = frame.to_rgba8();
+                            for pixel in frame.pixels_mut() {
+                                let snapped = if alpha(*pixel) < self.cutoff { 0 } else { 255 };
+                                *pixel = with_alpha(*pixel, snapped);
+                            }
+                            DynamicImage::ImageRgba8(frame)
+                        })
+                        .collect(),
+                    ..state
+                }
+            })
+            .collect();
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.cutoff == 0 {
+            return Ok(vec![ProcessorWarning::SuspiciousConfig(
+                "cutoff is 0, so every pixel will be made fully opaque, even transparent ones"
+                    .to_string(),
+            )]);
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn soft_edges_become_hard() {
+        // A fade from fully opaque down to fully transparent
+        let alphas = [255, 200, 129, 128, 127, 60, 1, 0];
+        let image = RgbaImage::from_fn(alphas.len() as u32, 1, |x, _| {
+            Rgba([10, 20, 30, alphas[x as usize]])
+        });
+        let state = |name: &str| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(image.clone())],
+                ..Default::default()
+            }
+        };
+        let icon = Icon {
+            width: alphas.len() as u32,
+            height: 1,
+            states: vec![state("sprite"), state("soft")],
+            ..Default::default()
+        };
+        let config = ThresholdAlpha {
+            cutoff: 128,
+            targets: StateTargets {
+                target_states: vec!["sprite".to_string()],
+                ..Default::default()
+            },
+        };
+
+        let ProcessorPayload::Single(output) = config
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        let snapped: Vec<u8> = output.states[0].images[0]
+            .pixels()
+            .map(|(_, _, pixel)| pixel.0[3])
+            .collect();
+        // The cutoff itself is kept
+        assert_eq!(snapped, vec![255, 255, 255, 255, 0, 0, 0, 0]);
+        assert_eq!(
+            output.states[0].images[0].get_pixel(0, 0),
+            Rgba([10, 20, 30, 255])
+        );
+        // Untargeted states keep their soft edges
+        assert_eq!(
+            output.states[1].images[0].get_pixel(5, 0),
+            Rgba([10, 20, 30, 60])
+        );
+    }
+
+    #[test]
+    fn zero_cutoff_warns() {
+        let config = ThresholdAlpha {
+            cutoff: 0,
+            targets: StateTargets::default(),
+        };
+        assert_eq!(config.verify_config().unwrap().len(), 1);
+        let config = ThresholdAlpha {
+            cutoff: default_cutoff(),
+            ..config
+        };
+        assert!(config.verify_config().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use dmi::icon::{Icon, IconState};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Repeats one icon state over a larger area, for building repeating textures
+/// out of a small sample. Every frame and dir is tiled, so animations carry
+/// over.
+///
+/// Tiles start in the top left, and any that don't fit are clipped at the
+/// right and bottom edges. The output dmi holds just the tiled state, sized to
+/// match it
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Tile {
+    /// Name of the icon state to tile
+    pub source_state: String,
+    /// Name to give the tiled icon state
+    pub output_state: String,
+    /// Width of the output, in pixels
+    pub width: u32,
+    /// Height of the output, in pixels
+    pub height: u32,
+}
+
+impl IconOperationConfig for Tile {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+
+        let Some(source) = icon
+            .states
+            .iter()
+            .find(|state| state.name == self.source_state)
+        else {
+            return Err(ProcessorError::ConfigError(format!(
+                "No icon state named \"{}\" to tile",
+                self.source_state
+            )));
+        };
+
+        let tiled = IconState {
+            name: self.output_state.clone(),
+            images: source
+                .images
+                .iter()
+                .map(|frame| DynamicImage::ImageRgba8(self.tile_frame(frame)))
+                .collect(),
+            ..source.clone()
+        };
+
+        Ok(ProcessorPayload::from_icon(Icon {
+            width: self.width,
+            height: self.height,
+            states: vec![tiled],
+            ..icon.clone()
+        }))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ProcessorError::ConfigError(format!(
+                "Can't tile to a {}x{} output, both sides need to be at least 1 pixel",
+                self.width, self.height
+            )));
+        }
+        Ok(vec![])
+    }
+}
+
+impl Tile {
+    fn tile_frame(&self, frame: &DynamicImage) -> RgbaImage {
+        let (source_width, source_height) = frame.dimensions();
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            frame.get_pixel(x % source_width, y % source_height)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Rgba;
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    /// A 16x16 frame where every pixel's color gives its position
+    fn source_frame() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(16, 16, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }))
+    }
+
+    fn config(width: u32, height: u32) -> Tile {
+        Tile {
+            source_state: "floor".to_string(),
+            output_state: "floor_large".to_string(),
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn tiles_across_seams() {
+        let tiled = config(48, 32).tile_frame(&source_frame());
+        assert_eq!(tiled.dimensions(), (48, 32));
+
+        // Either side of the horizontal seams
+        assert_eq!(*tiled.get_pixel(15, 3), Rgba([15, 3, 0, 255]));
+        assert_eq!(*tiled.get_pixel(16, 3), Rgba([0, 3, 0, 255]));
+        assert_eq!(*tiled.get_pixel(31, 3), Rgba([15, 3, 0, 255]));
+        assert_eq!(*tiled.get_pixel(32, 3), Rgba([0, 3, 0, 255]));
+        // Either side of the vertical one
+        assert_eq!(*tiled.get_pixel(20, 15), Rgba([4, 15, 0, 255]));
+        assert_eq!(*tiled.get_pixel(20, 16), Rgba([4, 0, 0, 255]));
+        // Far corner
+        assert_eq!(*tiled.get_pixel(47, 31), Rgba([15, 15, 0, 255]));
+    }
+
+    #[test]
+    fn partial_tiles_are_clipped() {
+        let tiled = config(24, 20).tile_frame(&source_frame());
+        assert_eq!(tiled.dimensions(), (24, 20));
+        assert_eq!(*tiled.get_pixel(23, 19), Rgba([7, 3, 0, 255]));
+    }
+
+    #[test]
+    fn output_holds_only_tiled_state() {
+        let icon = Icon {
+            width: 16,
+            height: 16,
+            states: vec![
+                IconState {
+                    name: "wall".to_string(),
+                    images: vec![DynamicImage::ImageRgba8(RgbaImage::new(16, 16))],
+                    ..Default::default()
+                },
+                IconState {
+                    name: "floor".to_string(),
+                    images: vec![source_frame()],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let payload = config(48, 32)
+            .perform_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap();
+        let ProcessorPayload::Single(output) = payload else {
+            panic!("expected a single output");
+        };
+        let OutputImage::Dmi(icon) = *output else {
+            panic!("expected a dmi");
+        };
+        assert_eq!((icon.width, icon.height), (48, 32));
+        assert_eq!(icon.states.len(), 1);
+        assert_eq!(icon.states[0].name, "floor_large");
+        assert_eq!(icon.states[0].images[0].dimensions(), (48, 32));
+
+        assert!(config(48, 32)
+            .perform_operation(
+                &InputIcon::Dmi(Icon {
+                    states: vec![IconState::default()],
+                    ..Default::default()
+                }),
+                OperationMode::Standard
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::operations::context::PipelineContext;
+use crate::operations::error::{ProcessorError, ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::icon_ops::{content_bounds, Bounds};
+
+/// Where `Trim` cut an icon down to, within the icon it started with. Left in
+/// the [`PipelineContext`] for later operations, like `Pad` putting the
+/// trimmed space back
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TrimmedBounds {
+    /// The region that was kept
+    pub kept: Bounds,
+    /// Width of the icon before trimming
+    pub original_width: u32,
+    /// Height of the icon before trimming
+    pub original_height: u32,
+}
+
+fn default_round_to() -> u32 {
+    1
+}
+
+/// Crops away the fully transparent border shared by every frame of every
+/// icon state, shrinking the icon to fit its content. An icon with no content
+/// at all is left as is.
+///
+/// With `round_to`, the box kept grows to the next multiple of it in both
+/// dimensions, with the content centered in it. Space the box needs past the
+/// edges of the icon is filled in transparent, and can't be put back exactly
+/// by `Pad`'s `restore_trim`
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Trim {
+    /// The width and height of the result are made multiples of this
+    #[serde(default = "default_round_to")]
+    pub round_to: u32,
+}
+
+impl Default for Trim {
+    fn default() -> Self {
+        Self {
+            round_to: default_round_to(),
+        }
+    }
+}
+
+impl IconOperationConfig for Trim {
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        self.perform_operation_in_context(input, mode, &mut PipelineContext::new())
+    }
+
+    #[tracing::instrument(skip(input, context))]
+    fn perform_operation_in_context(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        context: &mut PipelineContext,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        let Some(content) = icon
+            .states
+            .iter()
+            .flat_map(|state| &state.images)
+            .filter_map(content_bounds)
+            .reduce(Bounds::union)
+        else {
+            context.insert(TrimmedBounds {
+                kept: Bounds {
+                    x: 0,
+                    y: 0,
+                    width: icon.width,
+                    height: icon.height,
+                },
+                original_width: icon.width,
+                original_height: icon.height,
+            });
+            return Ok(ProcessorPayload::from_icon(icon.clone()));
+        };
+
+        let width = content.width.next_multiple_of(self.round_to);
+        let height = content.height.next_multiple_of(self.round_to);
+        // Where the content goes in the result, to keep it centered
+        let offset_x = (width - content.width) / 2;
+        let offset_y = (height - content.height) / 2;
+        context.insert(TrimmedBounds {
+            kept: Bounds {
+                x: content.x.saturating_sub(offset_x),
+                y: content.y.saturating_sub(offset_y),
+                width,
+                height,
+            },
+            original_width: icon.width,
+            original_height: icon.height,
+        });
+
+        let mut icon = icon.clone();
+        icon.width = width;
+        icon.height = height;
+        for state in &mut icon.states {
+            for image in &mut state.images {
+                let mut trimmed = RgbaImage::new(width, height);
+                for (x, y, pixel) in image
+                    .view(content.x, content.y, content.width, content.height)
+                    .pixels()
+                {
+                    trimmed.put_pixel(x + offset_x, y + offset_y, pixel);
+                }
+                *image = DynamicImage::ImageRgba8(trimmed);
+            }
+        }
+
+        Ok(ProcessorPayload::from_icon(icon))
+    }
+
+    fn verify_config(&self) -> ProcessorResult<Vec<ProcessorWarning>> {
+        if self.round_to == 0 {
+            return Err(ProcessorError::ConfigError(
+                "round_to must be at least 1".to_string(),
+            ));
+        }
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    #[test]
+    fn trims_to_content_of_every_state() {
+        let dot = |x, y| {
+            let mut image = RgbaImage::new(8, 8);
+            image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            DynamicImage::ImageRgba8(image)
+        };
+        let icon = Icon {
+            width: 8,
+            height: 8,
+            states: vec![
+                IconState {
+                    name: "a".to_string(),
+                    images: vec![dot(2, 3)],
+                    ..Default::default()
+                },
+                IconState {
+                    name: "b".to_string(),
+                    images: vec![dot(5, 4)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut context = PipelineContext::new();
+        let ProcessorPayload::Single(output) = Trim::default()
+            .perform_operation_in_context(
+                &InputIcon::Dmi(icon),
+                OperationMode::Standard,
+                &mut context,
+            )
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+
+        assert_eq!((output.width, output.height), (4, 2));
+        assert_eq!(output.states[0].images[0].dimensions(), (4, 2));
+        assert_eq!(
+            output.states[0].images[0].get_pixel(0, 0),
+            Rgba([255, 0, 0, 255])
+        );
+        assert_eq!(
+            output.states[1].images[0].get_pixel(3, 1),
+            Rgba([255, 0, 0, 255])
+        );
+        assert_eq!(
+            context.get::<TrimmedBounds>(),
+            Some(&TrimmedBounds {
+                kept: Bounds {
+                    x: 2,
+                    y: 3,
+                    width: 4,
+                    height: 2,
+                },
+                original_width: 8,
+                original_height: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn rounds_up_around_centered_content() {
+        // A 30x31 block of content, off in a corner of a bigger icon
+        let mut image = RgbaImage::new(40, 40);
+        for x in 1..31 {
+            for y in 2..33 {
+                image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let icon = Icon {
+            width: 40,
+            height: 40,
+            states: vec![IconState {
+                name: "crate".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let ProcessorPayload::Single(output) = Trim { round_to: 32 }
+            .do_operation(&InputIcon::Dmi(icon), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+
+        assert_eq!((output.width, output.height), (32, 32));
+        let image = &output.states[0].images[0];
+        assert_eq!(image.dimensions(), (32, 32));
+        // The two spare columns go either side, and the odd spare row on the bottom
+        assert_eq!(image.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(image.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(30, 30), Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(31, 30), Rgba([0, 0, 0, 0]));
+        assert_eq!(image.get_pixel(1, 31), Rgba([0, 0, 0, 0]));
+
+        assert!(Trim { round_to: 0 }.verify_config().is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use dmi::icon::Icon;
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::operations::error::{ProcessorError, ProcessorResult};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+
+/// Makes sure every frame of every icon state is the size the dmi says it is,
+/// handing back the input untouched if so. Operations assume frames are all
+/// the same size, so this is a cheap guard to put in front of them when the
+/// input might be malformed
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct ValidateDimensions {}
+
+impl IconOperationConfig for ValidateDimensions {
+    #[tracing::instrument(skip(input))]
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        check_dimensions(icon)?;
+        Ok(ProcessorPayload::from_icon(icon.clone()))
+    }
+}
+
+/// Checks every image in `icon` against its declared width and height
+/// # Errors
+/// Returns `ProcessorError::DimensionViolation` for the first image that
+/// doesn't match
+pub fn check_dimensions(icon: &Icon) -> ProcessorResult<()> {
+    let expected = (icon.width, icon.height);
+    for state in &icon.states {
+        let dirs = usize::from(state.dirs.max(1));
+        // Images are stored frame by frame, with every dir of a frame together
+        for (index, image) in state.images.iter().enumerate() {
+            let got = image.dimensions();
+            if got != expected {
+                return Err(ProcessorError::DimensionViolation {
+                    state: state.name.clone(),
+                    frame: (index / dirs) as u32,
+                    expected,
+                    got,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::IconState;
+    use image::{DynamicImage, RgbaImage};
+
+    use super::*;
+
+    fn state(name: &str, sizes: &[(u32, u32)]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            dirs: 2,
+            frames: sizes.len() as u32 / 2,
+            images: sizes
+                .iter()
+                .map(|&(width, height)| DynamicImage::ImageRgba8(RgbaImage::new(width, height)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_first_mismatched_frame() {
+        let icon = Icon {
+            width: 32,
+            height: 32,
+            states: vec![
+                state("fine", &[(32, 32); 4]),
+                state("broken", &[(32, 32), (32, 32), (32, 32), (32, 16)]),
+                state("also_broken", &[(8, 8); 2]),
+            ],
+            ..Default::default()
+        };
+
+        let Err(ProcessorError::DimensionViolation {
+            state,
+            frame,
+            expected,
+            got,
+        }) = check_dimensions(&icon)
+        else {
+            panic!("expected a dimension violation");
+        };
+        assert_eq!(state, "broken");
+        // The second dir of the second frame
+        assert_eq!(frame, 1);
+        assert_eq!(expected, (32, 32));
+        assert_eq!(got, (32, 16));
+    }
+
+    #[test]
+    fn well_formed_icons_pass() {
+        let icon = Icon {
+            width: 32,
+            height: 32,
+            states: vec![state("fine", &[(32, 32); 4])],
+            ..Default::default()
+        };
+        assert!(check_dimensions(&icon).is_ok());
+    }
+}
@@ -0,0 +1,158 @@
+use dmi::icon::IconState;
+use serde::{Deserialize, Serialize};
+
+use crate::config::blocks::modifiers::StateTargets;
+use crate::operations::context::PipelineContext;
+use crate::operations::error::{ProcessorResult, ProcessorWarning};
+use crate::operations::{IconOperationConfig, InputIcon, OperationMode, ProcessorPayload};
+use crate::util::color::alpha;
+
+/// Makes sure the targeted mask states are hard edged, handing back the input
+/// untouched. Masks select every pixel that isn't fully transparent, so a
+/// partly transparent pixel is masked in as if it were opaque, which is rarely
+/// what whoever drew it meant. Each state with any gets a warning, with how
+/// many it has
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct ValidateMask {
+    #[serde(flatten)]
+    pub targets: StateTargets,
+}
+
+impl IconOperationConfig for ValidateMask {
+    fn perform_operation(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> ProcessorResult<ProcessorPayload> {
+        self.perform_operation_in_context(input, mode, &mut PipelineContext::new())
+    }
+
+    #[tracing::instrument(skip(input, context))]
+    fn perform_operation_in_context(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        context: &mut PipelineContext,
+    ) -> ProcessorResult<ProcessorPayload> {
+        let icon = input.require_states()?;
+        for state in &icon.states {
+            if !self.targets.matches(&state.name) {
+                continue;
+            }
+            let pixels = soft_pixels(state);
+            if pixels > 0 {
+                context.warn(ProcessorWarning::SoftMask {
+                    state: state.name.clone(),
+                    pixels,
+                });
+            }
+        }
+        Ok(ProcessorPayload::from_icon(icon.clone()))
+    }
+
+    fn state_targets(&self) -> Option<&StateTargets> {
+        Some(&self.targets)
+    }
+}
+
+/// How many pixels across every frame and dir of `state` are neither fully
+/// opaque nor fully transparent
+fn soft_pixels(state: &IconState) -> usize {
+    state
+        .images
+        .iter()
+        .map(|image| {
+            image
+                .to_rgba8()
+                .pixels()
+                .filter(|&&pixel| !matches!(alpha(pixel), 0 | 255))
+                .count()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::Icon;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    /// A 4x4 mask, opaque on the left half and transparent on the right, with
+    /// the given alphas written along its top row
+    fn mask(name: &str, top_row: [u8; 4]) -> IconState {
+        let mut image = RgbaImage::from_fn(4, 4, |x, _| {
+            Rgba([255, 255, 255, if x < 2 { 255 } else { 0 }])
+        });
+        for (x, alpha) in (0..).zip(top_row) {
+            image.put_pixel(x, 0, Rgba([255, 255, 255, alpha]));
+        }
+        IconState {
+            name: name.to_string(),
+            frames: 2,
+            images: vec![DynamicImage::ImageRgba8(image); 2],
+            ..Default::default()
+        }
+    }
+
+    fn warnings(config: &ValidateMask, states: Vec<IconState>) -> Vec<ProcessorWarning> {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states,
+            ..Default::default()
+        };
+        let mut context = PipelineContext::new();
+        let ProcessorPayload::Single(_) = config
+            .perform_operation_in_context(
+                &InputIcon::Dmi(icon),
+                OperationMode::Standard,
+                &mut context,
+            )
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        context.take_warnings()
+    }
+
+    #[test]
+    fn binary_mask_passes() {
+        let warnings = warnings(
+            &ValidateMask::default(),
+            vec![mask("hard", [255, 0, 255, 0])],
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn soft_edges_are_counted_per_state() {
+        let warnings = warnings(
+            &ValidateMask::default(),
+            vec![
+                mask("hard", [255, 255, 0, 0]),
+                mask("soft", [255, 128, 1, 254]),
+            ],
+        );
+        // Three soft pixels, in each of two frames
+        assert_eq!(
+            warnings,
+            vec![ProcessorWarning::SoftMask {
+                state: "soft".to_string(),
+                pixels: 6,
+            }]
+        );
+    }
+
+    #[test]
+    fn only_targets_named_states() {
+        let config = ValidateMask {
+            targets: StateTargets {
+                target_states: vec!["hard".to_string()],
+                ..Default::default()
+            },
+        };
+        let states = vec![mask("hard", [0; 4]), mask("soft", [100; 4])];
+        assert!(warnings(&config, states).is_empty());
+    }
+}
@@ -0,0 +1,1603 @@
+use std::fmt::Write;
+use std::path::{Component, PathBuf};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+use toml::Value;
+use tracing::{debug, info, info_span};
+use user_error::UFE;
+
+use crate::operations::assertions::Assertions;
+use crate::operations::condition::Condition;
+use crate::operations::context::PipelineContext;
+use crate::operations::error::{ErrorCode, ProcessorError, ProcessorWarning, WarningCode};
+use crate::operations::metadata::Metadata;
+use crate::operations::{
+    IconOperation,
+    IconOperationConfig,
+    InputIcon,
+    NamedIcon,
+    OperationMode,
+    OutputFormat,
+    ProcessorPayload,
+};
+use crate::process::ProcessEvent;
+
+/// A chain of icon operations, each one working on the output of the one
+/// before it.
+///
+/// Configs can either hold a single operation at the top level, or a list of
+/// them under `[[operations]]`. Both end up as a pipeline.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pipeline {
+    pub operations: Vec<PipelineStep>,
+    /// The order to run the operations in, as their positions in
+    /// `operations` counting from 1. Runs them in the order they're written
+    /// if not set
+    pub order: Option<Vec<usize>>,
+    /// What kind of file dmi outputs are written as
+    pub output_format: OutputFormat,
+    /// Checked against the outputs once every operation has run
+    pub assert: Option<Assertions>,
+    /// Fail if the input has icon states that no operation touches, for
+    /// configs meant to handle every state of their dmi
+    pub require_all_states_referenced: bool,
+    /// Warnings with these codes are known and accepted, so they're dropped
+    /// instead of reported
+    pub allow_warnings: Vec<WarningCode>,
+}
+
+impl From<IconOperation> for Pipeline {
+    fn from(operation: IconOperation) -> Self {
+        Self {
+            operations: vec![operation.into()],
+            order: None,
+            output_format: OutputFormat::default(),
+            assert: None,
+            require_all_states_referenced: false,
+            allow_warnings: vec![],
+        }
+    }
+}
+
+/// An operation in a pipeline, along with when it should run
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct PipelineStep {
+    #[serde(flatten)]
+    pub operation: IconOperation,
+    /// Only run the operation if its input passes this. Skipped operations
+    /// hand their input on to the next one untouched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<Condition>,
+    /// Write the operation's output to this file, relative to the input's
+    /// folder, instead of handing it on. The next operation gets the same
+    /// input this one did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// Disabled operations are left out as if they weren't in the config at
+    /// all: never checked, never run, and not referencing any states
+    #[serde(skip_serializing_if = "is_true")]
+    pub enabled: bool,
+}
+
+impl From<IconOperation> for PipelineStep {
+    fn from(operation: IconOperation) -> Self {
+        Self {
+            operation,
+            when: None,
+            output: None,
+            enabled: true,
+        }
+    }
+}
+
+impl PipelineStep {
+    /// Says what this step does in a line, as its mode followed by every
+    /// setting it ends up with, and when and where it runs
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let mut text = self.operation.mode_name().to_string();
+        if let Ok(Value::Table(mut settings)) = Value::try_from(&self.operation) {
+            settings.remove("mode");
+            if !settings.is_empty() {
+                let settings: Vec<String> = settings
+                    .iter()
+                    .map(|(name, value)| format!("{name} = {value}"))
+                    .collect();
+                let _ = write!(text, " ({})", settings.join(", "));
+            }
+        }
+        if let Some(when) = self
+            .when
+            .as_ref()
+            .and_then(|when| Value::try_from(when).ok())
+        {
+            let _ = write!(text, ", when {when}");
+        }
+        if let Some(output) = &self.output {
+            let _ = write!(text, ", written to {}", output.display());
+        }
+        if !self.enabled {
+            text.push_str(", disabled so never run");
+        }
+        text
+    }
+}
+
+impl<'de> Deserialize<'de> for PipelineStep {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // `when`, `output` and `enabled` sit next to the operation's own
+        // settings, so take them out before the operation sees them
+        let mut value = Value::deserialize(deserializer)?;
+        let when = match value.as_table_mut().and_then(|table| table.remove("when")) {
+            Some(when) => Some(Condition::deserialize(when).map_err(D::Error::custom)?),
+            None => None,
+        };
+        let output = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("output"))
+        {
+            Some(output) => Some(PathBuf::deserialize(output).map_err(D::Error::custom)?),
+            None => None,
+        };
+        let enabled = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("enabled"))
+        {
+            Some(enabled) => bool::deserialize(enabled).map_err(D::Error::custom)?,
+            None => true,
+        };
+        let operation = IconOperation::deserialize(value).map_err(D::Error::custom)?;
+        Ok(Self {
+            operation,
+            when,
+            output,
+            enabled,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Pipeline")]
+struct PipelineRepr<T> {
+    operations: T,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<Vec<usize>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "OutputFormat::is_default")]
+    output_format: OutputFormat,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assert: Option<Assertions>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    require_all_states_referenced: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    allow_warnings: Vec<WarningCode>,
+}
+
+// serde hands skip_serializing_if a reference
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// A single operation, with the pipeline wide settings alongside it
+#[derive(Serialize)]
+struct SingleRepr<'a> {
+    #[serde(flatten)]
+    operation: &'a PipelineStep,
+    #[serde(skip_serializing_if = "OutputFormat::is_default")]
+    output_format: OutputFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assert: &'a Option<Assertions>,
+    #[serde(skip_serializing_if = "is_false")]
+    require_all_states_referenced: bool,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    allow_warnings: &'a [WarningCode],
+}
+
+impl Serialize for Pipeline {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Write single operations back out the way they're usually written
+        if let ([operation], None) = (self.operations.as_slice(), &self.order) {
+            return SingleRepr {
+                operation,
+                output_format: self.output_format,
+                assert: &self.assert,
+                require_all_states_referenced: self.require_all_states_referenced,
+                allow_warnings: &self.allow_warnings,
+            }
+            .serialize(serializer);
+        }
+        self.list_repr().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pipeline {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Sniff out which form we have first, so errors come from the form the
+        // config actually uses rather than a vague "matched neither"
+        let mut value = Value::deserialize(deserializer)?;
+        let is_list = value
+            .as_table()
+            .is_some_and(|table| table.contains_key("operations"));
+        if is_list {
+            let repr: PipelineRepr<Vec<PipelineStep>> =
+                PipelineRepr::deserialize(value).map_err(D::Error::custom)?;
+            return Ok(Self {
+                operations: repr.operations,
+                order: repr.order,
+                output_format: repr.output_format,
+                assert: repr.assert,
+                require_all_states_referenced: repr.require_all_states_referenced,
+                allow_warnings: repr.allow_warnings,
+            });
+        }
+        // Pipeline wide settings sit next to the operation, so take them out
+        // before it sees them
+        let output_format = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("output_format"))
+        {
+            Some(format) => OutputFormat::deserialize(format).map_err(D::Error::custom)?,
+            None => OutputFormat::default(),
+        };
+        let assert = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("assert"))
+        {
+            Some(assert) => Some(Assertions::deserialize(assert).map_err(D::Error::custom)?),
+            None => None,
+        };
+        let require_all_states_referenced = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("require_all_states_referenced"))
+        {
+            Some(require) => bool::deserialize(require).map_err(D::Error::custom)?,
+            None => false,
+        };
+        let allow_warnings = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("allow_warnings"))
+        {
+            Some(allowed) => Vec::deserialize(allowed).map_err(D::Error::custom)?,
+            None => vec![],
+        };
+        let operation = PipelineStep::deserialize(value).map_err(D::Error::custom)?;
+        Ok(Self {
+            operations: vec![operation],
+            order: None,
+            output_format,
+            assert,
+            require_all_states_referenced,
+            allow_warnings,
+        })
+    }
+}
+
+impl Pipeline {
+    fn list_repr(&self) -> PipelineRepr<&[PipelineStep]> {
+        PipelineRepr {
+            operations: &self.operations,
+            order: self.order.clone(),
+            output_format: self.output_format,
+            assert: self.assert.clone(),
+            require_all_states_referenced: self.require_all_states_referenced,
+            allow_warnings: self.allow_warnings.clone(),
+        }
+    }
+
+    /// The pipeline as toml in its `[[operations]]` form, even if it only has
+    /// a single operation and would usually be written flat
+    /// # Errors
+    /// Returns an error if an operation can't be written as toml
+    pub fn to_list_toml(&self) -> Result<Value, toml::ser::Error> {
+        Value::try_from(self.list_repr())
+    }
+
+    /// Verifies every operation, then runs them in order, feeding each output
+    /// in to the next operation. That's the order they're written in, unless
+    /// the pipeline has an `order`.
+    ///
+    /// All invalid operations are reported together. Once running, the first
+    /// operation to fail stops the pipeline, since nothing after it has an
+    /// input. The operations share one [`PipelineContext`], fresh for each
+    /// run.
+    /// # Errors
+    /// Returns a `PipelineError` holding every operation that failed,
+    /// `PipelineError::Unmet` if the outputs don't pass the pipeline's
+    /// `assert`, or `PipelineError::UnreferencedStates` if the input has
+    /// states no operation touches and the pipeline requires them all to be
+    pub fn run(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+    ) -> Result<ProcessorPayload, PipelineError> {
+        self.run_with_progress(input, mode, |_| {})
+    }
+
+    /// Checks the config of every operation, without running anything,
+    /// returning the warnings they raise that the pipeline doesn't allow
+    /// # Errors
+    /// Returns a `PipelineError` holding every operation with a bad config or
+    /// `output`, `PipelineError::Empty` if there are no operations at all, or
+    /// `PipelineError::InvalidOrder` if `order` doesn't list every operation
+    /// exactly once
+    pub fn verify(&self) -> Result<Vec<ProcessorWarning>, PipelineError> {
+        if self.operations.is_empty() {
+            return Err(PipelineError::Empty);
+        }
+        self.verify_order()?;
+        let mut warnings = vec![];
+        let mut failures = vec![];
+        for (index, step) in self.operations.iter().enumerate() {
+            let operation = &step.operation;
+            if !step.enabled {
+                continue;
+            }
+            if let Err(error) = self.verify_output(index) {
+                failures.push(OperationFailure::new(index, operation, error));
+                continue;
+            }
+            match operation.verify_config() {
+                Ok(raised) => {
+                    warnings.extend(
+                        raised
+                            .into_iter()
+                            .filter(|warning| !self.allows(warning))
+                            .map(|warning| {
+                                ProcessorWarning::InOperation {
+                                    index,
+                                    mode: operation.mode_name(),
+                                    warning: Box::new(warning),
+                                }
+                            }),
+                    );
+                }
+                Err(error) => failures.push(OperationFailure::new(index, operation, error)),
+            }
+        }
+        if !failures.is_empty() {
+            return Err(PipelineError::OperationsFailed(failures));
+        }
+        Ok(warnings)
+    }
+
+    /// What the pipeline would do, without doing it: a line describing each
+    /// operation, see [`PipelineStep::describe`], in the order they'd run.
+    /// Each starts with the operation's position in `operations`, counting
+    /// from 1
+    #[must_use]
+    pub fn explain(&self) -> Vec<String> {
+        let order = if self.verify_order().is_ok() {
+            self.execution_order()
+        } else {
+            (0..self.operations.len()).collect()
+        };
+        order
+            .into_iter()
+            .map(|index| format!("{}. {}", index + 1, self.operations[index].describe()))
+            .collect()
+    }
+
+    /// `run`, but calls `progress` as each operation starts and finishes.
+    /// Each operation runs in an `operation` span, with its index and mode
+    /// # Errors
+    /// Same as `run`
+    pub fn run_with_progress(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        progress: impl Fn(ProcessEvent),
+    ) -> Result<ProcessorPayload, PipelineError> {
+        self.run_with_metadata(input, mode, progress)
+            .map(|(payload, _)| payload)
+    }
+
+    /// `run_with_progress`, but also collects what the operations had to say
+    /// about the icon states they made, see
+    /// [`IconOperationConfig::describe_states`]
+    /// # Errors
+    /// Same as `run`
+    #[tracing::instrument(skip_all)]
+    pub fn run_with_metadata(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        progress: impl Fn(ProcessEvent),
+    ) -> Result<(ProcessorPayload, Metadata), PipelineError> {
+        self.verify()?;
+        self.check_referenced(input)?;
+        let mut metadata = Metadata::default();
+        let payload = self.run_operations(input, mode, progress, &mut metadata)?;
+        if let Some(assert) = &self.assert {
+            assert.check(&payload).map_err(PipelineError::Unmet)?;
+        }
+        Ok((payload, metadata))
+    }
+
+    fn run_operations(
+        &self,
+        input: &InputIcon,
+        mode: OperationMode,
+        progress: impl Fn(ProcessEvent),
+        metadata: &mut Metadata,
+    ) -> Result<ProcessorPayload, PipelineError> {
+        let mut context = PipelineContext::new();
+        let mut intermediate: Option<InputIcon> = None;
+        // The last operation's output, held until we know whether an enabled
+        // operation comes after it
+        let mut pending: Option<(usize, ProcessorPayload)> = None;
+        let mut routed: Vec<NamedIcon> = vec![];
+        for index in self.execution_order() {
+            let step = &self.operations[index];
+            let operation = &step.operation;
+            let _span = info_span!("operation", index, mode = operation.mode_name()).entered();
+            if !step.enabled {
+                info!("Skipping disabled operation");
+                continue;
+            }
+            // Its `when` has to look at the input, so a step that might be
+            // skipped still needs the previous output to be a single icon
+            if let Some((previous, payload)) = pending.take() {
+                intermediate = Some(self.next_input(previous, payload)?);
+            }
+            let current = intermediate.as_ref().unwrap_or(input);
+            if let Some(when) = &step.when {
+                if !when.matches(current) {
+                    info!(condition = ?when, "Skipping operation");
+                    continue;
+                }
+            }
+            debug!("Running operation");
+            progress(ProcessEvent::StartedOperation {
+                index,
+                mode: operation.mode_name(),
+            });
+            let payload = operation
+                .check_targets(current)
+                .and_then(|()| operation.perform_operation_in_context(current, mode, &mut context))
+                .map_err(|error| OperationFailure::new(index, operation, error))?;
+            progress(ProcessEvent::FinishedOperation {
+                index,
+                mode: operation.mode_name(),
+            });
+            for warning in context.take_warnings() {
+                if self.allows(&warning) {
+                    continue;
+                }
+                progress(ProcessEvent::Warning(ProcessorWarning::InOperation {
+                    index,
+                    mode: operation.mode_name(),
+                    warning: Box::new(warning),
+                }));
+            }
+            metadata.extend(operation.describe_states(&payload));
+            if let Some(output) = &step.output {
+                let ProcessorPayload::Single(image) = payload else {
+                    return Err(OperationFailure::new(
+                        index,
+                        operation,
+                        ProcessorError::ConfigError(
+                            "This operation produces more than one output, so it can't be given \
+                             an output file"
+                                .to_string(),
+                        ),
+                    )
+                    .into());
+                };
+                routed.push(NamedIcon {
+                    path_hint: None,
+                    name_hint: None,
+                    path: Some(output.clone()),
+                    image: *image,
+                });
+                continue;
+            }
+            pending = Some((index, payload));
+        }
+        let payload = match pending {
+            Some((_, payload)) => payload,
+            // The last operation that ran was skipped, or wrote to its own output
+            None => ProcessorPayload::from_input(intermediate.unwrap_or_else(|| input.clone())),
+        };
+        Ok(with_routed(payload, routed))
+    }
+
+    /// Turns the output of the operation at `index` into the input for the
+    /// next one, which only works if it produced a single icon
+    fn next_input(
+        &self,
+        index: usize,
+        payload: ProcessorPayload,
+    ) -> Result<InputIcon, PipelineError> {
+        payload.into_input().ok_or_else(|| {
+            OperationFailure::new(
+                index,
+                &self.operations[index].operation,
+                ProcessorError::ConfigError(
+                    "This operation produces more than one output, so it has to be the last \
+                     operation"
+                        .to_string(),
+                ),
+            )
+            .into()
+        })
+    }
+
+    /// Checks every icon state in `input` is touched by some operation, if the
+    /// pipeline requires that. Operations skipped by their `when` still
+    /// count, since the config does reference the states, but disabled ones
+    /// don't
+    fn check_referenced(&self, input: &InputIcon) -> Result<(), PipelineError> {
+        let InputIcon::Dmi(icon) = input else {
+            return Ok(());
+        };
+        if !self.require_all_states_referenced {
+            return Ok(());
+        }
+        let unreferenced: Vec<String> = icon
+            .states
+            .iter()
+            .filter(|state| {
+                !self
+                    .operations
+                    .iter()
+                    .any(|step| step.enabled && step.operation.references_state(&state.name))
+            })
+            .map(|state| state.name.clone())
+            .collect();
+        if !unreferenced.is_empty() {
+            return Err(PipelineError::UnreferencedStates(unreferenced));
+        }
+        Ok(())
+    }
+
+    /// Whether `warning` is one the pipeline accepts, and so shouldn't be
+    /// reported
+    #[must_use]
+    pub fn allows(&self, warning: &ProcessorWarning) -> bool {
+        self.allow_warnings.contains(&warning.code())
+    }
+
+    /// Checks the `output` of the operation at `index` stays within the
+    /// input's folder, and isn't shared with an earlier operation
+    fn verify_output(&self, index: usize) -> Result<(), ProcessorError> {
+        let Some(output) = &self.operations[index].output else {
+            return Ok(());
+        };
+        let escapes = output
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if escapes || output.file_name().is_none() {
+            return Err(ProcessorError::ConfigError(format!(
+                "output \"{}\" has to be a file name or a path inside the input's folder",
+                output.display()
+            )));
+        }
+        if self.operations[..index]
+            .iter()
+            .any(|step| step.output.as_ref() == Some(output))
+        {
+            return Err(ProcessorError::ConfigError(format!(
+                "output \"{}\" is already written by an earlier operation",
+                output.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Indexes in to `operations`, in the order they run
+    fn execution_order(&self) -> Vec<usize> {
+        match &self.order {
+            Some(order) => order.iter().map(|position| position - 1).collect(),
+            None => (0..self.operations.len()).collect(),
+        }
+    }
+
+    fn verify_order(&self) -> Result<(), PipelineError> {
+        let Some(order) = &self.order else {
+            return Ok(());
+        };
+        let count = self.operations.len();
+        let mut seen = vec![false; count];
+        for &position in order {
+            if position == 0 || position > count {
+                return Err(PipelineError::InvalidOrder(format!(
+                    "{position} isn't an operation, there are only {count}"
+                )));
+            }
+            if std::mem::replace(&mut seen[position - 1], true) {
+                return Err(PipelineError::InvalidOrder(format!(
+                    "Operation {position} is listed more than once"
+                )));
+            }
+        }
+        if let Some(missing) = seen.iter().position(|seen| !seen) {
+            return Err(PipelineError::InvalidOrder(format!(
+                "Operation {} is never listed",
+                missing + 1
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// One failed operation in a pipeline
+#[derive(Debug)]
+pub struct OperationFailure {
+    /// Where the operation sits in the pipeline, starting from 0
+    pub index: usize,
+    /// The `mode` of the operation
+    pub mode: &'static str,
+    pub error: ProcessorError,
+}
+
+impl OperationFailure {
+    fn new(index: usize, operation: &IconOperation, error: ProcessorError) -> Self {
+        Self {
+            index,
+            mode: operation.mode_name(),
+            error,
+        }
+    }
+}
+
+/// Adds the outputs of operations that wrote to their own file to the
+/// pipeline's final `payload`
+fn with_routed(payload: ProcessorPayload, routed: Vec<NamedIcon>) -> ProcessorPayload {
+    if routed.is_empty() {
+        return payload;
+    }
+    let mut outputs = match payload {
+        ProcessorPayload::Single(image) => {
+            vec![NamedIcon {
+                path_hint: None,
+                name_hint: None,
+                path: None,
+                image: *image,
+            }]
+        }
+        ProcessorPayload::SingleNamed(named) => vec![*named],
+        ProcessorPayload::MultipleNamed(named) => named,
+        ProcessorPayload::ConfigWrapped(payload, text) => {
+            return ProcessorPayload::ConfigWrapped(Box::new(with_routed(*payload, routed)), text);
+        }
+    };
+    outputs.extend(routed);
+    ProcessorPayload::MultipleNamed(outputs)
+}
+
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error("Config has no operations")]
+    Empty,
+    #[error("Invalid Operation Order")]
+    InvalidOrder(String),
+    /// The outputs didn't pass the pipeline's `assert`
+    #[error("{0}")]
+    Unmet(ProcessorError),
+    #[error("Processing Failed")]
+    OperationsFailed(Vec<OperationFailure>),
+    /// The input has icon states no operation touches, and the pipeline
+    /// requires every state to be referenced
+    #[error("Unreferenced Icon States")]
+    UnreferencedStates(Vec<String>),
+}
+
+impl From<OperationFailure> for PipelineError {
+    fn from(failure: OperationFailure) -> Self {
+        Self::OperationsFailed(vec![failure])
+    }
+}
+
+impl PipelineError {
+    /// Which kind of error this is. For failed operations, that's the code of
+    /// the first one to fail
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            PipelineError::Empty => ErrorCode::EmptyPipeline,
+            PipelineError::InvalidOrder(_) => ErrorCode::InvalidConfig,
+            PipelineError::Unmet(error) => error.code(),
+            PipelineError::UnreferencedStates(_) => ErrorCode::UnreferencedStates,
+            PipelineError::OperationsFailed(failures) => {
+                failures
+                    .first()
+                    .map_or(ErrorCode::ProcessingFailed, |failure| failure.error.code())
+            }
+        }
+    }
+}
+
+impl UFE for PipelineError {
+    fn summary(&self) -> String {
+        format!("{self}")
+    }
+
+    fn reasons(&self) -> Option<Vec<String>> {
+        match self {
+            PipelineError::Empty => Some(vec!["The operations list is empty".to_string()]),
+            PipelineError::InvalidOrder(reason) => Some(vec![reason.clone()]),
+            PipelineError::Unmet(error) => error.reasons(),
+            PipelineError::UnreferencedStates(states) => {
+                Some(
+                    states
+                        .iter()
+                        .map(|state| {
+                            format!("Icon state \"{state}\" isn't touched by any operation")
+                        })
+                        .collect(),
+                )
+            }
+            PipelineError::OperationsFailed(failures) => {
+                let mut reasons = vec![];
+                for failure in failures {
+                    // Some summaries carry their details on later lines, which
+                    // get repeated in the reasons anyway
+                    let summary = failure.error.summary();
+                    let summary = summary.lines().next().unwrap_or_default();
+                    let summary = summary.trim_end_matches(':');
+                    reasons.push(format!(
+                        "Operation {} ({}): {summary}",
+                        failure.index + 1,
+                        failure.mode
+                    ));
+                    for reason in failure.error.reasons().unwrap_or_default() {
+                        reasons.push(format!("    {reason}"));
+                    }
+                    if let Some(help) = failure.error.helptext() {
+                        reasons.push(format!("    {help}"));
+                    }
+                }
+                Some(reasons)
+            }
+        }
+    }
+
+    fn helptext(&self) -> Option<String> {
+        match self {
+            PipelineError::Empty => {
+                Some(
+                    "Add at least one [[operations]] entry to the config, or pass \
+                     --allow-empty-configs to copy its input through unchanged"
+                        .to_string(),
+                )
+            }
+            PipelineError::InvalidOrder(_) => {
+                Some(
+                    "order has to list where every operation is written, counting from 1, exactly \
+                     once"
+                        .to_string(),
+                )
+            }
+            PipelineError::Unmet(error) => error.helptext(),
+            PipelineError::UnreferencedStates(_) => {
+                Some(
+                    "Target the states with an operation, or turn off \
+                     require_all_states_referenced if they can go through untouched"
+                        .to_string(),
+                )
+            }
+            PipelineError::OperationsFailed(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::operations::OutputImage;
+
+    fn test_input() -> InputIcon {
+        InputIcon::Dmi(Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "anim".to_string(),
+                frames: 4,
+                images: (0..4)
+                    .map(|_| {
+                        DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([9, 9, 9, 255])))
+                    })
+                    .collect(),
+                delay: Some(vec![1.0; 4]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn single_operation_configs_still_parse() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            mode = "Blur"
+            radius = 1.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(pipeline.operations.len(), 1);
+        assert_eq!(pipeline.operations[0].operation.mode_name(), "Blur");
+
+        let serialized = toml::to_string(&pipeline).unwrap();
+        assert!(serialized.starts_with("mode = \"Blur\""));
+        assert_eq!(toml::from_str::<Pipeline>(&serialized).unwrap(), pipeline);
+
+        let listed = pipeline.to_list_toml().unwrap();
+        assert_eq!(listed["operations"][0]["mode"].as_str(), Some("Blur"));
+        assert_eq!(listed.try_into::<Pipeline>().unwrap(), pipeline);
+    }
+
+    #[test]
+    fn operations_run_in_sequence() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "DropFrames"
+            stride = 2
+
+            [[operations]]
+            mode = "Blur"
+            radius = 1.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+            pipeline
+        );
+
+        let ProcessorPayload::Single(output) = pipeline
+            .run(&test_input(), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output.states[0].frames, 2);
+    }
+
+    #[test]
+    fn order_overrides_how_operations_are_written() {
+        let input = InputIcon::Dmi(Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "gray".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    1,
+                    1,
+                    Rgba([128, 128, 128, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let run = |order: &str| {
+            let pipeline: Pipeline = toml::from_str(&format!(
+                r#"
+                {order}
+
+                [[operations]]
+                mode = "Gamma"
+                gamma = 2.2
+
+                [[operations]]
+                mode = "Posterize"
+                levels = 2
+                "#
+            ))
+            .unwrap();
+            assert_eq!(
+                toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+                pipeline
+            );
+            let ProcessorPayload::Single(output) =
+                pipeline.run(&input, OperationMode::Standard).unwrap()
+            else {
+                panic!("Expected a single icon");
+            };
+            let OutputImage::Dmi(output) = *output else {
+                panic!("Expected a dmi");
+            };
+            output.states[0].images[0].to_rgba8().get_pixel(0, 0).0[0]
+        };
+
+        // Darkened below the middle before posterizing
+        assert_eq!(run(""), 0);
+        // Posterized up to white, which gamma leaves alone
+        assert_eq!(run("order = [2, 1]"), 255);
+    }
+
+    #[test]
+    fn order_lists_every_operation_once() {
+        for order in ["[1]", "[1, 1]", "[1, 3]", "[0, 1]", "[1, 2, 2]"] {
+            let pipeline: Pipeline = toml::from_str(&format!(
+                r#"
+                order = {order}
+
+                [[operations]]
+                mode = "Blur"
+                radius = 1.0
+
+                [[operations]]
+                mode = "Passthrough"
+                "#
+            ))
+            .unwrap();
+            assert!(
+                matches!(pipeline.verify(), Err(PipelineError::InvalidOrder(_))),
+                "{order}"
+            );
+        }
+    }
+
+    #[test]
+    fn operations_only_run_when_their_condition_passes() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "DropFrames"
+            stride = 2
+            when = { dims = "4x4" }
+
+            [[operations]]
+            mode = "DropFrames"
+            stride = 2
+            when = { dims = "32x32" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+            pipeline
+        );
+
+        let ProcessorPayload::Single(output) = pipeline
+            .run(&test_input(), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        // Only the first one ran
+        assert_eq!(output.states[0].frames, 2);
+    }
+
+    #[test]
+    fn disabled_operations_are_left_out() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "CapAlpha"
+            max_alpha = 10
+            enabled = false
+
+            [[operations]]
+            mode = "DropFrames"
+            stride = 2
+            "#,
+        )
+        .unwrap();
+        assert!(!pipeline.operations[0].enabled);
+        assert!(pipeline.operations[1].enabled);
+        let written = toml::to_string(&pipeline).unwrap();
+        assert_eq!(written.matches("enabled").count(), 1, "{written}");
+        assert_eq!(toml::from_str::<Pipeline>(&written).unwrap(), pipeline);
+
+        let ProcessorPayload::Single(output) = pipeline
+            .run(&test_input(), OperationMode::Standard)
+            .unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let (OutputImage::Dmi(output), InputIcon::Dmi(input)) = (*output, test_input()) else {
+            panic!("Expected a dmi");
+        };
+        // DropFrames ran, but CapAlpha didn't touch the alpha
+        assert_eq!(output.states[0].frames, 2);
+        assert_eq!(output.states[0].images[0], input.states[0].images[0]);
+
+        // A disabled operation isn't checked either
+        let broken: Pipeline = toml::from_str(
+            r#"
+            mode = "AlphaBlur"
+            radius = -1.0
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert!(broken.verify().unwrap().is_empty());
+        let ProcessorPayload::Single(output) =
+            broken.run(&test_input(), OperationMode::Standard).unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn skipped_operations_leave_the_icon_alone() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            mode = "Blur"
+            radius = 1.0
+            when = { has_state = "walk" }
+            "#,
+        )
+        .unwrap();
+        assert!(pipeline.operations[0].when.is_some());
+
+        let input = test_input();
+        let ProcessorPayload::Single(output) =
+            pipeline.run(&input, OperationMode::Standard).unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let (OutputImage::Dmi(output), InputIcon::Dmi(input)) = (*output, input) else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn multiple_outputs_can_be_followed_by_disabled_operations() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "ContactState"
+
+            [[operations]]
+            mode = "Blur"
+            radius = 1.0
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        let payload = pipeline
+            .run(&test_input(), OperationMode::Standard)
+            .unwrap();
+        let ProcessorPayload::MultipleNamed(outputs) = payload else {
+            panic!("Expected named icons");
+        };
+        assert_eq!(outputs.len(), 2);
+
+        // Once the next operation is enabled, it has nothing to run on
+        let enabled = toml::to_string(&pipeline)
+            .unwrap()
+            .replace("enabled = false", "");
+        let enabled: Pipeline = toml::from_str(&enabled).unwrap();
+        let Err(PipelineError::OperationsFailed(failures)) =
+            enabled.run(&test_input(), OperationMode::Standard)
+        else {
+            panic!("Expected the pipeline to fail");
+        };
+        assert_eq!(failures[0].index, 0);
+    }
+
+    #[test]
+    fn second_operation_failure_is_nested() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "Blur"
+            radius = 1.0
+
+            [[operations]]
+            mode = "Overlay"
+            overlay_state = "missing"
+            "#,
+        )
+        .unwrap();
+
+        let Err(PipelineError::OperationsFailed(failures)) =
+            pipeline.run(&test_input(), OperationMode::Standard)
+        else {
+            panic!("Expected the pipeline to fail");
+        };
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].index, 1);
+        assert_eq!(failures[0].mode, "Overlay");
+        assert!(matches!(failures[0].error, ProcessorError::ConfigError(_)));
+
+        let reasons = PipelineError::OperationsFailed(failures).reasons().unwrap();
+        assert!(reasons[0].starts_with("Operation 2 (Overlay): "));
+        assert!(reasons[1].starts_with("    "));
+    }
+
+    #[test]
+    fn unmatched_targets_fail_the_operation() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            mode = "Blur"
+            radius = 1.0
+            target_states = ["anmi"]
+            "#,
+        )
+        .unwrap();
+
+        let Err(PipelineError::OperationsFailed(failures)) =
+            pipeline.run(&test_input(), OperationMode::Standard)
+        else {
+            panic!("Expected the pipeline to fail");
+        };
+        assert!(matches!(
+            &failures[0].error,
+            ProcessorError::UnmatchedTargets(targets) if targets == &["anmi"]
+        ));
+    }
+
+    #[test]
+    fn every_invalid_operation_is_reported() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "Blur"
+            radius = -1.0
+
+            [[operations]]
+            mode = "Blur"
+            radius = 1.0
+
+            [[operations]]
+            mode = "DropFrames"
+            "#,
+        )
+        .unwrap();
+
+        let Err(PipelineError::OperationsFailed(failures)) =
+            pipeline.run(&test_input(), OperationMode::Standard)
+        else {
+            panic!("Expected the pipeline to fail");
+        };
+        let failed: Vec<(usize, &str)> = failures
+            .iter()
+            .map(|failure| (failure.index, failure.mode))
+            .collect();
+        assert_eq!(failed, vec![(0, "Blur"), (2, "DropFrames")]);
+    }
+
+    #[test]
+    fn failed_assertions_fail_the_pipeline() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            mode = "Passthrough"
+            assert = { width = 32, required_states = ["anim"] }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+            pipeline
+        );
+
+        let Err(PipelineError::Unmet(error)) = pipeline.run(&test_input(), OperationMode::Standard)
+        else {
+            panic!("Expected the assertions to fail");
+        };
+        assert_eq!(error.code(), ErrorCode::AssertionFailed);
+        assert_eq!(
+            error.reasons().unwrap(),
+            vec!["Output is 4 pixels wide, not 32"]
+        );
+
+        let passing: Pipeline = toml::from_str(
+            r#"
+            assert = { width = 4, height = 4 }
+
+            [[operations]]
+            mode = "Passthrough"
+            "#,
+        )
+        .unwrap();
+        assert!(passing.run(&test_input(), OperationMode::Standard).is_ok());
+    }
+
+    #[test]
+    fn unreferenced_states_fail_when_required() {
+        let state = |name: &str| {
+            IconState {
+                name: name.to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::new(1, 1))],
+                ..Default::default()
+            }
+        };
+        let input = InputIcon::Dmi(Icon {
+            width: 1,
+            height: 1,
+            states: vec![state("wall"), state("floor"), state("extra")],
+            ..Default::default()
+        });
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            require_all_states_referenced = true
+
+            [[operations]]
+            mode = "Gamma"
+            gamma = 2.2
+            target_states = ["wall"]
+
+            [[operations]]
+            mode = "SwapChannels"
+            preset = "rgb_to_bgr"
+            target_states = ["floor"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+            pipeline
+        );
+
+        let Err(error) = pipeline.run(&input, OperationMode::Standard) else {
+            panic!("Expected the pipeline to fail");
+        };
+        let PipelineError::UnreferencedStates(states) = &error else {
+            panic!("Expected unreferenced states, got {error:?}");
+        };
+        assert_eq!(states, &vec!["extra".to_string()]);
+        assert_eq!(error.code(), ErrorCode::UnreferencedStates);
+
+        // Off by default, and an operation on every state references them all
+        let relaxed = Pipeline {
+            require_all_states_referenced: false,
+            ..pipeline.clone()
+        };
+        assert!(relaxed.run(&input, OperationMode::Standard).is_ok());
+        let single: Pipeline = toml::from_str(
+            r#"
+            mode = "Gamma"
+            gamma = 2.2
+            require_all_states_referenced = true
+            "#,
+        )
+        .unwrap();
+        assert!(single.require_all_states_referenced);
+        assert!(single.run(&input, OperationMode::Standard).is_ok());
+    }
+
+    #[test]
+    fn warnings_say_which_operation_raised_them() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "Passthrough"
+
+            [[operations]]
+            mode = "Blur"
+            radius = 0.0
+            "#,
+        )
+        .unwrap();
+
+        let warnings = pipeline.verify().unwrap();
+        let [ProcessorWarning::InOperation {
+            index,
+            mode,
+            warning,
+        }] = warnings.as_slice()
+        else {
+            panic!("Expected one warning, got {warnings:?}");
+        };
+        assert_eq!((*index, *mode), (1, "Blur"));
+        assert!(matches!(**warning, ProcessorWarning::SuspiciousConfig(_)));
+        assert!(warnings[0].reasons().unwrap()[0].starts_with("Operation 2 (Blur)"));
+
+        // Warnings don't stop anything
+        assert!(pipeline.run(&test_input(), OperationMode::Standard).is_ok());
+    }
+
+    #[test]
+    fn operations_can_write_their_own_output() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "SwapChannels"
+            preset = "rgb_to_bgr"
+            output = "bgr/anim.dmi"
+
+            [[operations]]
+            mode = "CapAlpha"
+            max_alpha = 100
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            pipeline.operations[0].output,
+            Some(PathBuf::from("bgr/anim.dmi"))
+        );
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+            pipeline
+        );
+
+        let input = InputIcon::Dmi(Icon {
+            width: 1,
+            height: 1,
+            states: vec![IconState {
+                name: "anim".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    1,
+                    1,
+                    Rgba([200, 0, 50, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let ProcessorPayload::MultipleNamed(outputs) =
+            pipeline.run(&input, OperationMode::Standard).unwrap()
+        else {
+            panic!("Expected named outputs");
+        };
+        let [main, swapped] = &outputs[..] else {
+            panic!("Expected two outputs, got {outputs:?}");
+        };
+        let pixel = |named: &NamedIcon| {
+            let OutputImage::Dmi(icon) = &named.image else {
+                panic!("Expected a dmi");
+            };
+            *icon.states[0].images[0].to_rgba8().get_pixel(0, 0)
+        };
+        // The swap went to its own file, so CapAlpha got the untouched input
+        assert_eq!(main.path, None);
+        assert_eq!(pixel(main), Rgba([200, 0, 50, 100]));
+        assert_eq!(swapped.path, Some(PathBuf::from("bgr/anim.dmi")));
+        assert_eq!(pixel(swapped), Rgba([50, 0, 200, 255]));
+    }
+
+    #[test]
+    fn outputs_have_to_stay_in_the_input_folder() {
+        let pipeline = |first: &str, second: &str| {
+            toml::from_str::<Pipeline>(&format!(
+                r#"
+                [[operations]]
+                mode = "Passthrough"
+                output = "{first}"
+
+                [[operations]]
+                mode = "Passthrough"
+                output = "{second}"
+                "#
+            ))
+            .unwrap()
+        };
+        assert!(pipeline("a.dmi", "sub/b.dmi").verify().is_ok());
+        for (first, second) in [
+            ("../a.dmi", "b.dmi"),
+            ("/a.dmi", "b.dmi"),
+            ("a.dmi", "./a.dmi/.."),
+            ("a.dmi", "a.dmi"),
+        ] {
+            let Err(PipelineError::OperationsFailed(failures)) = pipeline(first, second).verify()
+            else {
+                panic!("Expected {first} and {second} to be rejected");
+            };
+            assert_eq!(failures.len(), 1);
+        }
+    }
+
+    #[test]
+    fn explain_lists_operations_in_the_order_they_run() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            order = [2, 1, 3]
+
+            [[operations]]
+            mode = "Blur"
+            radius = 1.5
+            target_states = ["glow"]
+            when = { has_state = "glow" }
+
+            [[operations]]
+            mode = "Passthrough"
+            output = "copy.dmi"
+
+            [[operations]]
+            mode = "Gamma"
+            gamma = 2.0
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            pipeline.explain(),
+            vec![
+                "2. Passthrough, written to copy.dmi",
+                "1. Blur (kind = \"gaussian\", radius = 1.5, target_states = [\"glow\"]), when { \
+                 has_state = \"glow\" }",
+                "3. Gamma (gamma = 2.0), disabled so never run",
+            ]
+        );
+    }
+
+    #[test]
+    fn allowed_warnings_are_dropped() {
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            allow_warnings = ["suspicious_config"]
+
+            [[operations]]
+            mode = "Blur"
+            radius = 0.0
+
+            [[operations]]
+            mode = "ValidateMask"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(pipeline.allow_warnings, vec![WarningCode::SuspiciousConfig]);
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&pipeline).unwrap()).unwrap(),
+            pipeline
+        );
+        assert!(pipeline.verify().unwrap().is_empty());
+
+        // Other warnings still come through
+        let input = InputIcon::Dmi(Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "mask".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([255, 255, 255, 128]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let raised = std::cell::RefCell::new(vec![]);
+        pipeline
+            .run_with_progress(&input, OperationMode::Standard, |event| {
+                if let ProcessEvent::Warning(warning) = event {
+                    raised.borrow_mut().push(warning.code());
+                }
+            })
+            .unwrap();
+        assert_eq!(raised.into_inner(), vec![WarningCode::SoftMask]);
+
+        let single: Pipeline = toml::from_str(
+            r#"
+            mode = "Blur"
+            radius = 0.0
+            allow_warnings = ["suspicious_config"]
+            "#,
+        )
+        .unwrap();
+        assert!(single.verify().unwrap().is_empty());
+        assert!(toml::from_str::<Pipeline>(
+            r#"
+            mode = "Blur"
+            radius = 1.0
+            allow_warnings = ["not_a_warning"]
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn output_format_round_trips() {
+        let single: Pipeline = toml::from_str(
+            r#"
+            mode = "Blur"
+            radius = 1.0
+            output_format = "Gif"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(single.operations.len(), 1);
+        assert_eq!(single.output_format, OutputFormat::Gif);
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&single).unwrap()).unwrap(),
+            single
+        );
+
+        let list: Pipeline = toml::from_str(
+            r#"
+            output_format = "PngSheet"
+
+            [[operations]]
+            mode = "Passthrough"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(list.output_format, OutputFormat::PngSheet);
+        assert_eq!(
+            toml::from_str::<Pipeline>(&toml::to_string(&list).unwrap()).unwrap(),
+            list
+        );
+
+        // Left out when it's the default
+        let default: Pipeline = toml::from_str("mode = \"Passthrough\"").unwrap();
+        assert_eq!(default.output_format, OutputFormat::Dmi);
+        assert!(!toml::to_string(&default).unwrap().contains("output_format"));
+    }
+
+    #[test]
+    fn empty_pipeline_fails() {
+        let pipeline: Pipeline = toml::from_str("operations = []").unwrap();
+        assert!(matches!(
+            pipeline.run(&test_input(), OperationMode::Standard),
+            Err(PipelineError::Empty)
+        ));
+    }
+
+    #[test]
+    fn operations_share_context() {
+        let mut image = RgbaImage::new(8, 8);
+        image.put_pixel(3, 5, Rgba([100, 100, 100, 255]));
+        let input = InputIcon::Dmi(Icon {
+            width: 8,
+            height: 8,
+            states: vec![IconState {
+                name: "dot".to_string(),
+                images: vec![DynamicImage::ImageRgba8(image)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        // Trim leaves its bounds behind for Pad to put the icon back together
+        // with, even with something else in between
+        let pipeline: Pipeline = toml::from_str(
+            r#"
+            [[operations]]
+            mode = "Trim"
+
+            [[operations]]
+            mode = "HsvShift"
+            value = 2.0
+
+            [[operations]]
+            mode = "Pad"
+            restore_trim = true
+            "#,
+        )
+        .unwrap();
+
+        let ProcessorPayload::Single(output) =
+            pipeline.run(&input, OperationMode::Standard).unwrap()
+        else {
+            panic!("Expected a single icon");
+        };
+        let OutputImage::Dmi(output) = *output else {
+            panic!("Expected a dmi");
+        };
+        assert_eq!((output.width, output.height), (8, 8));
+        let image = output.states[0].images[0].to_rgba8();
+        assert_eq!(image.dimensions(), (8, 8));
+        assert_eq!(*image.get_pixel(3, 5), Rgba([200, 200, 200, 255]));
+        assert_eq!(image.pixels().filter(|pixel| pixel.0[3] != 0).count(), 1);
+
+        // Each run starts with a fresh context
+        let pad_only: Pipeline = toml::from_str(
+            r#"
+            mode = "Pad"
+            restore_trim = true
+            "#,
+        )
+        .unwrap();
+        assert!(pad_only.run(&input, OperationMode::Standard).is_err());
+    }
+}
@@ -0,0 +1,317 @@
+//! Entry points for running a config over an icon held in memory, for
+//! embedding hypnagogic somewhere without going through the filesystem
+
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use thiserror::Error;
+use user_error::UFE;
+
+use crate::operations::error::ProcessorWarning;
+use crate::operations::pipeline::{Pipeline, PipelineError};
+use crate::operations::{InputError, InputIcon, OperationMode, OutputError, ProcessorPayload};
+
+#[derive(Debug, Error)]
+pub enum ProcessError {
+    #[error("Image Parsing Failed")]
+    Input(#[from] InputError),
+    #[error("{0}")]
+    Pipeline(#[from] PipelineError),
+    #[error("Output Failed")]
+    Output(#[from] OutputError),
+    #[error("Multiple Outputs")]
+    MultipleOutputs,
+    #[error("Processing Panicked")]
+    Panicked(String),
+}
+
+impl UFE for ProcessError {
+    fn summary(&self) -> String {
+        format!("{self}")
+    }
+
+    fn reasons(&self) -> Option<Vec<String>> {
+        match self {
+            ProcessError::Input(error) => error.reasons(),
+            ProcessError::Pipeline(error) => error.reasons(),
+            ProcessError::Output(error) => error.reasons(),
+            ProcessError::MultipleOutputs => {
+                Some(vec!["The config produces more than one output file, but \
+                           only one can be returned"
+                    .to_string()])
+            }
+            ProcessError::Panicked(message) => Some(vec![message.clone()]),
+        }
+    }
+
+    fn helptext(&self) -> Option<String> {
+        match self {
+            ProcessError::Input(error) => error.helptext(),
+            ProcessError::Pipeline(error) => error.helptext(),
+            ProcessError::Output(error) => error.helptext(),
+            ProcessError::MultipleOutputs => {
+                Some("Use the CLI for configs that split their output into files".to_string())
+            }
+            ProcessError::Panicked(_) => {
+                Some("This is a program error, not a config error! Please report!".to_string())
+            }
+        }
+    }
+}
+
+/// Something that happened while processing, for showing progress.
+///
+/// The pipeline only knows about operations, so file events are sent by
+/// whatever is reading and writing the files
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ProcessEvent {
+    /// Started reading the file at this path
+    StartedFile(PathBuf),
+    /// An operation in a pipeline is about to run
+    StartedOperation {
+        /// Where the operation sits in the pipeline, starting from 0
+        index: usize,
+        /// The `mode` of the operation
+        mode: &'static str,
+    },
+    /// An operation in a pipeline ran successfully
+    FinishedOperation { index: usize, mode: &'static str },
+    /// An operation in a pipeline raised a warning about its input, always a
+    /// `ProcessorWarning::InOperation`
+    Warning(ProcessorWarning),
+    /// Finished writing an output to this path
+    WroteOutput(PathBuf),
+}
+
+/// Runs `config` over the icon in `input`, returning the encoded output.
+/// `extension` is the kind of file `input` holds, either "png" or "dmi".
+///
+/// Only configs producing a single output are supported, since there's
+/// nowhere to put the rest
+/// # Errors
+/// Returns a `ProcessError` if the input can't be read, the config fails, or
+/// the config produces more than one output
+pub fn process_bytes(
+    input: &[u8],
+    extension: &str,
+    config: &Pipeline,
+) -> Result<Vec<u8>, ProcessError> {
+    process_bytes_with_progress(input, extension, config, |_| {})
+}
+
+/// `process_bytes`, but calls `progress` as each operation starts and
+/// finishes
+/// # Errors
+/// Same as `process_bytes`
+pub fn process_bytes_with_progress(
+    input: &[u8],
+    extension: &str,
+    config: &Pipeline,
+    progress: impl Fn(ProcessEvent),
+) -> Result<Vec<u8>, ProcessError> {
+    let input = InputIcon::from_reader(&mut Cursor::new(input), extension)?;
+    let image = match config.run_with_progress(&input, OperationMode::Standard, progress)? {
+        ProcessorPayload::Single(image) => *image,
+        ProcessorPayload::SingleNamed(named) => named.image,
+        ProcessorPayload::MultipleNamed(_) | ProcessorPayload::ConfigWrapped(..) => {
+            return Err(ProcessError::MultipleOutputs);
+        }
+    };
+    let mut output = Cursor::new(vec![]);
+    image.write_as(config.output_format, &mut output)?;
+    Ok(output.into_inner())
+}
+
+/// `process_bytes`, but run on its own thread so the caller's executor isn't
+/// blocked by the actual image work. Doesn't depend on any particular async
+/// runtime. If processing panics, the panic is caught and returned as
+/// `ProcessError::Panicked`
+#[cfg(feature = "async")]
+pub fn process_bytes_async(
+    input: Vec<u8>,
+    extension: String,
+    config: Pipeline,
+) -> impl std::future::Future<Output = Result<Vec<u8>, ProcessError>> {
+    on_own_thread(move || process_bytes(&input, &extension, &config))
+}
+
+/// Runs `work` on a new thread, as a future that finishes when it does. A
+/// panic in `work` finishes it with `ProcessError::Panicked` instead of
+/// leaving it pending forever
+#[cfg(feature = "async")]
+fn on_own_thread(
+    work: impl FnOnce() -> Result<Vec<u8>, ProcessError> + Send + 'static,
+) -> impl std::future::Future<Output = Result<Vec<u8>, ProcessError>> {
+    use std::future::Future;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::task::{Context, Poll, Waker};
+
+    type Shared = Arc<Mutex<(Option<Result<Vec<u8>, ProcessError>>, Option<Waker>)>>;
+
+    struct Processing(Shared);
+
+    impl Future for Processing {
+        type Output = Result<Vec<u8>, ProcessError>;
+
+        fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+            let mut shared = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(result) = shared.0.take() {
+                return Poll::Ready(result);
+            }
+            shared.1 = Some(context.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    let shared: Shared = Arc::default();
+    let worker = Arc::clone(&shared);
+    std::thread::spawn(move || {
+        // Nothing `work` touches is seen again after a panic, besides the
+        // message
+        let result = catch_unwind(AssertUnwindSafe(work)).unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(ToString::to_string)
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".to_string());
+            Err(ProcessError::Panicked(message))
+        });
+        let mut shared = worker.lock().unwrap_or_else(PoisonError::into_inner);
+        shared.0 = Some(result);
+        if let Some(waker) = shared.1.take() {
+            waker.wake();
+        }
+    });
+    Processing(shared)
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn dmi_bytes() -> Vec<u8> {
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            states: vec![IconState {
+                name: "red".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    2,
+                    2,
+                    Rgba([255, 0, 0, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut bytes = vec![];
+        icon.save(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn hue_shift() -> Pipeline {
+        toml::from_str("mode = \"HsvShift\"\nhue = 120.0").unwrap()
+    }
+
+    fn assert_green(output: &[u8]) {
+        let icon = Icon::load(output).unwrap();
+        assert_eq!(icon.states[0].name, "red");
+        assert_eq!(
+            icon.states[0].images[0].get_pixel(0, 0),
+            Rgba([0, 255, 0, 255])
+        );
+    }
+
+    #[test]
+    fn processes_dmi_in_memory() {
+        assert_green(&process_bytes(&dmi_bytes(), "dmi", &hue_shift()).unwrap());
+    }
+
+    #[test]
+    fn progress_follows_operations() {
+        use std::cell::RefCell;
+
+        let config: Pipeline = toml::from_str(
+            "[[operations]]\nmode = \"HsvShift\"\nhue = 60.0\n[[operations]]\nmode = \
+             \"HsvShift\"\nhue = 60.0",
+        )
+        .unwrap();
+        let events = RefCell::new(vec![]);
+        let output = process_bytes_with_progress(&dmi_bytes(), "dmi", &config, |event| {
+            events.borrow_mut().push(event);
+        })
+        .unwrap();
+        assert_green(&output);
+
+        let mode = "HsvShift";
+        assert_eq!(
+            events.into_inner(),
+            vec![
+                ProcessEvent::StartedOperation { index: 0, mode },
+                ProcessEvent::FinishedOperation { index: 0, mode },
+                ProcessEvent::StartedOperation { index: 1, mode },
+                ProcessEvent::FinishedOperation { index: 1, mode },
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_input_is_reported() {
+        assert!(matches!(
+            process_bytes(b"not an icon", "dmi", &hue_shift()),
+            Err(ProcessError::Input(_))
+        ));
+    }
+
+    /// Polls `future` on this thread until it's done
+    #[cfg(feature = "async")]
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+        use std::thread::Thread;
+
+        struct Unpark(Thread);
+
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(Unpark(std::thread::current())).into();
+        let mut context = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => break output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn processes_dmi_asynchronously() {
+        let output = block_on(process_bytes_async(
+            dmi_bytes(),
+            "dmi".to_string(),
+            hue_shift(),
+        ));
+        assert_green(&output.unwrap());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn panics_finish_the_future() {
+        let output = block_on(on_own_thread(|| panic!("out of pixels")));
+        let Err(ProcessError::Panicked(message)) = output else {
+            panic!("Expected a panic, got {output:?}");
+        };
+        assert_eq!(message, "out of pixels");
+    }
+}
@@ -0,0 +1,144 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::util::color::{alpha, rgb, TRANSPARENT};
+
+/// How the color of a source pixel combines with the backdrop under it.
+/// Follows the separable blend modes from the W3C compositing spec
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    /// Plain alpha-over, the source covers the backdrop
+    #[default]
+    Normal,
+    /// Darkens, good for shading
+    Multiply,
+    /// Lightens, good for soft lighting
+    Screen,
+    /// Adds the colors together, good for glows
+    Add,
+    /// Multiplies or screens depending on the backdrop, boosting contrast
+    Overlay,
+    /// Takes the source away from the backdrop
+    Subtract,
+}
+
+impl BlendMode {
+    /// Blends one color channel, with both values in the 0 to 1 range
+    fn blend_channel(self, backdrop: f32, source: f32) -> f32 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Add => (backdrop + source).min(1.0),
+            BlendMode::Overlay => {
+                if backdrop <= 0.5 {
+                    2.0 * backdrop * source
+                } else {
+                    1.0 - 2.0 * (1.0 - backdrop) * (1.0 - source)
+                }
+            }
+            BlendMode::Subtract => (backdrop - source).max(0.0),
+        }
+    }
+}
+
+/// Composites `source` over `backdrop` using `mode`.
+///
+/// Blending only happens where both pixels have coverage; where the backdrop is
+/// transparent the source shows through as is, so nothing gets blended against
+/// invisible colors
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn blend_pixel(backdrop: Rgba<u8>, source: Rgba<u8>, mode: BlendMode) -> Rgba<u8> {
+    let to_unit = |value: u8| f32::from(value) / 255.0;
+    let to_byte = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+
+    let backdrop_alpha = to_unit(alpha(backdrop));
+    let source_alpha = to_unit(alpha(source));
+    let out_alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+    if out_alpha <= 0.0 {
+        return TRANSPARENT;
+    }
+
+    let mut out = [0; 3];
+    for ((out, backdrop_color), source_color) in out.iter_mut().zip(rgb(backdrop)).zip(rgb(source))
+    {
+        let (backdrop_color, source_color) = (to_unit(backdrop_color), to_unit(source_color));
+        let blended = mode.blend_channel(backdrop_color, source_color);
+        let premultiplied = source_alpha * (1.0 - backdrop_alpha) * source_color
+            + source_alpha * backdrop_alpha * blended
+            + (1.0 - source_alpha) * backdrop_alpha * backdrop_color;
+        *out = to_byte(premultiplied / out_alpha);
+    }
+    let [red, green, blue] = out;
+    Rgba([red, green, blue, to_byte(out_alpha)])
+}
+
+/// Composites `source` over `backdrop` pixel by pixel, anchored at the top
+/// left. Anything in `source` past the edges of `backdrop` is ignored
+#[must_use]
+pub fn blend_images(backdrop: &DynamicImage, source: &DynamicImage, mode: BlendMode) -> RgbaImage {
+    let mut out = backdrop.to_rgba8();
+    let (width, height) = source.dimensions();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        if x < width && y < height {
+            *pixel = blend_pixel(*pixel, source.get_pixel(x, y), mode);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BACKDROP: Rgba<u8> = Rgba([200, 100, 50, 255]);
+    const SOURCE: Rgba<u8> = Rgba([100, 200, 250, 255]);
+
+    #[test]
+    fn opaque_modes_match_hand_computed() {
+        let cases = [
+            (BlendMode::Normal, [100, 200, 250]),
+            // 200 * 100 / 255, 100 * 200 / 255, 50 * 250 / 255
+            (BlendMode::Multiply, [78, 78, 49]),
+            // b + s - b * s
+            (BlendMode::Screen, [222, 222, 251]),
+            // Everything overflows and clamps
+            (BlendMode::Add, [255, 255, 255]),
+            // Screened where the backdrop is light, multiplied where it's dark
+            (BlendMode::Overlay, [188, 157, 98]),
+            (BlendMode::Subtract, [100, 0, 0]),
+        ];
+        for (mode, [r, g, b]) in cases {
+            assert_eq!(
+                blend_pixel(BACKDROP, SOURCE, mode),
+                Rgba([r, g, b, 255]),
+                "{mode:?}"
+            );
+        }
+        assert_eq!(
+            blend_pixel(
+                Rgba([100, 50, 20, 255]),
+                Rgba([100, 60, 250, 255]),
+                BlendMode::Add
+            ),
+            Rgba([200, 110, 255, 255])
+        );
+    }
+
+    #[test]
+    fn alpha_is_respected() {
+        // Half transparent source lands halfway between the two
+        assert_eq!(
+            blend_pixel(BACKDROP, Rgba([100, 200, 250, 128]), BlendMode::Normal),
+            Rgba([150, 150, 150, 255])
+        );
+        for mode in [BlendMode::Multiply, BlendMode::Add, BlendMode::Subtract] {
+            // Transparent source leaves the backdrop alone
+            assert_eq!(blend_pixel(BACKDROP, Rgba([0, 0, 0, 0]), mode), BACKDROP);
+            // Transparent backdrop just shows the source
+            assert_eq!(blend_pixel(Rgba([0, 0, 0, 0]), SOURCE, mode), SOURCE);
+        }
+    }
+}
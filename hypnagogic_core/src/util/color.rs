@@ -1,6 +1,6 @@
 use std::num::ParseIntError;
 
-use image::DynamicImage;
+use image::{DynamicImage, Rgba};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -136,6 +136,24 @@ impl From<[u8; 4]> for Color {
     }
 }
 
+impl From<Rgba<u8>> for Color {
+    fn from(pixel: Rgba<u8>) -> Self {
+        let Rgba([red, green, blue, alpha]) = pixel;
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+}
+
+impl From<Color> for Rgba<u8> {
+    fn from(color: Color) -> Self {
+        Rgba([color.red, color.green, color.blue, color.alpha])
+    }
+}
+
 impl TryFrom<Color> for [u8; 3] {
     type Error = ColorError;
 
@@ -215,6 +233,86 @@ pub enum HexConversionError {
     BadHex(#[from] ParseIntError),
 }
 
+/// A fully transparent pixel
+pub const TRANSPARENT: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+// Pixel loops should go through these rather than indexing channels by hand,
+// so which channel is which only lives in one place
+
+/// The alpha channel of a pixel
+#[must_use]
+pub fn alpha(pixel: Rgba<u8>) -> u8 {
+    let Rgba([_, _, _, alpha]) = pixel;
+    alpha
+}
+
+/// The color channels of a pixel, without its alpha
+#[must_use]
+pub fn rgb(pixel: Rgba<u8>) -> [u8; 3] {
+    let Rgba([red, green, blue, _]) = pixel;
+    [red, green, blue]
+}
+
+/// The same pixel with its alpha channel replaced
+#[must_use]
+pub fn with_alpha(pixel: Rgba<u8>, alpha: u8) -> Rgba<u8> {
+    let [red, green, blue] = rgb(pixel);
+    Rgba([red, green, blue, alpha])
+}
+
+/// Whether a pixel can't be seen at all
+#[must_use]
+pub fn is_transparent(pixel: Rgba<u8>) -> bool {
+    alpha(pixel) == 0
+}
+
+/// Converts color channels to hue (in degrees, 0 to 360), saturation and value
+/// (both 0 to 1)
+#[must_use]
+#[allow(clippy::float_cmp)] // max is always exactly one of the channels
+pub fn rgb_to_hsv(rgb: [u8; 3]) -> [f32; 3] {
+    let [red, green, blue] = rgb.map(|channel| f32::from(channel) / 255.0);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let chroma = max - min;
+
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == red {
+        60.0 * ((green - blue) / chroma).rem_euclid(6.0)
+    } else if max == green {
+        60.0 * ((blue - red) / chroma + 2.0)
+    } else {
+        60.0 * ((red - green) / chroma + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+    [hue, saturation, max]
+}
+
+/// The inverse of `rgb_to_hsv`. Hue is wrapped into 0 to 360 first, saturation
+/// and value are clamped to 0 to 1
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn hsv_to_rgb(hsv: [f32; 3]) -> [u8; 3] {
+    let [hue, saturation, value] = hsv;
+    let hue = hue.rem_euclid(360.0);
+    let (saturation, value) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+
+    let chroma = value * saturation;
+    let sector = hue / 60.0;
+    let second = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (red, green, blue) = match sector as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let lightness = value - chroma;
+    [red, green, blue].map(|channel| ((channel + lightness) * 255.0).round() as u8)
+}
+
 pub fn fill_image_color(image: &mut DynamicImage, color: Color) {
     let mut buffer = image.clone().into_rgba8();
     for image::Rgba([r, g, b, a]) in buffer.pixels_mut() {
@@ -254,4 +352,48 @@ mod tests {
         let color = Color::from_hex_str(hex).unwrap();
         assert_eq!(color, Color::new(240, 15, 15, 255));
     }
+
+    const PIXEL: Rgba<u8> = Rgba([10, 20, 30, 40]);
+
+    #[test]
+    fn alpha_reads_the_last_channel() {
+        assert_eq!(alpha(PIXEL), 40);
+        assert_eq!(alpha(TRANSPARENT), 0);
+    }
+
+    #[test]
+    fn rgb_drops_alpha() {
+        assert_eq!(rgb(PIXEL), [10, 20, 30]);
+    }
+
+    #[test]
+    fn with_alpha_keeps_color() {
+        assert_eq!(with_alpha(PIXEL, 255), Rgba([10, 20, 30, 255]));
+        assert_eq!(with_alpha(PIXEL, 0), Rgba([10, 20, 30, 0]));
+    }
+
+    #[test]
+    fn transparency_is_alpha_only() {
+        assert!(is_transparent(TRANSPARENT));
+        assert!(is_transparent(with_alpha(PIXEL, 0)));
+        assert!(!is_transparent(PIXEL));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)] // primaries convert exactly
+    fn hsv_round_trips() {
+        assert_eq!(rgb_to_hsv([255, 0, 0]), [0.0, 1.0, 1.0]);
+        assert_eq!(rgb_to_hsv([0, 0, 255]), [240.0, 1.0, 1.0]);
+        assert_eq!(rgb_to_hsv([0, 0, 0]), [0.0, 0.0, 0.0]);
+        for color in [[255, 0, 0], [12, 200, 99], [128, 128, 128], [250, 240, 5]] {
+            assert_eq!(hsv_to_rgb(rgb_to_hsv(color)), color);
+        }
+    }
+
+    #[test]
+    fn pixel_conversions_round_trip() {
+        let color = Color::from(PIXEL);
+        assert_eq!(color, Color::new(10, 20, 30, 40));
+        assert_eq!(Rgba::from(color), PIXEL);
+    }
 }
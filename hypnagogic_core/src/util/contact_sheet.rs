@@ -0,0 +1,213 @@
+use dmi::icon::Icon;
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::generation::text::{generate_text_line, lookup_coords};
+use crate::util::color::{fill_image_color, Color};
+
+/// Default gap around and between cells, in pixels
+const PADDING: u32 = 2;
+/// Cells are at least this wide, so short names aren't cut off on small icons
+const MIN_CELL_WIDTH: u32 = 48;
+/// Space under each frame for its label, which is one line of text
+const LABEL_HEIGHT: u32 = 7;
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+const LABEL_COLOR: Color = Color {
+    red: 224,
+    green: 224,
+    blue: 224,
+    alpha: 255,
+};
+
+/// Lays out the first frame (south facing) of every icon state in a dmi as a
+/// grid, with each state's name written under it. Handy for showing off a
+/// whole file at once.
+///
+/// Big dmis get split over several pages rather than one enormous sheet
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ContactSheet {
+    /// How many cells go in each row
+    pub columns: u32,
+    /// How many icon states go on each page before starting a new one
+    pub states_per_page: usize,
+    /// Gap around and between cells, in pixels
+    pub padding: u32,
+}
+
+impl Default for ContactSheet {
+    fn default() -> Self {
+        Self {
+            columns: 8,
+            states_per_page: 64,
+            padding: PADDING,
+        }
+    }
+}
+
+impl ContactSheet {
+    /// Width and height of a single cell, label included
+    #[must_use]
+    pub fn cell_size(icon: &Icon) -> (u32, u32) {
+        (icon.width.max(MIN_CELL_WIDTH), icon.height + LABEL_HEIGHT)
+    }
+
+    /// Renders every page of the sheet. A dmi with no icon states has no pages
+    #[must_use]
+    pub fn render(&self, icon: &Icon) -> Vec<RgbaImage> {
+        icon.states
+            .chunks(self.states_per_page.max(1))
+            .map(|states| {
+                let (cell_width, cell_height) = Self::cell_size(icon);
+                let columns = self.columns.clamp(1, states.len() as u32);
+                let rows = (states.len() as u32).div_ceil(columns);
+                let mut page = RgbaImage::from_pixel(
+                    columns * (cell_width + self.padding) + self.padding,
+                    rows * (cell_height + self.padding) + self.padding,
+                    BACKGROUND,
+                );
+                for (index, state) in (0..).zip(states) {
+                    let cell_x = self.padding + (index % columns) * (cell_width + self.padding);
+                    let cell_y = self.padding + (index / columns) * (cell_height + self.padding);
+                    // Images are stored frame by frame, so the first one is
+                    // the first frame facing south
+                    if let Some(frame) = state.images.first() {
+                        let frame_x = cell_x + (cell_width - icon.width) / 2;
+                        imageops::overlay(
+                            &mut page,
+                            &frame.to_rgba8(),
+                            i64::from(frame_x),
+                            i64::from(cell_y),
+                        );
+                    }
+                    if let Some(label) = label(&state.name, cell_width) {
+                        let label_x = cell_x + (cell_width - label.width()) / 2;
+                        imageops::overlay(
+                            &mut page,
+                            &label,
+                            i64::from(label_x),
+                            i64::from(cell_y + icon.height + 1),
+                        );
+                    }
+                }
+                page
+            })
+            .collect()
+    }
+}
+
+/// Renders an icon state's name, cut down to fit in a cell. Unnamed states get
+/// no label
+fn label(name: &str, max_width: u32) -> Option<RgbaImage> {
+    if name.is_empty() {
+        return None;
+    }
+    // The font only covers printable ascii
+    let name: String = name
+        .chars()
+        .map(|char| {
+            if char == ' ' || lookup_coords(char).is_some() {
+                char
+            } else {
+                '?'
+            }
+        })
+        .collect();
+    let mut text = generate_text_line(&name);
+    fill_image_color(&mut text, LABEL_COLOR);
+    let width = text.width().min(max_width);
+    Some(text.crop_imm(0, 0, width, text.height()).into_rgba8())
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::IconState;
+    use image::DynamicImage;
+
+    use super::*;
+
+    fn icon(colors: &[[u8; 4]]) -> Icon {
+        Icon {
+            width: 4,
+            height: 4,
+            states: colors
+                .iter()
+                .enumerate()
+                .map(|(index, &color)| {
+                    IconState {
+                        name: format!("state{index}"),
+                        images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                            4,
+                            4,
+                            Rgba(color),
+                        ))],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn one_cell_per_state() {
+        let colors = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 0, 255],
+            [0, 255, 255, 255],
+        ];
+        let icon = icon(&colors);
+        let sheet = ContactSheet {
+            columns: 2,
+            states_per_page: 64,
+            padding: PADDING,
+        };
+        let pages = sheet.render(&icon);
+        assert_eq!(pages.len(), 1);
+
+        let (cell_width, cell_height) = ContactSheet::cell_size(&icon);
+        let page = &pages[0];
+        assert_eq!(page.width(), 2 * (cell_width + PADDING) + PADDING);
+        assert_eq!(page.height(), 3 * (cell_height + PADDING) + PADDING);
+
+        // Each cell holds its own state's frame, centered over the label
+        for (index, color) in (0..).zip(colors) {
+            let x = PADDING + (index % 2) * (cell_width + PADDING) + (cell_width - 4) / 2;
+            let y = PADDING + (index / 2) * (cell_height + PADDING);
+            assert_eq!(*page.get_pixel(x, y), Rgba(color), "cell {index}");
+        }
+        // The sixth cell is left empty
+        let x = PADDING + cell_width + PADDING + (cell_width - 4) / 2;
+        let y = PADDING + 2 * (cell_height + PADDING);
+        assert_eq!(*page.get_pixel(x, y), BACKGROUND);
+    }
+
+    #[test]
+    fn large_icons_are_paginated() {
+        let icon = icon(&[[255, 255, 255, 255]; 5]);
+        let sheet = ContactSheet {
+            columns: 8,
+            states_per_page: 2,
+            padding: PADDING,
+        };
+        let pages = sheet.render(&icon);
+        assert_eq!(pages.len(), 3);
+        // Narrow pages don't keep empty columns around
+        assert!(pages[2].width() < pages[0].width());
+
+        assert!(sheet.render(&Icon::default()).is_empty());
+    }
+
+    #[test]
+    fn labels_fit_their_cell() {
+        assert!(label("", 48).is_none());
+        let short = label("on", 48).unwrap();
+        assert!(short.width() < 48);
+        assert_eq!(
+            label("a_very_long_icon_state_name", 48).unwrap().width(),
+            48
+        );
+        // Characters the font doesn't have don't panic
+        assert!(label("caf\u{e9}", 48).is_some());
+    }
+}
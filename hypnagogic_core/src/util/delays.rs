@@ -1,3 +1,7 @@
+use dmi::icon::Icon;
+
+use crate::util::dirs::facing;
+
 // Takes a list of delays and a suffix as input, returns a set of textified
 // delays
 #[must_use]
@@ -11,3 +15,86 @@ pub fn text_delays(textify: &[f32], suffix: &str) -> String {
             .unwrap_or_default()
     )
 }
+
+/// The frame delays of one direction of an icon state, in deciseconds
+#[derive(Clone, PartialEq, Debug)]
+pub struct DirDelays {
+    pub state: String,
+    pub dir: &'static str,
+    /// Empty for states that aren't animated
+    pub delays: Vec<f32>,
+}
+
+impl DirDelays {
+    /// How long one play through of the animation takes
+    #[must_use]
+    pub fn total(&self) -> f32 {
+        // Summing nothing gives -0, which reads oddly in reports
+        self.delays.iter().fold(0.0, |total, delay| total + delay)
+    }
+}
+
+/// The delays of every direction of every icon state in `icon`, in order.
+/// Every direction of a state shares its delays, but each gets its own entry
+/// so they line up with the frames
+#[must_use]
+pub fn dir_delays(icon: &Icon) -> Vec<DirDelays> {
+    icon.states
+        .iter()
+        .flat_map(|state| {
+            let delays = state.delay.clone().unwrap_or_default();
+            facing(state.dirs).iter().map(move |dir| {
+                DirDelays {
+                    state: state.name.clone(),
+                    dir: dir.name(),
+                    delays: delays.clone(),
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::IconState;
+
+    use super::*;
+
+    #[test]
+    fn every_dir_gets_the_state_delays() {
+        let icon = Icon {
+            states: vec![
+                IconState {
+                    name: "spin".to_string(),
+                    dirs: 4,
+                    frames: 2,
+                    delay: Some(vec![1.0, 2.5]),
+                    ..Default::default()
+                },
+                IconState {
+                    name: "idle".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let delays = dir_delays(&icon);
+        let dirs: Vec<(&str, &str)> = delays
+            .iter()
+            .map(|row| (row.state.as_str(), row.dir))
+            .collect();
+        assert_eq!(
+            dirs,
+            vec![
+                ("spin", "south"),
+                ("spin", "north"),
+                ("spin", "east"),
+                ("spin", "west"),
+                ("idle", "south"),
+            ]
+        );
+        assert_eq!(delays[3].delays, vec![1.0, 2.5]);
+        assert!((delays[3].total() - 3.5).abs() < f32::EPSILON);
+        assert!(delays[4].delays.is_empty());
+    }
+}
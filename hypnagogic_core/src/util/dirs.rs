@@ -0,0 +1,175 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::corners::Side;
+
+/// A direction an icon state can face. Unlike `Side`, this covers the
+/// diagonals too. Ordered the way dmis store them
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dir {
+    South,
+    North,
+    East,
+    West,
+    SouthEast,
+    SouthWest,
+    NorthEast,
+    NorthWest,
+}
+
+/// Every direction, in the order dmis store them. A state with `n` directions
+/// faces the first `n` of these. Yes, south really does come before north
+pub const DMI_ORDER: [Dir; 8] = [
+    Dir::South,
+    Dir::North,
+    Dir::East,
+    Dir::West,
+    Dir::SouthEast,
+    Dir::SouthWest,
+    Dir::NorthEast,
+    Dir::NorthWest,
+];
+
+/// The directions a state with `dirs` directions faces, in the order each
+/// frame's images are stored
+#[must_use]
+pub fn facing(dirs: u8) -> &'static [Dir] {
+    &DMI_ORDER[..usize::from(dirs.clamp(1, 8))]
+}
+
+impl Dir {
+    /// Matches directions to Byond bitfield directions
+    #[must_use]
+    pub const fn byond_dir(self) -> u8 {
+        const NORTH: u8 = 0b0000_0001;
+        const SOUTH: u8 = 0b0000_0010;
+        const EAST: u8 = 0b0000_0100;
+        const WEST: u8 = 0b0000_1000;
+        match self {
+            Dir::South => SOUTH,
+            Dir::North => NORTH,
+            Dir::East => EAST,
+            Dir::West => WEST,
+            Dir::SouthEast => SOUTH | EAST,
+            Dir::SouthWest => SOUTH | WEST,
+            Dir::NorthEast => NORTH | EAST,
+            Dir::NorthWest => NORTH | WEST,
+        }
+    }
+
+    /// The name configs and reports use for this direction
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Dir::South => "south",
+            Dir::North => "north",
+            Dir::East => "east",
+            Dir::West => "west",
+            Dir::SouthEast => "southeast",
+            Dir::SouthWest => "southwest",
+            Dir::NorthEast => "northeast",
+            Dir::NorthWest => "northwest",
+        }
+    }
+
+    /// The direction called `name`, if there is one
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        DMI_ORDER.into_iter().find(|dir| dir.name() == name)
+    }
+
+    /// The direction of the image at `index` within one frame of a state with
+    /// `dirs` directions
+    #[must_use]
+    pub fn at_index(index: usize, dirs: u8) -> Option<Self> {
+        facing(dirs).get(index).copied()
+    }
+
+    /// Where this direction's image sits within one frame of a state with
+    /// `dirs` directions. `None` if such a state doesn't face this way
+    #[must_use]
+    pub fn index(self, dirs: u8) -> Option<usize> {
+        facing(dirs).iter().position(|&dir| dir == self)
+    }
+
+    /// The side this direction faces, if it's a cardinal
+    #[must_use]
+    pub const fn side(self) -> Option<Side> {
+        match self {
+            Dir::South => Some(Side::South),
+            Dir::North => Some(Side::North),
+            Dir::East => Some(Side::East),
+            Dir::West => Some(Side::West),
+            _ => None,
+        }
+    }
+}
+
+impl From<Side> for Dir {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::North => Dir::North,
+            Side::South => Dir::South,
+            Side::East => Dir::East,
+            Side::West => Dir::West,
+        }
+    }
+}
+
+impl Display for Dir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn single_dir_only_faces_south() {
+        assert_eq!(Dir::at_index(0, 1), Some(Dir::South));
+        assert_eq!(Dir::at_index(1, 1), None);
+        assert_eq!(Dir::South.index(1), Some(0));
+        assert_eq!(Dir::North.index(1), None);
+        // Some states claim 0 dirs, which byond treats as 1
+        assert_eq!(facing(0), [Dir::South]);
+    }
+
+    #[test]
+    fn four_dirs_are_the_cardinals() {
+        let order = [Dir::South, Dir::North, Dir::East, Dir::West];
+        for (index, dir) in order.into_iter().enumerate() {
+            assert_eq!(Dir::at_index(index, 4), Some(dir));
+            assert_eq!(dir.index(4), Some(index));
+        }
+        assert_eq!(Dir::at_index(4, 4), None);
+        assert_eq!(Dir::NorthEast.index(4), None);
+        let sides: Vec<Dir> = Side::dmi_cardinals().into_iter().map(Dir::from).collect();
+        assert_eq!(sides, order);
+    }
+
+    #[test]
+    fn eight_dirs_add_the_diagonals() {
+        for (index, dir) in DMI_ORDER.into_iter().enumerate() {
+            assert_eq!(Dir::at_index(index, 8), Some(dir));
+            assert_eq!(dir.index(8), Some(index));
+            assert_eq!(Dir::from_name(dir.name()), Some(dir));
+        }
+        assert_eq!(Dir::SouthEast.index(8), Some(4));
+        assert_eq!(Dir::NorthWest.index(8), Some(7));
+        assert_eq!(Dir::at_index(8, 8), None);
+    }
+
+    #[test]
+    fn byond_dirs_combine_for_diagonals() {
+        assert_eq!(Dir::North.byond_dir(), 1);
+        assert_eq!(Dir::South.byond_dir(), 2);
+        assert_eq!(Dir::East.byond_dir(), 4);
+        assert_eq!(Dir::West.byond_dir(), 8);
+        assert_eq!(Dir::SouthEast.byond_dir(), 6);
+        assert_eq!(Dir::NorthWest.byond_dir(), 9);
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::Cursor;
+
+use dmi::error::DmiError;
+use dmi::icon::Icon;
+use dmi::ztxt::create_ztxt_chunk;
+use dmi::RawDmi;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use png::{ColorType, Decoder, Transformations};
+use tracing::{debug, warn};
+
+/// An icon loaded from a DMI whose pixel data was partially unreadable
+pub struct RecoveredIcon {
+    /// The icon, minus any states that couldn't be decoded
+    pub icon: Icon,
+    /// Names of the icon states that were dropped, in file order
+    pub skipped_states: Vec<String>,
+}
+
+/// Loads a DMI, salvaging what it can if the pixel data is corrupt or
+/// truncated.
+///
+/// The metadata still has to parse. Pixel rows are decoded until the first
+/// failure, and any icon state with a frame touching the undecodable region is
+/// dropped and reported instead of failing the whole file.
+/// # Errors
+/// Returns the original load error if the file can't be salvaged (bad
+/// metadata, no readable rows at all, interlaced pixel data, etc)
+#[tracing::instrument(skip(bytes))]
+pub fn load_recovering(bytes: &[u8]) -> Result<RecoveredIcon, DmiError> {
+    // The dmi crate panics on chunks that run off the end of the file, so
+    // don't hand it truncated files
+    let original_error = if chunks_in_bounds(bytes) {
+        match Icon::load(bytes) {
+            Ok(icon) => {
+                return Ok(RecoveredIcon {
+                    icon,
+                    skipped_states: vec![],
+                })
+            }
+            Err(error) => error,
+        }
+    } else {
+        DmiError::Generic("Chunk data runs past the end of the file".to_string())
+    };
+    debug!(error = ?original_error, "Plain load failed, attempting recovery");
+
+    let Some(PartialDecode {
+        image,
+        decoded_rows,
+        metadata,
+    }) = decode_partial(bytes)
+    else {
+        return Err(original_error);
+    };
+
+    // Re-pack what we salvaged into a well formed dmi so the dmi crate can do
+    // the metadata parsing for us
+    let image_width = image.width();
+    let mut png_bytes = Cursor::new(vec![]);
+    DynamicImage::ImageRgba8(image).write_to(&mut png_bytes, ImageFormat::Png)?;
+    let mut raw_dmi = RawDmi::load(&png_bytes.into_inner()[..])?;
+    raw_dmi.chunk_ztxt = Some(create_ztxt_chunk(metadata.as_bytes())?);
+    let mut dmi_bytes = vec![];
+    raw_dmi.save(&mut dmi_bytes)?;
+    let Ok(mut icon) = Icon::load(&dmi_bytes[..]) else {
+        return Err(original_error);
+    };
+
+    // A sheet narrower than a single state has nowhere to put any of them
+    let width_in_states = image_width.checked_div(icon.width).unwrap_or(0);
+    if width_in_states == 0 {
+        return Err(original_error);
+    }
+    let mut index = 0;
+    let mut skipped_states = vec![];
+    icon.states.retain(|state| {
+        let first_cell = index;
+        index += state.images.len() as u32;
+        // States without any frames don't take up any of the sheet
+        if first_cell == index {
+            return true;
+        }
+        let last_row_needed = ((index - 1) / width_in_states + 1) * icon.height;
+        if last_row_needed > decoded_rows {
+            warn!(state = ?state.name, "Dropping icon state with undecodable pixels");
+            skipped_states.push(state.name.clone());
+            return false;
+        }
+        true
+    });
+
+    Ok(RecoveredIcon {
+        icon,
+        skipped_states,
+    })
+}
+
+/// Checks that every chunk in a png fits inside the buffer
+pub(crate) fn chunks_in_bounds(bytes: &[u8]) -> bool {
+    // Skip the 8 byte png signature
+    let mut index = 8;
+    while index + 12 <= bytes.len() {
+        let length = u32::from_be_bytes([
+            bytes[index],
+            bytes[index + 1],
+            bytes[index + 2],
+            bytes[index + 3],
+        ]) as usize;
+        let chunk_end = index + 12 + length;
+        if chunk_end > bytes.len() {
+            return false;
+        }
+        if &bytes[index + 4..index + 8] == b"IEND" {
+            return true;
+        }
+        index = chunk_end;
+    }
+    false
+}
+
+struct PartialDecode {
+    image: RgbaImage,
+    decoded_rows: u32,
+    metadata: String,
+}
+
+/// Decodes as many rows of a png as possible, stopping at the first error.
+/// Returns `None` if there's nothing worth salvaging.
+fn decode_partial(bytes: &[u8]) -> Option<PartialDecode> {
+    let mut decoder = Decoder::new(bytes);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    decoder.ignore_checksums(true);
+    let mut reader = decoder.read_info().ok()?;
+
+    let info = reader.info();
+    if info.interlaced {
+        return None;
+    }
+    let (width, height) = (info.width, info.height);
+    let metadata = info
+        .compressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == "Description")?
+        .get_text()
+        .ok()?;
+    let (color_type, _) = reader.output_color_type();
+
+    let mut image = RgbaImage::new(width, height);
+    let mut decoded_rows = 0;
+    while decoded_rows < height {
+        let Ok(Some(row)) = reader.next_row() else {
+            break;
+        };
+        let pixels: Vec<Rgba<u8>> = match color_type {
+            ColorType::Rgba => {
+                row.data()
+                    .chunks_exact(4)
+                    .map(|px| Rgba([px[0], px[1], px[2], px[3]]))
+                    .collect()
+            }
+            ColorType::Rgb => {
+                row.data()
+                    .chunks_exact(3)
+                    .map(|px| Rgba([px[0], px[1], px[2], 255]))
+                    .collect()
+            }
+            ColorType::GrayscaleAlpha => {
+                row.data()
+                    .chunks_exact(2)
+                    .map(|px| Rgba([px[0], px[0], px[0], px[1]]))
+                    .collect()
+            }
+            ColorType::Grayscale => row.data().iter().map(|&v| Rgba([v, v, v, 255])).collect(),
+            // Expansion means we never get palette indexes back
+            ColorType::Indexed => return None,
+        };
+        for (x, pixel) in pixels.into_iter().enumerate() {
+            image.put_pixel(x as u32, decoded_rows, pixel);
+        }
+        decoded_rows += 1;
+    }
+
+    if decoded_rows == 0 {
+        return None;
+    }
+    Some(PartialDecode {
+        image,
+        decoded_rows,
+        metadata,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use dmi::icon::IconState;
+    use image::GenericImageView;
+
+    use super::*;
+
+    const STATE_NAMES: [&str; 7] = ["a", "b", "c", "d", "e", "f", "broken"];
+
+    /// Noisy frames, so the compressed pixel data grows roughly evenly per row
+    fn noise_frame(seed: u32) -> DynamicImage {
+        let mut state = seed.wrapping_mul(2_654_435_761).wrapping_add(1);
+        let image = RgbaImage::from_fn(96, 96, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            Rgba(state.to_le_bytes())
+        });
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn test_icon() -> Icon {
+        let mut states: Vec<IconState> = STATE_NAMES[..6]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                IconState {
+                    name: (*name).to_string(),
+                    images: vec![noise_frame(index as u32)],
+                    ..Default::default()
+                }
+            })
+            .collect();
+        // 9 frames total get packed into a 3x3 grid, so this animation gets
+        // the entire bottom row to itself
+        states.push(IconState {
+            name: "broken".to_string(),
+            frames: 3,
+            images: (6..9).map(noise_frame).collect(),
+            delay: Some(vec![1.0, 1.0, 1.0]),
+            ..Default::default()
+        });
+        Icon {
+            width: 96,
+            height: 96,
+            states,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn intact_dmi_loads_unchanged() {
+        let icon = test_icon();
+        let mut bytes = vec![];
+        icon.save(&mut bytes).unwrap();
+
+        let recovered = load_recovering(&bytes).unwrap();
+        assert!(recovered.skipped_states.is_empty());
+        assert_eq!(recovered.icon.states.len(), STATE_NAMES.len());
+    }
+
+    #[test]
+    fn truncated_pixels_skip_only_broken_states() {
+        let icon = test_icon();
+        let mut bytes = vec![];
+        icon.save(&mut bytes).unwrap();
+        // Chopping off the tail of the pixel data only breaks the bottom row
+        let idat_start = bytes.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        let idat_length = bytes.len() - idat_start;
+        bytes.truncate(idat_start + idat_length * 85 / 100);
+        assert!(!chunks_in_bounds(&bytes));
+
+        let recovered = load_recovering(&bytes).unwrap();
+        assert_eq!(recovered.skipped_states, vec!["broken".to_string()]);
+        let names: Vec<&str> = recovered
+            .icon
+            .states
+            .iter()
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(names, STATE_NAMES[..6]);
+        for (recovered_state, original_state) in recovered.icon.states.iter().zip(&icon.states) {
+            let recovered_frame = &recovered_state.images[0];
+            let original_frame = &original_state.images[0];
+            assert_eq!(recovered_frame.dimensions(), original_frame.dimensions());
+            assert_eq!(recovered_frame.to_rgba8(), original_frame.to_rgba8());
+        }
+    }
+
+    #[test]
+    fn frameless_states_are_kept() {
+        let mut bytes = vec![];
+        test_icon().save(&mut bytes).unwrap();
+        // Put a state without any frames first, so it takes up no cells
+        let metadata = decode_partial(&bytes).unwrap().metadata.replacen(
+            "state = ",
+            "state = \"empty\"\n\tdirs = 1\n\tframes = 0\nstate = ",
+            1,
+        );
+        let mut raw_dmi = RawDmi::load(&bytes[..]).unwrap();
+        raw_dmi.chunk_ztxt = Some(create_ztxt_chunk(metadata.as_bytes()).unwrap());
+        let mut bytes = vec![];
+        raw_dmi.save(&mut bytes).unwrap();
+        let idat_start = bytes.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        let idat_length = bytes.len() - idat_start;
+        bytes.truncate(idat_start + idat_length * 85 / 100);
+
+        let recovered = load_recovering(&bytes).unwrap();
+        assert_eq!(recovered.skipped_states, vec!["broken".to_string()]);
+        assert_eq!(recovered.icon.states[0].name, "empty");
+        assert_eq!(recovered.icon.states.len(), STATE_NAMES.len());
+    }
+
+    #[test]
+    fn unreadable_metadata_keeps_original_error() {
+        let bytes = vec![0; 128];
+        assert!(load_recovering(&bytes).is_err());
+    }
+}
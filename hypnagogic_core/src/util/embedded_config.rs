@@ -0,0 +1,114 @@
+use dmi::chunk::RawGenericChunk;
+use dmi::error::DmiError;
+use dmi::RawDmi;
+use png::text_metadata::{EncodableTextChunk, ITXtChunk};
+use png::Decoder;
+
+/// Keyword of the png text chunk a config is embedded under. BYOND only
+/// reads the "Description" chunk, so anything else is left alone by it
+pub const EMBEDDED_CONFIG_KEYWORD: &str = "hypnagogic";
+
+/// Reads the config embedded in a dmi (or png) by [`embed_config`], if there
+/// is one. The config isn't parsed, so it's just as it was written
+/// # Errors
+/// Errors if the file isn't a readable png, or the embedded text isn't valid
+pub fn read_embedded_config(bytes: &[u8]) -> Result<Option<String>, DmiError> {
+    let reader = Decoder::new(bytes)
+        .read_info()
+        .map_err(|error| DmiError::Generic(error.to_string()))?;
+    reader
+        .info()
+        .utf8_text
+        .iter()
+        .find(|chunk| chunk.keyword == EMBEDDED_CONFIG_KEYWORD)
+        .map(|chunk| {
+            chunk
+                .get_text()
+                .map_err(|error| DmiError::Generic(error.to_string()))
+        })
+        .transpose()
+}
+
+/// Stores `config` inside a dmi, so the icon carries its own processing
+/// config around with it. Replaces any config that was already embedded
+/// # Errors
+/// Errors if `dmi` can't be read as a dmi
+pub fn embed_config(dmi: &[u8], config: &str) -> Result<Vec<u8>, DmiError> {
+    let mut raw_dmi = RawDmi::load(dmi)?;
+    let mut chunk_bytes = vec![];
+    let mut text_chunk = ITXtChunk::new(EMBEDDED_CONFIG_KEYWORD, config);
+    text_chunk
+        .compress_text()
+        .and_then(|()| text_chunk.encode(&mut chunk_bytes))
+        .map_err(|error| DmiError::Encoding(error.to_string()))?;
+    let chunk = RawGenericChunk::load(&mut &chunk_bytes[..])?;
+
+    let mut other_chunks = raw_dmi.other_chunks.take().unwrap_or_default();
+    other_chunks.retain(|existing| !is_embedded_config(existing));
+    other_chunks.push(chunk);
+    raw_dmi.other_chunks = Some(other_chunks);
+
+    let mut output = vec![];
+    raw_dmi.save(&mut output)?;
+    Ok(output)
+}
+
+fn is_embedded_config(chunk: &RawGenericChunk) -> bool {
+    // iTXt data starts with the keyword, then a null separator
+    &chunk.chunk_type == b"iTXt"
+        && chunk
+            .data
+            .strip_prefix(EMBEDDED_CONFIG_KEYWORD.as_bytes())
+            .is_some_and(|rest| rest.first() == Some(&0))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use dmi::icon::{Icon, IconState};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn dmi_bytes() -> Vec<u8> {
+        let icon = Icon {
+            width: 4,
+            height: 4,
+            states: vec![IconState {
+                name: "on".to_string(),
+                images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                    4,
+                    4,
+                    Rgba([200, 50, 50, 255]),
+                ))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut bytes = Cursor::new(vec![]);
+        icon.save(&mut bytes).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn embedded_config_round_trips() {
+        let plain = dmi_bytes();
+        assert_eq!(read_embedded_config(&plain).unwrap(), None);
+
+        let embedded = embed_config(&plain, "mode = \"Passthrough\"\n").unwrap();
+        assert_eq!(
+            read_embedded_config(&embedded).unwrap().as_deref(),
+            Some("mode = \"Passthrough\"\n")
+        );
+        // Still loads as the same icon
+        let icon = Icon::load(&embedded[..]).unwrap();
+        assert_eq!(icon.states[0].name, "on");
+
+        let replaced = embed_config(&embedded, "mode = \"Blur\"\n").unwrap();
+        assert_eq!(
+            read_embedded_config(&replaced).unwrap().as_deref(),
+            Some("mode = \"Blur\"\n")
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use dmi::icon::IconState;
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, RgbaImage};
 
-use crate::util::color::Color;
+use crate::util::color::{is_transparent, Color};
 
 // Removes duplicate frames from the icon state's animation, if it has any
 #[must_use]
@@ -64,10 +64,7 @@ pub fn colors_in_image(image: &DynamicImage) -> Vec<Color> {
             colors.push(color);
         }
     }
-    colors
-        .iter()
-        .map(|c| Color::new(c.0[0], c.0[1], c.0[2], c.0[3]))
-        .collect()
+    colors.into_iter().map(Color::from).collect()
 }
 
 pub fn sort_colors_by_luminance(colors: &mut [Color]) {
@@ -85,3 +82,63 @@ pub fn pick_contrasting_colors(colors: &[Color]) -> (Color, Color) {
     let second_index = (second.floor() as usize).saturating_sub(1);
     (sorted_colors[first_index], sorted_colors[second_index])
 }
+
+/// A rectangular region of an image
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Bounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Bounds {
+    /// The smallest region holding both `self` and `other`
+    #[must_use]
+    pub fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        }
+    }
+}
+
+/// The smallest region holding every pixel of `image` that isn't fully
+/// transparent, or `None` if there aren't any
+#[must_use]
+pub fn content_bounds(image: &DynamicImage) -> Option<Bounds> {
+    image
+        .pixels()
+        .filter(|&(_, _, pixel)| !is_transparent(pixel))
+        .map(|(x, y, _)| {
+            Bounds {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            }
+        })
+        .reduce(Bounds::union)
+}
+
+/// Moves everything in `image` over by `x` and `y` pixels, keeping the image
+/// the same size. Anything moved past an edge is lost
+#[must_use]
+pub fn translate_image(image: &DynamicImage, x: i64, y: i64) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let mut output = RgbaImage::new(width, height);
+    for (source_x, source_y, pixel) in image.pixels() {
+        let target_x = i64::from(source_x) + x;
+        let target_y = i64::from(source_y) + y;
+        if (0..i64::from(width)).contains(&target_x) && (0..i64::from(height)).contains(&target_y) {
+            output.put_pixel(target_x as u32, target_y as u32, pixel);
+        }
+    }
+    DynamicImage::ImageRgba8(output)
+}
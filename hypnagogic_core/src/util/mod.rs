@@ -1,11 +1,21 @@
+use std::collections::BTreeSet;
+
 use toml::map::Map;
 use toml::Value;
 
 pub mod adjacency;
+pub mod blend;
 pub mod color;
+pub mod contact_sheet;
 pub mod corners;
 pub mod delays;
+pub mod dirs;
+pub mod dmi_recovery;
+pub mod embedded_config;
 pub mod icon_ops;
+pub mod onion_skin;
+pub mod quantize;
+pub mod state_diff;
 
 #[tracing::instrument]
 pub(crate) fn deep_merge_toml(first: &mut Value, second: Value) {
@@ -20,6 +30,85 @@ pub(crate) fn deep_merge_toml(first: &mut Value, second: Value) {
     }
 }
 
+/// A single difference between two toml values, found by `diff_toml`.
+/// Paths are dotted keys from the root, ie `icon_size.x` or `operations.1.mode`
+#[derive(Clone, PartialEq, Debug)]
+pub enum TomlChange {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+/// Structurally compares two toml values, descending in to tables and arrays.
+/// Array elements are matched up by index
+#[must_use]
+pub fn diff_toml(old: &Value, new: &Value) -> Vec<TomlChange> {
+    fn diff_at(path: &str, old: &Value, new: &Value, changes: &mut Vec<TomlChange>) {
+        let join = |key: &dyn std::fmt::Display| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{path}.{key}")
+            }
+        };
+        let children: Vec<(String, Option<&Value>, Option<&Value>)> = match (old, new) {
+            (Value::Table(old_table), Value::Table(new_table)) => {
+                let keys: BTreeSet<&String> = old_table.keys().chain(new_table.keys()).collect();
+                keys.into_iter()
+                    .map(|key| (join(key), old_table.get(key), new_table.get(key)))
+                    .collect()
+            }
+            (Value::Array(old_array), Value::Array(new_array)) => {
+                (0..old_array.len().max(new_array.len()))
+                    .map(|index| (join(&index), old_array.get(index), new_array.get(index)))
+                    .collect()
+            }
+            _ => {
+                if old != new {
+                    changes.push(TomlChange::Changed {
+                        path: path.to_string(),
+                        old: old.clone(),
+                        new: new.clone(),
+                    });
+                }
+                return;
+            }
+        };
+        for (path, old_child, new_child) in children {
+            match (old_child, new_child) {
+                (Some(old), Some(new)) => diff_at(&path, old, new, changes),
+                (Some(old), None) => {
+                    changes.push(TomlChange::Removed {
+                        path,
+                        value: old.clone(),
+                    });
+                }
+                (None, Some(new)) => {
+                    changes.push(TomlChange::Added {
+                        path,
+                        value: new.clone(),
+                    });
+                }
+                (None, None) => unreachable!("key came from one of the two sides"),
+            }
+        }
+    }
+
+    let mut changes = vec![];
+    diff_at("", old, new, &mut changes);
+    changes
+}
+
 #[must_use]
 pub fn repeat_for<T: Clone>(to_repeat: &[T], amount: usize) -> Vec<T> {
     to_repeat.iter().cycle().take(amount).cloned().collect()
@@ -30,7 +119,7 @@ mod test {
 
     use toml::Value;
 
-    use crate::util::deep_merge_toml;
+    use crate::util::{deep_merge_toml, diff_toml, TomlChange};
 
     #[test]
     fn deep_merge_simple() {
@@ -155,4 +244,57 @@ mod test {
 
         assert_eq!(left, expected);
     }
+
+    #[test]
+    fn diff_nested_changes() {
+        let old: Value = toml::from_str(
+            r#"
+            mode = "BitmaskSlice"
+            list = [1, 2]
+            removed = 1
+            [icon_size]
+            x = 32
+            y = 32
+            "#,
+        )
+        .unwrap();
+        let new: Value = toml::from_str(
+            r#"
+            mode = "BitmaskSlice"
+            added = [1, 2]
+            list = [1, 3, 4]
+            [icon_size]
+            x = 32
+            y = 48
+            "#,
+        )
+        .unwrap();
+
+        let expected = vec![
+            TomlChange::Added {
+                path: "added".to_string(),
+                value: Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            },
+            TomlChange::Changed {
+                path: "icon_size.y".to_string(),
+                old: Value::Integer(32),
+                new: Value::Integer(48),
+            },
+            TomlChange::Changed {
+                path: "list.1".to_string(),
+                old: Value::Integer(2),
+                new: Value::Integer(3),
+            },
+            TomlChange::Added {
+                path: "list.2".to_string(),
+                value: Value::Integer(4),
+            },
+            TomlChange::Removed {
+                path: "removed".to_string(),
+                value: Value::Integer(1),
+            },
+        ];
+        assert_eq!(diff_toml(&old, &new), expected);
+        assert!(diff_toml(&old, &old).is_empty());
+    }
 }
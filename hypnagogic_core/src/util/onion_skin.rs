@@ -0,0 +1,193 @@
+use dmi::icon::{Icon, IconState};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use thiserror::Error;
+
+use crate::util::color::{alpha, Color};
+
+#[derive(Debug, Error)]
+pub enum OnionSkinError {
+    #[error("Can't onion skin a {}x{} dmi with a {}x{} one", old.0, old.1, new.0, new.1)]
+    SizeMismatch { old: (u32, u32), new: (u32, u32) },
+}
+
+/// The result of onion skinning two dmis
+#[derive(Clone, PartialEq, Debug)]
+pub struct OnionSkinned {
+    /// A state for every name in both dmis, laid out like the new one
+    pub icon: Icon,
+    /// States only in the old dmi, with nothing to compare against
+    pub only_old: Vec<String>,
+    /// States only in the new dmi, with nothing to compare against
+    pub only_new: Vec<String>,
+}
+
+/// Lays two versions of a dmi over each other, frame by frame, for reviewing
+/// what changed. Colors are reduced to brightness, with the old version in
+/// the red channel, the new one in green, and a blend of the two in blue. So
+/// anything only in the old one leans red, anything only in the new one leans
+/// green, and anything unchanged is a plain gray.
+///
+/// States are matched up by name. Frames are matched by index, with the old
+/// state's cycling if it has fewer
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct OnionSkin {
+    /// How much of the new version goes in to the blue channel, from 0 (all
+    /// old) to 1 (all new)
+    pub new_weight: f32,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self { new_weight: 0.5 }
+    }
+}
+
+impl OnionSkin {
+    /// # Errors
+    /// Returns `OnionSkinError::SizeMismatch` if the dmis have different icon
+    /// sizes
+    pub fn render(&self, old: &Icon, new: &Icon) -> Result<OnionSkinned, OnionSkinError> {
+        if (old.width, old.height) != (new.width, new.height) {
+            return Err(OnionSkinError::SizeMismatch {
+                old: (old.width, old.height),
+                new: (new.width, new.height),
+            });
+        }
+        let find = |icon: &Icon, name: &str| -> Option<IconState> {
+            icon.states.iter().find(|state| state.name == name).cloned()
+        };
+
+        let mut states = vec![];
+        let mut only_new = vec![];
+        for state in &new.states {
+            match find(old, &state.name) {
+                Some(old_state) => states.push(self.skin_state(&old_state, state)),
+                None => only_new.push(state.name.clone()),
+            }
+        }
+        let only_old = old
+            .states
+            .iter()
+            .filter(|state| find(new, &state.name).is_none())
+            .map(|state| state.name.clone())
+            .collect();
+
+        Ok(OnionSkinned {
+            icon: Icon {
+                states,
+                ..new.clone()
+            },
+            only_old,
+            only_new,
+        })
+    }
+
+    fn skin_state(self, old: &IconState, new: &IconState) -> IconState {
+        IconState {
+            images: new
+                .images
+                .iter()
+                .enumerate()
+                .map(|(index, new_image)| {
+                    let old_image = &old.images[index % old.images.len().max(1)];
+                    DynamicImage::ImageRgba8(self.skin_frame(old_image, new_image))
+                })
+                .collect(),
+            ..new.clone()
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn skin_frame(self, old: &DynamicImage, new: &DynamicImage) -> RgbaImage {
+        let weight = self.new_weight.clamp(0.0, 1.0);
+        let mix = |old: u8, new: u8| {
+            (f32::from(old) * (1.0 - weight) + f32::from(new) * weight).round() as u8
+        };
+        // Transparent pixels count as black, so they don't show up as a change
+        // in brightness on top of the change in alpha
+        let brightness = |pixel: Rgba<u8>| {
+            let covered = f32::from(alpha(pixel)) / 255.0;
+            (Color::from(pixel).luminance() * covered * 255.0).round() as u8
+        };
+
+        let (width, height) = new.dimensions();
+        RgbaImage::from_fn(width, height, |x, y| {
+            let new_pixel = new.get_pixel(x, y);
+            let old_pixel = if old.in_bounds(x, y) {
+                old.get_pixel(x, y)
+            } else {
+                Rgba([0, 0, 0, 0])
+            };
+            let (old_brightness, new_brightness) = (brightness(old_pixel), brightness(new_pixel));
+            let coverage = alpha(old_pixel).max(alpha(new_pixel));
+            if coverage == 0 {
+                return Rgba([0, 0, 0, 0]);
+            }
+            // Undo the darkening from alpha, now the pixel has it back
+            let lift = |value: u8| {
+                (f32::from(value) * 255.0 / f32::from(coverage))
+                    .round()
+                    .min(255.0) as u8
+            };
+            Rgba([
+                lift(old_brightness),
+                lift(new_brightness),
+                lift(mix(old_brightness, new_brightness)),
+                coverage,
+            ])
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn icon(states: &[(&str, Rgba<u8>)]) -> Icon {
+        Icon {
+            width: 2,
+            height: 2,
+            states: states
+                .iter()
+                .map(|(name, color)| {
+                    IconState {
+                        name: (*name).to_string(),
+                        images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                            2, 2, *color,
+                        ))],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn identical_inputs_come_out_gray() {
+        let orange = Rgba([255, 128, 0, 255]);
+        let both = icon(&[("lamp", orange)]);
+        let skinned = OnionSkin::default().render(&both, &both).unwrap();
+        assert!(skinned.only_old.is_empty() && skinned.only_new.is_empty());
+
+        let brightness = (Color::from(orange).luminance() * 255.0).round() as u8;
+        let image = &skinned.icon.states[0].images[0];
+        for (_, _, pixel) in image.pixels() {
+            assert_eq!(pixel, Rgba([brightness, brightness, brightness, 255]));
+        }
+    }
+
+    #[test]
+    fn changes_are_tinted_and_missing_states_reported() {
+        let white = Rgba([255, 255, 255, 255]);
+        let old = icon(&[("lamp", white), ("removed", white)]);
+        let new = icon(&[("lamp", Rgba([0, 0, 0, 0])), ("added", white)]);
+        let skinned = OnionSkin::default().render(&old, &new).unwrap();
+        assert_eq!(skinned.only_old, vec!["removed"]);
+        assert_eq!(skinned.only_new, vec!["added"]);
+
+        // Only in the old one, so red
+        let pixel = skinned.icon.states[0].images[0].get_pixel(0, 0);
+        assert_eq!(pixel, Rgba([255, 0, 128, 255]));
+    }
+}
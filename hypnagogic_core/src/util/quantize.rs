@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use image::DynamicImage;
+
+use crate::util::color::{is_transparent, rgb};
+
+/// How many pixels of each color there are across `images`. Fully
+/// transparent pixels don't count as a color, and alpha is ignored
+#[must_use]
+pub fn color_counts<'a>(
+    images: impl IntoIterator<Item = &'a DynamicImage>,
+) -> BTreeMap<[u8; 3], usize> {
+    let mut counts = BTreeMap::new();
+    for image in images {
+        for pixel in image.to_rgba8().pixels() {
+            if !is_transparent(*pixel) {
+                *counts.entry(rgb(*pixel)).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Picks a palette of at most `max_colors` colors to stand in for `counts`.
+/// The colors are split into boxes, and the box covering the widest range is
+/// cut through the middle of that range until there are enough boxes. Each box
+/// then becomes the average of its colors, weighted by how common they are.
+///
+/// Cutting through the middle rather than at the median keeps tight clusters
+/// of colors together, which suits the small palettes of pixel art
+#[must_use]
+pub fn reduce_palette(counts: &BTreeMap<[u8; 3], usize>, max_colors: usize) -> Vec<[u8; 3]> {
+    let mut boxes: Vec<Vec<([u8; 3], usize)>> = vec![counts
+        .iter()
+        .map(|(&color, &count)| (color, count))
+        .collect()];
+    boxes.retain(|colors| !colors.is_empty());
+    while boxes.len() < max_colors {
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (index, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_by_key(|&(color, _)| color[channel]);
+        let lowest = colors[0].0[channel];
+        let middle = lowest + (colors[colors.len() - 1].0[channel] - lowest) / 2;
+        // The box has a range, so there's always a color on each side
+        let split = colors
+            .iter()
+            .position(|&(color, _)| color[channel] > middle)
+            .unwrap_or(1);
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|colors| average(colors)).collect()
+}
+
+/// The color in `palette` closest to `color`
+#[must_use]
+pub fn nearest(palette: &[[u8; 3]], color: [u8; 3]) -> [u8; 3] {
+    palette
+        .iter()
+        .copied()
+        .min_by_key(|candidate| {
+            candidate
+                .iter()
+                .zip(color)
+                .map(|(&a, b)| (i32::from(a) - i32::from(b)).pow(2))
+                .sum::<i32>()
+        })
+        .unwrap_or(color)
+}
+
+/// Which channel the colors spread furthest over, and how far
+fn widest_channel(colors: &[([u8; 3], usize)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = colors.iter().map(|(color, _)| color[channel]);
+            let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+            (channel, range)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(colors: &[([u8; 3], usize)]) -> [u8; 3] {
+    let total: usize = colors.iter().map(|&(_, count)| count).sum();
+    std::array::from_fn(|channel| {
+        let sum: usize = colors
+            .iter()
+            .map(|&(color, count)| usize::from(color[channel]) * count)
+            .sum();
+        ((sum + total / 2) / total.max(1)) as u8
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cuts_down_to_the_budget() {
+        let counts = BTreeMap::from([
+            ([0, 0, 0], 10),
+            ([10, 0, 0], 10),
+            ([240, 240, 240], 5),
+            ([255, 255, 255], 5),
+        ]);
+        let mut palette = reduce_palette(&counts, 2);
+        palette.sort_unstable();
+        assert_eq!(palette, vec![[5, 0, 0], [248, 248, 248]]);
+        assert_eq!(nearest(&palette, [20, 5, 0]), [5, 0, 0]);
+
+        // Never more colors than there were to begin with
+        assert_eq!(reduce_palette(&counts, 10).len(), 4);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use dmi::icon::{Icon, IconState};
+use image::{DynamicImage, GenericImageView};
+
+/// What happened to each icon state between two versions of a dmi, going by
+/// state name. States are listed in the order they appear in their dmi
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct StateChanges {
+    /// Only in the new dmi
+    pub added: Vec<String>,
+    /// Only in the old dmi
+    pub removed: Vec<String>,
+    /// In both, but with different pixels, delays, dirs, or the like
+    pub modified: Vec<String>,
+    /// In both, exactly the same
+    pub unchanged: Vec<String>,
+}
+
+impl StateChanges {
+    /// Compares the icon states of `old` and `new`. If a dmi has several
+    /// states with the same name, only the last of them is compared
+    #[must_use]
+    pub fn between(old: &Icon, new: &Icon) -> Self {
+        let by_name = |icon: &Icon| -> BTreeMap<String, IconState> {
+            icon.states
+                .iter()
+                .map(|state| (state.name.clone(), state.clone()))
+                .collect()
+        };
+        let old_states = by_name(old);
+        let new_states = by_name(new);
+
+        let mut changes = Self::default();
+        for state in &new.states {
+            if changes.contains(&state.name) {
+                continue;
+            }
+            let name = state.name.clone();
+            match old_states.get(&name) {
+                None => changes.added.push(name),
+                Some(old_state) if *old_state == new_states[&name] => {
+                    changes.unchanged.push(name);
+                }
+                Some(_) => changes.modified.push(name),
+            }
+        }
+        for state in &old.states {
+            if !new_states.contains_key(&state.name) && !changes.removed.contains(&state.name) {
+                changes.removed.push(state.name.clone());
+            }
+        }
+        changes
+    }
+
+    /// True if every state was left alone
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Whether a state in the new dmi has already been sorted
+    fn contains(&self, name: &str) -> bool {
+        [&self.added, &self.modified, &self.unchanged]
+            .into_iter()
+            .any(|names| names.iter().any(|sorted| sorted == name))
+    }
+}
+
+/// How many pixels differ between two versions of a dmi, across every frame
+/// of every icon state. States are matched up by name, and pixels in a state
+/// or frame only one version has count as changed
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct PixelChange {
+    pub changed: u64,
+    /// Pixels compared, counting each frame at the size of the bigger version
+    pub total: u64,
+}
+
+impl PixelChange {
+    /// Compares every pixel of `old` against `new`. If a dmi has several
+    /// states with the same name, only the last of them is compared
+    #[must_use]
+    pub fn between(old: &Icon, new: &Icon) -> Self {
+        let old_states: BTreeMap<&str, &IconState> = old
+            .states
+            .iter()
+            .map(|state| (state.name.as_str(), state))
+            .collect();
+        let new_states: BTreeMap<&str, &IconState> = new
+            .states
+            .iter()
+            .map(|state| (state.name.as_str(), state))
+            .collect();
+        let no_images = vec![];
+
+        let mut change = Self::default();
+        let names = old_states.keys().chain(new_states.keys());
+        let mut seen = vec![];
+        for name in names {
+            if seen.contains(name) {
+                continue;
+            }
+            seen.push(*name);
+            let old_images = old_states
+                .get(name)
+                .map_or(&no_images, |state| &state.images);
+            let new_images = new_states
+                .get(name)
+                .map_or(&no_images, |state| &state.images);
+            for index in 0..old_images.len().max(new_images.len()) {
+                let (old_image, new_image) = (old_images.get(index), new_images.get(index));
+                let pixels = |image: Option<&DynamicImage>| {
+                    image.map_or(0, |image| {
+                        u64::from(image.width()) * u64::from(image.height())
+                    })
+                };
+                let size = pixels(old_image).max(pixels(new_image));
+                change.total += size;
+                match (old_image, new_image) {
+                    (Some(old_image), Some(new_image))
+                        if old_image.dimensions() == new_image.dimensions() =>
+                    {
+                        let (old_image, new_image) = (old_image.to_rgba8(), new_image.to_rgba8());
+                        change.changed += old_image
+                            .pixels()
+                            .zip(new_image.pixels())
+                            .filter(|(old_pixel, new_pixel)| old_pixel != new_pixel)
+                            .count() as u64;
+                    }
+                    _ => change.changed += size,
+                }
+            }
+        }
+        change
+    }
+
+    /// The changed pixels as a percentage of all of them, 0 if there are none
+    #[must_use]
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.changed as f64 / self.total as f64 * 100.0
+    }
+}
+
+/// Whether two icon states would look and behave the same in game, ignoring
+/// their names. Dirs, frames, delays, and every pixel all have to match
+#[must_use]
+pub fn same_content(first: &IconState, second: &IconState) -> bool {
+    first.dirs == second.dirs
+        && first.frames == second.frames
+        && first.delay == second.delay
+        && first.loop_flag == second.loop_flag
+        && first.rewind == second.rewind
+        && first.movement == second.movement
+        && first.hotspot == second.hotspot
+        && first.images.len() == second.images.len()
+        && first
+            .images
+            .iter()
+            .zip(&second.images)
+            .all(|(first, second)| first.to_rgba8() == second.to_rgba8())
+}
+
+/// An icon state that's an exact copy of one before it in the same dmi
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct DuplicateState {
+    /// Where the copy is in the dmi's list of states
+    pub index: usize,
+    /// Name of the copy
+    pub name: String,
+    /// Name of the first state with the same content
+    pub original: String,
+}
+
+/// Finds every icon state in `icon` with the same content as an earlier one,
+/// see [`same_content`]. States are listed in the order they appear
+#[must_use]
+pub fn find_duplicate_states(icon: &Icon) -> Vec<DuplicateState> {
+    let mut originals: Vec<&IconState> = vec![];
+    let mut duplicates = vec![];
+    for (index, state) in icon.states.iter().enumerate() {
+        match originals
+            .iter()
+            .find(|original| same_content(original, state))
+        {
+            Some(original) => {
+                duplicates.push(DuplicateState {
+                    index,
+                    name: state.name.clone(),
+                    original: original.name.clone(),
+                });
+            }
+            None => originals.push(state),
+        }
+    }
+    duplicates
+}
+
+#[cfg(test)]
+mod test {
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn state(name: &str, color: [u8; 4]) -> IconState {
+        IconState {
+            name: name.to_string(),
+            images: vec![DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                2,
+                2,
+                Rgba(color),
+            ))],
+            ..Default::default()
+        }
+    }
+
+    fn icon(states: Vec<IconState>) -> Icon {
+        Icon {
+            width: 2,
+            height: 2,
+            states,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sorts_states_by_what_happened() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let old = icon(vec![
+            state("kept", red),
+            state("recolored", red),
+            state("dropped", red),
+        ]);
+        let new = icon(vec![
+            state("kept", red),
+            state("recolored", blue),
+            state("kept_masked", blue),
+            state("recolored_masked", blue),
+        ]);
+
+        let changes = StateChanges::between(&old, &new);
+        assert_eq!(changes.added, vec!["kept_masked", "recolored_masked"]);
+        assert_eq!(changes.removed, vec!["dropped"]);
+        assert_eq!(changes.modified, vec!["recolored"]);
+        assert_eq!(changes.unchanged, vec!["kept"]);
+        assert!(!changes.is_empty());
+
+        assert!(StateChanges::between(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn counts_changed_pixels_across_states() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let mut touched_up = state("recolored", red);
+        let DynamicImage::ImageRgba8(image) = &mut touched_up.images[0] else {
+            unreachable!();
+        };
+        image.put_pixel(0, 0, Rgba(blue));
+        let old = icon(vec![state("kept", red), state("recolored", red)]);
+
+        let change = PixelChange::between(&old, &icon(vec![state("kept", red), touched_up]));
+        assert_eq!(
+            change,
+            PixelChange {
+                changed: 1,
+                total: 8
+            }
+        );
+        assert!((change.percent() - 12.5).abs() < f64::EPSILON);
+
+        let change = PixelChange::between(&old, &icon(vec![state("kept", red)]));
+        assert_eq!(
+            change,
+            PixelChange {
+                changed: 4,
+                total: 8
+            }
+        );
+        assert_eq!(PixelChange::between(&old, &old).changed, 0);
+    }
+}